@@ -4,7 +4,10 @@
 use anyhow::Result;
 use libfuzzer_sys::{arbitrary, arbitrary::Arbitrary, fuzz_target};
 use wasm_smith::{Config, ConfiguredModule};
-use wasmer::{imports, CompilerConfig, Instance, Module, Store, Val};
+use wasmer::{
+    imports, CompilerConfig, Features, Instance, InstantiationError, Module, RuntimeError, Store,
+    TrapCode, Val,
+};
 #[cfg(feature = "cranelift")]
 use wasmer_compiler_cranelift::Cranelift;
 #[cfg(feature = "llvm")]
@@ -48,7 +51,9 @@ impl std::fmt::Debug for WasmSmithModule {
 #[cfg(feature = "singlepass")]
 fn maybe_instantiate_singlepass(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
     let compiler = Singlepass::default();
-    let store = Store::new(&Universal::new(compiler).engine());
+    let mut features = Features::deterministic();
+    features.multi_value(false);
+    let store = Store::new(&Universal::new(compiler).features(features).engine());
     let module = Module::new(&store, &wasm_bytes);
     let module = match module {
         Ok(m) => m,
@@ -67,9 +72,12 @@ fn maybe_instantiate_singlepass(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
 #[cfg(feature = "cranelift")]
 fn maybe_instantiate_cranelift(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
     let mut compiler = Cranelift::default();
-    compiler.canonicalize_nans(true);
     compiler.enable_verifier();
-    let store = Store::new(&Universal::new(compiler).engine());
+    let store = Store::new(
+        &Universal::new(compiler)
+            .features(Features::deterministic())
+            .engine(),
+    );
     let module = Module::new(&store, &wasm_bytes)?;
     let instance = Instance::new(&module, &imports! {})?;
     Ok(Some(instance))
@@ -78,9 +86,12 @@ fn maybe_instantiate_cranelift(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
 #[cfg(feature = "llvm")]
 fn maybe_instantiate_llvm(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
     let mut compiler = LLVM::default();
-    compiler.canonicalize_nans(true);
     compiler.enable_verifier();
-    let store = Store::new(&Universal::new(compiler).engine());
+    let store = Store::new(
+        &Universal::new(compiler)
+            .features(Features::deterministic())
+            .engine(),
+    );
     let module = Module::new(&store, &wasm_bytes)?;
     let instance = Instance::new(&module, &imports! {})?;
     Ok(Some(instance))
@@ -88,33 +99,18 @@ fn maybe_instantiate_llvm(wasm_bytes: &[u8]) -> Result<Option<Instance>> {
 
 #[derive(Debug)]
 enum FunctionResult {
-    Error(String),
+    Trap(Option<TrapCode>),
     Values(Vec<Val>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
 enum InstanceResult {
-    Error(String),
+    Error(FunctionResult),
     Functions(Vec<FunctionResult>),
 }
 
 impl PartialEq for FunctionResult {
     fn eq(&self, other: &Self) -> bool {
-        /*
-        match self {
-            FunctionResult::Error(self_message) => {
-                if let FunctionResult::Error(other_message) = other {
-                    return self_message == other_message;
-                }
-            }
-            FunctionResult::Values(self_values) => {
-                if let FunctionResult::Values(other_values) = other {
-                    return self_values == other_values;
-                }
-            }
-        }
-        false
-         */
         match (self, other) {
             (FunctionResult::Values(self_values), FunctionResult::Values(other_values)) => {
                 self_values.len() == other_values.len()
@@ -127,45 +123,49 @@ impl PartialEq for FunctionResult {
                             _ => x == y,
                         })
             }
-            _ => true,
+            // The stack usage of a function depends on the compiler, so
+            // exhausting the stack isn't deterministic.
+            (FunctionResult::Trap(Some(TrapCode::StackOverflow)), _)
+            | (_, FunctionResult::Trap(Some(TrapCode::StackOverflow))) => true,
+            (FunctionResult::Trap(self_trap), FunctionResult::Trap(other_trap)) => {
+                self_trap == other_trap
+            }
+            _ => false,
         }
     }
 }
 
 impl Eq for FunctionResult {}
 
+fn evaluate_trap(error: RuntimeError) -> FunctionResult {
+    FunctionResult::Trap(error.to_trap())
+}
+
 fn evaluate_instance(instance: Result<Instance>) -> InstanceResult {
-    if let Err(_err) = instance {
-        /*let mut error_message = format!("{}", err);
-        // Remove the stack trace.
-        if error_message.starts_with("RuntimeError: unreachable\n") {
-            error_message = "RuntimeError: unreachable\n".into();
+    let instance = match instance {
+        Ok(instance) => instance,
+        Err(err) => {
+            // Only the traps raised by the start function are compared, since
+            // the other errors depend on the compiler.
+            return InstanceResult::Error(match err.downcast::<InstantiationError>() {
+                Ok(InstantiationError::Start(error)) => evaluate_trap(error),
+                _ => FunctionResult::Trap(None),
+            });
         }
-        InstanceResult::Error(error_message)*/
-        InstanceResult::Error("".into())
-    } else {
-        let instance = instance.unwrap();
-        let mut results = vec![];
-        for it in instance.exports.iter().functions() {
-            let (_, f) = it;
-            // TODO: support functions which take params.
-            if f.ty().params().is_empty() {
-                let result = f.call(&[]);
-                let result = if let Ok(values) = result {
-                    FunctionResult::Values(values.into())
-                } else {
-                    /*
-                    let err = result.unwrap_err();
-                    let error_message = err.message();
-                    FunctionResult::Error(error_message)
-                     */
-                    FunctionResult::Error("".into())
-                };
-                results.push(result);
-            }
+    };
+    let mut results = vec![];
+    for it in instance.exports.iter().functions() {
+        let (_, f) = it;
+        // TODO: support functions which take params.
+        if f.ty().params().is_empty() {
+            let result = match f.call(&[]) {
+                Ok(values) => FunctionResult::Values(values.into()),
+                Err(error) => evaluate_trap(error),
+            };
+            results.push(result);
         }
-        InstanceResult::Functions(results)
     }
+    InstanceResult::Functions(results)
 }
 
 fuzz_target!(|module: WasmSmithModule| {
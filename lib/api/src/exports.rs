@@ -1,5 +1,6 @@
 use crate::externals::{Extern, Function, Global, Memory, Table};
 use crate::import_object::LikeNamespace;
+use crate::module::Module;
 use crate::native::NativeFunc;
 use crate::WasmTypeList;
 use indexmap::IndexMap;
@@ -8,7 +9,7 @@ use std::fmt;
 use std::iter::{ExactSizeIterator, FromIterator};
 use std::sync::Arc;
 use thiserror::Error;
-use wasmer_engine::Export;
+use wasmer_engine::{Export, ExportInstance};
 
 /// The `ExportError` can happen when trying to get a specific
 /// export [`Extern`] from the [`Instance`] exports.
@@ -139,6 +140,17 @@ impl Exports {
         self.get(name)
     }
 
+    /// Get an export as the `Exports` of an instance, with the module
+    /// linking proposal.
+    pub fn get_instance(&self, name: &str) -> Result<&Exports, ExportError> {
+        self.get(name)
+    }
+
+    /// Get an export as a `Module`, with the module linking proposal.
+    pub fn get_module(&self, name: &str) -> Result<&Module, ExportError> {
+        self.get(name)
+    }
+
     /// Get an export as a `NativeFunc`.
     pub fn get_native_function<Args, Rets>(
         &self,
@@ -292,6 +304,25 @@ impl LikeNamespace for Exports {
     }
 }
 
+impl<'a> Exportable<'a> for Exports {
+    fn to_export(&self) -> Export {
+        Export::Instance(ExportInstance::new(self.get_namespace_exports()))
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Instance(exports) => Ok(exports),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        for export in Arc::make_mut(&mut self.map).values_mut() {
+            export.into_weak_instance_ref();
+        }
+    }
+}
+
 /// This trait is used to mark types as gettable from an [`Instance`].
 ///
 /// [`Instance`]: crate::Instance
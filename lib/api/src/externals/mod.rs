@@ -9,11 +9,12 @@ pub use self::function::{
 
 pub use self::global::Global;
 pub use self::memory::Memory;
-pub use self::table::Table;
+pub use self::table::{Table, TableReference, TypedTable};
 
-use crate::exports::{ExportError, Exportable};
+use crate::exports::{ExportError, Exportable, Exports};
+use crate::module::Module;
 use crate::store::{Store, StoreObject};
-use crate::ExternType;
+use crate::{ExportType, ExternType, InstanceType, ModuleType};
 use loupe::MemoryUsage;
 use std::fmt;
 use wasmer_engine::Export;
@@ -32,6 +33,11 @@ pub enum Extern {
     Table(Table),
     /// A external [`Memory`].
     Memory(Memory),
+    /// The [`Exports`] of an external instance, with the module linking
+    /// proposal.
+    Instance(Exports),
+    /// A external [`Module`], with the module linking proposal.
+    Module(Module),
 }
 
 impl Extern {
@@ -42,6 +48,16 @@ impl Extern {
             Self::Memory(ft) => ExternType::Memory(ft.ty()),
             Self::Table(tt) => ExternType::Table(*tt.ty()),
             Self::Global(gt) => ExternType::Global(*gt.ty()),
+            Self::Instance(exports) => ExternType::Instance(InstanceType::new(
+                exports
+                    .iter()
+                    .map(|(name, export)| ExportType::new(name, export.ty()))
+                    .collect(),
+            )),
+            Self::Module(module) => ExternType::Module(ModuleType::new(
+                module.imports().collect(),
+                module.exports().collect(),
+            )),
         }
     }
 
@@ -52,6 +68,15 @@ impl Extern {
             Export::Memory(m) => Self::Memory(Memory::from_vm_export(store, m)),
             Export::Global(g) => Self::Global(Global::from_vm_export(store, g)),
             Export::Table(t) => Self::Table(Table::from_vm_export(store, t)),
+            Export::Instance(i) => Self::Instance(
+                i.exports
+                    .iter()
+                    .map(|(name, export)| {
+                        (name.clone(), Self::from_vm_export(store, export.clone()))
+                    })
+                    .collect(),
+            ),
+            Export::Module(m) => Self::Module(Module::from_artifact(store, m.artifact)),
         }
     }
 }
@@ -63,6 +88,8 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.to_export(),
             Self::Memory(m) => m.to_export(),
             Self::Table(t) => t.to_export(),
+            Self::Instance(i) => i.to_export(),
+            Self::Module(m) => m.to_export(),
         }
     }
 
@@ -77,6 +104,8 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.into_weak_instance_ref(),
             Self::Memory(m) => m.into_weak_instance_ref(),
             Self::Table(t) => t.into_weak_instance_ref(),
+            Self::Instance(i) => i.into_weak_instance_ref(),
+            Self::Module(m) => m.into_weak_instance_ref(),
         }
     }
 }
@@ -88,6 +117,12 @@ impl StoreObject for Extern {
             Self::Global(g) => g.store(),
             Self::Memory(m) => m.store(),
             Self::Table(t) => t.store(),
+            Self::Instance(i) => {
+                return i
+                    .iter()
+                    .all(|(_, export)| export.comes_from_same_store(store))
+            }
+            Self::Module(m) => m.store(),
         };
         Store::same(my_store, store)
     }
@@ -103,6 +138,8 @@ impl fmt::Debug for Extern {
                 Self::Global(_) => "Global(...)",
                 Self::Memory(_) => "Memory(...)",
                 Self::Table(_) => "Table(...)",
+                Self::Instance(_) => "Instance(...)",
+                Self::Module(_) => "Module(...)",
             }
         )
     }
@@ -131,3 +168,15 @@ impl From<Table> for Extern {
         Self::Table(r)
     }
 }
+
+impl From<Exports> for Extern {
+    fn from(r: Exports) -> Self {
+        Self::Instance(r)
+    }
+}
+
+impl From<Module> for Extern {
+    fn from(r: Module) -> Self {
+        Self::Module(r)
+    }
+}
@@ -1,12 +1,14 @@
 use crate::exports::{ExportError, Exportable};
-use crate::externals::Extern;
+use crate::externals::{Extern, Function};
 use crate::store::Store;
-use crate::types::{Val, ValFuncRef};
+use crate::types::{Val, ValFuncRef, ValType};
 use crate::RuntimeError;
 use crate::TableType;
 use loupe::MemoryUsage;
+use std::marker::PhantomData;
 use std::sync::Arc;
 use wasmer_engine::Export;
+use wasmer_types::ExternRef;
 use wasmer_vm::{Table as RuntimeTable, TableElement, VMTable};
 
 /// A WebAssembly `table` instance.
@@ -32,6 +34,18 @@ fn set_table_item(
     table.set(item_index, item).map_err(|e| e.into())
 }
 
+/// Checks that `val` can be an element of a table of type `ty`.
+fn check_table_item(ty: &TableType, val: &Val) -> Result<(), RuntimeError> {
+    if val.ty() != ty.ty {
+        return Err(RuntimeError::new(format!(
+            "a table of `{}` can't hold a `{}`",
+            ty.ty,
+            val.ty()
+        )));
+    }
+    Ok(())
+}
+
 impl Table {
     /// Creates a new `Table` with the provided [`TableType`] definition.
     ///
@@ -88,6 +102,100 @@ impl Table {
         self.vm_table.from.size()
     }
 
+    /// Sets the `len` elements of the `Table` starting at `index` to
+    /// the provided `val`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds of the table, or
+    /// if `val` is not of the type of the elements of the table.
+    pub fn fill(&self, index: u32, val: Val, len: u32) -> Result<(), RuntimeError> {
+        check_table_item(self.ty(), &val)?;
+        let item = val.into_table_reference(&self.store)?;
+        self.vm_table
+            .from
+            .fill(index, item, len)
+            .map_err(RuntimeError::from_trap)
+    }
+
+    /// Sets the elements of the `Table` starting at `index` to the
+    /// values produced by `vals`, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without setting any element, if the values
+    /// don't fit in the table from `index`, or if one of them is not of
+    /// the type of the elements of the table.
+    pub fn init(
+        &self,
+        index: u32,
+        vals: impl IntoIterator<Item = Val>,
+    ) -> Result<(), RuntimeError> {
+        let items = vals
+            .into_iter()
+            .map(|val| {
+                check_table_item(self.ty(), &val)?;
+                val.into_table_reference(&self.store)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.init_items(index, items)
+    }
+
+    fn init_items(&self, index: u32, items: Vec<TableElement>) -> Result<(), RuntimeError> {
+        let table = self.vm_table.from.as_ref();
+        if (index as usize)
+            .checked_add(items.len())
+            .map_or(true, |end| end > table.size() as usize)
+        {
+            return Err(RuntimeError::new(format!(
+                "{} elements from index {} are out of the bounds of the table",
+                items.len(),
+                index
+            )));
+        }
+        for (item_index, item) in (index..).zip(items) {
+            set_table_item(table, item_index, item)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a [`TypedTable`] to access the elements of the `Table`
+    /// without going through [`Val`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `T` is not the type of the elements of the
+    /// table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{ExternRef, Store, Table, TableType, Type, Value};
+    /// # let store = Store::default();
+    /// let table = Table::new(
+    ///     &store,
+    ///     TableType::new(Type::ExternRef, 2, None),
+    ///     Value::ExternRef(ExternRef::null()),
+    /// ).unwrap();
+    /// let handles = table.typed::<ExternRef>().unwrap();
+    /// handles.set(1, ExternRef::new(String::from("hello"))).unwrap();
+    /// let handle = handles.get(1).unwrap();
+    /// assert_eq!(handle.downcast::<String>().unwrap(), "hello");
+    /// ```
+    pub fn typed<T: TableReference>(&self) -> Result<TypedTable<T>, RuntimeError> {
+        if self.ty().ty != T::TYPE {
+            return Err(RuntimeError::new(format!(
+                "the table holds `{}`, not `{}`",
+                self.ty().ty,
+                T::TYPE
+            )));
+        }
+        Ok(TypedTable {
+            table: self.clone(),
+            _phantom: PhantomData,
+        })
+    }
+
     /// Grows the size of the `Table` by `delta`, initializating
     /// the elements with the provided `init` value.
     ///
@@ -111,7 +219,8 @@ impl Table {
     /// # Errors
     ///
     /// Returns an error if the range is out of bounds of either the source or
-    /// destination tables.
+    /// destination tables, or if the tables belong to different stores and
+    /// hold function references, which are tied to their store.
     pub fn copy(
         dst_table: &Self,
         dst_index: u32,
@@ -119,9 +228,11 @@ impl Table {
         src_index: u32,
         len: u32,
     ) -> Result<(), RuntimeError> {
-        if !Store::same(&dst_table.store, &src_table.store) {
+        let hold_extern_refs =
+            dst_table.ty().ty == ValType::ExternRef && src_table.ty().ty == ValType::ExternRef;
+        if !hold_extern_refs && !Store::same(&dst_table.store, &src_table.store) {
             return Err(RuntimeError::new(
-                "cross-`Store` table copies are not supported",
+                "cross-`Store` table copies are only supported for `externref` tables",
             ));
         }
         RuntimeTable::copy(
@@ -191,3 +302,129 @@ impl<'a> Exportable<'a> for Table {
             .map(|v| *v = v.downgrade());
     }
 }
+
+/// A type of the references held by a [`TypedTable`]: [`ExternRef`]
+/// for the tables of `externref`s, and `Option<Function>` for the
+/// tables of `funcref`s.
+pub trait TableReference: Sized {
+    /// The type of the elements of the tables holding this type.
+    const TYPE: ValType;
+
+    #[doc(hidden)]
+    fn into_table_element(self, store: &Store) -> Result<TableElement, RuntimeError>;
+
+    #[doc(hidden)]
+    fn from_table_element(element: TableElement, store: &Store) -> Self;
+}
+
+impl TableReference for ExternRef {
+    const TYPE: ValType = ValType::ExternRef;
+
+    fn into_table_element(self, _store: &Store) -> Result<TableElement, RuntimeError> {
+        // `ExternRef`s are not tied to specific stores.
+        Ok(TableElement::ExternRef(self))
+    }
+
+    fn from_table_element(element: TableElement, _store: &Store) -> Self {
+        match element {
+            TableElement::ExternRef(extern_ref) => extern_ref,
+            TableElement::FuncRef(_) => unreachable!("the table holds `externref`s"),
+        }
+    }
+}
+
+impl TableReference for Option<Function> {
+    const TYPE: ValType = ValType::FuncRef;
+
+    fn into_table_element(self, store: &Store) -> Result<TableElement, RuntimeError> {
+        Val::FuncRef(self).into_table_reference(store)
+    }
+
+    fn from_table_element(element: TableElement, store: &Store) -> Self {
+        match Val::from_table_reference(element, store) {
+            Val::FuncRef(function) => function,
+            _ => unreachable!("the table holds `funcref`s"),
+        }
+    }
+}
+
+/// A [`Table`] whose elements are accessed as `T`, either
+/// [`ExternRef`] or `Option<Function>`, rather than as [`Val`].
+///
+/// It is made with [`Table::typed`], which checks the type of the
+/// elements of the table once.
+pub struct TypedTable<T> {
+    table: Table,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: TableReference> TypedTable<T> {
+    /// Returns the untyped [`Table`].
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Retrieves the size of the table (in elements).
+    pub fn size(&self) -> u32 {
+        self.table.size()
+    }
+
+    /// Retrieves an element of the table at the provided `index`.
+    pub fn get(&self, index: u32) -> Option<T> {
+        let element = self.table.vm_table.from.get(index)?;
+        Some(T::from_table_element(element, &self.table.store))
+    }
+
+    /// Sets an element `item` in the table at the provided `index`.
+    pub fn set(&self, index: u32, item: T) -> Result<(), RuntimeError> {
+        let element = item.into_table_element(&self.table.store)?;
+        set_table_item(self.table.vm_table.from.as_ref(), index, element)
+    }
+
+    /// Grows the size of the table by `delta`, initializating the
+    /// elements with the provided `init` value.
+    ///
+    /// It returns the previous size of the table in case is able to
+    /// grow the table successfully.
+    pub fn grow(&self, delta: u32, init: T) -> Result<u32, RuntimeError> {
+        let element = init.into_table_element(&self.table.store)?;
+        self.table
+            .vm_table
+            .from
+            .grow(delta, element)
+            .ok_or_else(|| RuntimeError::new(format!("failed to grow table by `{}`", delta)))
+    }
+
+    /// Sets the `len` elements of the table starting at `index` to the
+    /// provided `item`.
+    pub fn fill(&self, index: u32, item: T, len: u32) -> Result<(), RuntimeError> {
+        let element = item.into_table_element(&self.table.store)?;
+        self.table
+            .vm_table
+            .from
+            .fill(index, element, len)
+            .map_err(RuntimeError::from_trap)
+    }
+
+    /// Sets the elements of the table starting at `index` to the items
+    /// produced by `items`, in order.
+    ///
+    /// No element is set if the items don't fit in the table from
+    /// `index`.
+    pub fn init(&self, index: u32, items: impl IntoIterator<Item = T>) -> Result<(), RuntimeError> {
+        let elements = items
+            .into_iter()
+            .map(|item| item.into_table_element(&self.table.store))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.table.init_items(index, elements)
+    }
+}
+
+impl<T> Clone for TypedTable<T> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
@@ -7,8 +7,9 @@ use loupe::MemoryUsage;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_engine::{Export, LinkedEntities, Resolver};
+use wasmer_types::ExportIndex;
+use wasmer_vm::{InstanceHandle, InstanceId, InstanceSnapshot, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -72,12 +73,23 @@ impl From<wasmer_engine::InstantiationError> for InstantiationError {
     }
 }
 
+impl From<LinkError> for InstantiationError {
+    fn from(other: LinkError) -> Self {
+        Self::Link(other)
+    }
+}
+
 impl From<HostEnvInitError> for InstantiationError {
     fn from(other: HostEnvInitError) -> Self {
         Self::HostEnvInitialization(other)
     }
 }
 
+/// An error while taking a snapshot of an instance.
+#[derive(Error, Debug)]
+#[error("Failed to take a snapshot of the instance: {0}")]
+pub struct SnapshotError(String);
+
 impl Instance {
     /// Creates a new `Instance` from a WebAssembly [`Module`] and a
     /// set of imports resolved by the [`Resolver`].
@@ -113,15 +125,82 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
+        let mut linked = module.link(resolver)?;
+        let handle = module.instantiate(resolver, &linked)?;
+        module.link_definitions(resolver, &handle, &mut linked)?;
+        Self::from_handle(module, handle, &linked)
+    }
+
+    /// Creates a new `Instance` of a WebAssembly [`Module`] from a
+    /// [`InstanceSnapshot`] taken with [`Instance::snapshot`] on
+    /// another instance of it, and a set of imports resolved by the
+    /// [`Resolver`].
+    ///
+    /// The new instance starts with the state of the snapshot: the
+    /// data is not initialized again and the start function is not
+    /// called. The memories are mapped copy-on-write from the
+    /// snapshot when possible, which makes this much faster than
+    /// running the initialization of a large module again.
+    ///
+    /// With the module linking proposal, the instances of the nested
+    /// modules are not part of the snapshot, and are created again.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Store, Module, Instance};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, r#"
+    ///   (module
+    ///     (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///     (func $start (global.set $counter (i32.const 42)))
+    ///     (start $start))
+    /// "#)?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// let snapshot = instance.snapshot()?;
+    /// let copy = Instance::from_snapshot(&module, &imports! {}, &snapshot)?;
+    /// assert_eq!(copy.exports.get_global("counter")?.get().i32(), Some(42));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// The function can return [`InstantiationError`]s, which are
+    /// link errors if the snapshot was not taken from an instance of
+    /// this module.
+    pub fn from_snapshot(
+        module: &Module,
+        resolver: &dyn Resolver,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<Self, InstantiationError> {
+        let mut linked = module.link(resolver)?;
+        let handle = module.instantiate_from_snapshot(resolver, &linked, snapshot)?;
+        module.link_definitions(resolver, &handle, &mut linked)?;
+        Self::from_handle(module, handle, &linked)
+    }
+
+    /// Wraps a new instance handle of `module`, and initializes the
+    /// host environments of its imports. The instances and modules it
+    /// exports are the ones it was `linked` to.
+    fn from_handle(
+        module: &Module,
+        handle: InstanceHandle,
+        linked: &LinkedEntities,
+    ) -> Result<Self, InstantiationError> {
         let store = module.store();
-        let handle = module.instantiate(resolver)?;
         let exports = module
-            .exports()
-            .map(|export| {
-                let name = export.name().to_string();
-                let export = handle.lookup(&name).expect("export");
-                let extern_ = Extern::from_vm_export(store, export.into());
-                (name, extern_)
+            .info()
+            .exports
+            .iter()
+            .map(|(name, index)| {
+                let export = match index {
+                    ExportIndex::Instance(index) => {
+                        Export::Instance(linked.instances[*index].clone())
+                    }
+                    ExportIndex::Module(index) => Export::Module(linked.modules[*index].clone()),
+                    _ => handle.lookup(name).expect("export").into(),
+                };
+                (name.clone(), Extern::from_vm_export(store, export))
             })
             .collect::<Exports>();
 
@@ -150,6 +229,27 @@ impl Instance {
         Ok(instance)
     }
 
+    /// Takes a snapshot of the state of this instance: the contents of
+    /// its memories, the values of its mutable globals, the elements of
+    /// its tables and the passive segments it has not dropped. The
+    /// state of the imported memories, tables and globals is not part
+    /// of the snapshot.
+    ///
+    /// New instances of the same module can then be made from the
+    /// snapshot with [`Instance::from_snapshot`].
+    ///
+    /// ## Errors
+    ///
+    /// Taking a snapshot fails if a table or a global of the instance
+    /// holds a function of another instance.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.handle
+            .lock()
+            .unwrap()
+            .snapshot()
+            .map_err(SnapshotError)
+    }
+
     /// Gets the [`Module`] associated with this instance.
     pub fn module(&self) -> &Module {
         &self.module
@@ -160,6 +260,16 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the identifier of this instance, which the
+    /// [`ResourceLimiter`] of its memories and tables receives as
+    /// their [`ResourceOwner`].
+    ///
+    /// [`ResourceLimiter`]: crate::ResourceLimiter
+    /// [`ResourceOwner`]: crate::ResourceOwner
+    pub fn id(&self) -> InstanceId {
+        self.handle.lock().unwrap().id()
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
@@ -259,6 +259,7 @@ mod exports;
 mod externals;
 mod import_object;
 mod instance;
+mod linker;
 mod module;
 mod native;
 mod ptr;
@@ -286,25 +287,28 @@ pub use crate::cell::WasmCell;
 pub use crate::env::{HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, TableReference,
+    TypedTable, WasmTypeList,
 };
 pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
-pub use crate::instance::{Instance, InstantiationError};
+pub use crate::instance::{Instance, InstantiationError, SnapshotError};
+pub use crate::linker::{Linker, LinkerError};
 pub use crate::module::Module;
 pub use crate::native::NativeFunc;
 pub use crate::ptr::{Array, Item, WasmPtr};
 pub use crate::store::{Store, StoreObject};
 pub use crate::tunables::BaseTunables;
 pub use crate::types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Val, ValType,
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, InstanceType, MemoryType,
+    ModuleType, Mutability, TableType, Val, ValType,
 };
 pub use crate::types::{Val as Value, ValType as Type};
 pub use crate::utils::is_wasm;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
+    add_local_function, wasmparser, AddedFunctionBody, CompilerConfig, FunctionBodyData,
+    FunctionMiddleware, ImportedFunctions, MiddlewareError, MiddlewareReaderState,
     ModuleMiddleware,
 };
 pub use wasmer_compiler::{
@@ -314,7 +318,6 @@ pub use wasmer_engine::{
     ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
     NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
 };
-#[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
     Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
@@ -322,13 +325,24 @@ pub use wasmer_types::{
 };
 
 // TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError};
+pub use wasmer_vm::{
+    raise_user_trap, InstanceId, InstanceSnapshot, MemoryError, PoolingAllocator, PoolingLimits,
+    ResourceLimiter, ResourceOwner, TrapCode, DEFAULT_MAX_WASM_STACK,
+};
+// The coroutines need the `ucontext` functions, which the runtime
+// only builds with on these targets.
+#[cfg(any(
+    all(target_os = "linux", target_env = "gnu"),
+    target_os = "macos",
+    target_os = "freebsd"
+))]
+pub use wasmer_vm::{suspend, Coroutine, CoroutineError, CoroutineState};
 pub mod vm {
     //! The vm module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
+        Memory, MemoryError, MemoryStyle, ModuleInfo, Table, TableStyle, VMExtern,
+        VMMemoryDefinition, VMTableDefinition,
     };
 }
 
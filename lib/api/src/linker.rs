@@ -0,0 +1,260 @@
+//! The linker module contains [`Linker`], which composes instances
+//! of modules by name.
+
+use crate::externals::{Extern, Function};
+use crate::instance::{Instance, InstantiationError};
+use crate::module::Module;
+use crate::store::{Store, StoreObject};
+use crate::{Exportable, ExternType, RuntimeError};
+use std::collections::HashMap;
+use std::fmt;
+use thiserror::Error;
+use wasmer_engine::{Export, NamedResolver};
+
+/// An error while defining an item in a [`Linker`].
+#[derive(Error, Debug)]
+pub enum LinkerError {
+    /// The item is already defined, and shadowing is not allowed.
+    #[error("`{module}`.`{name}` is already defined")]
+    AlreadyDefined {
+        /// The module name of the item.
+        module: String,
+        /// The name of the item.
+        name: String,
+    },
+
+    /// The item belongs to another store than the linker.
+    #[error("`{module}`.`{name}` belongs to another store than the linker")]
+    WrongStore {
+        /// The module name of the item.
+        module: String,
+        /// The name of the item.
+        name: String,
+    },
+
+    /// The module could not be instantiated.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+
+    /// The `_initialize` function of a reactor trapped.
+    #[error(transparent)]
+    Initialization(RuntimeError),
+}
+
+/// A linker composes instances of modules by name: the items defined
+/// in it, by module name and name, satisfy the imports of the modules
+/// it instantiates.
+///
+/// The items can be host functions, memories, tables and globals
+/// defined one by one, all the exports of an instance registered
+/// under a module name, or the exports of a module registered with
+/// [`Linker::module`].
+///
+/// Defining an item with the same module name and name as an item
+/// already defined is an error, unless shadowing is allowed with
+/// [`Linker::allow_shadowing`], in which case the new item replaces
+/// the previous one for the modules instantiated afterwards.
+///
+/// ```
+/// # use wasmer::{Function, Linker, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let mut linker = Linker::new(&store);
+/// linker.define("host", "double", Function::new_native(&store, |x: i32| x * 2))?;
+///
+/// let library = Module::new(&store, r#"
+///   (module
+///     (import "host" "double" (func $double (param i32) (result i32)))
+///     (func (export "quadruple") (param i32) (result i32)
+///       (call $double (call $double (local.get 0)))))
+/// "#)?;
+/// let library = linker.instantiate(&library)?;
+/// linker.instance("library", &library)?;
+///
+/// let application = Module::new(&store, r#"
+///   (module
+///     (import "library" "quadruple" (func $quadruple (param i32) (result i32)))
+///     (func (export "run") (result i32)
+///       (call $quadruple (i32.const 10))))
+/// "#)?;
+/// let application = linker.instantiate(&application)?;
+/// let run = application.exports.get_native_function::<(), i32>("run")?;
+/// assert_eq!(run.call()?, 40);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Linker {
+    store: Store,
+    definitions: HashMap<(String, String), Extern>,
+    allow_shadowing: bool,
+}
+
+impl Linker {
+    /// Creates a new `Linker`, defining no item, for the modules of
+    /// the given store.
+    pub fn new(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            definitions: HashMap::new(),
+            allow_shadowing: false,
+        }
+    }
+
+    /// Returns the [`Store`] of the modules the linker instantiates.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Sets whether the items can be defined again, replacing the
+    /// previous definitions. It's not allowed by default.
+    pub fn allow_shadowing(&mut self, allow: bool) -> &mut Self {
+        self.allow_shadowing = allow;
+        self
+    }
+
+    /// Defines an item, such as a host function, under the given
+    /// module name and name.
+    pub fn define(
+        &mut self,
+        module: &str,
+        name: &str,
+        item: impl Into<Extern>,
+    ) -> Result<&mut Self, LinkerError> {
+        let item = item.into();
+        self.check_definition(module, name, &item)?;
+        self.definitions
+            .insert((module.to_string(), name.to_string()), item);
+        Ok(self)
+    }
+
+    /// Defines all the exports of an instance under the given module
+    /// name.
+    ///
+    /// No export is defined if one of them can't be.
+    pub fn instance(
+        &mut self,
+        module: &str,
+        instance: &Instance,
+    ) -> Result<&mut Self, LinkerError> {
+        for (name, item) in instance.exports.iter() {
+            self.check_definition(module, name, item)?;
+        }
+        for (name, item) in instance.exports.iter() {
+            self.definitions
+                .insert((module.to_string(), name.clone()), item.clone());
+        }
+        Ok(self)
+    }
+
+    /// Defines the exports of a module under the given module name,
+    /// depending on the kind of the module:
+    ///
+    /// - a command, which exports a `_start` function, is
+    ///   instantiated anew on each call to one of its exported
+    ///   functions, which are the only exports defined. The instance
+    ///   is discarded after the call, like a process after running a
+    ///   command.
+    ///
+    /// - otherwise a reactor, which is instantiated once. Its
+    ///   `_initialize` function, if it exports one, is called, and
+    ///   then all its exports are defined like with
+    ///   [`Linker::instance`].
+    ///
+    /// The imports of the module are satisfied by the items defined
+    /// when this method is called.
+    pub fn module(&mut self, module_name: &str, module: &Module) -> Result<&mut Self, LinkerError> {
+        let is_command = module.exports().any(|export| {
+            export.name() == "_start" && matches!(export.ty(), ExternType::Function(_))
+        });
+        if !is_command {
+            let instance = self.instantiate(module)?;
+            if let Ok(initialize) = instance.exports.get_function("_initialize") {
+                initialize.call(&[]).map_err(LinkerError::Initialization)?;
+            }
+            return self.instance(module_name, &instance);
+        }
+
+        let mut functions = Vec::new();
+        for export in module.exports() {
+            let function_type = match export.ty() {
+                ExternType::Function(function_type) => function_type.clone(),
+                _ => continue,
+            };
+            let linker = self.clone();
+            let command = module.clone();
+            let name = export.name().to_string();
+            let function = Function::new(&self.store, function_type, move |params| {
+                let instance = linker
+                    .instantiate(&command)
+                    .map_err(|e| RuntimeError::new(e.to_string()))?;
+                let results = instance.exports.get_function(&name).unwrap().call(params)?;
+                Ok(results.into_vec())
+            });
+            functions.push((export.name().to_string(), function));
+        }
+
+        for (name, function) in &functions {
+            self.check_definition(module_name, name, &function.clone().into())?;
+        }
+        for (name, function) in functions {
+            self.definitions
+                .insert((module_name.to_string(), name), function.into());
+        }
+        Ok(self)
+    }
+
+    /// Instantiates a module, whose imports are satisfied by the items
+    /// defined in the linker.
+    pub fn instantiate(&self, module: &Module) -> Result<Instance, InstantiationError> {
+        Instance::new(module, self)
+    }
+
+    /// Returns the item defined under the given module name and name,
+    /// if any.
+    pub fn get(&self, module: &str, name: &str) -> Option<&Extern> {
+        self.definitions
+            .get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Returns an iterator over the items defined in the linker, with
+    /// their module name and name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &Extern)> {
+        self.definitions
+            .iter()
+            .map(|((module, name), item)| (module.as_str(), name.as_str(), item))
+    }
+
+    /// Checks that an item can be defined under the given module name
+    /// and name.
+    fn check_definition(&self, module: &str, name: &str, item: &Extern) -> Result<(), LinkerError> {
+        if !item.comes_from_same_store(&self.store) {
+            return Err(LinkerError::WrongStore {
+                module: module.to_string(),
+                name: name.to_string(),
+            });
+        }
+        if !self.allow_shadowing && self.get(module, name).is_some() {
+            return Err(LinkerError::AlreadyDefined {
+                module: module.to_string(),
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl NamedResolver for Linker {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        self.get(module, field).map(|item| item.to_export())
+    }
+}
+
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("definitions", &self.definitions)
+            .field("allow_shadowing", &self.allow_shadowing)
+            .finish()
+    }
+}
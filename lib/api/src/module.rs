@@ -1,6 +1,8 @@
+use crate::exports::Exportable;
+use crate::instance::Instance;
 use crate::store::Store;
 use crate::types::{ExportType, ImportType};
-use crate::InstantiationError;
+use crate::{ExportError, Extern, InstantiationError};
 use loupe::MemoryUsage;
 use std::fmt;
 use std::io;
@@ -10,8 +12,12 @@ use thiserror::Error;
 use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, ModuleInfo};
+use wasmer_engine::{
+    resolve_instances, Artifact, Definitions, DeserializeError, Export, ExportInstance,
+    ExportModule, LinkedEntities, Resolver, SerializeError,
+};
+use wasmer_types::ExportIndex;
+use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, InstanceSnapshot, ModuleInfo};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -227,6 +233,43 @@ impl Module {
         Ok(Self::from_artifact(store, artifact))
     }
 
+    /// Deserializes a serialized Module binary into a `Module`, validating
+    /// the serialized data first.
+    /// > Note: the module has to be serialized before with the `serialize` method.
+    ///
+    /// Unlike [`Module::deserialize`], the provided bytes are checked before
+    /// being used: corrupted or truncated data, and relocations or function
+    /// pointers that fall outside of the compiled code, result in a
+    /// [`DeserializeError`] instead of undefined behavior. This makes it
+    /// suitable for loading artifacts from a shared cache.
+    ///
+    /// Engines that can't validate their artifacts (such as the `Dylib`
+    /// engine) return [`DeserializeError::Incompatible`].
+    ///
+    /// # Safety
+    ///
+    /// The validation covers the structure of the artifact, not the
+    /// behavior of the machine code it contains, which is still executed
+    /// as-is.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = unsafe { Module::deserialize_checked(&store, serialized_data)? };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize_checked(
+        store: &Store,
+        bytes: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        let artifact = store.engine().deserialize_checked(bytes)?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
     /// Deserializes a a serialized Module located in a `Path` into a `Module`.
     /// > Note: the module has to be serialized before with the `serialize` method.
     ///
@@ -252,23 +295,90 @@ impl Module {
         Ok(Self::from_artifact(store, artifact))
     }
 
-    fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
+    pub(crate) fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
         Self {
             store: store.clone(),
             artifact,
         }
     }
 
+    /// Resolves the instances and modules of the module, with the
+    /// module linking proposal. The instances of the nested modules
+    /// are created with [`Instance::new`].
+    ///
+    /// The resolution stops at the first instance which takes the
+    /// functions, tables, memories or globals of the module, and is
+    /// finished by [`Module::link_definitions`].
+    pub(crate) fn link(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<LinkedEntities, InstantiationError> {
+        let mut linked = LinkedEntities::default();
+        self.resolve_instances(resolver, &mut linked, None)?;
+        Ok(linked)
+    }
+
+    /// Finishes the resolution of [`Module::link`] once `handle` is
+    /// instantiated: the instances which take the functions, tables,
+    /// memories or globals of the module receive the ones of `handle`.
+    pub(crate) fn link_definitions(
+        &self,
+        resolver: &dyn Resolver,
+        handle: &InstanceHandle,
+        linked: &mut LinkedEntities,
+    ) -> Result<(), InstantiationError> {
+        let definitions = |index: &ExportIndex| {
+            Export::from(
+                handle
+                    .lookup_by_declaration(index)
+                    .expect("a function, table, memory or global"),
+            )
+        };
+        self.resolve_instances(resolver, linked, Some(&definitions))
+    }
+
+    fn resolve_instances(
+        &self,
+        resolver: &dyn Resolver,
+        linked: &mut LinkedEntities,
+        definitions: Option<Definitions>,
+    ) -> Result<(), InstantiationError> {
+        if self.info().module_linking.is_empty() {
+            return Ok(());
+        }
+        resolve_instances(
+            self.info(),
+            resolver,
+            self.artifact.nested_artifacts(),
+            linked,
+            definitions,
+            &mut |artifact, resolver| {
+                let module = Self::from_artifact(&self.store, artifact);
+                let instance = Instance::new(&module, resolver)?;
+                let exports = instance
+                    .exports
+                    .iter()
+                    .map(|(name, export)| (name.clone(), export.to_export()))
+                    .collect();
+                Ok(ExportInstance::new(exports))
+            },
+        )
+    }
+
     pub(crate) fn instantiate(
         &self,
         resolver: &dyn Resolver,
+        linked: &LinkedEntities,
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
+            // The instance keeps the instances it is linked to alive.
             let instance_handle = self.artifact.instantiate(
                 self.store.tunables(),
                 resolver,
-                Box::new((self.store.clone(), self.artifact.clone())),
+                linked,
+                Box::new((self.store.clone(), self.artifact.clone(), linked.clone())),
             )?;
+            instance_handle.set_stack_limit(self.store.stack_limit());
 
             // After the instance handle is created, we need to initialize
             // the data, call the start function and so. However, if any
@@ -282,6 +392,28 @@ impl Module {
         }
     }
 
+    /// Instantiates the module from a snapshot of another instance of
+    /// it, instead of initializing the data and calling the start
+    /// function.
+    pub(crate) fn instantiate_from_snapshot(
+        &self,
+        resolver: &dyn Resolver,
+        linked: &LinkedEntities,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        unsafe {
+            let instance_handle = self.artifact.instantiate_from_snapshot(
+                self.store.tunables(),
+                resolver,
+                linked,
+                Box::new((self.store.clone(), self.artifact.clone(), linked.clone())),
+                snapshot,
+            )?;
+            instance_handle.set_stack_limit(self.store.stack_limit());
+            Ok(instance_handle)
+        }
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
@@ -426,6 +558,23 @@ impl Module {
     }
 }
 
+impl<'a> Exportable<'a> for Module {
+    fn to_export(&self) -> Export {
+        Export::Module(ExportModule {
+            artifact: self.artifact.clone(),
+        })
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Module(module) => Ok(module),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {}
+}
+
 impl fmt::Debug for Module {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Module")
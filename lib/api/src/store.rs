@@ -6,7 +6,7 @@ use std::sync::{Arc, RwLock};
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
 use wasmer_engine::{is_wasm_pc, Engine, Tunables};
-use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn};
+use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn, VMStackLimit};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -24,6 +24,8 @@ pub struct Store {
     tunables: Arc<dyn Tunables + Send + Sync>,
     #[loupe(skip)]
     trap_handler: Arc<RwLock<Option<Box<TrapHandlerFn>>>>,
+    #[loupe(skip)]
+    stack_limit: Arc<VMStackLimit>,
 }
 
 impl Store {
@@ -54,9 +56,43 @@ impl Store {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
             trap_handler: Arc::new(RwLock::new(None)),
+            stack_limit: Arc::new(VMStackLimit::default()),
         }
     }
 
+    /// Returns the maximum size, in bytes, of the native stack used by
+    /// the WebAssembly code of this store.
+    ///
+    /// It defaults to [`DEFAULT_MAX_WASM_STACK`](crate::DEFAULT_MAX_WASM_STACK),
+    /// which doesn't limit the stack: only the guard page of the stack
+    /// of the thread catches the overflows.
+    pub fn max_wasm_stack(&self) -> usize {
+        self.stack_limit.max_wasm_stack()
+    }
+
+    /// Sets the maximum size, in bytes, of the native stack that the
+    /// WebAssembly code of this store may use below the host frame
+    /// calling into it. Exceeding it traps with
+    /// `TrapCode::StackOverflow`, whichever thread calls into the
+    /// WebAssembly code.
+    ///
+    /// The stack of the calling thread must still be large enough: if
+    /// it is exhausted first, the overflow is caught by its guard page.
+    /// `usize::MAX` removes the limit.
+    ///
+    /// The size applies to the following calls into WebAssembly, but
+    /// not to the calls made by host functions while WebAssembly code
+    /// is running, which share the limit of the running code.
+    pub fn set_max_wasm_stack(&self, max_wasm_stack: usize) {
+        self.stack_limit.set_max_wasm_stack(max_wasm_stack);
+    }
+
+    /// Returns the limit on the stack shared by the instances of this
+    /// store.
+    pub(crate) fn stack_limit(&self) -> &VMStackLimit {
+        &self.stack_limit
+    }
+
     /// Returns the [`Tunables`].
     pub fn tunables(&self) -> &dyn Tunables {
         self.tunables.as_ref()
@@ -94,6 +130,10 @@ unsafe impl TrapHandler for Store {
             false
         }
     }
+
+    fn stack_limit(&self) -> Option<&VMStackLimit> {
+        Some(&self.stack_limit)
+    }
 }
 
 // This is required to be able to set the trap_handler in the
@@ -5,12 +5,12 @@ use std::ptr::NonNull;
 use std::sync::Arc;
 use target_lexicon::{OperatingSystem, PointerWidth};
 use wasmer_compiler::Target;
-use wasmer_engine::Tunables;
-use wasmer_vm::MemoryError;
+use wasmer_engine::{LinkError, Tunables};
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
-    VMTableDefinition,
+    InstanceAllocator, LinearMemory, LinearTable, Memory, MemoryStyle, ModuleInfo, Table,
+    TableStyle, VMMemoryDefinition, VMTableDefinition,
 };
+use wasmer_vm::{MemoryError, MemoryImage, PoolingAllocator, ResourceLimiter};
 
 /// Tunable parameters for WebAssembly compilation.
 /// This is the reference implementation of the `Tunables` trait,
@@ -30,6 +30,15 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// The policy consulted before growing the memories and tables, if any.
+    #[loupe(skip)]
+    pub resource_limiter: Option<Arc<dyn ResourceLimiter>>,
+
+    /// The pool the instances, with their memories and tables, are
+    /// allocated in, if any.
+    #[loupe(skip)]
+    pub pooling_allocator: Option<Arc<PoolingAllocator>>,
 }
 
 impl BaseTunables {
@@ -65,6 +74,49 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            resource_limiter: None,
+            pooling_allocator: None,
+        }
+    }
+
+    /// Consults the given [`ResourceLimiter`] before growing any of the
+    /// memories and tables created with these tunables.
+    ///
+    /// Since each [`Store`] has its own tunables, use a store per tenant
+    /// to enforce a budget per tenant.
+    ///
+    /// [`Store`]: crate::Store
+    pub fn with_resource_limiter(mut self, resource_limiter: Arc<dyn ResourceLimiter>) -> Self {
+        self.resource_limiter = Some(resource_limiter);
+        self
+    }
+
+    /// Allocates the instances, with their memories and tables, in the
+    /// given pool.
+    ///
+    /// Instantiating then takes free slots of the pool instead of
+    /// allocating, and fails once all the slots of a kind are in use.
+    /// The memories are compiled with the static style of the pool,
+    /// whatever their maximum.
+    ///
+    /// The memories and tables created from the host are not
+    /// allocated in the pool.
+    pub fn with_pooling_allocator(mut self, pooling_allocator: Arc<PoolingAllocator>) -> Self {
+        self.pooling_allocator = Some(pooling_allocator);
+        self
+    }
+
+    fn limit_memory(&self, memory: LinearMemory) -> LinearMemory {
+        match &self.resource_limiter {
+            Some(resource_limiter) => memory.with_resource_limiter(resource_limiter.clone()),
+            None => memory,
+        }
+    }
+
+    fn limit_table(&self, table: LinearTable) -> LinearTable {
+        match &self.resource_limiter {
+            Some(resource_limiter) => table.with_resource_limiter(resource_limiter.clone()),
+            None => table,
         }
     }
 }
@@ -72,6 +124,10 @@ impl BaseTunables {
 impl Tunables for BaseTunables {
     /// Get a `MemoryStyle` for the provided `MemoryType`
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        if let Some(pooling_allocator) = &self.pooling_allocator {
+            return pooling_allocator.memory_style();
+        }
+
         // A heap with a maximum that doesn't exceed the static memory bound specified by the
         // tunables make it static.
         //
@@ -101,7 +157,7 @@ impl Tunables for BaseTunables {
         ty: &MemoryType,
         style: &MemoryStyle,
     ) -> Result<Arc<dyn Memory>, MemoryError> {
-        Ok(Arc::new(LinearMemory::new(&ty, &style)?))
+        Ok(Arc::new(self.limit_memory(LinearMemory::new(&ty, &style)?)))
     }
 
     /// Create a memory owned by the VM given a [`MemoryType`] and a [`MemoryStyle`].
@@ -115,11 +171,48 @@ impl Tunables for BaseTunables {
         style: &MemoryStyle,
         vm_definition_location: NonNull<VMMemoryDefinition>,
     ) -> Result<Arc<dyn Memory>, MemoryError> {
-        Ok(Arc::new(LinearMemory::from_definition(
+        if let Some(pooling_allocator) = &self.pooling_allocator {
+            let memory = pooling_allocator.create_memory(&ty, &style, vm_definition_location)?;
+            return Ok(match &self.resource_limiter {
+                Some(resource_limiter) => {
+                    Arc::new(memory.with_resource_limiter(resource_limiter.clone()))
+                }
+                None => Arc::new(memory),
+            });
+        }
+
+        Ok(Arc::new(self.limit_memory(LinearMemory::from_definition(
             &ty,
             &style,
             vm_definition_location,
-        )?))
+        )?)))
+    }
+
+    /// Create a memory owned by the VM given a [`MemoryType`] and a [`MemoryStyle`],
+    /// with the given [`MemoryImage`] mapped copy-on-write at its start.
+    ///
+    /// The image is copied instead into the memories allocated in the
+    /// pool, whose slots are reused.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMMemoryDefinition`,
+    ///   for example in `VMContext`.
+    unsafe fn create_vm_memory_with_image(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+        image: &MemoryImage,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        if self.pooling_allocator.is_some() {
+            let memory = self.create_vm_memory(ty, style, vm_definition_location)?;
+            image.copy_into(&*memory)?;
+            return Ok(memory);
+        }
+
+        Ok(Arc::new(self.limit_memory(
+            LinearMemory::from_definition_with_image(&ty, &style, vm_definition_location, image)?,
+        )))
     }
 
     /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
@@ -128,7 +221,7 @@ impl Tunables for BaseTunables {
         ty: &TableType,
         style: &TableStyle,
     ) -> Result<Arc<dyn Table>, String> {
-        Ok(Arc::new(LinearTable::new(&ty, &style)?))
+        Ok(Arc::new(self.limit_table(LinearTable::new(&ty, &style)?)))
     }
 
     /// Create a table owned by the VM given a [`TableType`] and a [`TableStyle`].
@@ -142,11 +235,41 @@ impl Tunables for BaseTunables {
         style: &TableStyle,
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String> {
-        Ok(Arc::new(LinearTable::from_definition(
+        if let Some(pooling_allocator) = &self.pooling_allocator {
+            let table = pooling_allocator.create_table(&ty, &style, vm_definition_location)?;
+            return Ok(match &self.resource_limiter {
+                Some(resource_limiter) => {
+                    Arc::new(table.with_resource_limiter(resource_limiter.clone()))
+                }
+                None => Arc::new(table),
+            });
+        }
+
+        Ok(Arc::new(self.limit_table(LinearTable::from_definition(
             &ty,
             &style,
             vm_definition_location,
-        )?))
+        )?)))
+    }
+
+    /// Allocate the data of an instance of the given module, in the
+    /// pool if any.
+    fn create_instance_allocator(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        match &self.pooling_allocator {
+            Some(pooling_allocator) => InstanceAllocator::new_pooled(module, pooling_allocator)
+                .map_err(LinkError::Resource),
+            None => Ok(InstanceAllocator::new(module)),
+        }
     }
 }
 
@@ -160,6 +283,8 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            resource_limiter: None,
+            pooling_allocator: None,
         };
 
         // No maximum
@@ -192,4 +317,38 @@ mod tests {
             s => panic!("Unexpected memory style: {:?}", s),
         }
     }
+
+    #[test]
+    fn pooled_memory_style() {
+        let pooling_allocator = PoolingAllocator::new(wasmer_vm::PoolingLimits {
+            instances: 1,
+            memories: 1,
+            memory_pages: Pages(16),
+            memory_offset_guard_size: 0x1_0000,
+            tables: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        let tunables = BaseTunables::for_target(&Target::default())
+            .with_pooling_allocator(Arc::new(pooling_allocator));
+
+        // Whatever the maximum, the memories have the static style of
+        // the pool.
+        for requested in &[
+            MemoryType::new(3, None, true),
+            MemoryType::new(3, Some(5_000_000), true),
+            MemoryType::new(3, Some(8), true),
+        ] {
+            match tunables.memory_style(requested) {
+                MemoryStyle::Static {
+                    bound,
+                    offset_guard_size,
+                } => {
+                    assert_eq!(bound, Pages(16));
+                    assert_eq!(offset_guard_size, 0x1_0000);
+                }
+                s => panic!("Unexpected memory style: {:?}", s),
+            }
+        }
+    }
 }
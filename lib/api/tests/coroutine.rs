@@ -0,0 +1,73 @@
+#![cfg(any(
+    all(target_os = "linux", target_env = "gnu"),
+    target_os = "macos",
+    target_os = "freebsd"
+))]
+
+use anyhow::Result;
+use std::cell::Cell;
+use wasmer::*;
+
+thread_local! {
+    static YIELDED: Cell<i32> = Cell::new(0);
+}
+
+#[test]
+fn guest_generator() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "host" "yield" (func $yield (param i32)))
+            (func (export "count") (param $n i32)
+                (local $i i32)
+                (block $done
+                    (loop $next
+                        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                        (call $yield (local.get $i))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br $next)))))"#,
+    )?;
+    let import_object = imports! {
+        "host" => {
+            "yield" => Function::new_native(&store, |value: i32| {
+                YIELDED.with(|yielded| yielded.set(value));
+                suspend().unwrap();
+            }),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let count = instance.exports.get_native_function::<i32, ()>("count")?;
+
+    let mut coroutine = Coroutine::new(256 * 1024, || count.call(3).unwrap())?;
+    for expected in 0..3 {
+        assert_eq!(coroutine.resume(), CoroutineState::Suspended);
+        assert_eq!(YIELDED.with(|yielded| yielded.get()), expected);
+    }
+    assert_eq!(coroutine.resume(), CoroutineState::Finished);
+
+    Ok(())
+}
+
+#[test]
+fn stack_overflow_in_a_coroutine() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (func $recurse (export "recurse")
+                (call $recurse)))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let recurse = instance.exports.get_native_function::<(), ()>("recurse")?;
+
+    let mut result = None;
+    let mut coroutine = Coroutine::new(64 * 1024, || result = Some(recurse.call()))?;
+    assert_eq!(coroutine.resume(), CoroutineState::Finished);
+    drop(coroutine);
+
+    let error = result.unwrap().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::StackOverflow));
+
+    Ok(())
+}
@@ -134,6 +134,82 @@ fn table_copy() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn table_fill_and_init() -> Result<()> {
+    let store = Store::default();
+    let table_type = TableType {
+        ty: Type::FuncRef,
+        minimum: 4,
+        maximum: None,
+    };
+    let f = Function::new_native(&store, |num: i32| num + 1);
+    let table = Table::new(&store, table_type, Value::FuncRef(None))?;
+
+    table.fill(1, Value::FuncRef(Some(f.clone())), 2)?;
+    assert!(table.fill(3, Value::FuncRef(None), 2).is_err());
+    assert!(table.fill(0, Value::I32(1), 1).is_err());
+    let is_null = |index| table.get(index).unwrap().unwrap_funcref().is_none();
+    assert_eq!(
+        (0..4).map(is_null).collect::<Vec<_>>(),
+        vec![true, false, false, true]
+    );
+
+    // No element is set when the values don't fit.
+    assert!(table
+        .init(
+            2,
+            vec![
+                Value::FuncRef(None),
+                Value::FuncRef(None),
+                Value::FuncRef(None)
+            ]
+        )
+        .is_err());
+    assert!(!is_null(2));
+    table.init(2, vec![Value::FuncRef(None), Value::FuncRef(Some(f))])?;
+    assert!(is_null(2));
+    assert!(!is_null(3));
+
+    Ok(())
+}
+
+#[test]
+fn typed_table() -> Result<()> {
+    let store = Store::default();
+    let table_type = TableType {
+        ty: Type::ExternRef,
+        minimum: 2,
+        maximum: None,
+    };
+    let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::null()))?;
+    assert!(table.typed::<Option<Function>>().is_err());
+
+    let handles = table.typed::<ExternRef>()?;
+    handles.init(0, vec![ExternRef::new(1u32), ExternRef::new(2u32)])?;
+    assert_eq!(handles.grow(1, ExternRef::new(3u32))?, 2);
+    let values = (0..handles.size())
+        .map(|index| *handles.get(index).unwrap().downcast::<u32>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![1, 2, 3]);
+    assert!(handles.get(0).unwrap().downcast::<String>().is_none());
+
+    handles.fill(1, ExternRef::null(), 2)?;
+    assert!(handles.get(2).unwrap().is_null());
+
+    // The tables of `externref`s can be copied across stores.
+    let other_store = Store::default();
+    let other_table = Table::new(
+        &other_store,
+        table_type,
+        Value::ExternRef(ExternRef::null()),
+    )?;
+    Table::copy(&other_table, 0, &table, 0, 1)?;
+    let other_handles = other_table.typed::<ExternRef>()?;
+    assert_eq!(other_handles.get(0).unwrap().downcast::<u32>(), Some(&1));
+
+    Ok(())
+}
+
 #[test]
 fn memory_new() -> Result<()> {
     let store = Store::default();
@@ -177,6 +253,84 @@ fn memory_grow() -> Result<()> {
     Ok(())
 }
 
+/// Allows the memories to grow up to 12 pages, and the tables up to 4
+/// elements, recording the owners of the memories it's consulted for.
+#[derive(Debug, Default)]
+struct Budget {
+    memory_owners: std::sync::Mutex<Vec<ResourceOwner>>,
+}
+
+impl ResourceLimiter for Budget {
+    fn memory_growing(
+        &self,
+        owner: ResourceOwner,
+        _current: Pages,
+        desired: Pages,
+        _maximum: Option<Pages>,
+    ) -> bool {
+        self.memory_owners.lock().unwrap().push(owner);
+        desired <= Pages(12)
+    }
+
+    fn table_growing(
+        &self,
+        _owner: ResourceOwner,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> bool {
+        desired <= 4
+    }
+}
+
+#[test]
+fn resource_limiter() -> Result<()> {
+    let budget = std::sync::Arc::new(Budget::default());
+    let engine = Store::default().engine().clone();
+    let tunables = BaseTunables::for_target(engine.target()).with_resource_limiter(budget.clone());
+    let store = Store::new_with_tunables(&*engine, tunables);
+
+    let memory = Memory::new(&store, MemoryType::new(Pages(10), None, false))?;
+    assert_eq!(memory.grow(Pages(2))?, Pages(10));
+    assert!(memory.grow(Pages(1)).is_err());
+    assert_eq!(memory.size(), Pages(12));
+
+    // The limiter isn't consulted for the growths beyond the maximum.
+    let bounded = Memory::new(&store, MemoryType::new(Pages(1), Some(Pages(2)), false))?;
+    assert!(bounded.grow(Pages(2)).is_err());
+    assert_eq!(
+        *budget.memory_owners.lock().unwrap(),
+        vec![ResourceOwner::Host, ResourceOwner::Host]
+    );
+
+    let table = Table::new(
+        &store,
+        TableType::new(Type::FuncRef, 2, None),
+        Value::FuncRef(None),
+    )?;
+    assert_eq!(table.grow(2, Value::FuncRef(None))?, 2);
+    assert!(table.grow(1, Value::FuncRef(None)).is_err());
+
+    // Denied growths don't trap, they return -1 to WebAssembly.
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory 10)
+            (func (export "grow") (param i32) (result i32)
+                (memory.grow (local.get 0))))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(2)?, 10);
+    assert_eq!(grow.call(1)?, -1);
+
+    // The memories of an instance are owned by the instance.
+    let owners = budget.memory_owners.lock().unwrap();
+    assert_eq!(owners[2..], [ResourceOwner::Instance(instance.id()); 2]);
+
+    Ok(())
+}
+
 #[test]
 fn function_new() -> Result<()> {
     let store = Store::default();
@@ -37,3 +37,159 @@ fn exports_work_after_multiple_instances_have_been_freed() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn pooling_allocator() -> Result<()> {
+    let engine = Store::default().engine().clone();
+    let pooling_allocator = PoolingAllocator::new(PoolingLimits {
+        instances: 2,
+        memories: 2,
+        memory_pages: Pages(4),
+        tables: 2,
+        table_elements: 16,
+        ..Default::default()
+    })
+    .map_err(anyhow::Error::msg)?;
+    let pooling_allocator = std::sync::Arc::new(pooling_allocator);
+    let tunables =
+        BaseTunables::for_target(engine.target()).with_pooling_allocator(pooling_allocator.clone());
+    let store = Store::new_with_tunables(&*engine, tunables);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory (export "memory") 1)
+            (table 2 funcref)
+            (func (export "grow") (param i32) (result i32)
+                (memory.grow (local.get 0))))"#,
+    )?;
+
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    assert_eq!(pooling_allocator.available_instances(), 0);
+    assert!(Instance::new(&module, &imports! {}).is_err());
+
+    // The memories grow in place up to the size of their slots.
+    let grow = first.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(3)?, 1);
+    assert_eq!(grow.call(1)?, -1);
+
+    let memory = first.exports.get_memory("memory")?;
+    unsafe { memory.data_unchecked_mut()[42] = 1 };
+    drop((grow, memory, first));
+    assert_eq!(pooling_allocator.available_instances(), 1);
+
+    // The slots are zeroed before being reused.
+    let third = Instance::new(&module, &imports! {})?;
+    let memory = third.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(1));
+    assert_eq!(unsafe { memory.data_unchecked()[42] }, 0);
+    drop(second);
+
+    Ok(())
+}
+
+#[test]
+fn data_segments_are_private_to_each_instance() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory (export "memory") 1)
+            (global $offset i32 (i32.const 8))
+            (data (i32.const 0) "hello")
+            (data (i32.const 3) "p!")
+            (data (global.get $offset) "world"))"#,
+    )?;
+
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    let first_memory = first.exports.get_memory("memory")?;
+    let second_memory = second.exports.get_memory("memory")?;
+    assert_eq!(
+        unsafe { &first_memory.data_unchecked()[..13] },
+        b"help!\0\0\0world"
+    );
+
+    // The writes to a memory are not seen by the other instances.
+    unsafe { first_memory.data_unchecked_mut()[0] = b'y' };
+    assert_eq!(unsafe { &first_memory.data_unchecked()[..5] }, b"yelp!");
+    assert_eq!(unsafe { &second_memory.data_unchecked()[..5] }, b"help!");
+
+    let third = Instance::new(&module, &imports! {})?;
+    let third_memory = third.exports.get_memory("memory")?;
+    assert_eq!(
+        unsafe { &third_memory.data_unchecked()[..13] },
+        b"help!\0\0\0world"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn instances_from_snapshot() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory (export "memory") 1)
+            (table (export "table") 1 funcref)
+            (global $counter (export "counter") (mut i32) (i32.const 0))
+            (data $passive "passive")
+            (type $get_t (func (result i32)))
+            (func $get (type $get_t) (global.get $counter))
+            (func $start
+                ;; Simulate an expensive initialization.
+                (drop (memory.grow (i32.const 1)))
+                (memory.init $passive (i32.const 65536) (i32.const 0) (i32.const 7))
+                (data.drop $passive)
+                (drop (table.grow (ref.func $get) (i32.const 1)))
+                (global.set $counter (i32.const 42)))
+            (func (export "increment") (result i32)
+                (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+                (call_indirect (type $get_t) (i32.const 1)))
+            (func (export "init_passive") (result i32)
+                (memory.init $passive (i32.const 0) (i32.const 0) (i32.const 1))
+                (i32.const 0))
+            (elem declare func $get)
+            (start $start))"#,
+    )?;
+
+    let instance = Instance::new(&module, &imports! {})?;
+    let snapshot = instance.snapshot()?;
+
+    let copy = Instance::from_snapshot(&module, &imports! {}, &snapshot)?;
+    let memory = copy.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(2));
+    assert_eq!(
+        unsafe { &memory.data_unchecked()[65536..65543] },
+        b"passive"
+    );
+    assert_eq!(copy.exports.get_table("table")?.size(), 2);
+    assert_eq!(copy.exports.get_global("counter")?.get().i32(), Some(42));
+
+    // The functions in the tables are the ones of the new instance.
+    let increment = copy.exports.get_native_function::<(), i32>("increment")?;
+    assert_eq!(increment.call()?, 43);
+    assert_eq!(
+        instance.exports.get_global("counter")?.get().i32(),
+        Some(42)
+    );
+
+    // The dropped segments stay dropped.
+    let init_passive = copy
+        .exports
+        .get_native_function::<(), i32>("init_passive")?;
+    assert!(init_passive.call().is_err());
+
+    // The instances made from the snapshot don't share their memories.
+    unsafe { memory.data_unchecked_mut()[65536] = b'P' };
+    let other_copy = Instance::from_snapshot(&module, &imports! {}, &snapshot)?;
+    let other_memory = other_copy.exports.get_memory("memory")?;
+    assert_eq!(unsafe { other_memory.data_unchecked()[65536] }, b'p');
+
+    // The snapshot can only make instances of the same module.
+    let other_module = Module::new(&store, "(module)")?;
+    assert!(Instance::from_snapshot(&other_module, &imports! {}, &snapshot).is_err());
+
+    Ok(())
+}
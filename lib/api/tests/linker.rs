@@ -0,0 +1,137 @@
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn instances_satisfy_later_imports() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.define("host", "offset", Global::new(&store, Value::I32(1)))?;
+
+    let library = Module::new(
+        &store,
+        r#"(module
+            (import "host" "offset" (global $offset i32))
+            (memory (export "memory") 1)
+            (func (export "increment") (param i32) (result i32)
+                (i32.add (local.get 0) (global.get $offset))))"#,
+    )?;
+    let library = linker.instantiate(&library)?;
+    linker.instance("library", &library)?;
+    assert!(linker.get("library", "memory").is_some());
+    assert_eq!(linker.iter().count(), 3);
+
+    let application = Module::new(
+        &store,
+        r#"(module
+            (import "library" "increment" (func $increment (param i32) (result i32)))
+            (import "library" "memory" (memory 1))
+            (func (export "run") (result i32)
+                (i32.store (i32.const 0) (call $increment (i32.const 41)))
+                (i32.load (i32.const 0))))"#,
+    )?;
+    let application = linker.instantiate(&application)?;
+    let run = application.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 42);
+    assert_eq!(
+        unsafe { library.exports.get_memory("memory")?.data_unchecked()[0] },
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shadowing() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.define("host", "answer", Function::new_native(&store, || 41))?;
+
+    let redefined = linker.define("host", "answer", Function::new_native(&store, || 42));
+    assert!(matches!(redefined, Err(LinkerError::AlreadyDefined { .. })));
+
+    linker
+        .allow_shadowing(true)
+        .define("host", "answer", Function::new_native(&store, || 42))?;
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "host" "answer" (func $answer (result i32)))
+            (export "answer" (func $answer)))"#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let answer = instance.exports.get_native_function::<(), i32>("answer")?;
+    assert_eq!(answer.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn items_of_another_store() -> Result<()> {
+    let store = Store::default();
+    let other_store = Store::default();
+    let mut linker = Linker::new(&store);
+
+    let defined = linker.define("host", "answer", Function::new_native(&other_store, || 42));
+    assert!(matches!(defined, Err(LinkerError::WrongStore { .. })));
+    assert!(linker.get("host", "answer").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn commands_are_instantiated_per_call() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    let command = Module::new(
+        &store,
+        r#"(module
+            (global $counter (mut i32) (i32.const 0))
+            (func (export "_start"))
+            (func (export "count") (result i32)
+                (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+                (global.get $counter)))"#,
+    )?;
+    linker.module("command", &command)?;
+
+    let count = linker.get("command", "count").unwrap().clone();
+    let count = match count {
+        Extern::Function(count) => count.native::<(), i32>()?,
+        _ => panic!("`count` is not a function"),
+    };
+    assert_eq!(count.call()?, 1);
+    assert_eq!(count.call()?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn reactors_are_initialized_once() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    let reactor = Module::new(
+        &store,
+        r#"(module
+            (global $counter (mut i32) (i32.const 0))
+            (func (export "_initialize")
+                (global.set $counter (i32.const 10)))
+            (func (export "count") (result i32)
+                (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+                (global.get $counter)))"#,
+    )?;
+    linker.module("reactor", &reactor)?;
+
+    let application = Module::new(
+        &store,
+        r#"(module
+            (import "reactor" "count" (func $count (result i32)))
+            (export "count" (func $count)))"#,
+    )?;
+    let application = linker.instantiate(&application)?;
+    let count = application
+        .exports
+        .get_native_function::<(), i32>("count")?;
+    assert_eq!(count.call()?, 11);
+    assert_eq!(count.call()?, 12);
+
+    Ok(())
+}
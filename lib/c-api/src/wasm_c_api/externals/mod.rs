@@ -145,6 +145,9 @@ impl From<Extern> for wasm_extern_t {
                     global: mem::ManuallyDrop::new(wasm_global_t::new(global)),
                 },
             },
+            Extern::Instance(_) | Extern::Module(_) => {
+                unreachable!("the instances and modules are filtered out of the C API")
+            }
         }
     }
 }
@@ -184,6 +184,7 @@ pub unsafe extern "C" fn wasm_instance_exports(
     let mut extern_vec = instance
         .exports
         .iter()
+        .filter(|(_name, r#extern)| !matches!(r#extern, Extern::Instance(_) | Extern::Module(_)))
         .map(|(_name, r#extern)| Box::into_raw(Box::new(r#extern.clone().into())))
         .collect::<Vec<*mut wasm_extern_t>>();
     extern_vec.shrink_to_fit();
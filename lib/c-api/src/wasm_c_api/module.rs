@@ -6,7 +6,7 @@ use super::types::{
 use crate::error::{update_last_error, CApiError};
 use std::ptr::NonNull;
 use std::sync::Arc;
-use wasmer::Module;
+use wasmer::{ExternType, Module};
 
 /// Opaque type representing a WebAssembly module.
 #[allow(non_camel_case_types)]
@@ -235,9 +235,12 @@ pub unsafe extern "C" fn wasm_module_exports(
     // own
     out: &mut wasm_exporttype_vec_t,
 ) {
+    // The instances and modules of the module linking proposal have
+    // no representation in the C API.
     let exports = module
         .inner
         .exports()
+        .filter(|export| !is_module_linking_type(export.ty()))
         .map(Into::into)
         .map(Box::new)
         .collect::<Vec<Box<wasm_exporttype_t>>>();
@@ -379,6 +382,7 @@ pub unsafe extern "C" fn wasm_module_imports(
     let imports = module
         .inner
         .imports()
+        .filter(|import| !is_module_linking_type(import.ty()))
         .map(Into::into)
         .map(Box::new)
         .collect::<Vec<Box<wasm_importtype_t>>>();
@@ -386,6 +390,12 @@ pub unsafe extern "C" fn wasm_module_imports(
     *out = imports.into();
 }
 
+/// Whether the type is the type of an instance or a module, of the
+/// module linking proposal.
+pub(crate) fn is_module_linking_type(ty: &ExternType) -> bool {
+    matches!(ty, ExternType::Instance(_) | ExternType::Module(_))
+}
+
 /// Deserializes a serialized module binary into a `wasm_module_t`.
 ///
 /// Note: the module has to be serialized before with the
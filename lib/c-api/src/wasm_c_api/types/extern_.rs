@@ -32,6 +32,9 @@ impl From<&ExternType> for wasm_externkind_enum {
             ExternType::Global(_) => Self::WASM_EXTERN_GLOBAL,
             ExternType::Table(_) => Self::WASM_EXTERN_TABLE,
             ExternType::Memory(_) => Self::WASM_EXTERN_MEMORY,
+            ExternType::Instance(_) | ExternType::Module(_) => {
+                unreachable!("the instances and modules are filtered out of the C API")
+            }
         }
     }
 }
@@ -66,6 +69,9 @@ impl wasm_externtype_t {
                 ExternType::Memory(memory_type) => {
                     WasmExternType::Memory(WasmMemoryType::new(memory_type))
                 }
+                ExternType::Instance(_) | ExternType::Module(_) => {
+                    unreachable!("the instances and modules are filtered out of the C API")
+                }
             },
         }
     }
@@ -134,10 +134,13 @@
 use super::super::super::instance::wasm_instance_t;
 use super::super::parser::operator::wasmer_parser_operator_t;
 use super::wasmer_middleware_t;
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use wasmer::wasmparser::Operator;
 use wasmer_middlewares::{
     metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    operators::{operator_info, OperatorCategory},
     Metering,
 };
 
@@ -184,6 +187,368 @@ pub extern "C" fn wasmer_metering_new(
     })
 }
 
+/// A class of operators, whose cost can be configured as a whole in a
+/// [`wasmer_metering_cost_table_t`].
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub enum wasmer_metering_operator_class_t {
+    /// `unreachable`, `nop`, the blocks, the branches, `return`, and the
+    /// operators of the exception handling proposal.
+    OPERATOR_CLASS_CONTROL = 0,
+    /// The direct and indirect calls, including the tail calls.
+    OPERATOR_CLASS_CALL = 1,
+    /// `drop` and `select`.
+    OPERATOR_CLASS_PARAMETRIC = 2,
+    /// The operators reading and writing locals and globals.
+    OPERATOR_CLASS_VARIABLE = 3,
+    /// The constants.
+    OPERATOR_CLASS_CONSTANT = 4,
+    /// The scalar loads.
+    OPERATOR_CLASS_LOAD = 5,
+    /// The scalar stores.
+    OPERATOR_CLASS_STORE = 6,
+    /// `memory.size`, `memory.grow`, and the bulk memory operators on
+    /// memories and data segments.
+    OPERATOR_CLASS_MEMORY = 7,
+    /// The integer operators.
+    OPERATOR_CLASS_INTEGER = 8,
+    /// The floating-point operators.
+    OPERATOR_CLASS_FLOAT = 9,
+    /// The operators on references, tables and element segments.
+    OPERATOR_CLASS_REFERENCE = 10,
+    /// The operators of the SIMD proposal, including their loads and
+    /// stores.
+    OPERATOR_CLASS_SIMD = 11,
+    /// The atomic operators of the threads proposal.
+    OPERATOR_CLASS_ATOMIC = 12,
+}
+
+/// The number of operator classes.
+const NUM_OPERATOR_CLASSES: usize = 13;
+
+impl From<OperatorCategory> for wasmer_metering_operator_class_t {
+    fn from(category: OperatorCategory) -> Self {
+        match category {
+            OperatorCategory::Control => Self::OPERATOR_CLASS_CONTROL,
+            OperatorCategory::Call => Self::OPERATOR_CLASS_CALL,
+            OperatorCategory::Parametric => Self::OPERATOR_CLASS_PARAMETRIC,
+            OperatorCategory::Variable => Self::OPERATOR_CLASS_VARIABLE,
+            OperatorCategory::Constant => Self::OPERATOR_CLASS_CONSTANT,
+            OperatorCategory::Load => Self::OPERATOR_CLASS_LOAD,
+            OperatorCategory::Store => Self::OPERATOR_CLASS_STORE,
+            OperatorCategory::Memory => Self::OPERATOR_CLASS_MEMORY,
+            OperatorCategory::Integer => Self::OPERATOR_CLASS_INTEGER,
+            OperatorCategory::Float => Self::OPERATOR_CLASS_FLOAT,
+            OperatorCategory::Reference => Self::OPERATOR_CLASS_REFERENCE,
+            OperatorCategory::Simd => Self::OPERATOR_CLASS_SIMD,
+            OperatorCategory::Atomic => Self::OPERATOR_CLASS_ATOMIC,
+        }
+    }
+}
+
+/// Opaque type representing a table of operator costs, to create a
+/// metering middleware with [`wasmer_metering_new_with_cost_table`].
+///
+/// The cost of an operator is the cost set for the operator itself,
+/// or else the cost set for its class, or else the default cost of the
+/// table. The costs are evaluated natively, without calling back into
+/// C while the modules are compiled.
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct wasmer_metering_cost_table_t {
+    default_cost: u64,
+    class_costs: [Option<u64>; NUM_OPERATOR_CLASSES],
+
+    /// The costs set for the operators, indexed by
+    /// `wasmer_parser_operator_t`.
+    operator_costs: Vec<Option<u64>>,
+}
+
+/// Marks the operator costs which have not been resolved yet.
+const UNRESOLVED_COST: u64 = u64::MAX;
+
+/// The cost function of a metering middleware created from a
+/// [`wasmer_metering_cost_table_t`].
+struct CostTable {
+    table: wasmer_metering_cost_table_t,
+
+    /// The costs of the operators, indexed by
+    /// `wasmer_parser_operator_t`, resolved the first time an operator
+    /// is met so that each operator is classified once.
+    resolved_costs: Vec<AtomicU64>,
+}
+
+impl CostTable {
+    fn new(table: wasmer_metering_cost_table_t) -> Self {
+        let num_operators = wasmer_parser_operator_t::I32x4TruncSatF64x2UZero as usize + 1;
+        Self {
+            table,
+            resolved_costs: (0..num_operators)
+                .map(|_| AtomicU64::new(UNRESOLVED_COST))
+                .collect(),
+        }
+    }
+
+    fn cost(&self, operator: &Operator) -> u64 {
+        let index = wasmer_parser_operator_t::from(operator) as usize;
+        let resolved_cost = match self.resolved_costs.get(index) {
+            Some(resolved_cost) => resolved_cost,
+            None => return self.resolve(index, operator),
+        };
+
+        match resolved_cost.load(Ordering::Relaxed) {
+            UNRESOLVED_COST => {
+                // The compilation threads may resolve the same cost
+                // concurrently, to the same value.
+                let cost = self.resolve(index, operator);
+                resolved_cost.store(cost, Ordering::Relaxed);
+                cost
+            }
+            cost => cost,
+        }
+    }
+
+    fn resolve(&self, index: usize, operator: &Operator) -> u64 {
+        let table = &self.table;
+        if let Some(Some(cost)) = table.operator_costs.get(index) {
+            return *cost;
+        }
+        if table.class_costs.iter().all(Option::is_none) {
+            return table.default_cost;
+        }
+        let class = wasmer_metering_operator_class_t::from(operator_info(operator).category);
+        table.class_costs[class as usize].unwrap_or(table.default_cost)
+    }
+}
+
+/// Creates a new table of operator costs, where every operator costs
+/// `default_cost` until its cost, or the cost of its class, is set.
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_cost_table_new(
+    default_cost: u64,
+) -> Box<wasmer_metering_cost_table_t> {
+    Box::new(wasmer_metering_cost_table_t {
+        default_cost,
+        class_costs: [None; NUM_OPERATOR_CLASSES],
+        operator_costs: vec![],
+    })
+}
+
+/// Deletes a [`wasmer_metering_cost_table_t`].
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_cost_table_delete(
+    _table: Option<Box<wasmer_metering_cost_table_t>>,
+) {
+}
+
+/// Sets the cost of the operators of a class, which is used for the
+/// operators without a cost of their own.
+///
+/// `operator_class` is a [`wasmer_metering_operator_class_t`]. Returns
+/// `false`, without changing the table, if it isn't a valid class.
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_cost_table_set_class_cost(
+    table: &mut wasmer_metering_cost_table_t,
+    operator_class: u32,
+    cost: u64,
+) -> bool {
+    match table.class_costs.get_mut(operator_class as usize) {
+        Some(class_cost) => {
+            *class_cost = Some(cost);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sets the cost of an operator.
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_cost_table_set_operator_cost(
+    table: &mut wasmer_metering_cost_table_t,
+    wasm_operator: wasmer_parser_operator_t,
+    cost: u64,
+) {
+    let index = wasm_operator as usize;
+    if table.operator_costs.len() <= index {
+        table.operator_costs.resize(index + 1, None);
+    }
+    table.operator_costs[index] = Some(cost);
+}
+
+/// Sets the costs of the operators from an array of `costs_length`
+/// costs, indexed by `wasmer_parser_operator_t`.
+///
+/// The operators whose cost is `UINT64_MAX` in the array keep their
+/// current cost.
+///
+/// # Safety
+///
+/// `costs` must point to `costs_length` costs.
+///
+/// # Example
+///
+/// See [`wasmer_metering_new_with_cost_table`].
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_metering_cost_table_set_operator_costs(
+    table: &mut wasmer_metering_cost_table_t,
+    costs: *const u64,
+    costs_length: usize,
+) {
+    if costs.is_null() || costs_length == 0 {
+        return;
+    }
+
+    let costs = slice::from_raw_parts(costs, costs_length);
+    if table.operator_costs.len() < costs.len() {
+        table.operator_costs.resize(costs.len(), None);
+    }
+    for (operator_cost, cost) in table.operator_costs.iter_mut().zip(costs) {
+        if *cost != u64::MAX {
+            *operator_cost = Some(*cost);
+        }
+    }
+}
+
+/// Creates a new metering middleware with an initial limit, like
+/// [`wasmer_metering_new`], but where the costs of the operators are
+/// defined by a table instead of a cost function.
+///
+/// This function takes ownership of `table`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Every operator costs 1 unit by default.
+///     wasmer_metering_cost_table_t* table = wasmer_metering_cost_table_new(1);
+///
+///     // The constants are free, except `i64.const`.
+///     assert(wasmer_metering_cost_table_set_class_cost(table, OPERATOR_CLASS_CONSTANT, 0));
+///     wasmer_metering_cost_table_set_operator_cost(table, I64Const, 1);
+///
+///     // Invalid classes are rejected.
+///     assert(!wasmer_metering_cost_table_set_class_cost(table, OPERATOR_CLASS_ATOMIC + 1, 0));
+///
+///     // `local.get` costs 3 units, set from an array of costs.
+///     uint64_t costs[LocalGet + 1];
+///
+///     for (int i = 0; i <= LocalGet; ++i) {
+///         costs[i] = UINT64_MAX;
+///     }
+///
+///     costs[LocalGet] = 3;
+///     wasmer_metering_cost_table_set_operator_costs(table, costs, LocalGet + 1);
+///
+///     // `i32.add` costs 2 units.
+///     wasmer_metering_cost_table_set_operator_cost(table, I32Add, 2);
+///
+///     // Create the metering middleware, which consumes `table`.
+///     wasmer_metering_t* metering = wasmer_metering_new_with_cost_table(10, table);
+///     wasmer_middleware_t* middleware = wasmer_metering_as_middleware(metering);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_push_middleware(config, middleware);
+///
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func $add_one_f (param $value i32) (result i32)\n"
+///         "    local.get $value\n"
+///         "    i32.const 1\n"
+///         "    i32.add)\n"
+///         "  (export \"add_one\" (func $add_one_f)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* traps = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &traps);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     assert(exports.size >= 1);
+///
+///     const wasm_func_t* add_one = wasm_extern_as_func(exports.data[0]);
+///     assert(add_one);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(41) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     wasm_trap_t* trap = wasm_func_call(add_one, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 42);
+///
+///     // `local.get` (3), `i32.const` (0), `i32.add` (2) and `end` (1)
+///     // have cost 6 units.
+///     assert(wasmer_metering_get_remaining_points(instance) == 4);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_metering_new_with_cost_table(
+    initial_limit: u64,
+    table: Box<wasmer_metering_cost_table_t>,
+) -> Box<wasmer_metering_t> {
+    let cost_table = CostTable::new(*table);
+    let cost_function = move |operator: &Operator| -> u64 { cost_table.cost(operator) };
+
+    Box::new(wasmer_metering_t {
+        inner: Arc::new(Metering::new(initial_limit, Box::new(cost_function))),
+    })
+}
+
 /// Deletes a [`wasmer_metering_t`].
 ///
 /// # Example
@@ -129,6 +129,22 @@ typedef enum wasmer_engine_t {
   STATICLIB = 2,
 } wasmer_engine_t;
 
+typedef enum wasmer_metering_operator_class_t {
+  OPERATOR_CLASS_CONTROL = 0,
+  OPERATOR_CLASS_CALL = 1,
+  OPERATOR_CLASS_PARAMETRIC = 2,
+  OPERATOR_CLASS_VARIABLE = 3,
+  OPERATOR_CLASS_CONSTANT = 4,
+  OPERATOR_CLASS_LOAD = 5,
+  OPERATOR_CLASS_STORE = 6,
+  OPERATOR_CLASS_MEMORY = 7,
+  OPERATOR_CLASS_INTEGER = 8,
+  OPERATOR_CLASS_FLOAT = 9,
+  OPERATOR_CLASS_REFERENCE = 10,
+  OPERATOR_CLASS_SIMD = 11,
+  OPERATOR_CLASS_ATOMIC = 12,
+} wasmer_metering_operator_class_t;
+
 typedef enum wasmer_parser_operator_t {
   Unreachable,
   Nop,
@@ -657,6 +673,8 @@ typedef struct wasmer_cpu_features_t wasmer_cpu_features_t;
 
 typedef struct wasmer_features_t wasmer_features_t;
 
+typedef struct wasmer_metering_cost_table_t wasmer_metering_cost_table_t;
+
 typedef struct wasmer_metering_t wasmer_metering_t;
 
 typedef struct wasmer_middleware_t wasmer_middleware_t;
@@ -815,6 +833,22 @@ int wasmer_last_error_message(char *buffer, int length);
 
 struct wasmer_middleware_t *wasmer_metering_as_middleware(struct wasmer_metering_t *metering);
 
+void wasmer_metering_cost_table_delete(struct wasmer_metering_cost_table_t *_table);
+
+struct wasmer_metering_cost_table_t *wasmer_metering_cost_table_new(uint64_t default_cost);
+
+bool wasmer_metering_cost_table_set_class_cost(struct wasmer_metering_cost_table_t *table,
+                                               uint32_t operator_class,
+                                               uint64_t cost);
+
+void wasmer_metering_cost_table_set_operator_cost(struct wasmer_metering_cost_table_t *table,
+                                                  enum wasmer_parser_operator_t wasm_operator,
+                                                  uint64_t cost);
+
+void wasmer_metering_cost_table_set_operator_costs(struct wasmer_metering_cost_table_t *table,
+                                                   const uint64_t *costs,
+                                                   uintptr_t costs_length);
+
 void wasmer_metering_delete(struct wasmer_metering_t *_metering);
 
 uint64_t wasmer_metering_get_remaining_points(const wasm_instance_t *instance);
@@ -822,6 +856,9 @@ uint64_t wasmer_metering_get_remaining_points(const wasm_instance_t *instance);
 struct wasmer_metering_t *wasmer_metering_new(uint64_t initial_limit,
                                               wasmer_metering_cost_function_t cost_function);
 
+struct wasmer_metering_t *wasmer_metering_new_with_cost_table(uint64_t initial_limit,
+                                                              struct wasmer_metering_cost_table_t *table);
+
 bool wasmer_metering_points_are_exhausted(const wasm_instance_t *instance);
 
 void wasmer_metering_set_remaining_points(const wasm_instance_t *instance, uint64_t new_limit);
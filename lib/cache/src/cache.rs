@@ -7,6 +7,11 @@ use std::error::Error;
 use wasmer::{Module, Store};
 
 /// A generic cache for storing and loading compiled wasm modules.
+///
+/// Storing modules requires exclusive access to the cache. Wrap a cache
+/// in a [`SharedCache`] to use it from several threads at once.
+///
+/// [`SharedCache`]: crate::SharedCache
 pub trait Cache {
     /// The serialization error for the implementation
     type SerializeError: Error + Send + Sync;
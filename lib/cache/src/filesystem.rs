@@ -1,10 +1,17 @@
 use crate::cache::Cache;
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use crate::metadata::{CacheEntry, CacheEntryMetadata};
+use std::fs::{self, create_dir_all, File};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use wasmer::{DeserializeError, Module, SerializeError, Store};
 
+/// The extension of the files holding the metadata of each entry.
+const METADATA_EXTENSION: &str = "meta";
+
 /// Representation of a directory that contains compiled wasm artifacts.
 ///
 /// The `FileSystemCache` type implements the [`Cache`] trait, which allows it to be used
@@ -30,9 +37,23 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 ///     Ok(())
 /// }
 /// ```
+///
+/// # Eviction
+///
+/// Each entry is stored along with a [`CacheEntryMetadata`] recording
+/// when it was created and last used. The cache can be bounded with
+/// [`FileSystemCache::set_max_size`], in which case the least recently
+/// used entries are evicted whenever a new module is stored and the
+/// cache exceeds the limit. [`FileSystemCache::prune`] does the same on
+/// demand.
+///
+/// Entries are written to a temporary file first and then renamed, so
+/// concurrent readers never observe a partially written artifact.
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    engine_name: Option<String>,
+    max_size: Option<u64>,
 }
 
 impl FileSystemCache {
@@ -43,7 +64,7 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self::with_path(path))
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -64,7 +85,16 @@ impl FileSystemCache {
         } else {
             // Create the directory and any parent directories if they don't yet exist.
             create_dir_all(&path)?;
-            Ok(Self { path, ext: None })
+            Ok(Self::with_path(path))
+        }
+    }
+
+    fn with_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            ext: None,
+            engine_name: None,
+            max_size: None,
         }
     }
 
@@ -75,6 +105,198 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
+
+    /// Set the name of the engine recorded in the metadata of the
+    /// entries stored from now on.
+    pub fn set_engine_name(&mut self, engine_name: Option<impl ToString>) {
+        self.engine_name = engine_name.map(|engine_name| engine_name.to_string());
+    }
+
+    /// Set the maximum size, in bytes, of the artifacts in this cache.
+    ///
+    /// When set, storing a module evicts the least recently used entries
+    /// until the cache fits in the given size.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// The directory this cache stores its entries in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Lists the entries stored in this cache.
+    ///
+    /// Entries without metadata (for example, stored by older versions)
+    /// get it inferred from the file system.
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = vec![];
+        for dir_entry in fs::read_dir(&self.path)? {
+            let dir_entry = dir_entry?;
+            let file_name = dir_entry.file_name();
+            let key = match file_name
+                .to_str()
+                .and_then(|name| self.key_from_filename(name))
+            {
+                Some(key) => key,
+                None => continue,
+            };
+            // The entry may have been removed concurrently.
+            let file_metadata = match dir_entry.metadata() {
+                Ok(file_metadata) if file_metadata.is_file() => file_metadata,
+                _ => continue,
+            };
+            let metadata = self.read_metadata(key).unwrap_or_else(|| {
+                let modified = file_metadata
+                    .modified()
+                    .unwrap_or_else(|_| std::time::SystemTime::now());
+                CacheEntryMetadata {
+                    engine: None,
+                    target: None,
+                    created: file_metadata.created().unwrap_or(modified),
+                    last_used: modified,
+                }
+            });
+            entries.push(CacheEntry {
+                key,
+                path: dir_entry.path(),
+                size: file_metadata.len(),
+                metadata,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// The total size, in bytes, of the artifacts in this cache.
+    pub fn size(&self) -> io::Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// Removes the entry with the given key from the cache.
+    pub fn remove(&self, key: Hash) -> io::Result<()> {
+        remove_if_exists(&self.path.join(self.filename(key)))?;
+        remove_if_exists(&self.metadata_path(key))
+    }
+
+    /// Evicts the least recently used entries until the artifacts in
+    /// this cache take at most `max_size` bytes.
+    ///
+    /// Returns the evicted entries.
+    pub fn prune(&self, max_size: u64) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| (entry.metadata.last_used, entry.metadata.created));
+        let mut total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut evicted = vec![];
+        for entry in entries {
+            if total_size <= max_size {
+                break;
+            }
+            self.remove(entry.key)?;
+            total_size -= entry.size;
+            evicted.push(entry);
+        }
+        Ok(evicted)
+    }
+
+    /// Reads the serialized artifact stored with the given key.
+    pub(crate) fn load_serialized(&self, key: Hash) -> io::Result<Vec<u8>> {
+        let bytes = fs::read(self.path.join(self.filename(key)))?;
+        self.record_use(key);
+        Ok(bytes)
+    }
+
+    /// Stores a serialized artifact with the given key, evicting older
+    /// entries if the cache grows bigger than its maximum size.
+    pub(crate) fn store_serialized(
+        &self,
+        key: Hash,
+        bytes: &[u8],
+        target: Option<String>,
+    ) -> io::Result<()> {
+        write_atomically(&self.path.join(self.filename(key)), bytes)?;
+
+        let metadata = CacheEntryMetadata::new(self.engine_name.clone(), target);
+        self.write_metadata(key, &metadata)?;
+
+        if let Some(max_size) = self.max_size {
+            self.prune(max_size)?;
+        }
+        Ok(())
+    }
+
+    /// Records the use of an entry for the LRU eviction. Failing to do
+    /// so shouldn't prevent using the cached module.
+    fn record_use(&self, key: Hash) {
+        if let Some(mut metadata) = self.read_metadata(key) {
+            metadata.last_used = std::time::SystemTime::now();
+            let _ = self.write_metadata(key, &metadata);
+        }
+    }
+
+    fn filename(&self, key: Hash) -> String {
+        if let Some(ref ext) = self.ext {
+            format!("{}.{}", key.to_string(), ext)
+        } else {
+            key.to_string()
+        }
+    }
+
+    fn key_from_filename(&self, filename: &str) -> Option<Hash> {
+        let mut parts = filename.splitn(2, '.');
+        let stem = parts.next()?;
+        let ext = parts.next();
+        if ext == Some(METADATA_EXTENSION) {
+            return None;
+        }
+        if self.ext.is_some() && ext != self.ext.as_deref() {
+            return None;
+        }
+        Hash::from_str(stem).ok()
+    }
+
+    fn metadata_path(&self, key: Hash) -> PathBuf {
+        self.path
+            .join(format!("{}.{}", key.to_string(), METADATA_EXTENSION))
+    }
+
+    fn read_metadata(&self, key: Hash) -> Option<CacheEntryMetadata> {
+        let contents = fs::read_to_string(self.metadata_path(key)).ok()?;
+        CacheEntryMetadata::parse(&contents)
+    }
+
+    fn write_metadata(&self, key: Hash, metadata: &CacheEntryMetadata) -> io::Result<()> {
+        write_atomically(&self.metadata_path(key), metadata.to_string().as_bytes())
+    }
+}
+
+/// Writes `contents` into a temporary file next to `path` and renames it
+/// to `path`, so that readers either see the previous contents or the
+/// new ones, but never a partial write.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let tmp_filename = format!(
+        ".{}.{}.{}.tmp",
+        path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+        process::id(),
+        NEXT_ID.fetch_add(1, Ordering::SeqCst)
+    );
+    let tmp_path = path.with_file_name(tmp_filename);
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(contents))
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
 }
 
 impl Cache for FileSystemCache {
@@ -82,27 +304,85 @@ impl Cache for FileSystemCache {
     type SerializeError = SerializeError;
 
     unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        Module::deserialize_from_file(&store, path)
+        let path = self.path.join(self.filename(key));
+        let module = Module::deserialize_from_file(&store, path)?;
+        self.record_use(key);
+        Ok(module)
     }
 
     fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let mut file = File::create(path)?;
-
         let buffer = module.serialize()?;
-        file.write_all(&buffer)?;
-
+        let target = module.store().engine().target().triple().to_string();
+        self.store_serialized(key, &buffer, Some(target))?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    fn module(store: &Store, name: &str) -> Module {
+        let wat = format!(r#"(module (func (export "{}")))"#, name);
+        Module::new(store, wat).unwrap()
+    }
+
+    #[test]
+    fn store_records_metadata() {
+        let tmp_dir = TempDir::new().unwrap();
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let mut cache = FileSystemCache::new(tmp_dir.path()).unwrap();
+        cache.set_cache_extension(Some("wasmu"));
+        cache.set_engine_name(Some("universal"));
+
+        let key = Hash::generate(b"module");
+        cache.store(key, &module(&store, "a")).unwrap();
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, key);
+        assert_eq!(entries[0].metadata.engine.as_deref(), Some("universal"));
+        assert!(entries[0].metadata.target.is_some());
+        assert!(entries[0].size > 0);
+
+        unsafe { cache.load(&store, key).unwrap() };
+        cache.remove(key).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_evicts_least_recently_used() {
+        let tmp_dir = TempDir::new().unwrap();
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let mut cache = FileSystemCache::new(tmp_dir.path()).unwrap();
+
+        let keys = [Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32])];
+        for (i, key) in keys.iter().enumerate() {
+            cache.store(*key, &module(&store, &i.to_string())).unwrap();
+            // Make the entries' last use distinguishable.
+            let mut metadata = cache.read_metadata(*key).unwrap();
+            metadata.last_used = std::time::UNIX_EPOCH + std::time::Duration::from_secs(i as u64);
+            cache.write_metadata(*key, &metadata).unwrap();
+        }
+
+        let size = cache.size().unwrap();
+        let evicted = cache.prune(size - 1).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].key, keys[0]);
+
+        let mut remaining = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.key.to_string())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec![keys[1].to_string(), keys[2].to_string()]);
+
+        assert_eq!(cache.prune(0).unwrap().len(), 2);
+        assert_eq!(cache.size().unwrap(), 0);
+    }
+}
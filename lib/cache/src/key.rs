@@ -0,0 +1,190 @@
+use crate::hash::Hash;
+use wasmer::{Engine, Features, Target};
+
+/// The version of the format of the serialized artifacts, mixed into
+/// every key. Bump it whenever the layout of the artifacts changes
+/// without a new version of Wasmer, so that the artifacts cached
+/// before are not loaded anymore.
+const ARTIFACT_FORMAT_VERSION: u32 = 2;
+
+/// A builder for cache keys that depend on the settings a module is
+/// compiled with, and not only on its WebAssembly bytes.
+///
+/// An artifact is only valid for the engine, compiler, compiler options,
+/// target, features and middlewares that produced it. Keying the cache on
+/// the WebAssembly bytes alone (with [`Hash::generate`]) makes it possible
+/// to load an artifact produced with different settings, so every setting
+/// that affects the generated code should be mixed in the key.
+///
+/// Each setting is hashed along with its name, so two keys are only equal
+/// if they were built by mixing in the same settings, in the same order.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Features, Target};
+/// use wasmer_cache::CacheKeyBuilder;
+///
+/// let key = CacheKeyBuilder::new()
+///     .engine("universal")
+///     .compiler("cranelift")
+///     .compiler_options("opt_level=speed")
+///     .target(&Target::default())
+///     .features(&Features::default())
+///     .hash(b"\0asm\x01\0\0\0");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CacheKeyBuilder {
+    hasher: blake3::Hasher,
+}
+
+impl CacheKeyBuilder {
+    /// Creates a new builder.
+    ///
+    /// The version of Wasmer and the version of the format of the
+    /// artifacts are always mixed in, since the artifacts are not
+    /// compatible across versions.
+    pub fn new() -> Self {
+        Self {
+            hasher: blake3::Hasher::new(),
+        }
+        .mix("version", env!("CARGO_PKG_VERSION").as_bytes())
+        .mix("format", &ARTIFACT_FORMAT_VERSION.to_le_bytes())
+    }
+
+    /// Mixes in the name of the engine, such as `universal` or `dylib`.
+    pub fn engine(self, name: &str) -> Self {
+        self.mix("engine", name.as_bytes())
+    }
+
+    /// Mixes in the identifier of the given engine instance, along with
+    /// its target.
+    ///
+    /// Engine identifiers are only unique within the current process, so
+    /// keys built with them must not be used with caches outliving the
+    /// process, such as a [`FileSystemCache`]. Use [`Self::engine`] for
+    /// those instead.
+    ///
+    /// [`FileSystemCache`]: crate::FileSystemCache
+    pub fn engine_id(self, engine: &dyn Engine) -> Self {
+        self.mix("engine_id", engine.id().id().as_bytes())
+            .target(engine.target())
+    }
+
+    /// Mixes in the name of the compiler, such as `cranelift` or `llvm`.
+    pub fn compiler(self, name: &str) -> Self {
+        self.mix("compiler", name.as_bytes())
+    }
+
+    /// Mixes in a description of the compiler options affecting the
+    /// generated code, such as the optimization level.
+    pub fn compiler_options(self, options: &str) -> Self {
+        self.mix("compiler_options", options.as_bytes())
+    }
+
+    /// Mixes in the target triple and CPU features.
+    pub fn target(self, target: &Target) -> Self {
+        let cpu_features = target
+            .cpu_features()
+            .iter()
+            .map(|feature| feature.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.mix("triple", target.triple().to_string().as_bytes())
+            .mix("cpu_features", cpu_features.as_bytes())
+    }
+
+    /// Mixes in the enabled WebAssembly features.
+    pub fn features(self, features: &Features) -> Self {
+        self.mix("features", format!("{:?}", features).as_bytes())
+    }
+
+    /// Mixes in the identity of a middleware, which should describe the
+    /// middleware and all the parameters changing its behaviour, like
+    /// `metering(initial_limit=1000, cost_function=v2)`.
+    ///
+    /// Middlewares must be mixed in in the order they are pushed onto the
+    /// compiler middleware chain.
+    pub fn middleware(self, identity: &str) -> Self {
+        self.mix("middleware", identity.as_bytes())
+    }
+
+    /// Returns the key for the given WebAssembly bytes.
+    pub fn hash(self, wasm: &[u8]) -> Hash {
+        let hash = self.mix("wasm", wasm).hasher.finalize();
+        Hash::new(hash.into())
+    }
+
+    fn mix(mut self, name: &str, value: &[u8]) -> Self {
+        for bytes in &[name.as_bytes(), value] {
+            self.hasher.update(&(bytes.len() as u64).to_le_bytes());
+            self.hasher.update(bytes);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::{CpuFeature, Triple};
+
+    const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn builder() -> CacheKeyBuilder {
+        CacheKeyBuilder::new()
+            .engine("universal")
+            .compiler("singlepass")
+            .features(&Features::default())
+    }
+
+    #[test]
+    fn key_depends_on_settings() {
+        let key = builder().hash(WASM);
+        assert_eq!(builder().hash(WASM), key);
+        assert_ne!(builder().hash(b"\0asm\x01\0\0\0\0"), key);
+        assert_ne!(Hash::generate(WASM), key);
+
+        let llvm = CacheKeyBuilder::new()
+            .engine("universal")
+            .compiler("llvm")
+            .features(&Features::default());
+        assert_ne!(llvm.hash(WASM), key);
+
+        let mut features = Features::default();
+        features.threads(true);
+        let threads = CacheKeyBuilder::new()
+            .engine("universal")
+            .compiler("singlepass")
+            .features(&features);
+        assert_ne!(threads.hash(WASM), key);
+
+        assert_ne!(builder().compiler_options("verifier").hash(WASM), key);
+        assert_ne!(builder().middleware("metering(10)").hash(WASM), key);
+    }
+
+    #[test]
+    fn key_depends_on_target() {
+        let triple = Triple::host();
+        let mut cpu_features = CpuFeature::set();
+        let baseline = builder()
+            .target(&Target::new(triple.clone(), cpu_features))
+            .hash(WASM);
+        cpu_features.insert(CpuFeature::AVX2);
+        let avx2 = builder()
+            .target(&Target::new(triple, cpu_features))
+            .hash(WASM);
+        assert_ne!(baseline, avx2);
+    }
+
+    #[test]
+    fn settings_are_not_ambiguous() {
+        let first = CacheKeyBuilder::new().compiler("ab").compiler("c");
+        let second = CacheKeyBuilder::new().compiler("a").compiler("bc");
+        assert_ne!(first.hash(WASM), second.hash(WASM));
+
+        let first = CacheKeyBuilder::new().engine("llvm");
+        let second = CacheKeyBuilder::new().compiler("llvm");
+        assert_ne!(first.hash(WASM), second.hash(WASM));
+    }
+}
@@ -22,10 +22,22 @@
 mod cache;
 mod filesystem;
 mod hash;
+mod key;
+mod memory;
+mod metadata;
+mod remote;
+mod shared;
+mod tiered;
 
 pub use crate::cache::Cache;
 pub use crate::filesystem::FileSystemCache;
 pub use crate::hash::Hash;
+pub use crate::key::CacheKeyBuilder;
+pub use crate::memory::InMemoryCache;
+pub use crate::metadata::{CacheEntry, CacheEntryMetadata};
+pub use crate::remote::RemoteCache;
+pub use crate::shared::SharedCache;
+pub use crate::tiered::TieredCache;
 
 // We re-export those for convinience of users
 pub use wasmer::{DeserializeError, SerializeError};
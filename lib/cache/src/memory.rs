@@ -0,0 +1,199 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache keeping compiled [`Module`]s in memory, for reusing them
+/// within a long-lived process without deserializing them again.
+///
+/// All the methods take `&self`, so an `InMemoryCache` can be shared
+/// across threads, for instance behind an `Arc`.
+///
+/// Since a module is tied to the engine that compiled it, a module is
+/// only returned when it is loaded with a [`Store`] using the same
+/// engine. Consider mixing the engine in the keys with
+/// [`CacheKeyBuilder::engine_id`] when the cache is shared by several
+/// engines.
+///
+/// [`CacheKeyBuilder::engine_id`]: crate::CacheKeyBuilder::engine_id
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{CompileError, Module, Store};
+/// use wasmer_cache::{Hash, InMemoryCache};
+///
+/// fn get_module(
+///     cache: &InMemoryCache,
+///     store: &Store,
+///     bytes: &[u8],
+/// ) -> Result<Module, CompileError> {
+///     let key = Hash::generate(bytes);
+///     if let Some(module) = cache.get(store, key) {
+///         return Ok(module);
+///     }
+///     let module = Module::new(store, bytes)?;
+///     cache.insert(key, module.clone());
+///     Ok(module)
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct InMemoryCache {
+    max_entries: Option<usize>,
+    modules: Mutex<Modules>,
+}
+
+#[derive(Debug, Default)]
+struct Modules {
+    clock: u64,
+    entries: HashMap<Hash, (Module, u64)>,
+}
+
+impl InMemoryCache {
+    /// Creates a new, unbounded, `InMemoryCache`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `InMemoryCache` keeping at most `max_entries`
+    /// modules, evicting the least recently used ones first.
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self {
+            max_entries: Some(max_entries),
+            modules: Default::default(),
+        }
+    }
+
+    /// Returns the module stored with the given key, if it was compiled
+    /// by the engine of the given store.
+    pub fn get(&self, store: &Store, key: Hash) -> Option<Module> {
+        let mut modules = self.modules.lock().unwrap();
+        modules.clock += 1;
+        let now = modules.clock;
+        let (module, last_used) = modules.entries.get_mut(&key)?;
+        if !Store::same(module.store(), store) {
+            return None;
+        }
+        *last_used = now;
+        Some(module.clone())
+    }
+
+    /// Stores a module with the given key, returning the module
+    /// previously stored with it, if any.
+    pub fn insert(&self, key: Hash, module: Module) -> Option<Module> {
+        if self.max_entries == Some(0) {
+            return None;
+        }
+        let mut modules = self.modules.lock().unwrap();
+        modules.clock += 1;
+        let now = modules.clock;
+        let previous = modules
+            .entries
+            .insert(key, (module, now))
+            .map(|(module, _)| module);
+        if let Some(max_entries) = self.max_entries {
+            while modules.entries.len() > max_entries {
+                let oldest = modules
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(key, _)| *key)
+                    .unwrap();
+                modules.entries.remove(&oldest);
+            }
+        }
+        previous
+    }
+
+    /// Removes the module stored with the given key, returning it.
+    pub fn remove(&self, key: Hash) -> Option<Module> {
+        let mut modules = self.modules.lock().unwrap();
+        modules.entries.remove(&key).map(|(module, _)| module)
+    }
+
+    /// Removes all the modules.
+    pub fn clear(&self) {
+        self.modules.lock().unwrap().entries.clear();
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.modules.lock().unwrap().entries.len()
+    }
+
+    /// Returns `true` if the cache holds no module.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Cache for InMemoryCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        self.get(store, key).ok_or_else(|| {
+            DeserializeError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no module for {} in the cache", key.to_string()),
+            ))
+        })
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.insert(key, module.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    fn module(store: &Store, name: &str) -> Module {
+        let wat = format!(r#"(module (func (export "{}")))"#, name);
+        Module::new(store, &wat).unwrap()
+    }
+
+    #[test]
+    fn get_and_insert() {
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let cache = InMemoryCache::new();
+        let key = Hash::generate(b"module");
+        assert!(cache.get(&store, key).is_none());
+        assert!(cache.insert(key, module(&store, "a")).is_none());
+        assert!(cache.insert(key, module(&store, "b")).is_some());
+
+        let loaded = cache.get(&store, key).unwrap();
+        assert_eq!(loaded.exports().next().unwrap().name(), "b");
+        assert_eq!(cache.len(), 1);
+
+        // A store with another engine can't use the module.
+        let other_store = Store::new(&Universal::new(Singlepass::default()).engine());
+        assert!(cache.get(&other_store, key).is_none());
+        assert!(unsafe { cache.load(&other_store, key) }.is_err());
+
+        assert!(cache.remove(key).is_some());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let cache = InMemoryCache::with_capacity(2);
+        let (a, b, c) = (Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32]));
+        cache.insert(a, module(&store, "a"));
+        cache.insert(b, module(&store, "b"));
+        assert!(cache.get(&store, a).is_some());
+        cache.insert(c, module(&store, "c"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&store, a).is_some());
+        assert!(cache.get(&store, b).is_none());
+        assert!(cache.get(&store, c).is_some());
+    }
+}
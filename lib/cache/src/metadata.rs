@@ -0,0 +1,132 @@
+//! Metadata stored alongside the cached artifacts.
+
+use crate::hash::Hash;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Information about how and when a cached artifact was produced
+/// and used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntryMetadata {
+    /// The name of the engine that produced the artifact, if known.
+    pub engine: Option<String>,
+    /// The target triple the artifact was compiled for, if known.
+    pub target: Option<String>,
+    /// When the artifact was stored in the cache.
+    pub created: SystemTime,
+    /// When the artifact was last stored or loaded.
+    pub last_used: SystemTime,
+}
+
+impl CacheEntryMetadata {
+    /// Creates metadata for an entry created right now.
+    pub fn new(engine: Option<String>, target: Option<String>) -> Self {
+        let now = SystemTime::now();
+        Self {
+            engine,
+            target,
+            created: now,
+            last_used: now,
+        }
+    }
+
+    /// Parses metadata from its textual representation, as written by
+    /// the [`fmt::Display`] implementation.
+    ///
+    /// Unknown keys are ignored, so that newer versions can add fields.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut engine = None;
+        let mut target = None;
+        let mut created = None;
+        let mut last_used = None;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            match key {
+                "engine" => engine = Some(value.to_string()),
+                "target" => target = Some(value.to_string()),
+                "created" => created = Some(parse_timestamp(value)?),
+                "last_used" => last_used = Some(parse_timestamp(value)?),
+                _ => {}
+            }
+        }
+        let created = created?;
+        Some(Self {
+            engine,
+            target,
+            created,
+            last_used: last_used.unwrap_or(created),
+        })
+    }
+}
+
+impl fmt::Display for CacheEntryMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(engine) = &self.engine {
+            writeln!(f, "engine={}", engine)?;
+        }
+        if let Some(target) = &self.target {
+            writeln!(f, "target={}", target)?;
+        }
+        writeln!(f, "created={}", timestamp(self.created))?;
+        writeln!(f, "last_used={}", timestamp(self.last_used))
+    }
+}
+
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    value
+        .parse::<u64>()
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// An artifact stored in a [`FileSystemCache`].
+///
+/// [`FileSystemCache`]: crate::FileSystemCache
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// The key the artifact was stored with.
+    pub key: Hash,
+    /// The path of the artifact.
+    pub path: PathBuf,
+    /// The size of the artifact, in bytes.
+    pub size: u64,
+    /// The entry metadata.
+    pub metadata: CacheEntryMetadata,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_roundtrip() {
+        let metadata = CacheEntryMetadata {
+            engine: Some("universal".to_string()),
+            target: Some("x86_64-unknown-linux-gnu".to_string()),
+            created: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            last_used: UNIX_EPOCH + Duration::from_secs(1_600_000_042),
+        };
+        let parsed = CacheEntryMetadata::parse(&metadata.to_string()).unwrap();
+        assert_eq!(parsed, metadata);
+    }
+
+    #[test]
+    fn metadata_parse_defaults() {
+        let parsed = CacheEntryMetadata::parse("created=10\nunknown=foo\n").unwrap();
+        assert_eq!(parsed.engine, None);
+        assert_eq!(parsed.target, None);
+        assert_eq!(parsed.last_used, parsed.created);
+
+        assert!(CacheEntryMetadata::parse("engine=universal\n").is_none());
+        assert!(CacheEntryMetadata::parse("created=yesterday\n").is_none());
+    }
+}
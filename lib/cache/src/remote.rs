@@ -0,0 +1,407 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache stored in a remote key/value store speaking a simple
+/// HTTP protocol.
+///
+/// Artifacts are stored in `{url}/{key}`:
+/// * `GET` returns the serialized artifact with a `200` status, or a
+///   `404` status if there is no artifact for the key.
+/// * `PUT` stores the request body as the artifact for the key, and
+///   returns any `2xx` status.
+///
+/// Only plain `http://` URLs are supported; use a local proxy to reach
+/// a server over TLS.
+///
+/// Since the artifacts come from another machine, they are loaded with
+/// [`Module::deserialize_checked`], so only engines able to validate
+/// their artifacts (such as Universal) can load modules from this cache.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, SerializeError};
+/// use wasmer_cache::{Cache, Hash, RemoteCache};
+///
+/// # use wasmer::{Module};
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     // Create a new remote cache.
+///     let mut remote_cache = RemoteCache::new("http://cache.example.com:8080/artifacts")?;
+///
+///     // Compute a key for a given WebAssembly binary
+///     let key = Hash::generate(bytes);
+///
+///     // Store a module into the cache given a key
+///     remote_cache.store(key, module)?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RemoteCache {
+    host: String,
+    port: u16,
+    base_path: String,
+    timeout: Option<Duration>,
+}
+
+impl RemoteCache {
+    /// Construct a new `RemoteCache` for the given `http://` URL.
+    pub fn new(url: &str) -> io::Result<Self> {
+        let invalid_url = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid remote cache URL `{}`: {}", url, message),
+            )
+        };
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid_url("only `http://` URLs are supported"))?;
+        let (authority, base_path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        // IPv6 addresses are enclosed in brackets, as their colons
+        // would be mistaken for the port separator.
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let end = bracketed
+                .find(']')
+                .ok_or_else(|| invalid_url("the IPv6 address is not closed"))?;
+            let port = match &bracketed[end + 1..] {
+                "" => None,
+                rest => Some(
+                    rest.strip_prefix(':')
+                        .ok_or_else(|| invalid_url("unexpected characters after the host"))?,
+                ),
+            };
+            (&bracketed[..end], port)
+        } else {
+            match authority.rfind(':') {
+                Some(index) => (&authority[..index], Some(&authority[index + 1..])),
+                None => (authority, None),
+            }
+        };
+        let port = match port {
+            Some(port) => port
+                .parse::<u16>()
+                .map_err(|_| invalid_url("the port is not a number"))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(invalid_url("the host is missing"));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            base_path: base_path.trim_end_matches('/').to_string(),
+            timeout: Some(Duration::from_secs(30)),
+        })
+    }
+
+    /// Set the timeout of the requests made to the remote store.
+    ///
+    /// By default, requests time out after 30 seconds.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Fetches the serialized artifact stored with the given key, if any.
+    pub fn get(&self, key: Hash) -> io::Result<Option<Vec<u8>>> {
+        let (status, body) = self.request("GET", key, None)?;
+        match status {
+            200 => Ok(Some(body)),
+            404 => Ok(None),
+            status => Err(unexpected_status("GET", status)),
+        }
+    }
+
+    /// Stores a serialized artifact with the given key.
+    pub fn put(&self, key: Hash, bytes: &[u8]) -> io::Result<()> {
+        let (status, _) = self.request("PUT", key, Some(bytes))?;
+        match status {
+            200..=299 => Ok(()),
+            status => Err(unexpected_status("PUT", status)),
+        }
+    }
+
+    /// Sends a request for the given key, returning the response status
+    /// and body.
+    fn request(&self, method: &str, key: Hash, body: Option<&[u8]>) -> io::Result<(u16, Vec<u8>)> {
+        let address = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("could not resolve `{}`", self.host),
+                )
+            })?;
+        let mut stream = match self.timeout {
+            Some(timeout) => TcpStream::connect_timeout(&address, timeout)?,
+            None => TcpStream::connect(address)?,
+        };
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+
+        let body = body.unwrap_or_default();
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        let header = format!(
+            "{} {}/{} HTTP/1.1\r\nHost: {}:{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            method,
+            self.base_path,
+            key.to_string(),
+            host,
+            self.port,
+            body.len()
+        );
+        stream.write_all(header.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream))
+    }
+}
+
+fn unexpected_status(method: &str, status: u16) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "remote cache {} request failed with status {}",
+            method, status
+        ),
+    )
+}
+
+fn invalid_response(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid remote cache response: {}", message),
+    )
+}
+
+/// Appends `len` bytes of `reader` to `body`.
+///
+/// The body grows with the bytes actually received, rather than with
+/// the announced length, so a bogus length can't make it allocate
+/// more than what the server sends.
+fn read_body(reader: &mut impl Read, body: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let expected = body
+        .len()
+        .checked_add(len)
+        .ok_or_else(|| invalid_response("the body is too large"))?;
+    reader.take(len as u64).read_to_end(body)?;
+    if body.len() != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the remote cache response is truncated",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads an HTTP/1.1 response, returning its status and body.
+fn read_response(mut reader: impl BufRead) -> io::Result<(u16, Vec<u8>)> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| invalid_response("malformed status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_response("unexpected end of headers"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(index) = line.find(':') {
+            let name = line[..index].trim().to_ascii_lowercase();
+            let value = line[index + 1..].trim();
+            match name.as_str() {
+                "content-length" => {
+                    content_length = Some(
+                        value
+                            .parse::<usize>()
+                            .map_err(|_| invalid_response("malformed content length"))?,
+                    )
+                }
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![];
+    if chunked {
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line)?;
+            let size = size_line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| invalid_response("malformed chunk size"))?;
+            if size == 0 {
+                break;
+            }
+            read_body(&mut reader, &mut body, size)?;
+            // Skip the CRLF following the chunk.
+            let mut crlf = [0; 2];
+            reader.read_exact(&mut crlf)?;
+        }
+    } else if let Some(content_length) = content_length {
+        read_body(&mut reader, &mut body, content_length)?;
+    } else {
+        reader.read_to_end(&mut body)?;
+    }
+    Ok((status, body))
+}
+
+impl Cache for RemoteCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        match self.get(key)? {
+            Some(bytes) => Module::deserialize_checked(store, &bytes),
+            None => Err(DeserializeError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no artifact for {} in the remote cache", key.to_string()),
+            ))),
+        }
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let buffer = module.serialize()?;
+        self.put(key, &buffer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Spawns a minimal key/value HTTP server, returning its URL.
+    pub(crate) fn spawn_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let storage: Arc<Mutex<HashMap<String, Vec<u8>>>> = Default::default();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Content-Length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut storage = storage.lock().unwrap();
+                let (status, response) = match method.as_str() {
+                    "GET" => match storage.get(&path) {
+                        Some(bytes) => ("200 OK", bytes.clone()),
+                        None => ("404 Not Found", vec![]),
+                    },
+                    "PUT" => {
+                        storage.insert(path, body);
+                        ("201 Created", vec![])
+                    }
+                    _ => ("405 Method Not Allowed", vec![]),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n",
+                    status,
+                    response.len()
+                )
+                .unwrap();
+                stream.write_all(&response).unwrap();
+            }
+        });
+        format!("http://{}/artifacts", address)
+    }
+
+    #[test]
+    fn parse_url() {
+        let cache = RemoteCache::new("http://localhost:8080/cache/").unwrap();
+        assert_eq!(cache.host, "localhost");
+        assert_eq!(cache.port, 8080);
+        assert_eq!(cache.base_path, "/cache");
+
+        let cache = RemoteCache::new("http://localhost").unwrap();
+        assert_eq!(cache.port, 80);
+        assert_eq!(cache.base_path, "");
+
+        assert!(RemoteCache::new("https://localhost").is_err());
+        assert!(RemoteCache::new("http://localhost:http").is_err());
+        assert!(RemoteCache::new("http://:80").is_err());
+
+        let cache = RemoteCache::new("http://[::1]:8080/cache").unwrap();
+        assert_eq!(cache.host, "::1");
+        assert_eq!(cache.port, 8080);
+        assert_eq!(cache.base_path, "/cache");
+
+        let cache = RemoteCache::new("http://[::1]").unwrap();
+        assert_eq!(cache.host, "::1");
+        assert_eq!(cache.port, 80);
+
+        assert!(RemoteCache::new("http://[::1").is_err());
+        assert!(RemoteCache::new("http://[::1]8080").is_err());
+    }
+
+    #[test]
+    fn read_chunked_response() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        let (status, body) = read_response(&response[..]).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"abcde");
+    }
+
+    #[test]
+    fn read_truncated_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nabc";
+        let error = read_response(&response[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn get_and_put() {
+        let cache = RemoteCache::new(&spawn_server()).unwrap();
+        let key = Hash::generate(b"module");
+
+        assert_eq!(cache.get(key).unwrap(), None);
+        cache.put(key, b"artifact").unwrap();
+        assert_eq!(cache.get(key).unwrap(), Some(b"artifact".to_vec()));
+    }
+}
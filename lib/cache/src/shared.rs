@@ -0,0 +1,119 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::sync::{Arc, RwLock};
+use wasmer::{Module, Store};
+
+/// A wrapper making any [`Cache`] usable from several threads at once.
+///
+/// `SharedCache` is cheap to clone: all the clones share the same
+/// underlying cache. Loads can run concurrently, while stores wait for
+/// exclusive access to the underlying cache.
+///
+/// # Usage
+///
+/// ```
+/// use std::thread;
+/// use wasmer::{DeserializeError, SerializeError};
+/// use wasmer_cache::{FileSystemCache, Hash, SharedCache};
+///
+/// # use wasmer::{Module};
+/// fn store_module(module: Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     let cache = SharedCache::new(FileSystemCache::new("some/directory/goes/here")?);
+///     let key = Hash::generate(bytes);
+///
+///     // Store a module into the cache from another thread.
+///     let worker_cache = cache.clone();
+///     thread::spawn(move || worker_cache.store(key, &module))
+///         .join()
+///         .unwrap()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedCache<C> {
+    inner: Arc<RwLock<C>>,
+}
+
+impl<C> Clone for SharedCache<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C: Cache> SharedCache<C> {
+    /// Wraps the given cache.
+    pub fn new(cache: C) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(cache)),
+        }
+    }
+
+    /// Loads a module using the default `Engine` of the `Store` and the
+    /// provided `Hash`.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe for the same reasons as [`Cache::load`].
+    pub unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, C::DeserializeError> {
+        self.inner.read().unwrap().load(store, key)
+    }
+
+    /// Stores a [`Module`] in the cache with the provided `Hash`.
+    pub fn store(&self, key: Hash, module: &Module) -> Result<(), C::SerializeError> {
+        self.inner.write().unwrap().store(key, module)
+    }
+}
+
+impl<C: Cache> Cache for SharedCache<C> {
+    type SerializeError = C::SerializeError;
+    type DeserializeError = C::DeserializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        Self::load(self, store, key)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        Self::store(self, key, module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystemCache;
+    use std::thread;
+    use tempfile::TempDir;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    #[test]
+    fn share_cache_across_threads() {
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let dir = TempDir::new().unwrap();
+        let cache = SharedCache::new(FileSystemCache::new(dir.path()).unwrap());
+
+        let workers = (0..4u8)
+            .map(|i| {
+                let cache = cache.clone();
+                let store = store.clone();
+                thread::spawn(move || {
+                    let wat = format!(r#"(module (func (export "run{}")))"#, i);
+                    let module = Module::new(&store, &wat).unwrap();
+                    let key = Hash::generate(wat.as_bytes());
+                    cache.store(key, &module).unwrap();
+                    key
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, worker) in workers.into_iter().enumerate() {
+            let key = worker.join().unwrap();
+            let module = unsafe { cache.load(&store, key).unwrap() };
+            let export = module.exports().next().unwrap();
+            assert_eq!(export.name(), format!("run{}", i));
+        }
+    }
+}
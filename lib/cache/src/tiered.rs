@@ -0,0 +1,297 @@
+use crate::cache::Cache;
+use crate::filesystem::FileSystemCache;
+use crate::hash::Hash;
+use crate::remote::RemoteCache;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache combining an in-memory LRU, a [`FileSystemCache`] and a
+/// [`RemoteCache`], each of them optional.
+///
+/// Modules are looked up in each tier in that order. When a module is
+/// found in a slower tier, it is copied into the faster ones, so the
+/// next lookups are served locally. Stored modules go to every tier.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, SerializeError};
+/// use wasmer_cache::{Cache, FileSystemCache, Hash, RemoteCache, TieredCache};
+///
+/// # use wasmer::{Module};
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     // Create a tiered cache, keeping up to 64MiB of artifacts in memory.
+///     let mut cache = TieredCache::new()
+///         .memory(64 * 1024 * 1024)
+///         .filesystem(FileSystemCache::new("some/directory/goes/here")?)
+///         .remote(RemoteCache::new("http://cache.example.com:8080/artifacts")?);
+///
+///     // Compute a key for a given WebAssembly binary
+///     let key = Hash::generate(bytes);
+///
+///     // Store a module into all the tiers given a key
+///     cache.store(key, module)?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct TieredCache {
+    memory: Option<Mutex<MemoryTier>>,
+    filesystem: Option<FileSystemCache>,
+    remote: Option<RemoteCache>,
+}
+
+impl TieredCache {
+    /// Creates a new `TieredCache` without any tier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps up to `max_size` bytes of serialized artifacts in memory.
+    pub fn memory(mut self, max_size: u64) -> Self {
+        self.memory = Some(Mutex::new(MemoryTier::new(max_size)));
+        self
+    }
+
+    /// Uses the given [`FileSystemCache`] as the local tier.
+    pub fn filesystem(mut self, filesystem: FileSystemCache) -> Self {
+        self.filesystem = Some(filesystem);
+        self
+    }
+
+    /// Uses the given [`RemoteCache`] as the shared tier.
+    pub fn remote(mut self, remote: RemoteCache) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    fn memory_get(&self, key: Hash) -> Option<Arc<[u8]>> {
+        self.memory.as_ref()?.lock().unwrap().get(key)
+    }
+
+    fn memory_insert(&self, key: Hash, bytes: &[u8]) {
+        if let Some(memory) = &self.memory {
+            memory.lock().unwrap().insert(key, bytes.into());
+        }
+    }
+}
+
+impl Cache for TieredCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        if let Some(bytes) = self.memory_get(key) {
+            return Module::deserialize(store, &bytes);
+        }
+
+        // An error of the file system tier, reported if the module isn't
+        // found in the remote tier either.
+        let mut filesystem_error = None;
+        if let Some(filesystem) = &self.filesystem {
+            let result = if self.memory.is_some() {
+                filesystem
+                    .load_serialized(key)
+                    .map_err(DeserializeError::from)
+                    .and_then(|bytes| {
+                        let module = Module::deserialize(store, &bytes)?;
+                        self.memory_insert(key, &bytes);
+                        Ok(module)
+                    })
+            } else {
+                // Without a memory tier, let the engine load the file
+                // directly instead of reading it first.
+                filesystem.load(store, key)
+            };
+            match result {
+                Ok(module) => return Ok(module),
+                Err(DeserializeError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => filesystem_error = Some(e),
+            }
+        }
+
+        if let Some(remote) = &self.remote {
+            if let Some(bytes) = remote.get(key)? {
+                // The artifact comes from another machine, so it's validated.
+                let module = Module::deserialize_checked(store, &bytes)?;
+                self.memory_insert(key, &bytes);
+                if let Some(filesystem) = &self.filesystem {
+                    // The module is loaded already: failing to keep a
+                    // local copy only makes the next lookup slower.
+                    let target = store.engine().target().triple().to_string();
+                    if let Err(e) = filesystem.store_serialized(key, &bytes, Some(target)) {
+                        log::warn!(
+                            "failed to copy the artifact {} to the file system cache: {}",
+                            key.to_string(),
+                            e
+                        );
+                    }
+                }
+                return Ok(module);
+            }
+        }
+
+        Err(filesystem_error.unwrap_or_else(|| {
+            DeserializeError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no artifact for {} in the cache", key.to_string()),
+            ))
+        }))
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let bytes = module.serialize()?;
+        self.memory_insert(key, &bytes);
+        if let Some(filesystem) = &self.filesystem {
+            let target = module.store().engine().target().triple().to_string();
+            filesystem.store_serialized(key, &bytes, Some(target))?;
+        }
+        if let Some(remote) = &self.remote {
+            remote.put(key, &bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized artifacts kept in memory, evicted in least recently used
+/// order once they exceed the maximum size.
+struct MemoryTier {
+    max_size: u64,
+    size: u64,
+    clock: u64,
+    entries: HashMap<Hash, (Arc<[u8]>, u64)>,
+}
+
+impl MemoryTier {
+    fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            size: 0,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: Hash) -> Option<Arc<[u8]>> {
+        let now = self.tick();
+        let (bytes, last_used) = self.entries.get_mut(&key)?;
+        *last_used = now;
+        Some(bytes.clone())
+    }
+
+    fn insert(&mut self, key: Hash, bytes: Arc<[u8]>) {
+        let size = bytes.len() as u64;
+        if size > self.max_size {
+            return;
+        }
+        let now = self.tick();
+        if let Some((previous, _)) = self.entries.insert(key, (bytes, now)) {
+            self.size -= previous.len() as u64;
+        }
+        self.size += size;
+        while self.size > self.max_size {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key)
+                .expect("the memory tier can't be empty while over its size");
+            let (evicted, _) = self.entries.remove(&oldest).unwrap();
+            self.size -= evicted.len() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote::tests::spawn_server;
+    use tempfile::TempDir;
+    use wasmer_compiler_singlepass::Singlepass;
+    use wasmer_engine_universal::Universal;
+
+    #[test]
+    fn memory_tier_evicts_least_recently_used() {
+        let mut memory = MemoryTier::new(10);
+        let (a, b, c) = (Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32]));
+        memory.insert(a, vec![0; 4].into());
+        memory.insert(b, vec![0; 4].into());
+        assert!(memory.get(a).is_some());
+        memory.insert(c, vec![0; 4].into());
+
+        assert!(memory.get(a).is_some());
+        assert!(memory.get(b).is_none());
+        assert!(memory.get(c).is_some());
+        assert_eq!(memory.size, 8);
+
+        memory.insert(a, vec![0; 11].into());
+        assert_eq!(memory.get(a).map(|bytes| bytes.len()), Some(4));
+    }
+
+    #[test]
+    fn remote_artifacts_are_copied_to_local_tiers() {
+        let url = spawn_server();
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let module = Module::new(&store, r#"(module (func (export "run")))"#).unwrap();
+        let key = Hash::generate(b"module");
+
+        // A first machine compiles and stores the module.
+        let producer_dir = TempDir::new().unwrap();
+        let mut producer = TieredCache::new()
+            .filesystem(FileSystemCache::new(producer_dir.path()).unwrap())
+            .remote(RemoteCache::new(&url).unwrap());
+        producer.store(key, &module).unwrap();
+
+        // Another one finds it in the remote tier.
+        let consumer_dir = TempDir::new().unwrap();
+        let consumer_filesystem = FileSystemCache::new(consumer_dir.path()).unwrap();
+        let consumer = TieredCache::new()
+            .memory(1024 * 1024 * 1024)
+            .filesystem(FileSystemCache::new(consumer_dir.path()).unwrap())
+            .remote(RemoteCache::new(&url).unwrap());
+        let loaded = unsafe { consumer.load(&store, key).unwrap() };
+        assert_eq!(
+            loaded.exports().collect::<Vec<_>>(),
+            module.exports().collect::<Vec<_>>()
+        );
+        assert!(consumer.memory_get(key).is_some());
+        assert_eq!(consumer_filesystem.entries().unwrap().len(), 1);
+
+        let missing = unsafe { consumer.load(&store, Hash::new([0; 32])) };
+        assert!(matches!(missing, Err(DeserializeError::Io(_))));
+    }
+
+    #[test]
+    fn broken_filesystem_tiers_fall_back_to_the_remote_tier() {
+        let url = spawn_server();
+        let store = Store::new(&Universal::new(Singlepass::default()).engine());
+        let module = Module::new(&store, r#"(module (func (export "run")))"#).unwrap();
+        let key = Hash::generate(b"module");
+        RemoteCache::new(&url).unwrap().store(key, &module).unwrap();
+
+        // The directory of the file system tier is replaced by a file,
+        // so reading from it and writing to it both fail.
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache");
+        let filesystem = FileSystemCache::new(&path).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        std::fs::write(&path, b"not a directory").unwrap();
+
+        let cache = TieredCache::new()
+            .filesystem(filesystem)
+            .remote(RemoteCache::new(&url).unwrap());
+        let loaded = unsafe { cache.load(&store, key).unwrap() };
+        assert_eq!(
+            loaded.exports().collect::<Vec<_>>(),
+            module.exports().collect::<Vec<_>>()
+        );
+    }
+}
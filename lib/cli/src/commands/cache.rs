@@ -1,7 +1,16 @@
 use crate::common::get_cache_dir;
+use crate::utils::parse_size;
+#[cfg(not(feature = "cache"))]
+use anyhow::bail;
 use anyhow::{Context, Result};
+#[cfg(feature = "cache")]
+use bytesize::ByteSize;
 use std::fs;
+#[cfg(feature = "cache")]
+use std::time::SystemTime;
 use structopt::StructOpt;
+#[cfg(feature = "cache")]
+use wasmer_cache::{CacheEntry, FileSystemCache};
 
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer cache` subcommand
@@ -13,6 +22,18 @@ pub enum Cache {
     /// Display the location of the cache
     #[structopt(name = "dir")]
     Dir,
+
+    /// List the cached modules
+    #[structopt(name = "list")]
+    List,
+
+    /// Evict the least recently used modules from the cache
+    #[structopt(name = "prune")]
+    Prune {
+        /// The maximum size of the cache once pruned (eg. `500MB`, `1GiB`)
+        #[structopt(long = "max-size", parse(try_from_str = parse_size))]
+        max_size: u64,
+    },
 }
 
 impl Cache {
@@ -25,6 +46,19 @@ impl Cache {
             Cache::Dir => {
                 self.dir()?;
             }
+            #[cfg(feature = "cache")]
+            Cache::List => {
+                self.list().context("failed to list wasmer cache.")?;
+            }
+            #[cfg(feature = "cache")]
+            Cache::Prune { max_size } => {
+                self.prune(*max_size)
+                    .context("failed to prune wasmer cache.")?;
+            }
+            #[cfg(not(feature = "cache"))]
+            Cache::List | Cache::Prune { .. } => {
+                bail!("the `cache` feature is required to inspect the cache contents");
+            }
         }
         Ok(())
     }
@@ -41,4 +75,87 @@ impl Cache {
         println!("{}", get_cache_dir().to_string_lossy());
         Ok(())
     }
+    #[cfg(feature = "cache")]
+    fn list(&self) -> Result<()> {
+        let now = SystemTime::now();
+        let mut total_size = 0;
+        for (cache, mut entries) in Self::caches()? {
+            if entries.is_empty() {
+                continue;
+            }
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.metadata.last_used));
+            println!("{}:", cache.path().to_string_lossy());
+            for entry in entries {
+                total_size += entry.size;
+                println!(
+                    "  {}  {:>10}  {:<10}  {:<26}  last used {} ago",
+                    entry.key.to_string(),
+                    ByteSize(entry.size).to_string(),
+                    entry.metadata.engine.as_deref().unwrap_or("-"),
+                    entry.metadata.target.as_deref().unwrap_or("-"),
+                    format_elapsed(now, entry.metadata.last_used),
+                );
+            }
+        }
+        println!("Total size: {}", ByteSize(total_size));
+        Ok(())
+    }
+    #[cfg(feature = "cache")]
+    fn prune(&self, max_size: u64) -> Result<()> {
+        // The limit applies to the whole cache, so the entries of all
+        // the compilers are evicted in least recently used order.
+        let caches = Self::caches()?;
+        let mut entries = caches
+            .iter()
+            .flat_map(|(cache, entries)| entries.iter().map(move |entry| (cache, entry)))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, entry)| (entry.metadata.last_used, entry.metadata.created));
+        let mut total_size: u64 = entries.iter().map(|(_, entry)| entry.size).sum();
+        let mut evicted = 0;
+        for (cache, entry) in entries {
+            if total_size <= max_size {
+                break;
+            }
+            cache.remove(entry.key)?;
+            total_size -= entry.size;
+            evicted += 1;
+        }
+        eprintln!(
+            "Wasmer cache pruned successfully: {} module(s) evicted, {} remaining.",
+            evicted,
+            ByteSize(total_size)
+        );
+        Ok(())
+    }
+    /// Get the caches of every compiler, along with their entries.
+    #[cfg(feature = "cache")]
+    fn caches() -> Result<Vec<(FileSystemCache, Vec<CacheEntry>)>> {
+        let cache_dir = get_cache_dir();
+        if !cache_dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut caches = vec![];
+        for dir_entry in fs::read_dir(cache_dir)? {
+            let path = dir_entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let cache = FileSystemCache::new(path)?;
+            let entries = cache.entries()?;
+            caches.push((cache, entries));
+        }
+        Ok(caches)
+    }
+}
+
+/// Format the time elapsed since `time` in a human-friendly way.
+#[cfg(feature = "cache")]
+fn format_elapsed(now: SystemTime, time: SystemTime) -> String {
+    let secs = now.duration_since(time).unwrap_or_default().as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
 }
@@ -3,13 +3,15 @@ use crate::common::get_cache_dir;
 use crate::logging;
 use crate::store::{CompilerType, EngineType, StoreOptions};
 use crate::suggestions::suggest_function_exports;
+#[cfg(feature = "cache")]
+use crate::utils::parse_size;
 use crate::warning;
 use anyhow::{anyhow, Context, Result};
 use std::path::PathBuf;
 use std::str::FromStr;
 use wasmer::*;
 #[cfg(feature = "cache")]
-use wasmer_cache::{Cache, FileSystemCache, Hash};
+use wasmer_cache::{Cache, FileSystemCache, Hash, RemoteCache, TieredCache};
 
 use structopt::StructOpt;
 
@@ -46,6 +48,18 @@ pub struct Run {
     #[structopt(long = "cache-key", hidden = true)]
     cache_key: Option<String>,
 
+    /// The URL of a remote cache (`http://host[:port][/path]`) shared across
+    /// machines, used when the module is not found in the local cache
+    #[cfg(feature = "cache")]
+    #[structopt(long = "cache-remote")]
+    cache_remote: Option<String>,
+
+    /// The maximum size of the local cache (eg. `500MB`, `1GiB`). The least
+    /// recently used modules are evicted when it's exceeded
+    #[cfg(feature = "cache")]
+    #[structopt(long = "cache-max-size", parse(try_from_str = parse_size))]
+    cache_max_size: Option<u64>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 
@@ -260,12 +274,19 @@ impl Run {
         // as it takes space and the speedup is minimal.
         let mut cache = self.get_cache(engine_type, compiler_type)?;
         // Try to get the hash from the provided `--cache-key`, otherwise
-        // generate one from the provided file `.wasm` contents.
-        let hash = self
+        // generate one from the provided file `.wasm` contents and the
+        // settings it's compiled with.
+        let hash = match self
             .cache_key
             .as_ref()
             .and_then(|key| Hash::from_str(&key).ok())
-            .unwrap_or_else(|| Hash::generate(&contents));
+        {
+            Some(hash) => hash,
+            None => self
+                .store
+                .get_cache_key_builder(store.engine().target())?
+                .hash(&contents),
+        };
         match unsafe { cache.load(&store, hash) } {
             Ok(module) => Ok(module),
             Err(e) => {
@@ -278,20 +299,24 @@ impl Run {
                     }
                 }
                 let module = Module::new(&store, &contents)?;
-                // Store the compiled Module in cache
-                cache.store(hash, &module)?;
+                // Store the compiled Module in cache. Failing to do so
+                // (eg. if the remote cache is unreachable) is not fatal.
+                if let Err(err) = cache.store(hash, &module) {
+                    warning!("failed to store module in cache: {}", err);
+                }
                 Ok(module)
             }
         }
     }
 
     #[cfg(feature = "cache")]
-    /// Get the Compiler Filesystem cache
+    /// Get the Compiler cache: the filesystem cache, backed by the
+    /// remote cache if any
     fn get_cache(
         &self,
         engine_type: &EngineType,
         compiler_type: &CompilerType,
-    ) -> Result<FileSystemCache> {
+    ) -> Result<TieredCache> {
         let mut cache_dir_root = get_cache_dir();
         cache_dir_root.push(compiler_type.to_string());
         let mut cache = FileSystemCache::new(cache_dir_root)?;
@@ -315,7 +340,14 @@ impl Run {
             _ => compiler_type.to_string(),
         };
         cache.set_cache_extension(Some(extension));
-        Ok(cache)
+        cache.set_engine_name(Some(engine_type.to_string()));
+        cache.set_max_size(self.cache_max_size);
+
+        let mut tiered_cache = TieredCache::new().filesystem(cache);
+        if let Some(url) = &self.cache_remote {
+            tiered_cache = tiered_cache.remote(RemoteCache::new(url)?);
+        }
+        Ok(tiered_cache)
     }
 
     fn try_find_function(
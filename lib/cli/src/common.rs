@@ -29,6 +29,10 @@ pub struct WasmFeatures {
     #[structopt(long = "enable-bulk-memory")]
     pub bulk_memory: bool,
 
+    /// Only enable the deterministic features, and canonicalize NaNs.
+    #[structopt(long = "deterministic")]
+    pub deterministic: bool,
+
     /// Enable support for all pre-standard proposals.
     #[structopt(long = "enable-all")]
     pub all: bool,
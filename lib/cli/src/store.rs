@@ -9,6 +9,8 @@ use std::string::ToString;
 use std::sync::Arc;
 use structopt::StructOpt;
 use wasmer::*;
+#[cfg(feature = "cache")]
+use wasmer_cache::CacheKeyBuilder;
 #[cfg(feature = "compiler")]
 use wasmer_compiler::CompilerConfig;
 
@@ -114,6 +116,9 @@ impl CompilerOptions {
         if self.features.reference_types || self.features.all {
             features.reference_types(true);
         }
+        if self.features.deterministic {
+            features.deterministic_only(true);
+        }
         Ok(features)
     }
 
@@ -397,6 +402,27 @@ impl StoreOptions {
 
         Ok((engine, engine_type))
     }
+
+    /// Gets a builder for the cache keys of the modules compiled for the
+    /// given target, mixing in the engine, compiler, compiler options and
+    /// features selected.
+    #[cfg(feature = "cache")]
+    pub fn get_cache_key_builder(&self, target: &Target) -> Result<CacheKeyBuilder> {
+        let engine_type = self.get_engine()?;
+        let (compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        let features = self
+            .compiler
+            .get_features(compiler_config.default_features_for_target(target))?;
+        Ok(CacheKeyBuilder::new()
+            .engine(&engine_type.to_string())
+            .compiler(&compiler_type.to_string())
+            .compiler_options(&format!(
+                "enable_verifier={}",
+                self.compiler.enable_verifier
+            ))
+            .target(target)
+            .features(&features))
+    }
 }
 
 #[cfg(feature = "engine")]
@@ -462,6 +488,17 @@ impl StoreOptions {
     ) -> Result<(Store, EngineType, CompilerType)> {
         bail!("You need compilers to retrieve a store for a specific target");
     }
+
+    /// Gets a builder for the cache keys of the modules loaded for the
+    /// given target
+    #[cfg(feature = "cache")]
+    pub fn get_cache_key_builder(&self, target: &Target) -> Result<CacheKeyBuilder> {
+        let engine_type = self.get_engine()?;
+        Ok(CacheKeyBuilder::new()
+            .engine(&engine_type.to_string())
+            .compiler(&CompilerType::Headless.to_string())
+            .target(target))
+    }
 }
 
 // If we don't have any engine enabled
@@ -479,4 +516,11 @@ impl StoreOptions {
     ) -> Result<(Store, EngineType, CompilerType)> {
         bail!("No engines are enabled");
     }
+
+    /// Gets a builder for the cache keys of the modules loaded for the
+    /// given target
+    #[cfg(feature = "cache")]
+    pub fn get_cache_key_builder(&self, _target: &Target) -> Result<CacheKeyBuilder> {
+        bail!("No engines are enabled");
+    }
 }
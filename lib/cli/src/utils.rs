@@ -64,9 +64,38 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     }
 }
 
+/// Parses a size in bytes, with an optional unit (eg. `512`, `500MB`, `1GiB`).
+pub fn parse_size(entry: &str) -> Result<u64> {
+    let entry = entry.trim();
+    let split = entry
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(entry.len());
+    let (number, unit) = entry.split_at(split);
+    let number = match number.parse::<u64>() {
+        Ok(number) => number,
+        Err(_) => bail!("Size must start with a number; found `{}`", entry),
+    };
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => bytesize::B,
+        "k" | "kb" => bytesize::KB,
+        "ki" | "kib" => bytesize::KIB,
+        "m" | "mb" => bytesize::MB,
+        "mi" | "mib" => bytesize::MIB,
+        "g" | "gb" => bytesize::GB,
+        "gi" | "gib" => bytesize::GIB,
+        "t" | "tb" => bytesize::TB,
+        "ti" | "tib" => bytesize::TIB,
+        _ => bail!("Unknown size unit `{}` in `{}`", unit.trim(), entry),
+    };
+    match number.checked_mul(multiplier) {
+        Some(size) => Ok(size),
+        None => bail!("Size `{}` is too big", entry),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_envvar;
+    use super::{parse_envvar, parse_size};
 
     #[test]
     fn test_parse_envvar() {
@@ -89,4 +118,20 @@ mod tests {
             ("A".into(), "B=C=D".into())
         );
     }
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10B").unwrap(), 10);
+        assert_eq!(parse_size("500MB").unwrap(), 500_000_000);
+        assert_eq!(parse_size("1 GiB").unwrap(), 1_073_741_824);
+        assert_eq!(parse_size("2kib").unwrap(), 2048);
+        assert_eq!(
+            parse_size("GB").unwrap_err().to_string(),
+            "Size must start with a number; found `GB`"
+        );
+        assert_eq!(
+            parse_size("10XB").unwrap_err().to_string(),
+            "Unknown size unit `XB` in `10XB`"
+        );
+    }
 }
@@ -118,6 +118,7 @@ impl Compiler for CraneliftCompiler {
                 );
                 context.func.name = get_function_name(func_index);
                 context.func.signature = signatures[module.functions[func_index]].clone();
+                context.func.stack_limit = Some(func_env.stack_limit(&mut context.func));
                 // if generate_debug_info {
                 //     context.func.collect_debug_info();
                 // }
@@ -173,6 +173,25 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         })
     }
 
+    /// Returns the global value of the lowest address of the stack
+    /// usable by the function, checked by Cranelift in its prologue.
+    pub(crate) fn stack_limit(&mut self, func: &mut Function) -> ir::GlobalValue {
+        let vmctx = self.vmctx(func);
+        let pointer_type = self.pointer_type();
+        let stack_limit_ptr = func.create_global_value(ir::GlobalValueData::Load {
+            base: vmctx,
+            offset: Offset32::new(i32::try_from(self.offsets.vmctx_stack_limit()).unwrap()),
+            global_type: pointer_type,
+            readonly: true,
+        });
+        func.create_global_value(ir::GlobalValueData::Load {
+            base: stack_limit_ptr,
+            offset: Offset32::new(0),
+            global_type: pointer_type,
+            readonly: false,
+        })
+    }
+
     fn get_table_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.table_fill_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -1538,6 +1557,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         self.module.signatures.get(sig_index)
     }
 
+    fn signature_index(&self, type_index: u32) -> WasmResult<SignatureIndex> {
+        self.module.signature_index(type_index).ok_or_else(|| {
+            WasmError::Generic(std::format!("type {} is not a function type", type_index))
+        })
+    }
+
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
         // TODO: this allocation can be removed without too much effort but it will require
         //       maneuvering around the borrow checker
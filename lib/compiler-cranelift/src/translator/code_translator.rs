@@ -95,9 +95,7 @@ use std::vec::Vec;
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator, Type as WPType};
 use wasmer_compiler::WasmResult;
 use wasmer_compiler::{wasm_unsupported, ModuleTranslationState};
-use wasmer_types::{
-    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, Type as WasmerType,
-};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex, Type as WasmerType};
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
 #[cfg_attr(
@@ -676,7 +674,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             bitcast_arguments(args, &types, builder);
 
             let (args, _args_metadata) = state.peekn(num_args);
-            let sig_idx = SignatureIndex::from_u32(*index);
+            let sig_idx = environ.signature_index(*index)?;
 
             let call = environ.translate_call_indirect(
                 builder.cursor(),
@@ -506,6 +506,10 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Get the type of a function with the given signature index.
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType>;
 
+    /// Get the signature index of the function type of the given index
+    /// in the type section.
+    fn signature_index(&self, type_index: u32) -> WasmResult<SignatureIndex>;
+
     /// Drops all locals that need to be dropped. Useful for returning from functions.
     fn translate_drop_locals(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()>;
 }
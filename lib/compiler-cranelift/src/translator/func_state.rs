@@ -551,7 +551,7 @@ impl FuncTranslationState {
         index: u32,
         environ: &mut FE,
     ) -> WasmResult<(ir::SigRef, usize)> {
-        let index = SignatureIndex::from_u32(index);
+        let index = environ.signature_index(index)?;
         match self.signatures.entry(index) {
             Occupied(entry) => Ok(*entry.get()),
             Vacant(entry) => {
@@ -31,8 +31,7 @@ use wasmer_compiler::{
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type,
+    FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, TableIndex, Type,
 };
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, VMOffsets};
 
@@ -209,6 +208,7 @@ impl FuncTranslator {
             fcg.ctx.basic(),
             &func_attrs,
         );
+        fcg.trap_if_stack_overflow();
 
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
@@ -730,6 +730,60 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(shouldnt_trap_block);
     }
 
+    // Trap with `StackOverflow` if the stack pointer is below the lowest
+    // address of the stack usable by WebAssembly code.
+    fn trap_if_stack_overflow(&self) {
+        let stack_limit = self.ctx.stack_limit(self.intrinsics, self.module);
+        let stack_pointer = self
+            .builder
+            .build_call(self.intrinsics.stacksave, &[], "stack_pointer")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+        let stack_pointer =
+            self.builder
+                .build_ptr_to_int(stack_pointer, self.intrinsics.i64_ty, "");
+        let should_trap = self.builder.build_int_compare(
+            IntPredicate::ULT,
+            stack_pointer,
+            stack_limit,
+            "stack_overflow",
+        );
+
+        let should_trap = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    should_trap.into(),
+                    self.intrinsics.i1_ty.const_zero().into(),
+                ],
+                "should_trap_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let shouldnt_trap_block = self
+            .context
+            .append_basic_block(self.function, "shouldnt_trap_block");
+        let should_trap_block = self
+            .context
+            .append_basic_block(self.function, "should_trap_block");
+        self.builder
+            .build_conditional_branch(should_trap, should_trap_block, shouldnt_trap_block);
+        self.builder.position_at_end(should_trap_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_stack_overflow.into()],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(shouldnt_trap_block);
+    }
+
     fn trap_if_zero(&self, value: IntValue) {
         let int_type = value.get_type();
         let should_trap = self.builder.build_int_compare(
@@ -2260,7 +2314,9 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     .for_each(|ret| self.state.push1(*ret));
             }
             Operator::CallIndirect { index, table_index } => {
-                let sigindex = SignatureIndex::from_u32(index);
+                let sigindex = self.wasm_module.signature_index(index).ok_or_else(|| {
+                    CompileError::Codegen(format!("type {} is not a function type", index))
+                })?;
                 let func_type = &self.wasm_module.signatures[sigindex];
                 let expected_dynamic_sigindex =
                     self.ctx
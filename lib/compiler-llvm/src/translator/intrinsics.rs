@@ -153,6 +153,7 @@ pub struct Intrinsics<'ctx> {
     pub usub_sat_i16x8: FunctionValue<'ctx>,
 
     pub expect_i1: FunctionValue<'ctx>,
+    pub stacksave: FunctionValue<'ctx>,
     pub trap: FunctionValue<'ctx>,
     pub debug_trap: FunctionValue<'ctx>,
 
@@ -215,6 +216,7 @@ pub struct Intrinsics<'ctx> {
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
+    pub trap_stack_overflow: BasicValueEnum<'ctx>,
 
     pub experimental_stackmap: FunctionValue<'ctx>,
 
@@ -684,6 +686,7 @@ impl<'ctx> Intrinsics<'ctx> {
             ),
 
             expect_i1: module.add_function("llvm.expect.i1", ret_i1_take_i1_i1, None),
+            stacksave: module.add_function("llvm.stacksave", i8_ptr_ty.fn_type(&[], false), None),
             trap: module.add_function("llvm.trap", void_ty.fn_type(&[], false), None),
             debug_trap: module.add_function("llvm.debugtrap", void_ty.fn_type(&[], false), None),
             personality: module.add_function(
@@ -768,6 +771,9 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_stack_overflow: i32_ty
+                .const_int(TrapCode::StackOverflow as _, false)
+                .as_basic_value_enum(),
 
             experimental_stackmap: module.add_function(
                 "llvm.experimental.stackmap",
@@ -1122,6 +1128,45 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         self.ctx_ptr_value.as_basic_value_enum()
     }
 
+    /// Loads the lowest address of the stack usable by the function.
+    pub fn stack_limit(
+        &self,
+        intrinsics: &Intrinsics<'ctx>,
+        module: &Module<'ctx>,
+    ) -> IntValue<'ctx> {
+        let cache_builder = self.cache_builder;
+        let offset = self.offsets.vmctx_stack_limit();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let stack_limit_ptr_ptr =
+            unsafe { cache_builder.build_gep(self.ctx_ptr_value, &[offset], "") };
+        let stack_limit_ptr_ptr = cache_builder
+            .build_bitcast(
+                stack_limit_ptr_ptr,
+                intrinsics.i64_ptr_ty.ptr_type(AddressSpace::Generic),
+                "",
+            )
+            .into_pointer_value();
+        let stack_limit_ptr = cache_builder
+            .build_load(stack_limit_ptr_ptr, "stack_limit_ptr")
+            .into_pointer_value();
+        tbaa_label(
+            module,
+            intrinsics,
+            "stack_limit_ptr".to_string(),
+            stack_limit_ptr.as_instruction_value().unwrap(),
+        );
+        let stack_limit = cache_builder
+            .build_load(stack_limit_ptr, "stack_limit")
+            .into_int_value();
+        tbaa_label(
+            module,
+            intrinsics,
+            "stack_limit".to_string(),
+            stack_limit.as_instruction_value().unwrap(),
+        );
+        stack_limit
+    }
+
     pub fn memory(
         &mut self,
         index: MemoryIndex,
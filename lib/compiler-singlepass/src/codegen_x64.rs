@@ -16,10 +16,7 @@ use wasmer_types::{
     entity::{EntityRef, PrimaryMap, SecondaryMap},
     FunctionType,
 };
-use wasmer_types::{
-    FunctionIndex, GlobalIndex, LocalFunctionIndex, LocalMemoryIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type,
-};
+use wasmer_types::{FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, TableIndex, Type};
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
 
 /// The singlepass per-function code generator.
@@ -97,6 +94,7 @@ struct SpecialLabelSet {
     table_access_oob: DynamicLabel,
     indirect_call_null: DynamicLabel,
     bad_signature: DynamicLabel,
+    stack_overflow: DynamicLabel,
 }
 
 /// A trap table for a `RunnableModuleInfo`.
@@ -1238,33 +1236,35 @@ impl<'a> FuncGen<'a> {
         value_size: usize,
         cb: F,
     ) -> Result<(), CodegenError> {
-        let need_check = match self.memory_styles[MemoryIndex::new(0)] {
+        let memory_index = MemoryIndex::from_u32(memarg.memory);
+        let need_check = match self.memory_styles[memory_index] {
             MemoryStyle::Static { .. } => false,
             MemoryStyle::Dynamic { .. } => true,
         };
         let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
 
         // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
-        let (base_loc, bound_loc) = if self.module.num_imported_memories != 0 {
-            // Imported memories require one level of indirection.
-            let offset = self
-                .vmoffsets
-                .vmctx_vmmemory_import_definition(MemoryIndex::new(0));
-            self.emit_relaxed_binop(
-                Assembler::emit_mov,
-                Size::S64,
-                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
-                Location::GPR(tmp_addr),
-            );
-            (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
-        } else {
-            let offset = self
-                .vmoffsets
-                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0));
-            (
-                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
-                Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
-            )
+        let (base_loc, bound_loc) = match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => {
+                let offset = self.vmoffsets.vmctx_vmmemory_definition(local_memory_index);
+                (
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
+                )
+            }
+            None => {
+                // Imported memories require one level of indirection.
+                let offset = self
+                    .vmoffsets
+                    .vmctx_vmmemory_import_definition(memory_index);
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::GPR(tmp_addr),
+                );
+                (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
+            }
         };
 
         let tmp_base = self.machine.acquire_temp_gpr().unwrap();
@@ -1738,6 +1738,35 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_mov(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP));
 
+        // Explicit stack check, before the frame is allocated, against
+        // the lowest address of the stack usable by WebAssembly code
+        // raised by the size of the frame. The vmctx is still in its
+        // parameter register and RAX is free until `init_locals`.
+        let frame_size = Machine::static_area_size(self.local_types.len()) + 32;
+        let vmctx = match Machine::get_param_location(0) {
+            Location::GPR(vmctx) => vmctx,
+            _ => unreachable!(),
+        };
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(vmctx, self.vmoffsets.vmctx_stack_limit() as i32),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(GPR::RAX, 0),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_add(
+            Size::S64,
+            Location::Imm32(frame_size as u32),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler
+            .emit_cmp(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RSP));
+        self.assembler
+            .emit_jmp(Condition::Below, self.special_labels.stack_overflow);
+
         // Initialize locals.
         self.locals = self.machine.init_locals(
             &mut self.assembler,
@@ -1749,7 +1778,6 @@ impl<'a> FuncGen<'a> {
         self.machine.state.register_values
             [X64Register::GPR(Machine::get_vmctx_reg()).to_index().0] = MachineValue::Vmctx;
 
-        // TODO: Explicit stack check is not supported for now.
         let diff = self.machine.state.diff(&new_machine_state());
         let state_diff_id = self.fsm.diffs.len();
         self.fsm.diffs.push(diff);
@@ -1837,6 +1865,7 @@ impl<'a> FuncGen<'a> {
             table_access_oob: assembler.get_label(),
             indirect_call_null: assembler.get_label(),
             bad_signature: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
         };
 
         let mut fg = FuncGen {
@@ -5244,7 +5273,12 @@ impl<'a> FuncGen<'a> {
                 // TODO: removed restriction on always being table idx 0;
                 // does any code depend on this?
                 let table_index = TableIndex::new(table_index as _);
-                let index = SignatureIndex::new(index as usize);
+                let index = self
+                    .module
+                    .signature_index(index)
+                    .ok_or_else(|| CodegenError {
+                        message: format!("type {} is not a function type", index),
+                    })?;
                 let sig = self.module.signatures.get(index).unwrap();
                 let param_types: SmallVec<[WpType; 8]> =
                     sig.params().iter().cloned().map(type_to_wp_type).collect();
@@ -8687,6 +8721,11 @@ impl<'a> FuncGen<'a> {
         self.mark_address_with_trap_code(TrapCode::BadSignature);
         self.assembler.emit_ud2();
 
+        self.assembler
+            .emit_label(self.special_labels.stack_overflow);
+        self.mark_address_with_trap_code(TrapCode::StackOverflow);
+        self.assembler.emit_ud2();
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.assembler.finalize_function();
 
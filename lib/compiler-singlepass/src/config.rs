@@ -32,7 +32,8 @@ impl Singlepass {
     /// to each function to prevent stack overflow.
     ///
     /// Note that this doesn't guarantee deterministic execution across
-    /// different platforms.
+    /// different platforms. Use the `StackLimit` middleware from the
+    /// `wasmer-middlewares` crate for a deterministic limit.
     pub fn enable_stack_check(&mut self, enable: bool) -> &mut Self {
         self.enable_stack_check = enable;
         self
@@ -54,6 +55,14 @@ impl CompilerConfig for Singlepass {
         // PIC code.
     }
 
+    fn enable_nan_canonicalization(&mut self) {
+        self.enable_nan_canonicalization = true;
+    }
+
+    fn canonicalize_nans(&mut self, enable: bool) {
+        self.enable_nan_canonicalization = enable;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(SinglepassCompiler::new(*self))
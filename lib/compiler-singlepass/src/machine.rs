@@ -325,6 +325,14 @@ impl Machine {
         }
     }
 
+    /// Returns the size (in bytes) of the "static area" allocated by
+    /// `init_locals` for `n` locals: each local takes a stack slot or
+    /// the save slot of its callee-saved register, and R15 is saved
+    /// for vmctx.
+    pub fn static_area_size(n: usize) -> usize {
+        (n + 1) * 8
+    }
+
     pub fn init_locals<E: Emitter>(
         &mut self,
         a: &mut E,
@@ -377,6 +385,7 @@ impl Machine {
 
         // Add size of locals on stack.
         static_area_size += num_mem_slots * 8;
+        debug_assert_eq!(static_area_size, Self::static_area_size(n));
 
         // Allocate save area, without actually writing to it.
         a.emit_sub(
@@ -82,6 +82,10 @@ pub trait Compiler: Send + MemoryUsage {
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
+    ///
+    /// When only deterministic features are enabled, the
+    /// non-deterministic proposals are rejected even if they are
+    /// enabled in `features`.
     fn validate_module<'data>(
         &self,
         features: &Features,
@@ -90,7 +94,7 @@ pub trait Compiler: Send + MemoryUsage {
         let mut validator = Validator::new();
         let wasm_features = WasmFeatures {
             bulk_memory: features.bulk_memory,
-            threads: features.threads,
+            threads: features.threads && !features.deterministic_only,
             reference_types: features.reference_types,
             multi_value: features.multi_value,
             simd: features.simd,
@@ -99,6 +103,9 @@ pub trait Compiler: Send + MemoryUsage {
             multi_memory: features.multi_memory,
             memory64: features.memory64,
             exceptions: features.exceptions,
+            // The NaN canonicalization done by the compilers already makes
+            // the floating-point operations deterministic, which wasmparser
+            // would reject otherwise.
             deterministic_only: false,
         };
         validator.wasm_features(wasm_features);
@@ -87,9 +87,10 @@ pub use crate::target::{
 };
 #[cfg(feature = "translator")]
 pub use crate::translator::{
-    translate_module, wptype_to_type, FunctionBinaryReader, FunctionBodyData, FunctionMiddleware,
-    MiddlewareBinaryReader, MiddlewareReaderState, ModuleEnvironment, ModuleInfoTranslation,
-    ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
+    add_local_function, push_added_function_bodies, translate_module, wptype_to_type,
+    AddedFunctionBody, FunctionBinaryReader, FunctionBodyData, FunctionMiddleware,
+    ImportedFunctions, MiddlewareBinaryReader, MiddlewareReaderState, ModuleEnvironment,
+    ModuleInfoTranslation, ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::CompiledFunctionUnwindInfo;
@@ -1,4 +1,6 @@
 use crate::lib::std::sync::Arc;
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -19,6 +21,7 @@ use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle};
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct CompileModuleInfo {
     /// The features used for compiling the module
     pub features: Features,
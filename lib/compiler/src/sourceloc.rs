@@ -8,6 +8,8 @@
 //! and tracing errors.
 
 use crate::lib::std::fmt;
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -27,6 +29,7 @@ use serde::{Deserialize, Serialize};
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
 pub struct SourceLoc(u32);
@@ -10,15 +10,17 @@ use crate::wasmparser::{Operator, Range, Type};
 use crate::{WasmError, WasmResult};
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
-use wasmer_types::entity::PrimaryMap;
-use wasmer_types::FunctionType;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     CustomSectionIndex, DataIndex, DataInitializer, DataInitializerLocation, ElemIndex,
-    ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
-    LocalFunctionIndex, MemoryIndex, MemoryType, SignatureIndex, TableIndex, TableInitializer,
-    TableType,
+    ExportIndex, ExternType, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
+    ImportIndex, InstanceIndex, LocalFunctionIndex, MemoryIndex, MemoryType, ModuleIndex,
+    SignatureIndex, TableIndex, TableInitializer, TableType,
+};
+use wasmer_vm::{
+    EntityType, InstanceDefinition, InstanceSignature, ModuleDefinition, ModuleInfo,
+    ModuleSignature, TypeDefinition,
 };
-use wasmer_vm::ModuleInfo;
 
 /// Contains function data: bytecode and its offset in the module.
 #[derive(Hash)]
@@ -68,11 +70,19 @@ pub struct ModuleInfoTranslation<'data> {
     /// References to the function bodies.
     pub function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
 
+    /// Offset of the contents of the code section relative to the module
+    /// file, or 0 if the module has no code section.
+    pub code_section_offset: usize,
+
     /// References to the data initializers.
     pub data_initializers: Vec<DataInitializer<'data>>,
 
     /// The decoded Wasm types for the module.
     pub module_translation_state: Option<ModuleTranslationState>,
+
+    /// The translations of the nested modules, with the module linking
+    /// proposal.
+    pub nested_modules: Vec<ModuleInfoTranslation<'data>>,
 }
 
 /// Object containing the standalone environment information.
@@ -89,8 +99,10 @@ impl<'data> ModuleEnvironment<'data> {
             result: ModuleInfoTranslation {
                 module: ModuleInfo::new(),
                 function_body_inputs: PrimaryMap::new(),
+                code_section_offset: 0,
                 data_initializers: Vec::new(),
                 module_translation_state: None,
+                nested_modules: Vec::new(),
             },
             imports: 0,
         }
@@ -136,10 +148,301 @@ impl<'data> ModuleEnvironment<'data> {
 
     pub(crate) fn declare_signature(&mut self, sig: FunctionType) -> WasmResult<()> {
         // TODO: Deduplicate signatures.
-        self.result.module.signatures.push(sig);
+        let sig_index = self.result.module.signatures.push(sig);
+        self.result
+            .module
+            .module_linking
+            .types
+            .push(TypeDefinition::Function(sig_index));
+        Ok(())
+    }
+
+    /// Declares an instance type, made of the given exports.
+    pub(crate) fn declare_instance_type(
+        &mut self,
+        exports: Vec<(String, EntityType)>,
+    ) -> WasmResult<()> {
+        let signature = self.push_instance_signature(exports);
+        self.result
+            .module
+            .module_linking
+            .types
+            .push(TypeDefinition::Instance(signature));
+        Ok(())
+    }
+
+    /// Declares a module type, made of the given imports and exports.
+    pub(crate) fn declare_module_type(
+        &mut self,
+        imports: Vec<(String, String, EntityType)>,
+        exports: Vec<(String, EntityType)>,
+    ) -> WasmResult<()> {
+        let signature = self.push_module_signature(imports, exports);
+        self.result
+            .module
+            .module_linking
+            .types
+            .push(TypeDefinition::Module(signature));
+        Ok(())
+    }
+
+    fn push_instance_signature(&mut self, exports: Vec<(String, EntityType)>) -> u32 {
+        let signatures = &mut self.result.module.module_linking.instance_signatures;
+        signatures.push(InstanceSignature { exports });
+        u32::try_from(signatures.len() - 1).unwrap()
+    }
+
+    fn push_module_signature(
+        &mut self,
+        imports: Vec<(String, String, EntityType)>,
+        exports: Vec<(String, EntityType)>,
+    ) -> u32 {
+        let signatures = &mut self.result.module.module_linking.module_signatures;
+        signatures.push(ModuleSignature { imports, exports });
+        u32::try_from(signatures.len() - 1).unwrap()
+    }
+
+    /// Returns the type definition of the given type index.
+    pub(crate) fn type_definition(&self, type_index: u32) -> WasmResult<TypeDefinition> {
+        self.result
+            .module
+            .module_linking
+            .types
+            .get(type_index as usize)
+            .cloned()
+            .ok_or_else(|| WasmError::Generic(format!("unknown type {}", type_index)))
+    }
+
+    /// Returns the signature of the function type of the given type index.
+    pub(crate) fn signature_index(&self, type_index: u32) -> WasmResult<SignatureIndex> {
+        match self.type_definition(type_index)? {
+            TypeDefinition::Function(sig_index) => Ok(sig_index),
+            _ => Err(WasmError::Generic(format!(
+                "type {} is not a function type",
+                type_index
+            ))),
+        }
+    }
+
+    /// Returns the type of the export of the given name of an instance.
+    pub(crate) fn instance_export(
+        &self,
+        instance_index: InstanceIndex,
+        name: &str,
+    ) -> WasmResult<EntityType> {
+        let module_linking = &self.result.module.module_linking;
+        module_linking
+            .instances
+            .get(instance_index)
+            .and_then(|signature| module_linking.instance_signatures.get(*signature as usize))
+            .and_then(|signature| signature.exports.iter().find(|(export, _)| export == name))
+            .map(|(_, ty)| ty.clone())
+            .ok_or_else(|| {
+                WasmError::Generic(format!(
+                    "unknown export `{}` of instance {}",
+                    name,
+                    instance_index.index()
+                ))
+            })
+    }
+
+    /// Converts the type of an import or an export of a nested module
+    /// into an entity type of this module, declaring the signatures it
+    /// needs.
+    fn entity_type_of(&mut self, ty: &ExternType) -> EntityType {
+        match ty {
+            ExternType::Function(function_type) => {
+                let signatures = &mut self.result.module.signatures;
+                let existing = signatures
+                    .iter()
+                    .find(|(_, signature)| *signature == function_type)
+                    .map(|(sig_index, _)| sig_index);
+                EntityType::Function(match existing {
+                    Some(sig_index) => sig_index,
+                    None => signatures.push(function_type.clone()),
+                })
+            }
+            ExternType::Table(table_type) => EntityType::Table(*table_type),
+            ExternType::Memory(memory_type) => EntityType::Memory(*memory_type),
+            ExternType::Global(global_type) => EntityType::Global(*global_type),
+            ExternType::Instance(instance_type) => {
+                let exports = instance_type
+                    .exports()
+                    .iter()
+                    .map(|export| (export.name().to_string(), self.entity_type_of(export.ty())))
+                    .collect();
+                EntityType::Instance(self.push_instance_signature(exports))
+            }
+            ExternType::Module(module_type) => {
+                let imports = module_type
+                    .imports()
+                    .iter()
+                    .map(|import| {
+                        (
+                            import.module().to_string(),
+                            import.name().to_string(),
+                            self.entity_type_of(import.ty()),
+                        )
+                    })
+                    .collect();
+                let exports = module_type
+                    .exports()
+                    .iter()
+                    .map(|export| (export.name().to_string(), self.entity_type_of(export.ty())))
+                    .collect();
+                EntityType::Module(self.push_module_signature(imports, exports))
+            }
+        }
+    }
+
+    /// Declares an import of an instance of the given signature.
+    pub(crate) fn declare_instance_import(
+        &mut self,
+        signature: u32,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        let module_linking = &mut self.result.module.module_linking;
+        let instance_index = module_linking.instances.push(signature);
+        module_linking
+            .instance_definitions
+            .push(InstanceDefinition::Imported);
+        self.declare_import(ImportIndex::Instance(instance_index), module, field)?;
+        self.imports += 1;
         Ok(())
     }
 
+    /// Declares an import of a module of the given signature.
+    pub(crate) fn declare_module_import(
+        &mut self,
+        signature: u32,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        let module_linking = &mut self.result.module.module_linking;
+        let module_index = module_linking.modules.push(signature);
+        module_linking
+            .module_definitions
+            .push(ModuleDefinition::Imported);
+        self.declare_import(ImportIndex::Module(module_index), module, field)?;
+        self.imports += 1;
+        Ok(())
+    }
+
+    /// Declares a module nested in this one, from its translation.
+    pub(crate) fn declare_nested_module(
+        &mut self,
+        translation: ModuleInfoTranslation<'data>,
+    ) -> WasmResult<()> {
+        let imports = translation
+            .module
+            .imports()
+            .map(|import| {
+                (
+                    import.module().to_string(),
+                    import.name().to_string(),
+                    self.entity_type_of(import.ty()),
+                )
+            })
+            .collect();
+        let exports = translation
+            .module
+            .exports()
+            .map(|export| (export.name().to_string(), self.entity_type_of(export.ty())))
+            .collect();
+        let signature = self.push_module_signature(imports, exports);
+
+        let nested_index = u32::try_from(self.result.nested_modules.len()).unwrap();
+        let module_linking = &mut self.result.module.module_linking;
+        module_linking.modules.push(signature);
+        module_linking
+            .module_definitions
+            .push(ModuleDefinition::Nested(nested_index));
+        module_linking.num_nested_modules += 1;
+        self.result.nested_modules.push(translation);
+        Ok(())
+    }
+
+    /// Declares an instance created by instantiating a module with the
+    /// given arguments.
+    pub(crate) fn declare_instantiation(
+        &mut self,
+        module_index: ModuleIndex,
+        args: Vec<(String, ExportIndex)>,
+    ) -> WasmResult<()> {
+        let module_linking = &self.result.module.module_linking;
+        let exports = module_linking
+            .modules
+            .get(module_index)
+            .map(|signature| {
+                module_linking.module_signatures[*signature as usize]
+                    .exports
+                    .clone()
+            })
+            .ok_or_else(|| {
+                WasmError::Generic(format!("unknown module {}", module_index.index()))
+            })?;
+        let signature = self.push_instance_signature(exports);
+
+        let module_linking = &mut self.result.module.module_linking;
+        module_linking.instances.push(signature);
+        module_linking
+            .instance_definitions
+            .push(InstanceDefinition::Instantiated {
+                module: module_index,
+                args,
+            });
+        Ok(())
+    }
+
+    /// Declares an alias of the export of the given name of an instance.
+    ///
+    /// The aliases of functions, tables, memories and globals take a
+    /// slot in the imports, so that they are resolved along with them
+    /// when instantiating the module.
+    pub(crate) fn declare_alias(
+        &mut self,
+        instance_index: InstanceIndex,
+        export: &str,
+    ) -> WasmResult<()> {
+        let ty = self.instance_export(instance_index, export)?;
+        let module_linking = &mut self.result.module.module_linking;
+        match ty {
+            EntityType::Instance(signature) => {
+                module_linking.instances.push(signature);
+                module_linking
+                    .instance_definitions
+                    .push(InstanceDefinition::Alias {
+                        instance: instance_index,
+                        export: export.to_string(),
+                    });
+                return Ok(());
+            }
+            EntityType::Module(signature) => {
+                module_linking.modules.push(signature);
+                module_linking
+                    .module_definitions
+                    .push(ModuleDefinition::Alias {
+                        instance: instance_index,
+                        export: export.to_string(),
+                    });
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        module_linking
+            .aliases
+            .insert(self.imports, (instance_index, export.to_string()));
+        match ty {
+            EntityType::Function(sig_index) => self.declare_func_import(sig_index, "", export),
+            EntityType::Table(table) => self.declare_table_import(table, "", export),
+            EntityType::Memory(memory) => self.declare_memory_import(memory, "", export),
+            EntityType::Global(global) => self.declare_global_import(global, "", export),
+            EntityType::Instance(_) | EntityType::Module(_) => unreachable!(),
+        }
+    }
+
     pub(crate) fn declare_func_import(
         &mut self,
         sig_index: SignatureIndex,
@@ -345,6 +648,22 @@ impl<'data> ModuleEnvironment<'data> {
         self.declare_export(ExportIndex::Global(global_index), name)
     }
 
+    pub(crate) fn declare_instance_export(
+        &mut self,
+        instance_index: InstanceIndex,
+        name: &str,
+    ) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Instance(instance_index), name)
+    }
+
+    pub(crate) fn declare_module_export(
+        &mut self,
+        module_index: ModuleIndex,
+        name: &str,
+    ) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Module(module_index), name)
+    }
+
     pub(crate) fn declare_start_function(&mut self, func_index: FunctionIndex) -> WasmResult<()> {
         debug_assert!(self.result.module.start_function.is_none());
         self.result.module.start_function = Some(func_index);
@@ -396,6 +715,11 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_code_section(&mut self, offset: usize) -> WasmResult<()> {
+        self.result.code_section_offset = offset;
+        Ok(())
+    }
+
     pub(crate) fn define_function_body(
         &mut self,
         _module_translation_state: &ModuleTranslationState,
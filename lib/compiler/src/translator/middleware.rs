@@ -5,13 +5,21 @@ use loupe::MemoryUsage;
 use smallvec::SmallVec;
 use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::mem;
 use std::ops::Deref;
-use wasmer_types::LocalFunctionIndex;
+use wasmer_types::{
+    ExportIndex, FunctionIndex, GlobalInit, ImportIndex, LocalFunctionIndex, SignatureIndex,
+};
 use wasmer_vm::ModuleInfo;
 use wasmparser::{BinaryReader, Operator, Range, Type};
 
 use crate::error::{MiddlewareError, WasmResult};
-use crate::translator::environ::FunctionBinaryReader;
+use crate::translator::environ::{FunctionBinaryReader, FunctionBodyData};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+
+/// The body of the local functions added by the middlewares, before
+/// the middlewares emit their operators: no locals, and an `end`.
+const ADDED_FUNCTION_BODY: &[u8] = &[0x00, 0x0b];
 
 /// A shared builder for function middlewares.
 pub trait ModuleMiddleware: Debug + Send + Sync + MemoryUsage {
@@ -25,12 +33,239 @@ pub trait ModuleMiddleware: Debug + Send + Sync + MemoryUsage {
         local_function_index: LocalFunctionIndex,
     ) -> Box<dyn FunctionMiddleware>;
 
+    /// Processes the bodies of the local functions. This is called before `transform_module_info`.
+    ///
+    /// `module_info` is the `ModuleInfo` of the original module, and
+    /// `code_section_offset` is the offset of the contents of the code
+    /// section relative to the module file.
+    fn feed_function_bodies(
+        &self,
+        _module_info: &ModuleInfo,
+        _code_section_offset: usize,
+        _function_bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+    }
+
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    ///
+    /// Besides editing the `ModuleInfo`, a middleware can import
+    /// functions with [`ImportedFunctions::insert`], and add local
+    /// functions with [`add_local_function`].
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// Returns the body of a local function added by this middleware
+    /// with [`add_local_function`], or `None` for the other functions.
+    ///
+    /// The added functions are only processed by the function
+    /// middlewares of the next middlewares in the chain.
+    fn added_function_body(
+        &self,
+        _local_function_index: LocalFunctionIndex,
+    ) -> Option<AddedFunctionBody> {
+        None
+    }
+}
+
+/// The body of a local function added by a middleware.
+#[derive(Debug, Clone, Default)]
+pub struct AddedFunctionBody {
+    /// The local declarations, as `(count, type)`.
+    pub locals: Vec<(u32, Type)>,
+
+    /// The operators of the body, including the final `end`.
+    pub operators: Vec<Operator<'static>>,
+}
+
+impl FunctionMiddleware for AddedFunctionBody {
+    fn declare_locals(&mut self) -> Vec<(u32, Type)> {
+        mem::take(&mut self.locals)
+    }
+
+    fn feed<'a>(
+        &mut self,
+        _operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // The original body is a single `end`.
+        for operator in self.operators.drain(..) {
+            state.push_operator(operator);
+        }
+        Ok(())
+    }
+}
+
+/// Adds a local function to a module, after its other local functions,
+/// and returns its index.
+///
+/// The body of the function is returned by
+/// [`ModuleMiddleware::added_function_body`].
+pub fn add_local_function(
+    module_info: &mut ModuleInfo,
+    signature_index: SignatureIndex,
+) -> LocalFunctionIndex {
+    let function_index = module_info.functions.push(signature_index);
+    module_info.local_func_index(function_index).unwrap()
+}
+
+/// Adds the bodies of the local functions added by the middlewares to
+/// the bodies of a module, once the middlewares have been applied on
+/// its `ModuleInfo`.
+pub fn push_added_function_bodies<'data>(
+    module_info: &ModuleInfo,
+    function_body_inputs: &mut PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+) {
+    let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+    for _ in function_body_inputs.len()..num_local_functions {
+        function_body_inputs.push(FunctionBodyData {
+            data: ADDED_FUNCTION_BODY,
+            module_offset: 0,
+        });
+    }
+}
+
+/// Functions imported by a middleware, after the functions already
+/// imported by the module.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+pub struct ImportedFunctions {
+    /// The number of functions imported before.
+    num_imported_functions: usize,
+
+    /// The number of functions imported by the middleware.
+    count: usize,
+}
+
+impl ImportedFunctions {
+    /// Imports functions from the given namespace, after the functions
+    /// already imported by a module, as `(field, signature)`.
+    ///
+    /// The indexes of the local functions are shifted by the number of
+    /// imported functions, in the `ModuleInfo`. The function
+    /// middlewares must update the operators they are fed with
+    /// [`Self::shift_operator`].
+    pub fn insert(
+        module_info: &mut ModuleInfo,
+        namespace: &str,
+        imports: &[(String, SignatureIndex)],
+    ) -> Self {
+        let imported = Self {
+            num_imported_functions: module_info.num_imported_functions,
+            count: imports.len(),
+        };
+        let shift = |function_index: &mut FunctionIndex| {
+            *function_index = imported.shift(*function_index);
+        };
+
+        let mut functions = PrimaryMap::with_capacity(module_info.functions.len() + imports.len());
+        for (function_index, signature_index) in module_info.functions.iter() {
+            if function_index.index() == imported.num_imported_functions {
+                functions.extend(imports.iter().map(|(_, signature_index)| *signature_index));
+            }
+            functions.push(*signature_index);
+        }
+        if module_info.functions.len() == imported.num_imported_functions {
+            functions.extend(imports.iter().map(|(_, signature_index)| *signature_index));
+        }
+        module_info.functions = functions;
+
+        for export in module_info.exports.values_mut() {
+            if let ExportIndex::Function(function_index) = export {
+                shift(function_index);
+            }
+        }
+        if let Some(function_index) = &mut module_info.start_function {
+            shift(function_index);
+        }
+        for table_initializer in &mut module_info.table_initializers {
+            table_initializer.elements.iter_mut().for_each(&shift);
+        }
+        for elements in module_info.passive_elements.values_mut() {
+            elements.iter_mut().for_each(&shift);
+        }
+        for global_initializer in module_info.global_initializers.values_mut() {
+            if let GlobalInit::RefFunc(function_index) = global_initializer {
+                shift(function_index);
+            }
+        }
+        module_info.function_names = module_info
+            .function_names
+            .drain()
+            .map(|(mut function_index, name)| {
+                shift(&mut function_index);
+                (function_index, name)
+            })
+            .collect();
+
+        let num_imports = module_info.imports.len() as u32;
+        for (n, (field, _)) in imports.iter().enumerate() {
+            module_info.imports.insert(
+                (namespace.to_string(), field.clone(), num_imports + n as u32),
+                ImportIndex::Function(imported.index(n)),
+            );
+        }
+        module_info.num_imported_functions += imports.len();
+
+        imported
+    }
+
+    /// Returns the index of the `n`th imported function.
+    pub fn index(&self, n: usize) -> FunctionIndex {
+        FunctionIndex::new(self.num_imported_functions + n)
+    }
+
+    /// Returns the index of a local function, once the functions have
+    /// been imported.
+    pub fn func_index(&self, local_function_index: LocalFunctionIndex) -> FunctionIndex {
+        FunctionIndex::new(self.num_imported_functions + self.count + local_function_index.index())
+    }
+
+    /// Returns the index of a function, once the functions have been
+    /// imported.
+    pub fn shift(&self, function_index: FunctionIndex) -> FunctionIndex {
+        if function_index.index() >= self.num_imported_functions {
+            FunctionIndex::new(function_index.index() + self.count)
+        } else {
+            function_index
+        }
+    }
+
+    /// Updates the function indexes used by an operator, once the
+    /// functions have been imported.
+    pub fn shift_operator<'a>(&self, operator: Operator<'a>) -> Operator<'a> {
+        let shift =
+            |function_index: u32| self.shift(FunctionIndex::from_u32(function_index)).as_u32();
+        match operator {
+            Operator::Call { function_index } => Operator::Call {
+                function_index: shift(function_index),
+            },
+            Operator::ReturnCall { function_index } => Operator::ReturnCall {
+                function_index: shift(function_index),
+            },
+            Operator::RefFunc { function_index } => Operator::RefFunc {
+                function_index: shift(function_index),
+            },
+            operator => operator,
+        }
+    }
 }
 
 /// A function middleware specialized for a single function.
 pub trait FunctionMiddleware: Debug {
+    /// Processes a declaration of `count` locals of the given type.
+    ///
+    /// All the local declarations of the function are processed before
+    /// its first operator.
+    fn feed_local_decl(&mut self, _count: u32, _ty: Type) {}
+
+    /// Declares locals, as `(count, type)`, after the locals of the
+    /// function and the ones declared by the previous middlewares in
+    /// the chain.
+    ///
+    /// This is called once, after all the local declarations of the
+    /// function have been processed.
+    fn declare_locals(&mut self) -> Vec<(u32, Type)> {
+        vec![]
+    }
+
     /// Processes the given operator.
     fn feed<'a>(
         &mut self,
@@ -60,6 +295,13 @@ pub struct MiddlewareReaderState<'a> {
 
     /// The pending operations added by the middleware.
     pending_operations: VecDeque<Operator<'a>>,
+
+    /// The offset of the original operator being processed.
+    operator_offset: usize,
+
+    /// The local declarations which have not been read yet, including
+    /// the ones declared by the middlewares.
+    local_decls: VecDeque<(u32, Type)>,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -70,6 +312,14 @@ pub trait ModuleMiddlewareChain {
         local_function_index: LocalFunctionIndex,
     ) -> Vec<Box<dyn FunctionMiddleware>>;
 
+    /// Feeds the function bodies to the chain.
+    fn apply_on_function_bodies(
+        &self,
+        module_info: &ModuleInfo,
+        code_section_offset: usize,
+        function_bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    );
+
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
 }
@@ -80,9 +330,39 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
         &self,
         local_function_index: LocalFunctionIndex,
     ) -> Vec<Box<dyn FunctionMiddleware>> {
-        self.iter()
-            .map(|x| x.generate_function_middleware(local_function_index))
-            .collect()
+        // A function added by a middleware is only processed by the
+        // next middlewares.
+        let added = self.iter().enumerate().find_map(|(position, x)| {
+            x.added_function_body(local_function_index)
+                .map(|body| (position, body))
+        });
+        match added {
+            Some((position, body)) => {
+                let mut chain: Vec<Box<dyn FunctionMiddleware>> = vec![Box::new(body)];
+                chain.extend(
+                    self[position + 1..]
+                        .iter()
+                        .map(|x| x.generate_function_middleware(local_function_index)),
+                );
+                chain
+            }
+            None => self
+                .iter()
+                .map(|x| x.generate_function_middleware(local_function_index))
+                .collect(),
+        }
+    }
+
+    /// Feeds the function bodies to the chain.
+    fn apply_on_function_bodies(
+        &self,
+        module_info: &ModuleInfo,
+        code_section_offset: usize,
+        function_bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        for item in self {
+            item.feed_function_bodies(module_info, code_section_offset, function_bodies);
+        }
     }
 
     /// Applies the chain on a `ModuleInfo` struct.
@@ -98,6 +378,15 @@ impl<'a> MiddlewareReaderState<'a> {
     pub fn push_operator(&mut self, operator: Operator<'a>) {
         self.pending_operations.push_back(operator);
     }
+
+    /// Returns the offset, in the module bytes, of the original operator
+    /// being processed.
+    ///
+    /// The operators pushed by a middleware have the offset of the
+    /// operator they were generated from.
+    pub fn operator_offset(&self) -> usize {
+        self.operator_offset
+    }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
@@ -120,6 +409,8 @@ impl<'a> MiddlewareBinaryReader<'a> {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                operator_offset: original_offset,
+                local_decls: VecDeque::new(),
             },
             chain: vec![],
         }
@@ -133,12 +424,38 @@ impl<'a> MiddlewareBinaryReader<'a> {
 
 impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
     fn read_local_count(&mut self) -> WasmResult<u32> {
-        Ok(self.state.inner.read_var_u32()?)
+        let count = self.state.inner.read_var_u32()?;
+        if self.chain.is_empty() {
+            return Ok(count);
+        }
+
+        // Read the local declarations ahead, to append the ones of the
+        // middlewares.
+        let mut local_decls = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let count = self.state.inner.read_var_u32()?;
+            let ty = self.state.inner.read_type()?;
+            local_decls.push((count, ty));
+        }
+        for stage in &mut self.chain {
+            for (count, ty) in &local_decls {
+                stage.feed_local_decl(*count, *ty);
+            }
+            local_decls.extend(stage.declare_locals());
+        }
+        self.state.local_decls = local_decls.into();
+        Ok(self.state.local_decls.len() as u32)
     }
 
     fn read_local_decl(&mut self) -> WasmResult<(u32, Type)> {
+        if let Some(local_decl) = self.state.local_decls.pop_front() {
+            return Ok(local_decl);
+        }
         let count = self.state.inner.read_var_u32()?;
         let ty = self.state.inner.read_type()?;
+        for stage in &mut self.chain {
+            stage.feed_local_decl(count, ty);
+        }
         Ok((count, ty))
     }
 
@@ -150,6 +467,7 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
 
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
+            self.state.operator_offset = self.state.inner.original_position();
             let raw_op = self.state.inner.read_operator()?;
 
             // Fill the initial raw operator into pending buffer.
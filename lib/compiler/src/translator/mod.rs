@@ -17,7 +17,8 @@ pub use self::environ::{
     FunctionBinaryReader, FunctionBodyData, ModuleEnvironment, ModuleInfoTranslation,
 };
 pub use self::middleware::{
-    FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleMiddleware,
+    add_local_function, push_added_function_bodies, AddedFunctionBody, FunctionMiddleware,
+    ImportedFunctions, MiddlewareBinaryReader, MiddlewareReaderState, ModuleMiddleware,
     ModuleMiddlewareChain,
 };
 pub use self::module::translate_module;
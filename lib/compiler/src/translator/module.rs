@@ -5,12 +5,13 @@
 //! to deal with each part of it.
 use super::environ::ModuleEnvironment;
 use super::sections::{
-    parse_data_section, parse_element_section, parse_export_section, parse_function_section,
-    parse_global_section, parse_import_section, parse_memory_section, parse_name_section,
-    parse_start_section, parse_table_section, parse_type_section,
+    parse_alias_section, parse_data_section, parse_element_section, parse_export_section,
+    parse_function_section, parse_global_section, parse_import_section, parse_instance_section,
+    parse_memory_section, parse_name_section, parse_start_section, parse_table_section,
+    parse_type_section,
 };
 use super::state::ModuleTranslationState;
-use crate::WasmResult;
+use crate::{wasm_unsupported, WasmResult};
 use wasmparser::{NameSectionReader, Parser, Payload};
 
 /// Translate a sequence of bytes forming a valid Wasm binary into a
@@ -20,9 +21,22 @@ pub fn translate_module<'data>(
     environ: &mut ModuleEnvironment<'data>,
 ) -> WasmResult<ModuleTranslationState> {
     let mut module_translation_state = ModuleTranslationState::new();
+    // The payloads of the nested modules are translated on their own,
+    // this is how deep we are in them.
+    let mut nested_depth = 0;
 
     for payload in Parser::new(0).parse_all(data) {
-        match payload? {
+        let payload = payload?;
+        if nested_depth > 0 {
+            match payload {
+                Payload::ModuleSectionEntry { .. } => nested_depth += 1,
+                Payload::End => nested_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        match payload {
             Payload::Version { .. } | Payload::End => {}
 
             Payload::TypeSection(types) => {
@@ -61,7 +75,9 @@ pub fn translate_module<'data>(
                 parse_element_section(elements, environ)?;
             }
 
-            Payload::CodeSectionStart { .. } => {}
+            Payload::CodeSectionStart { range, .. } => {
+                environ.declare_code_section(range.start)?;
+            }
             Payload::CodeSectionEntry(code) => {
                 let mut code = code.get_binary_reader();
                 let size = code.bytes_remaining();
@@ -81,12 +97,24 @@ pub fn translate_module<'data>(
                 environ.reserve_passive_data(count)?;
             }
 
-            Payload::InstanceSection(_)
-            | Payload::AliasSection(_)
-            | Payload::EventSection(_)
-            | Payload::ModuleSectionStart { .. }
-            | Payload::ModuleSectionEntry { .. } => {
-                unimplemented!("module linking not implemented yet")
+            Payload::AliasSection(aliases) => {
+                parse_alias_section(aliases, environ)?;
+            }
+
+            Payload::InstanceSection(instances) => {
+                parse_instance_section(instances, environ)?;
+            }
+
+            Payload::ModuleSectionStart { .. } => {}
+            Payload::ModuleSectionEntry { range, .. } => {
+                let translation =
+                    ModuleEnvironment::new().translate(&data[range.start..range.end])?;
+                environ.declare_nested_module(translation)?;
+                nested_depth = 1;
+            }
+
+            Payload::EventSection(_) => {
+                return Err(wasm_unsupported!("exceptions are not supported yet"));
             }
 
             Payload::CustomSection {
@@ -21,15 +21,19 @@ use std::vec::Vec;
 use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    DataIndex, ElemIndex, ExportIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit,
+    GlobalType, InstanceIndex, MemoryIndex, MemoryType, ModuleIndex, Pages, TableIndex, TableType,
+    Type, V128,
 };
+use wasmer_vm::{EntityType, TypeDefinition};
 use wasmparser::{
-    self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
-    ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
-    FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
-    ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    self, Alias, AliasSectionReader, Data, DataKind, DataSectionReader, Element, ElementItem,
+    ElementItems, ElementKind, ElementSectionReader, Export, ExportSectionReader,
+    ExportType as WPExportType, ExternalKind, FuncType as WPFunctionType, FunctionSectionReader,
+    GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType, ImportSectionReader,
+    InstanceArg, InstanceSectionReader, MemorySectionReader, MemoryType as WPMemoryType,
+    NameSectionReader, Naming, NamingReader, Operator, TableSectionReader, TypeDef,
+    TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -50,41 +54,130 @@ pub fn wptype_to_type(ty: wasmparser::Type) -> WasmResult<Type> {
 }
 
 /// Parses the Type section of the wasm module.
-pub fn parse_type_section(
-    types: TypeSectionReader,
+pub fn parse_type_section<'data>(
+    types: TypeSectionReader<'data>,
     module_translation_state: &mut ModuleTranslationState,
-    environ: &mut ModuleEnvironment,
+    environ: &mut ModuleEnvironment<'data>,
 ) -> WasmResult<()> {
     let count = types.get_count();
     environ.reserve_signatures(count)?;
 
     for entry in types {
-        if let Ok(TypeDef::Func(WPFunctionType { params, returns })) = entry {
-            let sig_params: Vec<Type> = params
-                .iter()
-                .map(|ty| {
-                    wptype_to_type(*ty)
-                        .expect("only numeric types are supported in function signatures")
-                })
-                .collect();
-            let sig_returns: Vec<Type> = returns
-                .iter()
-                .map(|ty| {
-                    wptype_to_type(*ty)
-                        .expect("only numeric types are supported in function signatures")
-                })
-                .collect();
-            let sig = FunctionType::new(sig_params, sig_returns);
-            environ.declare_signature(sig)?;
-            module_translation_state.wasm_types.push((params, returns));
-        } else {
-            unimplemented!("module linking not implemented yet")
+        match entry? {
+            TypeDef::Func(WPFunctionType { params, returns }) => {
+                let sig_params: Vec<Type> = params
+                    .iter()
+                    .map(|ty| {
+                        wptype_to_type(*ty)
+                            .expect("only numeric types are supported in function signatures")
+                    })
+                    .collect();
+                let sig_returns: Vec<Type> = returns
+                    .iter()
+                    .map(|ty| {
+                        wptype_to_type(*ty)
+                            .expect("only numeric types are supported in function signatures")
+                    })
+                    .collect();
+                let sig = FunctionType::new(sig_params, sig_returns);
+                environ.declare_signature(sig)?;
+                module_translation_state.wasm_types.push((params, returns));
+            }
+            TypeDef::Instance(instance_type) => {
+                let exports = export_types(environ, &instance_type.exports)?;
+                environ.declare_instance_type(exports)?;
+                module_translation_state
+                    .wasm_types
+                    .push((Box::new([]), Box::new([])));
+            }
+            TypeDef::Module(module_type) => {
+                let imports = module_type
+                    .imports
+                    .iter()
+                    .map(|import| {
+                        Ok((
+                            import.module.to_string(),
+                            import.field.unwrap_or_default().to_string(),
+                            entity_type(environ, import.ty)?,
+                        ))
+                    })
+                    .collect::<WasmResult<Vec<_>>>()?;
+                let exports = export_types(environ, &module_type.exports)?;
+                environ.declare_module_type(imports, exports)?;
+                module_translation_state
+                    .wasm_types
+                    .push((Box::new([]), Box::new([])));
+            }
         }
     }
 
     Ok(())
 }
 
+/// Converts the types of the exports of an instance or module type.
+fn export_types(
+    environ: &ModuleEnvironment,
+    exports: &[WPExportType],
+) -> WasmResult<Vec<(String, EntityType)>> {
+    exports
+        .iter()
+        .map(|export| Ok((export.name.to_string(), entity_type(environ, export.ty)?)))
+        .collect()
+}
+
+/// Converts the type of an entity imported or exported by an instance
+/// or module type.
+fn entity_type(environ: &ModuleEnvironment, ty: ImportSectionEntryType) -> WasmResult<EntityType> {
+    Ok(match ty {
+        ImportSectionEntryType::Function(type_index) => {
+            EntityType::Function(environ.signature_index(type_index)?)
+        }
+        ImportSectionEntryType::Table(ref tab) => EntityType::Table(TableType {
+            ty: wptype_to_type(tab.element_type)?,
+            minimum: tab.limits.initial,
+            maximum: tab.limits.maximum,
+        }),
+        ImportSectionEntryType::Memory(WPMemoryType::M32 {
+            limits: ref memlimits,
+            shared,
+        }) => EntityType::Memory(MemoryType {
+            minimum: Pages(memlimits.initial),
+            maximum: memlimits.maximum.map(Pages),
+            shared,
+        }),
+        ImportSectionEntryType::Memory(WPMemoryType::M64 { .. }) => {
+            return Err(wasm_unsupported!("64bit memory not implemented yet"))
+        }
+        ImportSectionEntryType::Global(ref ty) => EntityType::Global(GlobalType {
+            ty: wptype_to_type(ty.content_type)?,
+            mutability: ty.mutable.into(),
+        }),
+        ImportSectionEntryType::Instance(type_index) => {
+            match environ.type_definition(type_index)? {
+                TypeDefinition::Instance(signature) => EntityType::Instance(signature),
+                _ => {
+                    return Err(WasmError::Generic(format!(
+                        "type {} is not an instance type",
+                        type_index
+                    )))
+                }
+            }
+        }
+        ImportSectionEntryType::Module(type_index) => match environ.type_definition(type_index)? {
+            TypeDefinition::Module(signature) => EntityType::Module(signature),
+            _ => {
+                return Err(WasmError::Generic(format!(
+                    "type {} is not a module type",
+                    type_index
+                )))
+            }
+        },
+        ImportSectionEntryType::Event(_) => {
+            return Err(wasm_unsupported!("exceptions are not supported yet"))
+        }
+    })
+}
+
 /// Parses the Import section of the wasm module.
 pub fn parse_import_section<'data>(
     imports: ImportSectionReader<'data>,
@@ -95,58 +188,28 @@ pub fn parse_import_section<'data>(
     for entry in imports {
         let import = entry?;
         let module_name = import.module;
-        let field_name = import.field;
-
-        match import.ty {
-            ImportSectionEntryType::Function(sig) => {
-                environ.declare_func_import(
-                    SignatureIndex::from_u32(sig),
-                    module_name,
-                    field_name.unwrap_or_default(),
-                )?;
+        // The imports of the module linking proposal may have no
+        // field name, they are then imported with an empty one.
+        let field_name = import.field.unwrap_or_default();
+
+        match entity_type(environ, import.ty)? {
+            EntityType::Function(sig_index) => {
+                environ.declare_func_import(sig_index, module_name, field_name)?
             }
-            ImportSectionEntryType::Module(_)
-            | ImportSectionEntryType::Instance(_)
-            | ImportSectionEntryType::Event(_) => {
-                unimplemented!("module linking not implemented yet")
+            EntityType::Table(table) => {
+                environ.declare_table_import(table, module_name, field_name)?
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M32 {
-                limits: ref memlimits,
-                shared,
-            }) => {
-                environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(memlimits.initial),
-                        maximum: memlimits.maximum.map(Pages),
-                        shared,
-                    },
-                    module_name,
-                    field_name.unwrap_or_default(),
-                )?;
+            EntityType::Memory(memory) => {
+                environ.declare_memory_import(memory, module_name, field_name)?
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M64 { .. }) => {
-                unimplemented!("64bit memory not implemented yet")
+            EntityType::Global(global) => {
+                environ.declare_global_import(global, module_name, field_name)?
             }
-            ImportSectionEntryType::Global(ref ty) => {
-                environ.declare_global_import(
-                    GlobalType {
-                        ty: wptype_to_type(ty.content_type).unwrap(),
-                        mutability: ty.mutable.into(),
-                    },
-                    module_name,
-                    field_name.unwrap_or_default(),
-                )?;
+            EntityType::Instance(signature) => {
+                environ.declare_instance_import(signature, module_name, field_name)?
             }
-            ImportSectionEntryType::Table(ref tab) => {
-                environ.declare_table_import(
-                    TableType {
-                        ty: wptype_to_type(tab.element_type).unwrap(),
-                        minimum: tab.limits.initial,
-                        maximum: tab.limits.maximum,
-                    },
-                    module_name,
-                    field_name.unwrap_or_default(),
-                )?;
+            EntityType::Module(signature) => {
+                environ.declare_module_import(signature, module_name, field_name)?
             }
         }
     }
@@ -155,6 +218,66 @@ pub fn parse_import_section<'data>(
     Ok(())
 }
 
+/// Parses the Alias section of the wasm module.
+///
+/// Only the aliases of the exports of the instances are supported,
+/// the aliases of the types and modules of the outer modules are not.
+pub fn parse_alias_section<'data>(
+    aliases: AliasSectionReader<'data>,
+    environ: &mut ModuleEnvironment<'data>,
+) -> WasmResult<()> {
+    for entry in aliases {
+        match entry? {
+            Alias::InstanceExport {
+                instance, export, ..
+            } => environ.declare_alias(InstanceIndex::from_u32(instance), export)?,
+            Alias::OuterType { .. } | Alias::OuterModule { .. } => {
+                return Err(wasm_unsupported!(
+                    "module linking: aliases of outer modules are not supported yet"
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the Instance section of the wasm module.
+///
+/// The arguments of the instantiations can only be imported or
+/// aliased entities, as the local functions, tables, memories and
+/// globals are not defined yet when instantiating the module.
+pub fn parse_instance_section<'data>(
+    instances: InstanceSectionReader<'data>,
+    environ: &mut ModuleEnvironment<'data>,
+) -> WasmResult<()> {
+    for entry in instances {
+        let instance = entry?;
+        let mut args = Vec::new();
+        for arg in instance.args()? {
+            let InstanceArg { name, kind, index } = arg?;
+            let index = match kind {
+                ExternalKind::Function => ExportIndex::Function(FunctionIndex::from_u32(index)),
+                ExternalKind::Table => ExportIndex::Table(TableIndex::from_u32(index)),
+                ExternalKind::Memory => ExportIndex::Memory(MemoryIndex::from_u32(index)),
+                ExternalKind::Global => ExportIndex::Global(GlobalIndex::from_u32(index)),
+                ExternalKind::Instance => ExportIndex::Instance(InstanceIndex::from_u32(index)),
+                ExternalKind::Module => ExportIndex::Module(ModuleIndex::from_u32(index)),
+                ExternalKind::Type | ExternalKind::Event => {
+                    return Err(wasm_unsupported!(
+                        "module linking: instantiation arguments of {:?} are not supported yet",
+                        kind
+                    ))
+                }
+            };
+            args.push((name.to_string(), index));
+        }
+        environ.declare_instantiation(ModuleIndex::from_u32(instance.module()), args)?;
+    }
+
+    Ok(())
+}
+
 /// Parses the Function section of the wasm module.
 pub fn parse_function_section(
     functions: FunctionSectionReader,
@@ -169,8 +292,9 @@ pub fn parse_function_section(
     environ.reserve_func_types(num_functions)?;
 
     for entry in functions {
-        let sigindex = entry?;
-        environ.declare_func_type(SignatureIndex::from_u32(sigindex))?;
+        let type_index = entry?;
+        let sig_index = environ.signature_index(type_index)?;
+        environ.declare_func_type(sig_index)?;
     }
 
     Ok(())
@@ -294,11 +418,19 @@ pub fn parse_export_section<'data>(
             ExternalKind::Global => {
                 environ.declare_global_export(GlobalIndex::new(index), field)?
             }
-            ExternalKind::Type
-            | ExternalKind::Module
-            | ExternalKind::Instance
-            | ExternalKind::Event => {
-                unimplemented!("module linking not implemented yet")
+            ExternalKind::Instance => {
+                environ.declare_instance_export(InstanceIndex::new(index), field)?
+            }
+            ExternalKind::Module => {
+                environ.declare_module_export(ModuleIndex::new(index), field)?
+            }
+            ExternalKind::Type => {
+                return Err(wasm_unsupported!(
+                    "module linking: exports of types are not supported yet"
+                ))
+            }
+            ExternalKind::Event => {
+                return Err(wasm_unsupported!("exceptions are not supported yet"))
             }
         }
     }
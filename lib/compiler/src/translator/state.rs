@@ -3,12 +3,11 @@
 
 use crate::{wasm_unsupported, WasmResult};
 use std::boxed::Box;
-use wasmer_types::entity::PrimaryMap;
-use wasmer_types::SignatureIndex;
+use std::vec::Vec;
 
-/// Map of signatures to a function's parameter and return types.
-pub(crate) type WasmTypes =
-    PrimaryMap<SignatureIndex, (Box<[wasmparser::Type]>, Box<[wasmparser::Type]>)>;
+/// The parameter and return types of the function types, by type
+/// index. The instance and module types have neither.
+pub(crate) type WasmTypes = Vec<(Box<[wasmparser::Type]>, Box<[wasmparser::Type]>)>;
 
 /// Contains information decoded from the Wasm module that must be referenced
 /// during each Wasm function's translation.
@@ -29,7 +28,7 @@ impl ModuleTranslationState {
     /// Creates a new empty ModuleTranslationState.
     pub fn new() -> Self {
         Self {
-            wasm_types: PrimaryMap::new(),
+            wasm_types: Vec::new(),
         }
     }
 
@@ -51,8 +50,7 @@ impl ModuleTranslationState {
                 ty => return Err(wasm_unsupported!("blocktype_params_results: type {:?}", ty)),
             },
             wasmparser::TypeOrFuncType::FuncType(ty_index) => {
-                let sig_idx = SignatureIndex::from_u32(ty_index);
-                let (ref params, ref results) = self.wasm_types[sig_idx];
+                let (ref params, ref results) = self.wasm_types[ty_index as usize];
                 (&*params, &*results)
             }
         })
@@ -15,14 +15,14 @@ use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
 #[cfg(feature = "compiler")]
 use tracing::trace;
+#[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileError, CompiledFunctionFrameInfo, Features, FunctionAddressMap, OperatingSystem, Symbol,
-    SymbolRegistry, Triple,
+    push_added_function_bodies, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    ModuleMiddlewareChain, ModuleTranslationState,
 };
-#[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment, ModuleMiddlewareChain,
-    ModuleTranslationState,
+    CompileError, CompiledFunctionFrameInfo, Features, FunctionAddressMap, OperatingSystem, Symbol,
+    SymbolRegistry, Triple,
 };
 use wasmer_engine::{
     register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
@@ -123,11 +123,23 @@ impl DylibArtifact {
     > {
         let environ = ModuleEnvironment::new();
         let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        if !translation.nested_modules.is_empty() {
+            return Err(CompileError::UnsupportedFeature(
+                "module linking: nested modules with the dylib engine".to_string(),
+            ));
+        }
 
         // We try to apply the middleware first
-        let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_function_bodies(
+            &translation.module,
+            translation.code_section_offset,
+            &translation.function_body_inputs,
+        );
+        let mut module = translation.module;
         middlewares.apply_on_module_info(&mut module);
+        let mut function_body_inputs = translation.function_body_inputs;
+        push_added_function_bodies(&module, &mut function_body_inputs);
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -148,7 +160,7 @@ impl DylibArtifact {
         };
         Ok((
             compile_info,
-            translation.function_body_inputs,
+            function_body_inputs,
             translation.data_initializers,
             translation.module_translation_state,
         ))
@@ -8,12 +8,12 @@ use std::collections::BTreeMap;
 use std::error::Error;
 use std::mem;
 use std::sync::Arc;
-use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, Triple};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment, ModuleMiddlewareChain,
-    ModuleTranslationState,
+    push_added_function_bodies, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    ModuleMiddlewareChain, ModuleTranslationState,
 };
+use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, Triple};
 use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
@@ -115,11 +115,23 @@ impl StaticlibArtifact {
     > {
         let environ = ModuleEnvironment::new();
         let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        if !translation.nested_modules.is_empty() {
+            return Err(CompileError::UnsupportedFeature(
+                "module linking: nested modules with the staticlib engine".to_string(),
+            ));
+        }
 
         // We try to apply the middleware first
-        let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_function_bodies(
+            &translation.module,
+            translation.code_section_offset,
+            &translation.function_body_inputs,
+        );
+        let mut module = translation.module;
         middlewares.apply_on_module_info(&mut module);
+        let mut function_body_inputs = translation.function_body_inputs;
+        push_added_function_bodies(&module, &mut function_body_inputs);
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -140,7 +152,7 @@ impl StaticlibArtifact {
         };
         Ok((
             compile_info,
-            translation.function_body_inputs,
+            function_body_inputs,
             translation.data_initializers,
             translation.module_translation_state,
         ))
@@ -51,11 +51,14 @@ impl Staticlib {
         if let Some(_compiler_config) = self.compiler_config {
             #[cfg(feature = "compiler")]
             {
-                let compiler_config = _compiler_config;
+                let mut compiler_config = _compiler_config;
                 let target = self.target.unwrap_or_default();
                 let features = self
                     .features
                     .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                if features.deterministic_only {
+                    compiler_config.canonicalize_nans(true);
+                }
                 let compiler = compiler_config.compiler();
                 StaticlibEngine::new(compiler, target, features)
             }
@@ -7,10 +7,15 @@ use crate::link::link_module;
 use crate::serialize::SerializableCompilation;
 use crate::serialize::SerializableModule;
 use loupe::MemoryUsage;
+#[cfg(feature = "compiler")]
+use std::mem;
 use std::sync::{Arc, Mutex};
-use wasmer_compiler::{CompileError, Features, Triple};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment, ModuleMiddlewareChain};
+use wasmer_compiler::{
+    push_added_function_bodies, CompileModuleInfo, ModuleEnvironment, ModuleInfoTranslation,
+    ModuleMiddlewareChain,
+};
+use wasmer_compiler::{CompileError, Features, Triple};
 use wasmer_engine::{
     register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
     SerializeError,
@@ -19,12 +24,12 @@ use wasmer_engine::{
 use wasmer_engine::{Engine, Tunables};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 use wasmer_types::{
-    FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer, SignatureIndex,
-    TableIndex,
+    DataInitializer, FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer,
+    SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex,
-    VMTrampoline,
+    FuncDataRegistry, FunctionBodyPtr, MemoryImages, MemoryStyle, ModuleInfo, TableStyle,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 const SERIALIZED_METADATA_LENGTH_OFFSET: usize = 22;
@@ -42,10 +47,18 @@ pub struct UniversalArtifact {
     func_data_registry: Arc<FuncDataRegistry>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    #[loupe(skip)]
+    memory_images: MemoryImages,
+    #[loupe(skip)]
+    nested_artifacts: Vec<Arc<dyn Artifact>>,
 }
 
 impl UniversalArtifact {
-    const MAGIC_HEADER: &'static [u8; 22] = b"\0wasmer-universal\0\0\0\0\0";
+    /// The header of the serialized artifacts. Its last byte is the
+    /// version of their format: bump it whenever the layout of the
+    /// serialized metadata changes, so that older artifacts are
+    /// rejected instead of being misread.
+    const MAGIC_HEADER: &'static [u8; 22] = b"\0wasmer-universal\0\0\0\0\x02";
 
     /// Check if the provided bytes look like a serialized `UniversalArtifact`.
     pub fn is_deserializable(bytes: &[u8]) -> bool {
@@ -59,18 +72,49 @@ impl UniversalArtifact {
         data: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Self, CompileError> {
-        let environ = ModuleEnvironment::new();
+        let translation = ModuleEnvironment::new()
+            .translate(data)
+            .map_err(CompileError::Wasm)?;
+        Self::compile(engine, translation, tunables)
+    }
+
+    /// Compile a translated module into a `UniversalArtifact`, along
+    /// with the modules nested in it.
+    #[cfg(feature = "compiler")]
+    fn compile(
+        engine: &UniversalEngine,
+        mut translation: ModuleInfoTranslation,
+        tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        let nested_artifacts = mem::take(&mut translation.nested_modules)
+            .into_iter()
+            .map(|nested| {
+                let artifact = Self::compile(engine, nested, tunables)?;
+                Ok(Arc::new(artifact) as Arc<dyn Artifact>)
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let nested_modules = nested_artifacts
+            .iter()
+            .map(|artifact| artifact.serialize())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CompileError::Codegen(format!("{}", e)))?;
+
         let mut inner_engine = engine.inner_mut();
         let features = inner_engine.features();
 
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
-
         let compiler = inner_engine.compiler()?;
 
         // We try to apply the middleware first
-        let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_function_bodies(
+            &translation.module,
+            translation.code_section_offset,
+            &translation.function_body_inputs,
+        );
+        let mut module = translation.module;
         middlewares.apply_on_module_info(&mut module);
+        let mut function_body_inputs = translation.function_body_inputs;
+        push_added_function_bodies(&module, &mut function_body_inputs);
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -98,7 +142,7 @@ impl UniversalArtifact {
             // `environ.translate()` above will write some data into
             // `module_translation_state`.
             translation.module_translation_state.as_ref().unwrap(),
-            translation.function_body_inputs,
+            function_body_inputs,
         )?;
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
@@ -127,8 +171,9 @@ impl UniversalArtifact {
             compilation: serializable_compilation,
             compile_info,
             data_initializers,
+            nested_modules,
         };
-        Self::from_parts(&mut inner_engine, serializable)
+        Self::from_parts_with_nested(&mut inner_engine, serializable, nested_artifacts)
     }
 
     /// Compile a data buffer into a `UniversalArtifact`, which may then be instantiated.
@@ -148,10 +193,60 @@ impl UniversalArtifact {
         universal: &UniversalEngine,
         bytes: &[u8],
     ) -> Result<Self, DeserializeError> {
+        let metadata_slice = Self::metadata_slice(bytes)?;
+        let serializable = SerializableModule::deserialize(metadata_slice)?;
+        let nested_artifacts = serializable
+            .nested_modules
+            .iter()
+            .map(|bytes| {
+                let artifact = Self::deserialize(universal, bytes)?;
+                Ok(Arc::new(artifact) as Arc<dyn Artifact>)
+            })
+            .collect::<Result<Vec<_>, DeserializeError>>()?;
+        Self::from_parts_with_nested(&mut universal.inner_mut(), serializable, nested_artifacts)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Deserialize a UniversalArtifact, validating the serialized data
+    /// before loading it.
+    ///
+    /// The archived metadata is checked structurally and every relocation,
+    /// jump table and trap offset is checked to fall within the code it
+    /// refers to, so malformed or truncated bytes are rejected with an
+    /// error rather than corrupting memory.
+    ///
+    /// # Safety
+    /// The machine code contained in the artifact is still executed as-is,
+    /// so this function can't guarantee that running it is safe.
+    pub unsafe fn deserialize_checked(
+        universal: &UniversalEngine,
+        bytes: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        let metadata_slice = Self::metadata_slice(bytes)?;
+        let serializable = SerializableModule::deserialize_checked(metadata_slice)?;
+        let nested_artifacts = serializable
+            .nested_modules
+            .iter()
+            .map(|bytes| {
+                let artifact = Self::deserialize_checked(universal, bytes)?;
+                Ok(Arc::new(artifact) as Arc<dyn Artifact>)
+            })
+            .collect::<Result<Vec<_>, DeserializeError>>()?;
+        Self::from_parts_with_nested(&mut universal.inner_mut(), serializable, nested_artifacts)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Get the serialized metadata contained in the given bytes, checking
+    /// the header and that the metadata fits in the provided bytes.
+    fn metadata_slice(bytes: &[u8]) -> Result<&[u8], DeserializeError> {
         if !Self::is_deserializable(bytes) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not wasmer-universal".to_string(),
-            ));
+            let (name, _version) = Self::MAGIC_HEADER.split_at(Self::MAGIC_HEADER.len() - 1);
+            let message = if bytes.starts_with(name) {
+                "The provided bytes were serialized with another version of wasmer-universal"
+            } else {
+                "The provided bytes are not wasmer-universal"
+            };
+            return Err(DeserializeError::Incompatible(message.to_string()));
         }
 
         let mut inner_bytes = &bytes[SERIALIZED_METADATA_LENGTH_OFFSET..];
@@ -159,21 +254,48 @@ impl UniversalArtifact {
         let metadata_len = leb128::read::unsigned(&mut inner_bytes).map_err(|_e| {
             DeserializeError::CorruptedBinary("Can't read metadata size".to_string())
         })?;
-        let metadata_slice: &[u8] = std::slice::from_raw_parts(
-            &bytes[SERIALIZED_METADATA_CONTENT_OFFSET] as *const u8,
-            metadata_len as usize,
-        );
-
-        let serializable = SerializableModule::deserialize(metadata_slice)?;
-        Self::from_parts(&mut universal.inner_mut(), serializable)
-            .map_err(DeserializeError::Compiler)
+        (metadata_len as usize)
+            .checked_add(SERIALIZED_METADATA_CONTENT_OFFSET)
+            .and_then(|end| bytes.get(SERIALIZED_METADATA_CONTENT_OFFSET..end))
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary(
+                    "The metadata size exceeds the provided bytes".to_string(),
+                )
+            })
     }
 
     /// Construct a `UniversalArtifact` from component parts.
+    ///
+    /// The module must not have nested modules, see
+    /// [`UniversalArtifact::from_parts_with_nested`].
     pub fn from_parts(
         inner_engine: &mut UniversalEngineInner,
         serializable: SerializableModule,
     ) -> Result<Self, CompileError> {
+        Self::from_parts_with_nested(inner_engine, serializable, Vec::new())
+    }
+
+    /// Construct a `UniversalArtifact` from component parts, and the
+    /// artifacts of the modules nested in it with the module linking
+    /// proposal.
+    pub fn from_parts_with_nested(
+        inner_engine: &mut UniversalEngineInner,
+        serializable: SerializableModule,
+        nested_artifacts: Vec<Arc<dyn Artifact>>,
+    ) -> Result<Self, CompileError> {
+        let num_nested_modules = serializable
+            .compile_info
+            .module
+            .module_linking
+            .num_nested_modules;
+        if nested_artifacts.len() != num_nested_modules as usize {
+            return Err(CompileError::Codegen(format!(
+                "expected {} nested modules, got {}",
+                num_nested_modules,
+                nested_artifacts.len()
+            )));
+        }
+
         let (
             finished_functions,
             finished_function_call_trampolines,
@@ -243,6 +365,19 @@ impl UniversalArtifact {
         let signatures = signatures.into_boxed_slice();
         let func_data_registry = inner_engine.func_data().clone();
 
+        // Lay out the data segments once, to be mapped copy-on-write
+        // into the memories of every instance.
+        let data_initializers = serializable
+            .data_initializers
+            .iter()
+            .map(|init| DataInitializer {
+                location: init.location.clone(),
+                data: &*init.data,
+            })
+            .collect::<Vec<_>>();
+        let memory_images =
+            MemoryImages::new(&serializable.compile_info.module, &data_initializers);
+
         Ok(Self {
             serializable,
             finished_functions,
@@ -252,6 +387,8 @@ impl UniversalArtifact {
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
             func_data_registry,
+            memory_images,
+            nested_artifacts,
         })
     }
 
@@ -308,6 +445,10 @@ impl Artifact for UniversalArtifact {
         &*self.serializable.data_initializers
     }
 
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        Some(&self.memory_images)
+    }
+
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
         &self.serializable.compile_info.memory_styles
     }
@@ -335,6 +476,10 @@ impl Artifact for UniversalArtifact {
     fn func_data_registry(&self) -> &FuncDataRegistry {
         &self.func_data_registry
     }
+
+    fn nested_artifacts(&self) -> &[Arc<dyn Artifact>] {
+        &self.nested_artifacts
+    }
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         // Prepend the header.
         let mut serialized = Self::MAGIC_HEADER.to_vec();
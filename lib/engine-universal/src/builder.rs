@@ -47,10 +47,13 @@ impl Universal {
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> UniversalEngine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
+        if let Some(mut compiler_config) = self.compiler_config {
             let features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+            if features.deterministic_only {
+                compiler_config.canonicalize_nans(true);
+            }
             let compiler = compiler_config.compiler();
             UniversalEngine::new(compiler, target, features)
         } else {
@@ -136,6 +136,16 @@ impl Engine for UniversalEngine {
         Ok(Arc::new(UniversalArtifact::deserialize(&self, &bytes)?))
     }
 
+    /// Deserializes a WebAssembly module, validating it first
+    unsafe fn deserialize_checked(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        Ok(Arc::new(UniversalArtifact::deserialize_checked(
+            &self, &bytes,
+        )?))
+    }
+
     fn id(&self) -> &EngineId {
         &self.engine_id
     }
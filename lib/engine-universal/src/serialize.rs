@@ -1,6 +1,7 @@
+use bytecheck::CheckBytes;
 use loupe::MemoryUsage;
 use rkyv::{
-    archived_value,
+    archived_value, check_archived_value,
     de::{adapters::SharedDeserializerAdapter, deserializers::AllocDeserializer},
     ser::adapters::SharedSerializerAdapter,
     ser::{serializers::WriteSerializer, Serializer as RkyvSerializer},
@@ -8,14 +9,19 @@ use rkyv::{
 };
 use wasmer_compiler::{
     CompileModuleInfo, CompiledFunctionFrameInfo, CustomSection, Dwarf, FunctionBody,
-    JumpTableOffsets, Relocation, SectionIndex,
+    JumpTableOffsets, Relocation, RelocationKind, RelocationTarget, SectionIndex,
 };
 use wasmer_engine::{DeserializeError, SerializeError};
-use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{FunctionIndex, LocalFunctionIndex, OwnedDataInitializer, SignatureIndex};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, ImportIndex, InstanceIndex,
+    LocalFunctionIndex, MemoryIndex, ModuleIndex, OwnedDataInitializer, SignatureIndex, TableIndex,
+};
+use wasmer_vm::{EntityType, InstanceDefinition, ModuleDefinition, ModuleInfo, TypeDefinition};
 
 /// The compilation related data for a serialized modules
 #[derive(MemoryUsage, Archive, RkyvDeserialize, RkyvSerialize)]
+#[archive(derive(CheckBytes))]
 pub struct SerializableCompilation {
     pub function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBody>,
     pub function_relocations: PrimaryMap<LocalFunctionIndex, Vec<Relocation>>,
@@ -32,16 +38,40 @@ pub struct SerializableCompilation {
 /// Serializable struct that is able to serialize from and to
 /// a `UniversalArtifactInfo`.
 #[derive(MemoryUsage, Archive, RkyvDeserialize, RkyvSerialize)]
+#[archive(derive(CheckBytes))]
 pub struct SerializableModule {
     pub compilation: SerializableCompilation,
     pub compile_info: CompileModuleInfo,
     pub data_initializers: Box<[OwnedDataInitializer]>,
+    // The serialized artifacts of the nested modules, with the module
+    // linking proposal
+    pub nested_modules: Vec<Vec<u8>>,
 }
 
 fn to_serialize_error(err: impl std::error::Error) -> SerializeError {
     SerializeError::Generic(format!("{}", err))
 }
 
+fn corrupted(message: String) -> DeserializeError {
+    DeserializeError::CorruptedBinary(message)
+}
+
+/// The number of bytes patched in the code by a relocation of the given kind.
+fn relocation_size(kind: RelocationKind) -> usize {
+    match kind {
+        RelocationKind::Abs8 | RelocationKind::X86PCRel8 => 8,
+        RelocationKind::Abs4
+        | RelocationKind::X86PCRel4
+        | RelocationKind::X86PCRelRodata4
+        | RelocationKind::X86CallPCRel4
+        | RelocationKind::X86CallPLTRel4
+        | RelocationKind::X86GOTPCRel4
+        | RelocationKind::Arm32Call
+        | RelocationKind::Arm64Call
+        | RelocationKind::ElfX86_64TlsGd => 4,
+    }
+}
+
 impl SerializableModule {
     /// Serialize a Module into bytes
     /// The bytes will have the following format:
@@ -72,13 +102,27 @@ impl SerializableModule {
         Self::deserialize_from_archive(archived)
     }
 
-    /// # Safety
+    /// Deserialize a Module from a slice, validating it first.
+    /// The slice must have the following format:
+    /// RKYV serialization (any length) + POS (8 bytes)
     ///
-    /// This method is unsafe.
-    /// Please check `SerializableModule::deserialize` for more details.
-    unsafe fn archive_from_slice<'a>(
-        metadata_slice: &'a [u8],
-    ) -> Result<&'a ArchivedSerializableModule, DeserializeError> {
+    /// Unlike [`SerializableModule::deserialize`], the archived data is
+    /// structurally checked with `bytecheck` before being accessed, and the
+    /// deserialized module is then checked with
+    /// [`SerializableModule::validate`], so malformed input results in an
+    /// error instead of undefined behavior.
+    pub fn deserialize_checked(metadata_slice: &[u8]) -> Result<Self, DeserializeError> {
+        let (data, pos) = Self::split_metadata(metadata_slice)?;
+        let archived = check_archived_value::<SerializableModule>(data, pos)
+            .map_err(|e| corrupted(format!("invalid archive: {:?}", e)))?;
+        let module = Self::deserialize_from_archive(archived)?;
+        module.validate()?;
+        Ok(module)
+    }
+
+    /// Splits the metadata slice into the RKYV serialization and the
+    /// position of the archived root value.
+    fn split_metadata(metadata_slice: &[u8]) -> Result<(&[u8], usize), DeserializeError> {
         if metadata_slice.len() < 8 {
             return Err(DeserializeError::Incompatible(
                 "invalid serialized data".into(),
@@ -87,10 +131,18 @@ impl SerializableModule {
         let mut pos: [u8; 8] = Default::default();
         pos.copy_from_slice(&metadata_slice[metadata_slice.len() - 8..metadata_slice.len()]);
         let pos: u64 = u64::from_le_bytes(pos);
-        Ok(archived_value::<SerializableModule>(
-            &metadata_slice[..metadata_slice.len() - 8],
-            pos as usize,
-        ))
+        Ok((&metadata_slice[..metadata_slice.len() - 8], pos as usize))
+    }
+
+    /// # Safety
+    ///
+    /// This method is unsafe.
+    /// Please check `SerializableModule::deserialize` for more details.
+    unsafe fn archive_from_slice<'a>(
+        metadata_slice: &'a [u8],
+    ) -> Result<&'a ArchivedSerializableModule, DeserializeError> {
+        let (data, pos) = Self::split_metadata(metadata_slice)?;
+        Ok(archived_value::<SerializableModule>(data, pos))
     }
 
     pub fn deserialize_from_archive(
@@ -100,4 +152,510 @@ impl SerializableModule {
         RkyvDeserialize::deserialize(archived, &mut deserializer)
             .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
     }
+
+    /// Checks that the compilation is consistent with the module it was
+    /// compiled from, and that everything the linker is going to patch
+    /// or point to lies within the code and custom sections.
+    ///
+    /// This is what makes it possible to load serialized modules coming
+    /// from an untrusted source without corrupting memory while linking.
+    pub fn validate(&self) -> Result<(), DeserializeError> {
+        let module = &self.compile_info.module;
+        let compilation = &self.compilation;
+        let num_local_functions = compilation.function_bodies.len();
+
+        if module.functions.len() != module.num_imported_functions + num_local_functions {
+            return Err(corrupted(format!(
+                "expected {} local functions, found {}",
+                module
+                    .functions
+                    .len()
+                    .saturating_sub(module.num_imported_functions),
+                num_local_functions
+            )));
+        }
+        validate_module_indexes(module).map_err(corrupted)?;
+        if module.module_linking.num_nested_modules as usize != self.nested_modules.len() {
+            return Err(corrupted(format!(
+                "expected {} nested modules, found {}",
+                module.module_linking.num_nested_modules,
+                self.nested_modules.len()
+            )));
+        }
+        if compilation.function_relocations.len() != num_local_functions
+            || compilation.function_jt_offsets.len() != num_local_functions
+            || compilation.function_frame_info.len() != num_local_functions
+        {
+            return Err(corrupted(
+                "function metadata doesn't match the number of functions".to_string(),
+            ));
+        }
+        if compilation.function_call_trampolines.len() != module.signatures.len() {
+            return Err(corrupted(format!(
+                "expected {} function call trampolines, found {}",
+                module.signatures.len(),
+                compilation.function_call_trampolines.len()
+            )));
+        }
+        if compilation.dynamic_function_trampolines.len() != module.num_imported_functions {
+            return Err(corrupted(format!(
+                "expected {} dynamic function trampolines, found {}",
+                module.num_imported_functions,
+                compilation.dynamic_function_trampolines.len()
+            )));
+        }
+        if compilation.custom_section_relocations.len() != compilation.custom_sections.len() {
+            return Err(corrupted(
+                "custom section relocations don't match the number of custom sections".to_string(),
+            ));
+        }
+        if let Some(debug) = &compilation.debug {
+            if compilation.custom_sections.get(debug.eh_frame).is_none() {
+                return Err(corrupted(format!(
+                    "eh_frame points to an unknown custom section {}",
+                    debug.eh_frame.index()
+                )));
+            }
+        }
+        if self.compile_info.memory_styles.len() != module.memories.len()
+            || self.compile_info.table_styles.len() != module.tables.len()
+        {
+            return Err(corrupted(
+                "memory or table styles don't match the module".to_string(),
+            ));
+        }
+
+        for (index, body) in compilation.function_bodies.iter() {
+            let body_len = body.body.len();
+            for (jt, offset) in compilation.function_jt_offsets[index].iter() {
+                if *offset as usize > body_len {
+                    return Err(corrupted(format!(
+                        "jump table {} of function {} is out of bounds",
+                        jt.index(),
+                        index.index()
+                    )));
+                }
+            }
+            for trap in compilation.function_frame_info[index].traps.iter() {
+                if trap.code_offset as usize > body_len {
+                    return Err(corrupted(format!(
+                        "trap information of function {} is out of bounds",
+                        index.index()
+                    )));
+                }
+            }
+            self.validate_relocations(&compilation.function_relocations[index], body_len)
+                .map_err(|e| corrupted(format!("function {}: {}", index.index(), e)))?;
+        }
+        for (index, section) in compilation.custom_sections.iter() {
+            self.validate_relocations(
+                &compilation.custom_section_relocations[index],
+                section.bytes.len(),
+            )
+            .map_err(|e| corrupted(format!("custom section {}: {}", index.index(), e)))?;
+        }
+
+        for initializer in self.data_initializers.iter() {
+            let memory_index = initializer.location.memory_index;
+            if module.memories.get(memory_index).is_none() {
+                return Err(corrupted(format!(
+                    "data initializer targets an unknown memory {}",
+                    memory_index.index()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every relocation is patched within a body of
+    /// `body_len` bytes and targets something that exists.
+    fn validate_relocations(
+        &self,
+        relocations: &[Relocation],
+        body_len: usize,
+    ) -> Result<(), String> {
+        let compilation = &self.compilation;
+        for relocation in relocations {
+            let end = (relocation.offset as usize)
+                .checked_add(relocation_size(relocation.kind))
+                .ok_or_else(|| "relocation offset overflows".to_string())?;
+            if end > body_len {
+                return Err(format!(
+                    "relocation at offset {} is out of bounds",
+                    relocation.offset
+                ));
+            }
+            match relocation.reloc_target {
+                RelocationTarget::LocalFunc(index) => {
+                    if compilation.function_bodies.get(index).is_none() {
+                        return Err(format!(
+                            "relocation targets an unknown function {}",
+                            index.index()
+                        ));
+                    }
+                }
+                RelocationTarget::CustomSection(index) => {
+                    if compilation.custom_sections.get(index).is_none() {
+                        return Err(format!(
+                            "relocation targets an unknown custom section {}",
+                            index.index()
+                        ));
+                    }
+                }
+                RelocationTarget::JumpTable(index, jt) => {
+                    let has_jump_table = compilation
+                        .function_jt_offsets
+                        .get(index)
+                        .map_or(false, |offsets| offsets.get(jt).is_some());
+                    if !has_jump_table {
+                        return Err(format!(
+                            "relocation targets an unknown jump table {} of function {}",
+                            jt.index(),
+                            index.index()
+                        ));
+                    }
+                }
+                RelocationTarget::LibCall(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every index stored in `module` refers to an entity of
+/// the module, so that instantiating it can't index out of bounds.
+fn validate_module_indexes(module: &ModuleInfo) -> Result<(), String> {
+    let check_function = |index: FunctionIndex, what: &str| {
+        if module.functions.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown function {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+    let check_table = |index: TableIndex, what: &str| {
+        if module.tables.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown table {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+    let check_memory = |index: MemoryIndex, what: &str| {
+        if module.memories.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown memory {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+    let check_global = |index: GlobalIndex, what: &str| {
+        if module.globals.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown global {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+    let check_instance = |index: InstanceIndex, what: &str| {
+        if module.module_linking.instances.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown instance {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+    let check_module = |index: ModuleIndex, what: &str| {
+        if module.module_linking.modules.get(index).is_none() {
+            return Err(format!(
+                "{} refers to an unknown module {}",
+                what,
+                index.index()
+            ));
+        }
+        Ok(())
+    };
+
+    if module.num_imported_functions > module.functions.len()
+        || module.num_imported_tables > module.tables.len()
+        || module.num_imported_memories > module.memories.len()
+        || module.num_imported_globals > module.globals.len()
+    {
+        return Err("the module imports more entities than it has".to_string());
+    }
+    if module.global_initializers.len() != module.globals.len() - module.num_imported_globals {
+        return Err("global initializers don't match the local globals".to_string());
+    }
+
+    for (index, signature) in module.functions.iter() {
+        if module.signatures.get(*signature).is_none() {
+            return Err(format!(
+                "function {} has an unknown signature {}",
+                index.index(),
+                signature.index()
+            ));
+        }
+    }
+    for import in module.imports.values() {
+        match *import {
+            ImportIndex::Function(index) => check_function(index, "an import")?,
+            ImportIndex::Table(index) => check_table(index, "an import")?,
+            ImportIndex::Memory(index) => check_memory(index, "an import")?,
+            ImportIndex::Global(index) => check_global(index, "an import")?,
+            ImportIndex::Instance(index) => check_instance(index, "an import")?,
+            ImportIndex::Module(index) => check_module(index, "an import")?,
+        }
+    }
+    for export in module.exports.values() {
+        match *export {
+            ExportIndex::Function(index) => check_function(index, "an export")?,
+            ExportIndex::Table(index) => check_table(index, "an export")?,
+            ExportIndex::Memory(index) => check_memory(index, "an export")?,
+            ExportIndex::Global(index) => check_global(index, "an export")?,
+            ExportIndex::Instance(index) => check_instance(index, "an export")?,
+            ExportIndex::Module(index) => check_module(index, "an export")?,
+        }
+    }
+    if let Some(start_function) = module.start_function {
+        check_function(start_function, "the start function")?;
+    }
+    for initializer in module.table_initializers.iter() {
+        check_table(initializer.table_index, "a table initializer")?;
+        if let Some(base) = initializer.base {
+            check_global(base, "a table initializer")?;
+        }
+        for function in initializer.elements.iter() {
+            check_function(*function, "a table initializer")?;
+        }
+    }
+    for elements in module.passive_elements.values() {
+        for function in elements.iter() {
+            check_function(*function, "a passive element segment")?;
+        }
+    }
+    for initializer in module.global_initializers.values() {
+        match *initializer {
+            GlobalInit::GetGlobal(index) => check_global(index, "a global initializer")?,
+            GlobalInit::RefFunc(index) => check_function(index, "a global initializer")?,
+            _ => {}
+        }
+    }
+    for section in module.custom_sections.values() {
+        if module.custom_sections_data.get(*section).is_none() {
+            return Err(format!("custom section {} has no data", section.index()));
+        }
+    }
+
+    validate_module_linking(module)
+}
+
+/// Checks that every index stored in the entities of the module
+/// linking proposal of `module` refers to an entity of the module.
+fn validate_module_linking(module: &ModuleInfo) -> Result<(), String> {
+    let module_linking = &module.module_linking;
+    let check_signature = |index: SignatureIndex| {
+        if module.signatures.get(index).is_none() {
+            return Err(format!("unknown signature {}", index.index()));
+        }
+        Ok(())
+    };
+    let check_instance_signature = |index: u32| {
+        if module_linking.instance_signatures.len() <= index as usize {
+            return Err(format!("unknown instance signature {}", index));
+        }
+        Ok(())
+    };
+    let check_module_signature = |index: u32| {
+        if module_linking.module_signatures.len() <= index as usize {
+            return Err(format!("unknown module signature {}", index));
+        }
+        Ok(())
+    };
+    // The signatures may only refer to the signatures declared before
+    // them, which makes sure that their types are finite.
+    let check_entity_type =
+        |ty: &EntityType, instance_signatures: u32, module_signatures: u32| match *ty {
+            EntityType::Function(index) => check_signature(index),
+            EntityType::Instance(index) if index < instance_signatures => Ok(()),
+            EntityType::Module(index) if index < module_signatures => Ok(()),
+            EntityType::Instance(index) => Err(format!("unknown instance signature {}", index)),
+            EntityType::Module(index) => Err(format!("unknown module signature {}", index)),
+            EntityType::Table(_) | EntityType::Memory(_) | EntityType::Global(_) => Ok(()),
+        };
+
+    for definition in module_linking.types.iter() {
+        match *definition {
+            TypeDefinition::Function(index) => check_signature(index)?,
+            TypeDefinition::Instance(index) => check_instance_signature(index)?,
+            TypeDefinition::Module(index) => check_module_signature(index)?,
+        }
+    }
+    for (index, signature) in module_linking.instance_signatures.iter().enumerate() {
+        for (_, ty) in signature.exports.iter() {
+            check_entity_type(
+                ty,
+                index as u32,
+                module_linking.module_signatures.len() as u32,
+            )?;
+        }
+    }
+    for (index, signature) in module_linking.module_signatures.iter().enumerate() {
+        let imports = signature.imports.iter().map(|(_, _, ty)| ty);
+        for ty in imports.chain(signature.exports.iter().map(|(_, ty)| ty)) {
+            check_entity_type(
+                ty,
+                module_linking.instance_signatures.len() as u32,
+                index as u32,
+            )?;
+        }
+    }
+    if module_linking.instance_definitions.len() != module_linking.instances.len()
+        || module_linking.module_definitions.len() != module_linking.modules.len()
+    {
+        return Err("the definitions of the instances or modules don't match them".to_string());
+    }
+    for signature in module_linking.instances.values() {
+        check_instance_signature(*signature)?;
+    }
+    for signature in module_linking.modules.values() {
+        check_module_signature(*signature)?;
+    }
+    for (index, definition) in module_linking.instance_definitions.iter() {
+        match definition {
+            InstanceDefinition::Imported => {}
+            InstanceDefinition::Instantiated {
+                module: instantiated,
+                args,
+            } => {
+                if module_linking.modules.get(*instantiated).is_none() {
+                    return Err(format!(
+                        "instance {} instantiates an unknown module {}",
+                        index.index(),
+                        instantiated.index()
+                    ));
+                }
+                for (_, arg) in args.iter() {
+                    let known = match *arg {
+                        ExportIndex::Function(i) => module.functions.get(i).is_some(),
+                        ExportIndex::Table(i) => module.tables.get(i).is_some(),
+                        ExportIndex::Memory(i) => module.memories.get(i).is_some(),
+                        ExportIndex::Global(i) => module.globals.get(i).is_some(),
+                        ExportIndex::Instance(i) => i.index() < index.index(),
+                        ExportIndex::Module(i) => module_linking.modules.get(i).is_some(),
+                    };
+                    if !known {
+                        return Err(format!(
+                            "instance {} is instantiated with an unknown entity",
+                            index.index()
+                        ));
+                    }
+                }
+            }
+            InstanceDefinition::Alias { instance, .. } => {
+                if instance.index() >= index.index() {
+                    return Err(format!(
+                        "instance {} is an alias of an unknown instance {}",
+                        index.index(),
+                        instance.index()
+                    ));
+                }
+            }
+        }
+    }
+    for (index, definition) in module_linking.module_definitions.iter() {
+        match definition {
+            ModuleDefinition::Imported => {}
+            ModuleDefinition::Nested(nested) => {
+                if *nested >= module_linking.num_nested_modules {
+                    return Err(format!(
+                        "module {} is an unknown nested module {}",
+                        index.index(),
+                        nested
+                    ));
+                }
+            }
+            ModuleDefinition::Alias { instance, .. } => {
+                if module_linking.instances.get(*instance).is_none() {
+                    return Err(format!(
+                        "module {} is an alias of an unknown instance {}",
+                        index.index(),
+                        instance.index()
+                    ));
+                }
+            }
+        }
+    }
+    for (import, (instance, _)) in module_linking.aliases.iter() {
+        if !module.imports.keys().any(|(_, _, index)| index == import) {
+            return Err(format!("alias {} is not in the imports", import));
+        }
+        if module_linking.instances.get(*instance).is_none() {
+            return Err(format!(
+                "alias {} refers to an unknown instance {}",
+                import,
+                instance.index()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_types::FunctionType;
+
+    fn module_with_one_function() -> ModuleInfo {
+        let mut module = ModuleInfo::new();
+        let signature = module.signatures.push(FunctionType::new(vec![], vec![]));
+        module.functions.push(signature);
+        module
+    }
+
+    #[test]
+    fn valid_indexes() {
+        let mut module = module_with_one_function();
+        module.exports.insert(
+            "f".to_string(),
+            ExportIndex::Function(FunctionIndex::new(0)),
+        );
+        module.start_function = Some(FunctionIndex::new(0));
+        assert!(validate_module_indexes(&module).is_ok());
+    }
+
+    #[test]
+    fn unknown_indexes() {
+        let mut module = module_with_one_function();
+        module.exports.insert(
+            "f".to_string(),
+            ExportIndex::Function(FunctionIndex::new(1)),
+        );
+        assert!(validate_module_indexes(&module).is_err());
+
+        let mut module = module_with_one_function();
+        module.start_function = Some(FunctionIndex::new(1));
+        assert!(validate_module_indexes(&module).is_err());
+
+        let mut module = module_with_one_function();
+        module.functions.push(SignatureIndex::new(1));
+        assert!(validate_module_indexes(&module).is_err());
+
+        let mut module = module_with_one_function();
+        module
+            .exports
+            .insert("m".to_string(), ExportIndex::Memory(MemoryIndex::new(0)));
+        assert!(validate_module_indexes(&module).is_err());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{
-    resolve_imports, InstantiationError, Resolver, RuntimeError, SerializeError, Tunables,
+    resolve_imports, InstantiationError, LinkError, LinkedEntities, Resolver, RuntimeError,
+    SerializeError, Tunables,
 };
 use loupe::MemoryUsage;
 use std::any::Any;
@@ -13,8 +14,8 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, InstanceAllocator, InstanceHandle, MemoryStyle, ModuleInfo,
-    TableStyle, TrapHandler, VMSharedSignatureIndex, VMTrampoline,
+    FuncDataRegistry, FunctionBodyPtr, InstanceHandle, InstanceSnapshot, MemoryImages, MemoryStyle,
+    ModuleInfo, TableStyle, TrapHandler, VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// An `Artifact` is the product that the `Engine`
@@ -52,6 +53,12 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
     /// Returns data initializers to pass to `InstanceHandle::initialize`
     fn data_initializers(&self) -> &[OwnedDataInitializer];
 
+    /// Returns the copy-on-write images of the local memories, laid
+    /// out from the first data initializers, if any.
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        None
+    }
+
     /// Returns the functions allocated in memory or this `Artifact`
     /// ready to be run.
     fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>;
@@ -70,6 +77,12 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
     /// Get the func data registry
     fn func_data_registry(&self) -> &FuncDataRegistry;
 
+    /// Returns the artifacts of the modules nested in this one, with
+    /// the module linking proposal.
+    fn nested_artifacts(&self) -> &[Arc<dyn Artifact>] {
+        &[]
+    }
+
     /// Serializes an artifact into bytes
     fn serialize(&self) -> Result<Vec<u8>, SerializeError>;
 
@@ -87,6 +100,9 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
 
     /// Crate an `Instance` from this `Artifact`.
     ///
+    /// The instances and modules of the module, with the module linking
+    /// proposal, are the ones resolved in `linked`.
+    ///
     /// # Safety
     ///
     /// See [`InstanceHandle::new`].
@@ -94,63 +110,46 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
         &self,
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
+        linked: &LinkedEntities,
         host_state: Box<dyn Any>,
     ) -> Result<InstanceHandle, InstantiationError> {
-        self.preinstantiate()?;
-
-        let module = self.module();
-        let (imports, import_function_envs) = {
-            let mut imports = resolve_imports(
-                &module,
-                resolver,
-                &self.finished_dynamic_function_trampolines(),
-                self.memory_styles(),
-                self.table_styles(),
-            )
-            .map_err(InstantiationError::Link)?;
-
-            // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
-            // to the envs to call it on.
-            let import_function_envs = imports.get_imported_function_envs();
-
-            (imports, import_function_envs)
-        };
-
-        // Get pointers to where metadata about local memories should live in VM memory.
-        // Get pointers to where metadata about local tables should live in VM memory.
-
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&*module);
-        let finished_memories = tunables
-            .create_memories(&module, self.memory_styles(), &memory_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
-        let finished_tables = tunables
-            .create_tables(&module, self.table_styles(), &table_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
-        let finished_globals = tunables
-            .create_globals(&module)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
-
-        self.register_frame_info();
-
-        let handle = InstanceHandle::new(
-            allocator,
-            module,
-            self.finished_functions().clone(),
-            self.finished_function_call_trampolines().clone(),
-            finished_memories,
-            finished_tables,
-            finished_globals,
-            imports,
-            self.signatures().clone(),
-            self.func_data_registry(),
+        instantiate_with_memory_images(
+            self,
+            tunables,
+            resolver,
+            linked,
             host_state,
-            import_function_envs,
+            self.memory_images(),
         )
-        .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))?;
+    }
+
+    /// Crate an `Instance` from this `Artifact`, starting with the
+    /// state of a snapshot of another instance of it. The data
+    /// initializers and the start function are not run again, so
+    /// [`Artifact::finish_instantiation`] must not be called.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::new`].
+    unsafe fn instantiate_from_snapshot(
+        &self,
+        tunables: &dyn Tunables,
+        resolver: &dyn Resolver,
+        linked: &LinkedEntities,
+        host_state: Box<dyn Any>,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        let handle = instantiate_with_memory_images(
+            self,
+            tunables,
+            resolver,
+            linked,
+            host_state,
+            Some(snapshot.memory_images()),
+        )?;
+        handle
+            .restore_snapshot(snapshot)
+            .map_err(|e| InstantiationError::Link(LinkError::Resource(e)))?;
         Ok(handle)
     }
 
@@ -164,9 +163,15 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
         trap_handler: &dyn TrapHandler,
         handle: &InstanceHandle,
     ) -> Result<(), InstantiationError> {
+        // The data initializers laid out in the memory images have
+        // already been applied when creating the memories.
+        let num_initializers_in_images = self
+            .memory_images()
+            .map_or(0, |images| images.num_initializers());
         let data_initializers = self
             .data_initializers()
             .iter()
+            .skip(num_initializers_in_images)
             .map(|init| DataInitializer {
                 location: init.location.clone(),
                 data: &*init.data,
@@ -178,6 +183,89 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
     }
 }
 
+/// Creates an `Instance` from `artifact`, with the given images of its
+/// local memories.
+///
+/// # Safety
+///
+/// See [`InstanceHandle::new`].
+unsafe fn instantiate_with_memory_images<A: Artifact + ?Sized>(
+    artifact: &A,
+    tunables: &dyn Tunables,
+    resolver: &dyn Resolver,
+    linked: &LinkedEntities,
+    host_state: Box<dyn Any>,
+    memory_images: Option<&MemoryImages>,
+) -> Result<InstanceHandle, InstantiationError> {
+    artifact.preinstantiate()?;
+
+    let module = artifact.module();
+    let (imports, import_function_envs) = {
+        let mut imports = resolve_imports(
+            &module,
+            resolver,
+            linked,
+            &artifact.finished_dynamic_function_trampolines(),
+            artifact.memory_styles(),
+            artifact.table_styles(),
+        )
+        .map_err(InstantiationError::Link)?;
+
+        // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
+        // to the envs to call it on.
+        let import_function_envs = imports.get_imported_function_envs();
+
+        (imports, import_function_envs)
+    };
+
+    // Get pointers to where metadata about local memories should live in VM memory.
+    // Get pointers to where metadata about local tables should live in VM memory.
+
+    let (allocator, memory_definition_locations, table_definition_locations) = tunables
+        .create_instance_allocator(&*module)
+        .map_err(InstantiationError::Link)?;
+    let finished_memories = tunables
+        .create_memories(
+            &module,
+            artifact.memory_styles(),
+            &memory_definition_locations,
+            memory_images,
+        )
+        .map_err(InstantiationError::Link)?
+        .into_boxed_slice();
+    let finished_tables = tunables
+        .create_tables(
+            &module,
+            artifact.table_styles(),
+            &table_definition_locations,
+        )
+        .map_err(InstantiationError::Link)?
+        .into_boxed_slice();
+    let finished_globals = tunables
+        .create_globals(&module)
+        .map_err(InstantiationError::Link)?
+        .into_boxed_slice();
+
+    artifact.register_frame_info();
+
+    let handle = InstanceHandle::new(
+        allocator,
+        module,
+        artifact.finished_functions().clone(),
+        artifact.finished_function_call_trampolines().clone(),
+        finished_memories,
+        finished_tables,
+        finished_globals,
+        imports,
+        artifact.signatures().clone(),
+        artifact.func_data_registry(),
+        host_state,
+        import_function_envs,
+    )
+    .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))?;
+    Ok(handle)
+}
+
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
 /// Trait needed to get downcasting from `WasiFile` to work.
 pub trait Upcastable {
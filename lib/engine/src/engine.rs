@@ -47,6 +47,25 @@ pub trait Engine: MemoryUsage {
     /// The serialized content must represent a serialized WebAssembly module.
     unsafe fn deserialize(&self, bytes: &[u8]) -> Result<Arc<dyn Artifact>, DeserializeError>;
 
+    /// Deserializes a WebAssembly module, validating the serialized
+    /// content before loading it.
+    ///
+    /// Engines that can't validate their serialized artifacts return a
+    /// [`DeserializeError::Incompatible`] error.
+    ///
+    /// # Safety
+    ///
+    /// Malformed content is rejected, but the compiled code contained in
+    /// it is still executed as-is.
+    unsafe fn deserialize_checked(
+        &self,
+        _bytes: &[u8],
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        Err(DeserializeError::Incompatible(
+            "This engine doesn't support checked deserialization".to_string(),
+        ))
+    }
+
     /// Deserializes a WebAssembly module from a path
     ///
     /// # Safety
@@ -1,5 +1,9 @@
+use crate::Artifact;
 use loupe::MemoryUsage;
+use std::convert::TryFrom;
+use std::fmt;
 use std::sync::Arc;
+use wasmer_types::{ExportType, ExternType, InstanceType, ModuleType};
 use wasmer_vm::{ImportInitializerFuncPtr, VMExtern, VMFunction, VMGlobal, VMMemory, VMTable};
 
 /// The value of an export passed from one instance to another.
@@ -16,15 +20,40 @@ pub enum Export {
 
     /// A global export value.
     Global(VMGlobal),
+
+    /// An instance export value, with the module linking proposal.
+    Instance(ExportInstance),
+
+    /// A module export value, with the module linking proposal.
+    Module(ExportModule),
 }
 
-impl From<Export> for VMExtern {
-    fn from(other: Export) -> Self {
+impl Export {
+    /// Returns the type of the export.
+    pub fn ty(&self) -> ExternType {
+        match self {
+            Self::Function(f) => ExternType::Function(f.vm_function.signature.clone()),
+            Self::Table(t) => ExternType::Table(*t.ty()),
+            Self::Memory(m) => ExternType::Memory(m.ty()),
+            Self::Global(g) => ExternType::Global(*g.from.ty()),
+            Self::Instance(i) => ExternType::Instance(i.ty()),
+            Self::Module(m) => ExternType::Module(m.ty()),
+        }
+    }
+}
+
+/// The instances and modules are not part of the VM, so converting
+/// them fails and gives them back.
+impl TryFrom<Export> for VMExtern {
+    type Error = Export;
+
+    fn try_from(other: Export) -> Result<Self, Self::Error> {
         match other {
-            Export::Function(ExportFunction { vm_function, .. }) => Self::Function(vm_function),
-            Export::Memory(vm_memory) => Self::Memory(vm_memory),
-            Export::Table(vm_table) => Self::Table(vm_table),
-            Export::Global(vm_global) => Self::Global(vm_global),
+            Export::Function(ExportFunction { vm_function, .. }) => Ok(Self::Function(vm_function)),
+            Export::Memory(vm_memory) => Ok(Self::Memory(vm_memory)),
+            Export::Table(vm_table) => Ok(Self::Table(vm_table)),
+            Export::Global(vm_global) => Ok(Self::Global(vm_global)),
+            Export::Instance(_) | Export::Module(_) => Err(other),
         }
     }
 }
@@ -43,6 +72,66 @@ impl From<VMExtern> for Export {
     }
 }
 
+/// An instance export value: the exports of an instance, with the
+/// module linking proposal.
+#[derive(Debug, Clone)]
+pub struct ExportInstance {
+    /// The exports of the instance, by name.
+    pub exports: Arc<Vec<(String, Export)>>,
+}
+
+impl ExportInstance {
+    /// Creates an instance export value from the exports of an
+    /// instance.
+    pub fn new(exports: Vec<(String, Export)>) -> Self {
+        Self {
+            exports: Arc::new(exports),
+        }
+    }
+
+    /// Gets the export of the given name.
+    pub fn get(&self, name: &str) -> Option<&Export> {
+        self.exports
+            .iter()
+            .find(|(export, _)| export == name)
+            .map(|(_, export)| export)
+    }
+
+    /// Returns the type of the instance.
+    pub fn ty(&self) -> InstanceType {
+        InstanceType::new(
+            self.exports
+                .iter()
+                .map(|(name, export)| ExportType::new(name, export.ty()))
+                .collect(),
+        )
+    }
+}
+
+/// A module export value: a compiled module that can be instantiated,
+/// with the module linking proposal.
+#[derive(Clone)]
+pub struct ExportModule {
+    /// The artifact of the module.
+    pub artifact: Arc<dyn Artifact>,
+}
+
+impl ExportModule {
+    /// Returns the type of the module.
+    pub fn ty(&self) -> ModuleType {
+        let module = self.artifact.module_ref();
+        ModuleType::new(module.imports().collect(), module.exports().collect())
+    }
+}
+
+impl fmt::Debug for ExportModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExportModule")
+            .field("ty", &self.ty())
+            .finish()
+    }
+}
+
 /// Extra metadata about `ExportFunction`s.
 ///
 /// The metadata acts as a kind of manual virtual dispatch. We store the
@@ -168,3 +257,15 @@ impl From<VMGlobal> for Export {
         Self::Global(global)
     }
 }
+
+impl From<ExportInstance> for Export {
+    fn from(instance: ExportInstance) -> Self {
+        Self::Instance(instance)
+    }
+}
+
+impl From<ExportModule> for Export {
+    fn from(module: ExportModule) -> Self {
+        Self::Module(module)
+    }
+}
@@ -33,10 +33,12 @@ pub use crate::engine::{Engine, EngineId};
 pub use crate::error::{
     DeserializeError, ImportError, InstantiationError, LinkError, SerializeError,
 };
-pub use crate::export::{Export, ExportFunction, ExportFunctionMetadata};
+pub use crate::export::{
+    Export, ExportFunction, ExportFunctionMetadata, ExportInstance, ExportModule,
+};
 pub use crate::resolver::{
-    resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
-    Resolver,
+    resolve_imports, resolve_instances, ChainableNamedResolver, Definitions, LinkedEntities,
+    NamedResolver, NamedResolverChain, NullResolver, Resolver,
 };
 pub use crate::trap::*;
 pub use crate::tunables::Tunables;
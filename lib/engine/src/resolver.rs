@@ -1,15 +1,21 @@
 //! Define the `Resolver` trait, allowing custom resolution for external
 //! references.
 
-use crate::{Export, ExportFunctionMetadata, ImportError, LinkError};
+use crate::{
+    Artifact, Export, ExportFunctionMetadata, ExportInstance, ExportModule, ImportError, LinkError,
+};
 use more_asserts::assert_ge;
+use std::sync::Arc;
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
-use wasmer_types::{ExternType, FunctionIndex, ImportIndex, MemoryIndex, TableIndex};
+use wasmer_types::{
+    ExportIndex, ExternType, FunctionIndex, ImportIndex, InstanceIndex, MemoryIndex, ModuleIndex,
+    TableIndex,
+};
 
 use wasmer_vm::{
-    FunctionBodyPtr, ImportFunctionEnv, Imports, MemoryStyle, ModuleInfo, TableStyle,
-    VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalImport,
-    VMMemoryImport, VMTableImport,
+    FunctionBodyPtr, ImportFunctionEnv, Imports, InstanceDefinition, MemoryStyle, ModuleDefinition,
+    ModuleInfo, TableStyle, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport,
+    VMFunctionKind, VMGlobalImport, VMMemoryImport, VMTableImport,
 };
 
 /// Import resolver connects imports with available exported values.
@@ -105,19 +111,360 @@ fn get_extern_from_import(module: &ModuleInfo, import_index: &ImportIndex) -> Ex
             let global = module.globals[*index];
             ExternType::Global(global)
         }
+        ImportIndex::Instance(index) => {
+            let instance = module.instance_type(module.module_linking.instances[*index]);
+            ExternType::Instance(instance)
+        }
+        ImportIndex::Module(index) => {
+            let module_type = module.module_type(module.module_linking.modules[*index]);
+            ExternType::Module(module_type)
+        }
     }
 }
 
 /// Get an `ExternType` given an export (and Engine signatures in case is a function).
 fn get_extern_from_export(_module: &ModuleInfo, export: &Export) -> ExternType {
-    match export {
-        Export::Function(ref f) => ExternType::Function(f.vm_function.signature.clone()),
-        Export::Table(ref t) => ExternType::Table(*t.ty()),
-        Export::Memory(ref m) => ExternType::Memory(m.ty()),
-        Export::Global(ref g) => {
-            let global = g.from.ty();
-            ExternType::Global(*global)
+    export.ty()
+}
+
+/// The instances and modules of a module, with the module linking
+/// proposal, resolved with [`resolve_instances`] before instantiating
+/// the module.
+///
+/// The instances made from the nested modules live as long as the
+/// exports of this structure.
+#[derive(Debug, Clone, Default)]
+pub struct LinkedEntities {
+    /// The instances of the module, imported, instantiated or aliased.
+    pub instances: PrimaryMap<InstanceIndex, ExportInstance>,
+    /// The modules of the module, imported, nested or aliased.
+    pub modules: PrimaryMap<ModuleIndex, ExportModule>,
+}
+
+/// The arguments of the instantiation of a module, which resolve
+/// its imports by name.
+///
+/// An import with a field name resolves to the export of that name
+/// of the instance passed as the argument named as its module.
+struct InstantiationArgs(Vec<(String, Export)>);
+
+impl Resolver for InstantiationArgs {
+    fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export> {
+        let (_, arg) = self.0.iter().find(|(name, _)| name == module)?;
+        match arg {
+            _ if field.is_empty() => Some(arg.clone()),
+            Export::Instance(instance) => instance.get(field).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// The functions, tables, memories and globals of a module, from
+/// their index, once the module is instantiated.
+pub type Definitions<'a> = &'a dyn Fn(&ExportIndex) -> Export;
+
+/// Resolves the instances and modules of a `ModuleInfo`, with the
+/// module linking proposal: the imported ones with the `Resolver`,
+/// the nested ones with `nested_modules`, in the order of the module.
+/// The instances of the module are created with `instantiate`.
+///
+/// The resolution resumes after the entities already in `linked`.
+/// Without `definitions`, it stops at the first instance which takes
+/// a function, table, memory or global defined by the module: it is
+/// resolved by calling this function again with the `definitions` of
+/// the instance of the module, along with the entities after it.
+///
+/// An imported instance which the `Resolver` doesn't provide is made
+/// from the exports of its namespace if it has no field name, so that
+/// the imports registered in an `ImportObject` satisfy it.
+pub fn resolve_instances<E: From<LinkError>>(
+    module: &ModuleInfo,
+    resolver: &dyn Resolver,
+    nested_modules: &[Arc<dyn Artifact>],
+    linked: &mut LinkedEntities,
+    definitions: Option<Definitions>,
+    instantiate: &mut dyn FnMut(Arc<dyn Artifact>, &dyn Resolver) -> Result<ExportInstance, E>,
+) -> Result<(), E> {
+    let module_linking = &module.module_linking;
+    let mut instances = module_linking
+        .instance_definitions
+        .iter()
+        .skip(linked.instances.len());
+    let mut modules = module_linking
+        .module_definitions
+        .iter()
+        .skip(linked.modules.len());
+    let mut next_instance = instances.next();
+    let mut next_module = modules.next();
+
+    // The definitions only refer to the instances and modules defined
+    // before them, so they are resolved in the order of the module:
+    // an instance is resolved as soon as the entities it refers to are.
+    loop {
+        match next_instance {
+            Some((_, definition))
+                if definitions.is_none() && takes_definitions(module, definition) =>
+            {
+                return Ok(());
+            }
+            Some((index, definition)) if is_instance_ready(definition, linked) => {
+                let instance = resolve_instance(
+                    module,
+                    resolver,
+                    linked,
+                    definitions,
+                    index,
+                    definition,
+                    instantiate,
+                )?;
+                linked.instances.push(instance);
+                next_instance = instances.next();
+                continue;
+            }
+            _ => {}
+        }
+        match next_module {
+            Some((index, definition)) => {
+                let export_module =
+                    resolve_module(module, resolver, linked, nested_modules, index, definition)?;
+                linked.modules.push(export_module);
+                next_module = modules.next();
+            }
+            None if next_instance.is_none() => return Ok(()),
+            None => {
+                return Err(LinkError::Resource(
+                    "the instances of the module refer to unknown entities".to_string(),
+                )
+                .into())
+            }
+        }
+    }
+}
+
+/// Whether an instance definition takes a function, table, memory or
+/// global defined by the module.
+fn takes_definitions(module: &ModuleInfo, definition: &InstanceDefinition) -> bool {
+    match definition {
+        InstanceDefinition::Instantiated { args, .. } => args.iter().any(|(_, arg)| match arg {
+            ExportIndex::Function(index) => module.local_func_index(*index).is_some(),
+            ExportIndex::Table(index) => module.local_table_index(*index).is_some(),
+            ExportIndex::Memory(index) => module.local_memory_index(*index).is_some(),
+            ExportIndex::Global(index) => module.local_global_index(*index).is_some(),
+            ExportIndex::Instance(_) | ExportIndex::Module(_) => false,
+        }),
+        InstanceDefinition::Imported | InstanceDefinition::Alias { .. } => false,
+    }
+}
+
+/// Whether the instances and modules an instance definition refers
+/// to are already resolved.
+fn is_instance_ready(definition: &InstanceDefinition, linked: &LinkedEntities) -> bool {
+    match definition {
+        InstanceDefinition::Imported => true,
+        InstanceDefinition::Instantiated { module, args } => {
+            module.index() < linked.modules.len()
+                && args.iter().all(|(_, arg)| match arg {
+                    ExportIndex::Instance(index) => index.index() < linked.instances.len(),
+                    ExportIndex::Module(index) => index.index() < linked.modules.len(),
+                    _ => true,
+                })
+        }
+        InstanceDefinition::Alias { instance, .. } => instance.index() < linked.instances.len(),
+    }
+}
+
+/// Finds the import of the given kind and index, returning its module
+/// and field names and the index of the import.
+fn find_import<'a>(
+    module: &'a ModuleInfo,
+    import_index: &ImportIndex,
+) -> Option<(&'a str, &'a str, u32)> {
+    module
+        .imports
+        .iter()
+        .find(|(_, index)| *index == import_index)
+        .map(|((module_name, field, index), _)| (module_name.as_str(), field.as_str(), *index))
+}
+
+/// Resolves an import of an instance or a module with the `Resolver`,
+/// checking its type.
+fn resolve_linked_import(
+    module: &ModuleInfo,
+    resolver: &dyn Resolver,
+    import_index: &ImportIndex,
+) -> Result<Export, LinkError> {
+    let (module_name, field, index) =
+        find_import(module, import_index).expect("the import of an instance or a module");
+    let import_extern = get_extern_from_import(module, import_index);
+    let resolved = resolver.resolve(index, module_name, field).or_else(|| {
+        // Make the instance from the exports of the namespace.
+        let instance_type = match (&import_extern, field) {
+            (ExternType::Instance(instance_type), "") => instance_type,
+            _ => return None,
+        };
+        instance_type
+            .exports()
+            .iter()
+            .map(|export| {
+                let resolved = resolver.resolve(index, module_name, export.name())?;
+                Some((export.name().to_string(), resolved))
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|exports| Export::Instance(ExportInstance::new(exports)))
+    });
+    let resolved = resolved.ok_or_else(|| {
+        LinkError::Import(
+            module_name.to_string(),
+            field.to_string(),
+            ImportError::UnknownImport(import_extern.clone()),
+        )
+    })?;
+    let export_extern = get_extern_from_export(module, &resolved);
+    if !export_extern.is_compatible_with(&import_extern) {
+        return Err(LinkError::Import(
+            module_name.to_string(),
+            field.to_string(),
+            ImportError::IncompatibleType(import_extern, export_extern),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Resolves the export of the given name of a resolved instance.
+fn resolve_instance_export(
+    linked: &LinkedEntities,
+    instance: InstanceIndex,
+    export: &str,
+) -> Result<Export, LinkError> {
+    linked
+        .instances
+        .get(instance)
+        .and_then(|resolved| resolved.get(export))
+        .cloned()
+        .ok_or_else(|| {
+            LinkError::Resource(format!(
+                "instance {} has no export `{}`",
+                instance.index(),
+                export
+            ))
+        })
+}
+
+/// Resolves an entity of the module to pass it to an instantiation:
+/// a function, table, memory or global is either imported, an alias
+/// of the export of an instance or one of the `definitions` of the
+/// module.
+fn resolve_entity(
+    module: &ModuleInfo,
+    resolver: &dyn Resolver,
+    linked: &LinkedEntities,
+    definitions: Option<Definitions>,
+    export_index: &ExportIndex,
+) -> Result<Export, LinkError> {
+    let import_index = match *export_index {
+        ExportIndex::Function(index) => ImportIndex::Function(index),
+        ExportIndex::Table(index) => ImportIndex::Table(index),
+        ExportIndex::Memory(index) => ImportIndex::Memory(index),
+        ExportIndex::Global(index) => ImportIndex::Global(index),
+        ExportIndex::Instance(index) => {
+            return Ok(Export::Instance(linked.instances[index].clone()))
+        }
+        ExportIndex::Module(index) => return Ok(Export::Module(linked.modules[index].clone())),
+    };
+    let (module_name, field, index) = match find_import(module, &import_index) {
+        Some(import) => import,
+        None => {
+            return definitions
+                .map(|definitions| definitions(export_index))
+                .ok_or_else(|| {
+                    LinkError::Resource(
+                        "the definitions of the module are only passed once it is instantiated"
+                            .to_string(),
+                    )
+                })
         }
+    };
+    if let Some((instance, export)) = module.module_linking.aliases.get(&index) {
+        return resolve_instance_export(linked, *instance, export);
+    }
+    resolver.resolve(index, module_name, field).ok_or_else(|| {
+        LinkError::Import(
+            module_name.to_string(),
+            field.to_string(),
+            ImportError::UnknownImport(get_extern_from_import(module, &import_index)),
+        )
+    })
+}
+
+fn resolve_instance<E: From<LinkError>>(
+    module: &ModuleInfo,
+    resolver: &dyn Resolver,
+    linked: &LinkedEntities,
+    definitions: Option<Definitions>,
+    index: InstanceIndex,
+    definition: &InstanceDefinition,
+    instantiate: &mut dyn FnMut(Arc<dyn Artifact>, &dyn Resolver) -> Result<ExportInstance, E>,
+) -> Result<ExportInstance, E> {
+    let resolved = match definition {
+        InstanceDefinition::Imported => {
+            resolve_linked_import(module, resolver, &ImportIndex::Instance(index))?
+        }
+        InstanceDefinition::Instantiated {
+            module: module_index,
+            args,
+        } => {
+            let args = args
+                .iter()
+                .map(|(name, arg)| {
+                    let resolved = resolve_entity(module, resolver, linked, definitions, arg)?;
+                    Ok((name.clone(), resolved))
+                })
+                .collect::<Result<Vec<_>, LinkError>>()?;
+            let artifact = linked.modules[*module_index].artifact.clone();
+            return instantiate(artifact, &InstantiationArgs(args));
+        }
+        InstanceDefinition::Alias { instance, export } => {
+            resolve_instance_export(linked, *instance, export)?
+        }
+    };
+    match resolved {
+        Export::Instance(instance) => Ok(instance),
+        _ => Err(
+            LinkError::Resource(format!("instance {} is not an instance", index.index())).into(),
+        ),
+    }
+}
+
+fn resolve_module(
+    module: &ModuleInfo,
+    resolver: &dyn Resolver,
+    linked: &LinkedEntities,
+    nested_modules: &[Arc<dyn Artifact>],
+    index: ModuleIndex,
+    definition: &ModuleDefinition,
+) -> Result<ExportModule, LinkError> {
+    let resolved = match definition {
+        ModuleDefinition::Imported => {
+            resolve_linked_import(module, resolver, &ImportIndex::Module(index))?
+        }
+        ModuleDefinition::Nested(nested_index) => {
+            let artifact = nested_modules.get(*nested_index as usize).ok_or_else(|| {
+                LinkError::Resource(format!("unknown nested module {}", nested_index))
+            })?;
+            return Ok(ExportModule {
+                artifact: artifact.clone(),
+            });
+        }
+        ModuleDefinition::Alias { instance, export } => {
+            resolve_instance_export(linked, *instance, export)?
+        }
+    };
+    match resolved {
+        Export::Module(export_module) => Ok(export_module),
+        _ => Err(LinkError::Resource(format!(
+            "module {} is not a module",
+            index.index()
+        ))),
     }
 }
 
@@ -125,9 +472,13 @@ fn get_extern_from_export(_module: &ModuleInfo, export: &Export) -> ExternType {
 /// a `Resolver`.
 ///
 /// If all imports are satisfied returns an `Imports` instance required for a module instantiation.
+///
+/// The instances and modules of the module must be resolved in `linked`,
+/// which also resolves the aliases of the exports of the instances.
 pub fn resolve_imports(
     module: &ModuleInfo,
     resolver: &dyn Resolver,
+    linked: &LinkedEntities,
     finished_dynamic_function_trampolines: &BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
     _table_styles: &PrimaryMap<TableIndex, TableStyle>,
@@ -140,7 +491,22 @@ pub fn resolve_imports(
     let mut global_imports = PrimaryMap::with_capacity(module.num_imported_globals);
 
     for ((module_name, field, import_idx), import_index) in module.imports.iter() {
-        let resolved = resolver.resolve(*import_idx, module_name, field);
+        if let ImportIndex::Instance(_) | ImportIndex::Module(_) = import_index {
+            // Resolved with `resolve_instances`.
+            continue;
+        }
+        let resolved = match module.module_linking.aliases.get(import_idx) {
+            Some((instance, _)) if instance.index() >= linked.instances.len() => {
+                return Err(LinkError::Resource(format!(
+                    "import `{}` `{}` is an alias of instance {}, which takes definitions of the module",
+                    module_name,
+                    field,
+                    instance.index()
+                )));
+            }
+            Some((instance, export)) => linked.instances[*instance].get(export).cloned(),
+            None => resolver.resolve(*import_idx, module_name, field),
+        };
         let import_extern = get_extern_from_import(module, import_index);
         let resolved = match resolved {
             None => {
@@ -280,6 +646,12 @@ pub fn resolve_imports(
                     from: g.from.clone(),
                 });
             }
+
+            Export::Instance(_) | Export::Module(_) => {
+                // This should never be reached, as we did compatibility
+                // checks before
+                unreachable!("Instance or module resolution for another import");
+            }
         }
     }
 
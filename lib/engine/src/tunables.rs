@@ -1,14 +1,15 @@
 use crate::error::LinkError;
 use loupe::MemoryUsage;
+use std::convert::TryFrom;
 use std::ptr::NonNull;
 use std::sync::Arc;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
-    GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
-    TableIndex, TableType,
+    Bytes, GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    MemoryType, Pages, TableIndex, TableType,
 };
 use wasmer_vm::MemoryError;
-use wasmer_vm::{Global, Memory, ModuleInfo, Table};
+use wasmer_vm::{Global, InstanceAllocator, Memory, MemoryImage, MemoryImages, ModuleInfo, Table};
 use wasmer_vm::{MemoryStyle, TableStyle};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -39,6 +40,25 @@ pub trait Tunables: MemoryUsage {
         vm_definition_location: NonNull<VMMemoryDefinition>,
     ) -> Result<Arc<dyn Memory>, MemoryError>;
 
+    /// Create a memory owned by the VM given a [`MemoryType`] and a [`MemoryStyle`],
+    /// starting with the contents of the given [`MemoryImage`].
+    ///
+    /// The image is copied into a memory made by `create_vm_memory` by default.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid location in VM memory.
+    unsafe fn create_vm_memory_with_image(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+        image: &MemoryImage,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        let memory = self.create_vm_memory(ty, style, vm_definition_location)?;
+        image.copy_into(&*memory)?;
+        Ok(memory)
+    }
+
     /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
     fn create_host_table(
         &self,
@@ -57,17 +77,39 @@ pub trait Tunables: MemoryUsage {
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String>;
 
+    /// Allocate the data of an instance of the given module, returning
+    /// the locations where the definitions of its local memories and
+    /// tables are to be written.
+    ///
+    /// The data is allocated with the global allocator by default.
+    #[allow(clippy::type_complexity)]
+    fn create_instance_allocator(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        Ok(InstanceAllocator::new(module))
+    }
+
     /// Create a global with an unset value.
     fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
         Ok(Arc::new(Global::new(ty)))
     }
 
-    /// Allocate memory for just the memories of the current module.
+    /// Allocate memory for just the memories of the current module,
+    /// starting with the contents of their images, if any.
     unsafe fn create_memories(
         &self,
         module: &ModuleInfo,
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         memory_definition_locations: &[NonNull<VMMemoryDefinition>],
+        memory_images: Option<&MemoryImages>,
     ) -> Result<PrimaryMap<LocalMemoryIndex, Arc<dyn Memory>>, LinkError> {
         let num_imports = module.num_imported_memories;
         let mut memories: PrimaryMap<LocalMemoryIndex, _> =
@@ -77,8 +119,24 @@ pub trait Tunables: MemoryUsage {
             let ty = &module.memories[mi];
             let style = &memory_styles[mi];
             let mdl = memory_definition_locations[index];
+            let image = memory_images
+                .and_then(|images| images.get(LocalMemoryIndex::new(index - num_imports)));
+            let memory = match image {
+                Some(image) => {
+                    // The images taken from an instance may be larger
+                    // than the minimum, if its memory has grown.
+                    let image_pages = Pages::try_from(Bytes(image.len()))
+                        .map_err(|e| LinkError::Resource(e.to_string()))?;
+                    let ty = MemoryType {
+                        minimum: ty.minimum.max(image_pages),
+                        ..ty.clone()
+                    };
+                    self.create_vm_memory_with_image(&ty, style, mdl, image)
+                }
+                None => self.create_vm_memory(ty, style, mdl),
+            };
             memories.push(
-                self.create_vm_memory(ty, style, mdl)
+                memory
                     .map_err(|e| LinkError::Resource(format!("Failed to create memory: {}", e)))?,
             );
         }
@@ -0,0 +1,928 @@
+//! `coverage` is a middleware for measuring the code coverage of a
+//! WebAssembly module: how many times each basic block of each
+//! function has been executed.
+//!
+//! A counter is added at the start of each function and after each
+//! branch source or target, with the same block boundaries as
+//! [`Metering`][crate::Metering], plus the start of the `if` branches.
+//! The counters are kept in a memory added to the module, exported as
+//! [`COVERAGE_MEMORY`], which holds an `i64` per block.
+//!
+//! When the module embeds DWARF debug information, like the Rust and C
+//! code compiled to WebAssembly in debug mode, the blocks are mapped to
+//! their source lines, and the coverage can be written in the `lcov`
+//! format with [`CoverageReport::write_lcov`].
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::str;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{BinaryReader, MemoryImmediate, Operator};
+use wasmer::{
+    ExportIndex, FunctionBodyData, FunctionMiddleware, Instance, LocalFunctionIndex, MemoryType,
+    MemoryView, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Pages, WASM_PAGE_SIZE,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::MemoryIndex;
+use wasmer_vm::ModuleInfo;
+
+use crate::profiling::function_name;
+
+/// The name of the custom section describing the blocks of the module.
+///
+/// It contains a line per block: the local function index, the offset
+/// of the block in the module, and its source line and file if they
+/// are known, separated by tabs.
+const COVERAGE_SECTION: &str = "wasmer_coverage";
+
+/// The name of the memory holding the counters of the blocks, exported
+/// by the modules processed with the [`Coverage`] middleware.
+///
+/// The counter of the `n`-th block of the module, in the order of the
+/// coverage section, is the little-endian `i64` at the offset `8 * n`.
+pub const COVERAGE_MEMORY: &str = "wasmer_coverage_counters";
+
+/// The size in bytes of a counter.
+const COUNTER_SIZE: usize = 8;
+
+/// What the middleware knows about the bodies of the local functions.
+#[derive(Debug, MemoryUsage)]
+struct FunctionBodies {
+    /// The offset of the contents of the code section in the module.
+    code_section_offset: usize,
+
+    /// The offsets in the module of the blocks of each function.
+    block_offsets: Vec<Vec<usize>>,
+}
+
+/// The counters of a local function.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+struct FunctionCounters {
+    /// The memory holding the counters.
+    memory_index: MemoryIndex,
+
+    /// The index of the first block of the function among the blocks
+    /// of the module. The counters of the other blocks follow its
+    /// counter.
+    first_block: usize,
+
+    num_blocks: usize,
+}
+
+/// The module-level coverage middleware.
+///
+/// `Coverage` must be pushed before any other middleware, so that it
+/// instruments the operators of the module as they are in the module.
+///
+/// # Panic
+///
+/// An instance of `Coverage` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// index of the memory holding the counters. Attempts to use a
+/// `Coverage` instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::Coverage;
+///
+/// fn create_coverage_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     let coverage = Arc::new(Coverage::new());
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(coverage);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Coverage {
+    /// The bodies of the local functions, until the module is transformed.
+    function_bodies: Mutex<Option<FunctionBodies>>,
+
+    /// The counters of each local function.
+    counters: Mutex<Option<Vec<FunctionCounters>>>,
+}
+
+/// The function-level coverage middleware.
+pub struct FunctionCoverage {
+    /// The counters of the function, `None` for a function added by a
+    /// previous middleware.
+    counters: Option<FunctionCounters>,
+
+    /// The index of the next block.
+    next_block: usize,
+
+    /// Whether the next operator starts a block.
+    starts_block: bool,
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("counters", &self.counters)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let counters = self
+            .counters
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .get(local_function_index.index())
+            .copied();
+        Box::new(FunctionCoverage {
+            counters,
+            next_block: 0,
+            starts_block: true,
+        })
+    }
+
+    /// Finds the blocks of the function bodies.
+    fn feed_function_bodies(
+        &self,
+        _module_info: &ModuleInfo,
+        code_section_offset: usize,
+        function_bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        *self.function_bodies.lock().unwrap() = Some(FunctionBodies {
+            code_section_offset,
+            block_offsets: function_bodies.values().map(block_offsets).collect(),
+        });
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut counters = self.counters.lock().unwrap();
+
+        if counters.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+
+        let function_bodies = self
+            .function_bodies
+            .lock()
+            .unwrap()
+            .take()
+            .expect("Coverage::transform_module_info: The function bodies have not been fed to the `Coverage` middleware.");
+
+        // Map the blocks to their source lines, if the module has some
+        // valid debug information.
+        let line_rows = line_rows(module_info).unwrap_or_default();
+        let mut section = String::new();
+        let num_blocks = function_bodies
+            .block_offsets
+            .iter()
+            .map(|offsets| offsets.len())
+            .sum::<usize>();
+
+        // Append a memory large enough for all the counters, and
+        // export it.
+        let pages =
+            Pages(((num_blocks * COUNTER_SIZE + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE) as u32);
+        let memory_index = module_info
+            .memories
+            .push(MemoryType::new(pages, Some(pages), false));
+        module_info.exports.insert(
+            COVERAGE_MEMORY.to_string(),
+            ExportIndex::Memory(memory_index),
+        );
+
+        let mut function_counters = Vec::with_capacity(function_bodies.block_offsets.len());
+        let mut first_block = 0;
+        for (local_function_index, offsets) in function_bodies.block_offsets.iter().enumerate() {
+            for &offset in offsets {
+                let location = offset
+                    .checked_sub(function_bodies.code_section_offset)
+                    .and_then(|address| source_location(&line_rows, address as u64));
+                section.push_str(&format!("{}\t{}", local_function_index, offset));
+                if let Some(location) = location {
+                    section.push_str(&format!("\t{}\t{}", location.line, location.file));
+                }
+                section.push('\n');
+            }
+            function_counters.push(FunctionCounters {
+                memory_index,
+                first_block,
+                num_blocks: offsets.len(),
+            });
+            first_block += offsets.len();
+        }
+
+        let section_index = module_info
+            .custom_sections_data
+            .push(Arc::from(section.into_bytes()));
+        module_info
+            .custom_sections
+            .insert(COVERAGE_SECTION.to_string(), section_index);
+
+        *counters = Some(function_counters);
+    }
+}
+
+impl MemoryUsage for Coverage {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.function_bodies.size_of_val(tracker)
+            - mem::size_of_val(&self.function_bodies)
+            + self.counters.size_of_val(tracker)
+            - mem::size_of_val(&self.counters)
+    }
+}
+
+impl fmt::Debug for FunctionCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCoverage")
+            .field("counters", &self.counters)
+            .field("next_block", &self.next_block)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let counters = match self.counters {
+            Some(counters) => counters,
+            None => {
+                state.push_operator(operator);
+                return Ok(());
+            }
+        };
+
+        if self.starts_block {
+            if self.next_block == counters.num_blocks {
+                return Err(MiddlewareError::new(
+                    "coverage",
+                    "the operators don't match the function body, the `Coverage` middleware must be pushed before the other middlewares",
+                ));
+            }
+
+            let memarg = MemoryImmediate {
+                align: 3,
+                offset: ((counters.first_block + self.next_block) * COUNTER_SIZE) as u32,
+                memory: counters.memory_index.as_u32(),
+            };
+            state.extend(&[
+                // counters[block] += 1;
+                Operator::I32Const { value: 0 },
+                Operator::I32Const { value: 0 },
+                Operator::I64Load { memarg },
+                Operator::I64Const { value: 1 },
+                Operator::I64Add,
+                Operator::I64Store { memarg },
+            ]);
+
+            self.next_block += 1;
+        }
+
+        self.starts_block = ends_block(&operator);
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Returns whether a block starts after the given operator: after the
+/// possible sources and targets of a branch.
+fn ends_block(operator: &Operator) -> bool {
+    matches!(
+        operator,
+        Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::End
+            | Operator::Else
+            | Operator::Br { .. }
+            | Operator::BrTable { .. }
+            | Operator::BrIf { .. }
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::Return
+    )
+}
+
+/// Returns the offsets in the module of the blocks of a function body.
+///
+/// Invalid function bodies are reported by the compiler, so the blocks
+/// are only searched until the first error.
+fn block_offsets(function_body: &FunctionBodyData) -> Vec<usize> {
+    let mut reader = BinaryReader::new_with_offset(function_body.data, function_body.module_offset);
+    let mut offsets = vec![];
+
+    let mut skip_locals = || -> Result<(), wasmer::wasmparser::BinaryReaderError> {
+        for _ in 0..reader.read_var_u32()? {
+            reader.read_var_u32()?;
+            reader.read_type()?;
+        }
+        Ok(())
+    };
+    if skip_locals().is_err() {
+        return offsets;
+    }
+
+    let mut starts_block = true;
+    while !reader.eof() {
+        let offset = reader.original_position();
+        let operator = match reader.read_operator() {
+            Ok(operator) => operator,
+            Err(_) => break,
+        };
+        if starts_block {
+            offsets.push(offset);
+        }
+        starts_block = ends_block(&operator);
+    }
+
+    offsets
+}
+
+/// A row of the DWARF line tables of a module.
+#[derive(Debug)]
+struct LineRow {
+    /// The address of the row, relative to the code section.
+    address: u64,
+
+    /// The source location of the row, or `None` if the row ends a
+    /// sequence of instructions.
+    location: Option<SourceLocation>,
+}
+
+/// Reads the DWARF line tables of a module, sorted by address.
+fn line_rows(module_info: &ModuleInfo) -> Result<Vec<LineRow>, gimli::Error> {
+    let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+        let data = module_info
+            .custom_sections
+            .get(id.name())
+            .map(|index| &*module_info.custom_sections_data[*index])
+            .unwrap_or(&[]);
+        Ok(gimli::EndianSlice::new(data, gimli::LittleEndian))
+    })?;
+
+    let mut rows = vec![];
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let program = match unit.line_program.clone() {
+            Some(program) => program,
+            None => continue,
+        };
+        let mut program_rows = program.rows();
+        while let Some((header, row)) = program_rows.next_row()? {
+            let location = match (row.file(header), row.line()) {
+                (Some(file), Some(line)) if !row.end_sequence() => {
+                    let mut path = PathBuf::new();
+                    if let Some(directory) = file.directory(header) {
+                        let directory = dwarf.attr_string(&unit, directory)?;
+                        path.push(&*String::from_utf8_lossy(directory.slice()));
+                    }
+                    let name = dwarf.attr_string(&unit, file.path_name())?;
+                    path.push(&*String::from_utf8_lossy(name.slice()));
+                    Some(SourceLocation {
+                        file: path.to_string_lossy().into_owned(),
+                        line: u64::from(line),
+                    })
+                }
+                _ => None,
+            };
+            rows.push(LineRow {
+                address: row.address(),
+                location,
+            });
+        }
+    }
+
+    rows.sort_by_key(|row| row.address);
+    Ok(rows)
+}
+
+/// Returns the source location of the given address, relative to the
+/// code section.
+fn source_location(rows: &[LineRow], address: u64) -> Option<SourceLocation> {
+    let index = match rows.binary_search_by_key(&address, |row| row.address) {
+        Ok(index) => index,
+        Err(0) => return None,
+        Err(index) => index - 1,
+    };
+    rows[index].location.clone()
+}
+
+/// A source location, from the DWARF debug information of a module.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceLocation {
+    /// The path of the source file.
+    pub file: String,
+
+    /// The line in the source file, starting at 1.
+    pub line: u64,
+}
+
+/// The coverage of a basic block, as returned by [`get_coverage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoveredBlock {
+    /// The offset of the first operator of the block in the module.
+    pub offset: usize,
+
+    /// The source location of the block, if the module has debug
+    /// information.
+    pub location: Option<SourceLocation>,
+
+    /// The number of times the block has been executed.
+    pub count: u64,
+}
+
+/// The coverage of a function, as returned by [`get_coverage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoveredFunction {
+    /// The name of the function, from the `name` section, or the name it
+    /// is exported with, or else `func{index}`.
+    pub name: String,
+
+    /// The blocks of the function, in the order of the function body.
+    pub blocks: Vec<CoveredBlock>,
+}
+
+impl CoveredFunction {
+    /// Returns the number of times the function has been called.
+    pub fn calls(&self) -> u64 {
+        self.blocks.first().map_or(0, |block| block.count)
+    }
+}
+
+/// The coverage of an [`Instance`][wasmer::Instance], as returned by
+/// [`get_coverage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The functions defined in the module.
+    pub functions: Vec<CoveredFunction>,
+}
+
+impl CoverageReport {
+    /// Writes the coverage in the `lcov` tracefile format, as read by
+    /// tools like `genhtml`.
+    ///
+    /// Only the blocks with a source location are written. A line is
+    /// reported with the highest count of the blocks starting on it.
+    pub fn write_lcov<W: Write>(&self, mut out: W) -> io::Result<()> {
+        #[derive(Default)]
+        struct SourceFile<'a> {
+            functions: Vec<(u64, &'a str, u64)>,
+            lines: BTreeMap<u64, u64>,
+        }
+
+        let mut files = BTreeMap::<&str, SourceFile>::new();
+        for function in &self.functions {
+            for (index, block) in function.blocks.iter().enumerate() {
+                let location = match &block.location {
+                    Some(location) => location,
+                    None => continue,
+                };
+                let file = files.entry(&location.file).or_default();
+                if index == 0 {
+                    file.functions
+                        .push((location.line, &function.name, block.count));
+                }
+                let count = file.lines.entry(location.line).or_insert(0);
+                *count = (*count).max(block.count);
+            }
+        }
+
+        writeln!(out, "TN:")?;
+        for (path, file) in &files {
+            writeln!(out, "SF:{}", path)?;
+            for (line, name, _) in &file.functions {
+                writeln!(out, "FN:{},{}", line, name)?;
+            }
+            for (_, name, calls) in &file.functions {
+                writeln!(out, "FNDA:{},{}", calls, name)?;
+            }
+            writeln!(out, "FNF:{}", file.functions.len())?;
+            writeln!(
+                out,
+                "FNH:{}",
+                file.functions
+                    .iter()
+                    .filter(|(_, _, calls)| *calls > 0)
+                    .count()
+            )?;
+            for (line, count) in &file.lines {
+                writeln!(out, "DA:{},{}", line, count)?;
+            }
+            writeln!(out, "LF:{}", file.lines.len())?;
+            writeln!(
+                out,
+                "LH:{}",
+                file.lines.values().filter(|count| **count > 0).count()
+            )?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+}
+
+/// A block, as described in the coverage section.
+struct BlockDescription<'a> {
+    /// The index of the block among the blocks of the module, which is
+    /// the index of its counter.
+    index: usize,
+    local_function_index: usize,
+    block: usize,
+    offset: usize,
+    location: Option<(u64, &'a str)>,
+}
+
+/// Reads the coverage section of the module of an instance, and calls
+/// `f` for each block.
+fn for_each_block(instance: &Instance, mut f: impl FnMut(BlockDescription)) {
+    let section = instance
+        .module()
+        .custom_sections(COVERAGE_SECTION)
+        .next()
+        .expect("Can't get the coverage section from the Module");
+    let section = str::from_utf8(&section).expect("The coverage section is invalid");
+
+    let mut previous_function = None;
+    let mut block = 0;
+    for (index, line) in section.lines().enumerate() {
+        let mut fields = line.splitn(4, '\t');
+        let mut field = || fields.next().and_then(|field| field.parse().ok());
+        let (local_function_index, offset): (usize, usize) = field()
+            .zip(field())
+            .expect("The coverage section is invalid");
+        let source_line: Option<u64> = field();
+        let location = source_line.and_then(|line| Some((line, fields.next()?)));
+
+        if previous_function == Some(local_function_index) {
+            block += 1;
+        } else {
+            previous_function = Some(local_function_index);
+            block = 0;
+        }
+        f(BlockDescription {
+            index,
+            local_function_index,
+            block,
+            offset,
+            location,
+        });
+    }
+}
+
+/// Get the coverage of an [`Instance`][wasmer::Instance]: the number of
+/// executions of each basic block of each function defined in the
+/// module.
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance and its
+/// module.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`Coverage`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::File;
+/// use std::io;
+/// use wasmer::Instance;
+/// use wasmer_middlewares::coverage::get_coverage;
+///
+/// fn write_coverage(instance: &Instance) -> io::Result<()> {
+///     get_coverage(instance).write_lcov(File::create("coverage.lcov")?)
+/// }
+/// ```
+pub fn get_coverage(instance: &Instance) -> CoverageReport {
+    let module_info = instance.module().info();
+    let counters = counters(instance);
+    let mut functions = Vec::<CoveredFunction>::new();
+
+    for_each_block(instance, |description| {
+        if description.block == 0 {
+            functions.push(CoveredFunction {
+                name: function_name(
+                    module_info,
+                    LocalFunctionIndex::new(description.local_function_index),
+                ),
+                blocks: vec![],
+            });
+        }
+
+        let count = counters
+            .get(description.index)
+            .unwrap_or_else(|| panic!("`{}` from Instance is too small", COVERAGE_MEMORY))
+            .get();
+
+        functions.last_mut().unwrap().blocks.push(CoveredBlock {
+            offset: description.offset,
+            location: description.location.map(|(line, file)| SourceLocation {
+                file: file.to_string(),
+                line,
+            }),
+            count: u64::from_le(count),
+        });
+    });
+
+    CoverageReport { functions }
+}
+
+/// Reset all the coverage counters of an [`Instance`][wasmer::Instance]
+/// to zero.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`Coverage`] middleware at compile time, otherwise this will
+/// panic.
+pub fn reset_coverage(instance: &Instance) {
+    for counter in counters(instance).iter() {
+        counter.set(0);
+    }
+}
+
+/// Returns the counters of the blocks of an instance.
+fn counters(instance: &Instance) -> MemoryView<'_, u64> {
+    instance
+        .exports
+        .get_memory(COVERAGE_MEMORY)
+        .unwrap_or_else(|_| panic!("Can't get `{}` from Instance", COVERAGE_MEMORY))
+        .view()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $abs (export "abs") (param i32) (result i32)
+                local.get 0
+                i32.const 0
+                i32.lt_s
+                if (result i32)
+                    i32.const 0
+                    local.get 0
+                    i32.sub
+                else
+                    local.get 0
+                end)
+            (func $unused (export "unused")))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn get_coverage_works() {
+        let coverage = Arc::new(Coverage::new());
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(coverage);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let abs = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+
+        assert_eq!(abs.call(-3).unwrap(), 3);
+        assert_eq!(abs.call(4).unwrap(), 4);
+        assert_eq!(abs.call(5).unwrap(), 5);
+
+        // The blocks of `abs` are: the function entry, the `then`
+        // branch, the `else` branch, and after the `if`.
+        let report = get_coverage(&instance);
+        assert_eq!(report.functions.len(), 2);
+        let abs = &report.functions[0];
+        assert_eq!(abs.name, "abs");
+        assert_eq!(abs.calls(), 3);
+        assert_eq!(
+            abs.blocks
+                .iter()
+                .map(|block| block.count)
+                .collect::<Vec<_>>(),
+            vec![3, 1, 2, 3]
+        );
+        assert!(abs.blocks.iter().all(|block| block.location.is_none()));
+        let unused = &report.functions[1];
+        assert_eq!(unused.name, "unused");
+        assert_eq!(unused.calls(), 0);
+
+        reset_coverage(&instance);
+        assert!(get_coverage(&instance)
+            .functions
+            .iter()
+            .all(|function| function.calls() == 0));
+    }
+
+    /// Appends to a module the DWARF debug information of a single
+    /// source file, `/project/abs.rs`, where the code at address `a` of
+    /// the code section comes from the line `a + 1`.
+    fn with_line_tables(mut bytecode: Vec<u8>) -> Vec<u8> {
+        use gimli::write::{
+            Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+        };
+
+        let code_section = wasmer::wasmparser::Parser::new(0)
+            .parse_all(&bytecode)
+            .find_map(|payload| match payload.unwrap() {
+                wasmer::wasmparser::Payload::CodeSectionStart { range, .. } => Some(range),
+                _ => None,
+            })
+            .unwrap();
+        let code_size = (code_section.end - code_section.start) as u64;
+
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            LineString::String(b"/project".to_vec()),
+            LineString::String(b"abs.rs".to_vec()),
+            None,
+        );
+        let directory = program.default_directory();
+        let file = program.add_file(LineString::String(b"abs.rs".to_vec()), directory, None);
+        program.begin_sequence(Some(Address::Constant(0)));
+        for address in 0..code_size {
+            program.row().address_offset = address;
+            program.row().file = file;
+            program.row().line = address + 1;
+            program.generate_row();
+        }
+        program.end_sequence(code_size);
+        dwarf.unit.line_program = program;
+        let root = dwarf.unit.root();
+        dwarf.unit.get_mut(root).set(
+            gimli::DW_AT_comp_dir,
+            AttributeValue::String(b"/project".to_vec()),
+        );
+
+        let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+        sections
+            .for_each(|id, data| -> Result<(), ()> {
+                let data = data.slice();
+                if !data.is_empty() {
+                    // A custom section: its id, its size, and its name
+                    // followed by its contents.
+                    let name = id.name().as_bytes();
+                    let mut contents = vec![];
+                    write_u32(&mut contents, name.len() as u32);
+                    contents.extend_from_slice(name);
+                    contents.extend_from_slice(data);
+                    bytecode.push(0);
+                    write_u32(&mut bytecode, contents.len() as u32);
+                    bytecode.extend_from_slice(&contents);
+                }
+                Ok(())
+            })
+            .unwrap();
+        bytecode
+    }
+
+    /// Writes an unsigned LEB128 integer.
+    fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    #[test]
+    fn get_coverage_with_line_tables() {
+        let bytecode = bytecode();
+        let code_section_offset = wasmer::wasmparser::Parser::new(0)
+            .parse_all(&bytecode)
+            .find_map(|payload| match payload.unwrap() {
+                wasmer::wasmparser::Payload::CodeSectionStart { range, .. } => Some(range.start),
+                _ => None,
+            })
+            .unwrap();
+
+        let coverage = Arc::new(Coverage::new());
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(coverage);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, with_line_tables(bytecode)).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let report = get_coverage(&instance);
+        let blocks = report
+            .functions
+            .iter()
+            .flat_map(|function| &function.blocks)
+            .collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 5);
+        for block in blocks {
+            assert_eq!(
+                block.location,
+                Some(SourceLocation {
+                    file: "/project/abs.rs".to_string(),
+                    line: (block.offset - code_section_offset) as u64 + 1,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn write_lcov() {
+        let location = |line| {
+            Some(SourceLocation {
+                file: "src/lib.rs".to_string(),
+                line,
+            })
+        };
+        let report = CoverageReport {
+            functions: vec![
+                CoveredFunction {
+                    name: "abs".to_string(),
+                    blocks: vec![
+                        CoveredBlock {
+                            offset: 10,
+                            location: location(1),
+                            count: 3,
+                        },
+                        CoveredBlock {
+                            offset: 20,
+                            location: location(2),
+                            count: 0,
+                        },
+                        CoveredBlock {
+                            offset: 30,
+                            location: location(2),
+                            count: 2,
+                        },
+                    ],
+                },
+                CoveredFunction {
+                    name: "unused".to_string(),
+                    blocks: vec![CoveredBlock {
+                        offset: 40,
+                        location: location(5),
+                        count: 0,
+                    }],
+                },
+            ],
+        };
+
+        let mut lcov = vec![];
+        report.write_lcov(&mut lcov).unwrap();
+        assert_eq!(
+            String::from_utf8(lcov).unwrap(),
+            "TN:\n\
+             SF:src/lib.rs\n\
+             FN:1,abs\n\
+             FN:5,unused\n\
+             FNDA:3,abs\n\
+             FNDA:0,unused\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:1,3\n\
+             DA:2,2\n\
+             DA:5,0\n\
+             LF:3\n\
+             LH:2\n\
+             end_of_record\n"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Helpers for the middlewares instrumenting a module with calls to
+//! host functions.
+
+use loupe::MemoryUsage;
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::Type;
+use wasmer_types::FunctionIndex;
+use wasmer_vm::ModuleInfo;
+
+/// The type of a block.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+pub(crate) enum BlockType {
+    Empty,
+    Value(Type),
+    FuncType(u32),
+}
+
+impl BlockType {
+    /// Returns the type of a block wrapping the body of the given
+    /// function, so that the branches to the function label land after
+    /// the block instead of returning.
+    ///
+    /// A block returning multiple values needs a function type of the
+    /// type section without parameters returning the same values.
+    /// `None` is returned if the module declares none.
+    pub(crate) fn of_body(module_info: &ModuleInfo, function_index: FunctionIndex) -> Option<Self> {
+        let results = module_info.signatures[module_info.functions[function_index]].results();
+        match results {
+            [] => Some(Self::Empty),
+            [result] => Some(Self::Value(*result)),
+            _ => module_info
+                .function_types()
+                .find(|(_, signature)| {
+                    let signature = &module_info.signatures[*signature];
+                    signature.params().is_empty() && signature.results() == results
+                })
+                .map(|(type_index, _)| Self::FuncType(type_index)),
+        }
+    }
+
+    /// Returns the `wasmparser` type of the block.
+    pub(crate) fn to_wasmparser(self) -> WpTypeOrFuncType {
+        match self {
+            Self::Empty => WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            Self::Value(ty) => WpTypeOrFuncType::Type(match ty {
+                Type::I32 => WpType::I32,
+                Type::I64 => WpType::I64,
+                Type::F32 => WpType::F32,
+                Type::F64 => WpType::F64,
+                Type::V128 => WpType::V128,
+                Type::ExternRef => WpType::ExternRef,
+                Type::FuncRef => WpType::FuncRef,
+            }),
+            Self::FuncType(index) => WpTypeOrFuncType::FuncType(index),
+        }
+    }
+}
+
+/// Tracks the blocks of a function body to find where the function
+/// returns.
+#[derive(Debug, Default)]
+pub(crate) struct FunctionExits {
+    /// The number of blocks enclosing the current operator, not
+    /// including the function body.
+    depth: u32,
+}
+
+impl FunctionExits {
+    /// Returns whether the function returns after the given operator:
+    /// it is a `return`, a tail call, or the `end` of the function body.
+    pub(crate) fn is_exit(&mut self, operator: &Operator) -> bool {
+        match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => {
+                self.depth += 1;
+                false
+            }
+            Operator::End if self.depth > 0 => {
+                self.depth -= 1;
+                false
+            }
+            Operator::End
+            | Operator::Return
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. } => true,
+            _ => false,
+        }
+    }
+}
@@ -1,6 +1,20 @@
+pub mod coverage;
+mod instrumentation;
 pub mod metering;
+pub mod operator_filter;
+pub mod operators;
+pub mod profiling;
+pub mod sanitizer;
+pub mod stack_limit;
+pub mod tracing;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use coverage::Coverage;
 pub use metering::Metering;
+pub use operator_filter::OperatorFilter;
+pub use profiling::Profiling;
+pub use sanitizer::Sanitizer;
+pub use stack_limit::StackLimit;
+pub use tracing::Tracing;
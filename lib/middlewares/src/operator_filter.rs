@@ -0,0 +1,414 @@
+//! `operator_filter` is a middleware for rejecting the modules using
+//! some operators, like floating-point operators or `memory.grow`,
+//! while they are compiled.
+//!
+//! Sandboxing policies often forbid some operators. Checking them with
+//! `OperatorFilter` doesn't need a separate parsing pass over the
+//! module: the compilation fails with a `MiddlewareError` naming the
+//! first rejected operator, the function using it and its offset in
+//! the module.
+
+use crate::operators::{
+    is_operator_name, operator_info, OperatorCategory, OperatorInfo, OPERATOR_NAMES,
+};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::Operator;
+use wasmer::{
+    FunctionMiddleware, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::FunctionIndex;
+use wasmer_vm::ModuleInfo;
+
+/// A class of operators that can be allowed or denied as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+pub enum OperatorClass {
+    /// The scalar floating-point operators, including the loads,
+    /// stores, constants and conversions of `f32` and `f64` values.
+    Float,
+    /// The operators of the SIMD proposal.
+    Simd,
+    /// The atomic operators of the threads proposal.
+    Atomic,
+    /// The `memory.grow` operator.
+    MemoryGrow,
+    /// The `call_indirect` and `return_call_indirect` operators.
+    IndirectCall,
+}
+
+impl OperatorClass {
+    /// Returns the class of an operator, if any.
+    fn of(operator: &Operator, info: &OperatorInfo) -> Option<Self> {
+        match operator {
+            Operator::MemoryGrow { .. } => return Some(Self::MemoryGrow),
+            Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+                return Some(Self::IndirectCall)
+            }
+            _ => {}
+        }
+
+        match info.category {
+            OperatorCategory::Simd => Some(Self::Simd),
+            OperatorCategory::Atomic => Some(Self::Atomic),
+            _ if info.scalar_float => Some(Self::Float),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the configured operators are allowed or denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+enum Mode {
+    Allow,
+    Deny,
+}
+
+/// The operators configured in an `OperatorFilter`.
+#[derive(Debug, MemoryUsage)]
+struct Policy {
+    mode: Mode,
+    classes: HashSet<OperatorClass>,
+    operators: HashSet<String>,
+}
+
+impl Policy {
+    fn allows(&self, operator: &Operator, info: &OperatorInfo) -> bool {
+        let class = OperatorClass::of(operator, info);
+        let listed = self.operators.contains(info.name)
+            || class.map_or(false, |class| self.classes.contains(&class));
+        match self.mode {
+            Mode::Allow => listed || class.is_none(),
+            Mode::Deny => !listed,
+        }
+    }
+}
+
+/// What the function middlewares need to know about the module.
+#[derive(Debug, MemoryUsage)]
+struct ModuleState {
+    num_imported_functions: usize,
+
+    function_names: HashMap<FunctionIndex, String>,
+}
+
+/// The module-level operator filter middleware.
+///
+/// Operators are configured either by class, with [`OperatorClass`],
+/// or individually by name. The names are the ones of the
+/// `wasmer::wasmparser::Operator` variants, like `F32Add` or
+/// `MemoryGrow`.
+///
+/// An `OperatorFilter` is either:
+///
+/// * a deny list, created with [`OperatorFilter::deny`], rejecting the
+///   configured operators and classes, or
+/// * an allow list, created with [`OperatorFilter::allow`], rejecting
+///   all the operators belonging to an `OperatorClass`, except the
+///   configured ones. The operators not belonging to any class, like
+///   the integer and control flow operators, are always allowed.
+///
+/// # Panic
+///
+/// An instance of `OperatorFilter` should _not_ be shared among
+/// different modules, since it tracks module-specific information like
+/// the function names. Attempts to use an `OperatorFilter` instance
+/// from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::{operator_filter::OperatorClass, OperatorFilter};
+///
+/// fn create_operator_filter_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's reject the floating-point operators and `memory.grow`.
+///     let operator_filter = Arc::new(
+///         OperatorFilter::deny()
+///             .class(OperatorClass::Float)
+///             .operator("MemoryGrow"),
+///     );
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(operator_filter);
+/// }
+/// ```
+pub struct OperatorFilter {
+    policy: Arc<Policy>,
+
+    /// The module information used by the function middlewares.
+    state: Mutex<Option<Arc<ModuleState>>>,
+}
+
+/// The function-level operator filter middleware.
+pub struct FunctionOperatorFilter {
+    policy: Arc<Policy>,
+
+    state: Arc<ModuleState>,
+
+    /// The index of the function being compiled.
+    function_index: FunctionIndex,
+}
+
+impl OperatorFilter {
+    /// Creates an `OperatorFilter` rejecting the configured operators.
+    pub fn deny() -> Self {
+        Self::new(Mode::Deny)
+    }
+
+    /// Creates an `OperatorFilter` rejecting all the operators of the
+    /// classes, except the configured ones.
+    pub fn allow() -> Self {
+        Self::new(Mode::Allow)
+    }
+
+    fn new(mode: Mode) -> Self {
+        Self {
+            policy: Arc::new(Policy {
+                mode,
+                classes: HashSet::new(),
+                operators: HashSet::new(),
+            }),
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Adds a class of operators to the configured operators.
+    pub fn class(mut self, class: OperatorClass) -> Self {
+        self.policy_mut().classes.insert(class);
+        self
+    }
+
+    /// Adds an operator, given by name, to the configured operators.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `name` isn't the name of a
+    /// `wasmer::wasmparser::Operator` variant, so that a misspelled
+    /// operator isn't silently ignored.
+    pub fn operator(mut self, name: &str) -> Self {
+        if !is_operator_name(name) {
+            panic!(
+                "OperatorFilter::operator: unknown operator `{}`, expected one of: {}",
+                name,
+                OPERATOR_NAMES.join(", ")
+            );
+        }
+        self.policy_mut().operators.insert(name.to_string());
+        self
+    }
+
+    fn policy_mut(&mut self) -> &mut Policy {
+        Arc::get_mut(&mut self.policy)
+            .expect("The policy can't be changed once the middleware is in use")
+    }
+}
+
+impl fmt::Debug for OperatorFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperatorFilter")
+            .field("mode", &self.policy.mode)
+            .field("classes", &self.policy.classes)
+            .field("operators", &self.policy.operators)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for OperatorFilter {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let function_index =
+            FunctionIndex::new(state.num_imported_functions + local_function_index.index());
+        Box::new(FunctionOperatorFilter {
+            policy: self.policy.clone(),
+            state,
+            function_index,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("OperatorFilter::transform_module_info: Attempting to use an `OperatorFilter` middleware from multiple modules.");
+        }
+
+        *state = Some(Arc::new(ModuleState {
+            num_imported_functions: module_info.num_imported_functions,
+            function_names: module_info.function_names.clone(),
+        }));
+    }
+}
+
+impl MemoryUsage for OperatorFilter {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.policy.size_of_val(tracker) - mem::size_of_val(&self.policy)
+            + self.state.size_of_val(tracker)
+            - mem::size_of_val(&self.state)
+    }
+}
+
+impl fmt::Debug for FunctionOperatorFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionOperatorFilter")
+            .field("policy", &self.policy)
+            .field("function_index", &self.function_index)
+            .finish()
+    }
+}
+
+impl FunctionOperatorFilter {
+    /// Describes the function being compiled, for error messages.
+    fn function_description(&self) -> String {
+        match self.state.function_names.get(&self.function_index) {
+            Some(name) => format!("`{}` (index {})", name, self.function_index.index()),
+            None => format!("{}", self.function_index.index()),
+        }
+    }
+}
+
+impl FunctionMiddleware for FunctionOperatorFilter {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let info = operator_info(&operator);
+
+        if !self.policy.allows(&operator, &info) {
+            return Err(MiddlewareError::new(
+                "operator_filter",
+                format!(
+                    "operator `{}` is not allowed, in function {} at offset {}",
+                    info.name,
+                    self.function_description(),
+                    state.operator_offset()
+                ),
+            ));
+        }
+
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{wat2wasm, CompileError, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (memory 1)
+            (func $grow (param i32) (result i32)
+                local.get 0
+                memory.grow)
+            (func $average (export "average") (param f32 f32) (result f32)
+                local.get 0
+                local.get 1
+                f32.add
+                f32.const 2
+                f32.div)
+            (func $sum (param $x i32) (param $y i32) (result i32)
+                local.get $x
+                local.get $y
+                i32.add))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn compile(operator_filter: OperatorFilter) -> Result<Module, CompileError> {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(operator_filter));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        Module::new(&store, bytecode())
+    }
+
+    #[test]
+    fn classify_operators() {
+        let class = |operator: Operator| OperatorClass::of(&operator, &operator_info(&operator));
+
+        assert_eq!(class(Operator::F32Add), Some(OperatorClass::Float));
+        assert_eq!(class(Operator::I64TruncF64S), Some(OperatorClass::Float));
+        assert_eq!(class(Operator::F32x4Add), Some(OperatorClass::Simd));
+        assert_eq!(
+            class(Operator::AtomicFence { flags: 0 }),
+            Some(OperatorClass::Atomic)
+        );
+        assert_eq!(
+            class(Operator::MemoryGrow {
+                mem: 0,
+                mem_byte: 0
+            }),
+            Some(OperatorClass::MemoryGrow)
+        );
+        assert_eq!(
+            class(Operator::CallIndirect {
+                index: 0,
+                table_index: 0
+            }),
+            Some(OperatorClass::IndirectCall)
+        );
+        assert_eq!(class(Operator::I32Add), None);
+    }
+
+    #[test]
+    fn deny_operators() {
+        assert!(compile(OperatorFilter::deny().class(OperatorClass::Simd)).is_ok());
+
+        let error = compile(OperatorFilter::deny().class(OperatorClass::Float)).unwrap_err();
+        // `f32.add` follows `local.get 0` and `local.get 1`.
+        let offset = bytecode()
+            .windows(5)
+            .position(|bytes| bytes == [0x20, 0x00, 0x20, 0x01, 0x92])
+            .unwrap()
+            + 4;
+        assert!(error.to_string().contains(&format!(
+            "operator `F32Add` is not allowed, in function `average` (index 1) at offset {}",
+            offset
+        )));
+
+        let error = compile(OperatorFilter::deny().operator("MemoryGrow")).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("operator `MemoryGrow` is not allowed, in function `grow` (index 0)"));
+    }
+
+    #[test]
+    fn allow_operators() {
+        let error = compile(OperatorFilter::allow().class(OperatorClass::Float)).unwrap_err();
+        assert!(error.to_string().contains("operator `MemoryGrow`"));
+
+        let error = compile(OperatorFilter::allow().operator("MemoryGrow")).unwrap_err();
+        assert!(error.to_string().contains("operator `F32Add`"));
+
+        assert!(compile(
+            OperatorFilter::allow()
+                .class(OperatorClass::Float)
+                .class(OperatorClass::MemoryGrow)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown operator `MemoryGrwo`")]
+    fn misspelled_operator() {
+        OperatorFilter::deny().operator("MemoryGrwo");
+    }
+}
@@ -0,0 +1,1158 @@
+//! `operators` classifies the WebAssembly operators, for the
+//! middlewares and the embedders filtering or pricing operators by
+//! kind rather than one by one.
+
+use loupe::MemoryUsage;
+use wasmer::wasmparser::Operator;
+
+/// The category of an operator, following the sections of the
+/// WebAssembly specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+pub enum OperatorCategory {
+    /// `unreachable`, `nop`, the blocks, the branches, `return`, and
+    /// the operators of the exception handling proposal.
+    Control,
+    /// The direct and indirect calls, including the tail calls.
+    Call,
+    /// `drop` and `select`.
+    Parametric,
+    /// The operators reading and writing locals and globals.
+    Variable,
+    /// The scalar constants.
+    Constant,
+    /// The scalar loads.
+    Load,
+    /// The scalar stores.
+    Store,
+    /// `memory.size`, `memory.grow`, and the bulk memory operators on
+    /// memories and data segments.
+    Memory,
+    /// The integer operators, including the conversions to integers.
+    Integer,
+    /// The floating-point operators, including the conversions to
+    /// floating-point values.
+    Float,
+    /// The operators on references, tables and element segments.
+    Reference,
+    /// The operators of the SIMD proposal, including their loads,
+    /// stores and constants.
+    Simd,
+    /// The atomic operators of the threads proposal.
+    Atomic,
+}
+
+/// What [`operator_info`] knows about an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorInfo {
+    /// The name of the `Operator` variant, like `F32Add` or `Call`.
+    pub name: &'static str,
+    /// The category of the operator.
+    pub category: OperatorCategory,
+    /// Whether the operator handles scalar `f32` or `f64` values:
+    /// the floating-point operators, and the loads, stores, constants
+    /// and conversions of floating-point values.
+    pub scalar_float: bool,
+}
+
+/// The names of the operators, as given by [`operator_info`], sorted.
+pub const OPERATOR_NAMES: &[&str] = &[
+    "AtomicFence",
+    "Block",
+    "Br",
+    "BrIf",
+    "BrTable",
+    "Call",
+    "CallIndirect",
+    "Catch",
+    "CatchAll",
+    "DataDrop",
+    "Delegate",
+    "Drop",
+    "ElemDrop",
+    "Else",
+    "End",
+    "F32Abs",
+    "F32Add",
+    "F32Ceil",
+    "F32Const",
+    "F32ConvertI32S",
+    "F32ConvertI32U",
+    "F32ConvertI64S",
+    "F32ConvertI64U",
+    "F32Copysign",
+    "F32DemoteF64",
+    "F32Div",
+    "F32Eq",
+    "F32Floor",
+    "F32Ge",
+    "F32Gt",
+    "F32Le",
+    "F32Load",
+    "F32Lt",
+    "F32Max",
+    "F32Min",
+    "F32Mul",
+    "F32Ne",
+    "F32Nearest",
+    "F32Neg",
+    "F32ReinterpretI32",
+    "F32Sqrt",
+    "F32Store",
+    "F32Sub",
+    "F32Trunc",
+    "F32x4Abs",
+    "F32x4Add",
+    "F32x4Ceil",
+    "F32x4ConvertI32x4S",
+    "F32x4ConvertI32x4U",
+    "F32x4DemoteF64x2Zero",
+    "F32x4Div",
+    "F32x4Eq",
+    "F32x4ExtractLane",
+    "F32x4Floor",
+    "F32x4Ge",
+    "F32x4Gt",
+    "F32x4Le",
+    "F32x4Lt",
+    "F32x4Max",
+    "F32x4Min",
+    "F32x4Mul",
+    "F32x4Ne",
+    "F32x4Nearest",
+    "F32x4Neg",
+    "F32x4PMax",
+    "F32x4PMin",
+    "F32x4ReplaceLane",
+    "F32x4Splat",
+    "F32x4Sqrt",
+    "F32x4Sub",
+    "F32x4Trunc",
+    "F64Abs",
+    "F64Add",
+    "F64Ceil",
+    "F64Const",
+    "F64ConvertI32S",
+    "F64ConvertI32U",
+    "F64ConvertI64S",
+    "F64ConvertI64U",
+    "F64Copysign",
+    "F64Div",
+    "F64Eq",
+    "F64Floor",
+    "F64Ge",
+    "F64Gt",
+    "F64Le",
+    "F64Load",
+    "F64Lt",
+    "F64Max",
+    "F64Min",
+    "F64Mul",
+    "F64Ne",
+    "F64Nearest",
+    "F64Neg",
+    "F64PromoteF32",
+    "F64ReinterpretI64",
+    "F64Sqrt",
+    "F64Store",
+    "F64Sub",
+    "F64Trunc",
+    "F64x2Abs",
+    "F64x2Add",
+    "F64x2Ceil",
+    "F64x2ConvertLowI32x4S",
+    "F64x2ConvertLowI32x4U",
+    "F64x2Div",
+    "F64x2Eq",
+    "F64x2ExtractLane",
+    "F64x2Floor",
+    "F64x2Ge",
+    "F64x2Gt",
+    "F64x2Le",
+    "F64x2Lt",
+    "F64x2Max",
+    "F64x2Min",
+    "F64x2Mul",
+    "F64x2Ne",
+    "F64x2Nearest",
+    "F64x2Neg",
+    "F64x2PMax",
+    "F64x2PMin",
+    "F64x2PromoteLowF32x4",
+    "F64x2ReplaceLane",
+    "F64x2Splat",
+    "F64x2Sqrt",
+    "F64x2Sub",
+    "F64x2Trunc",
+    "GlobalGet",
+    "GlobalSet",
+    "I16x8Abs",
+    "I16x8Add",
+    "I16x8AddSatS",
+    "I16x8AddSatU",
+    "I16x8AllTrue",
+    "I16x8Bitmask",
+    "I16x8Eq",
+    "I16x8ExtAddPairwiseI8x16S",
+    "I16x8ExtAddPairwiseI8x16U",
+    "I16x8ExtMulHighI8x16S",
+    "I16x8ExtMulHighI8x16U",
+    "I16x8ExtMulLowI8x16S",
+    "I16x8ExtMulLowI8x16U",
+    "I16x8ExtendHighI8x16S",
+    "I16x8ExtendHighI8x16U",
+    "I16x8ExtendLowI8x16S",
+    "I16x8ExtendLowI8x16U",
+    "I16x8ExtractLaneS",
+    "I16x8ExtractLaneU",
+    "I16x8GeS",
+    "I16x8GeU",
+    "I16x8GtS",
+    "I16x8GtU",
+    "I16x8LeS",
+    "I16x8LeU",
+    "I16x8LtS",
+    "I16x8LtU",
+    "I16x8MaxS",
+    "I16x8MaxU",
+    "I16x8MinS",
+    "I16x8MinU",
+    "I16x8Mul",
+    "I16x8NarrowI32x4S",
+    "I16x8NarrowI32x4U",
+    "I16x8Ne",
+    "I16x8Neg",
+    "I16x8Q15MulrSatS",
+    "I16x8ReplaceLane",
+    "I16x8RoundingAverageU",
+    "I16x8Shl",
+    "I16x8ShrS",
+    "I16x8ShrU",
+    "I16x8Splat",
+    "I16x8Sub",
+    "I16x8SubSatS",
+    "I16x8SubSatU",
+    "I32Add",
+    "I32And",
+    "I32AtomicLoad",
+    "I32AtomicLoad16U",
+    "I32AtomicLoad8U",
+    "I32AtomicRmw16AddU",
+    "I32AtomicRmw16AndU",
+    "I32AtomicRmw16CmpxchgU",
+    "I32AtomicRmw16OrU",
+    "I32AtomicRmw16SubU",
+    "I32AtomicRmw16XchgU",
+    "I32AtomicRmw16XorU",
+    "I32AtomicRmw8AddU",
+    "I32AtomicRmw8AndU",
+    "I32AtomicRmw8CmpxchgU",
+    "I32AtomicRmw8OrU",
+    "I32AtomicRmw8SubU",
+    "I32AtomicRmw8XchgU",
+    "I32AtomicRmw8XorU",
+    "I32AtomicRmwAdd",
+    "I32AtomicRmwAnd",
+    "I32AtomicRmwCmpxchg",
+    "I32AtomicRmwOr",
+    "I32AtomicRmwSub",
+    "I32AtomicRmwXchg",
+    "I32AtomicRmwXor",
+    "I32AtomicStore",
+    "I32AtomicStore16",
+    "I32AtomicStore8",
+    "I32Clz",
+    "I32Const",
+    "I32Ctz",
+    "I32DivS",
+    "I32DivU",
+    "I32Eq",
+    "I32Eqz",
+    "I32Extend16S",
+    "I32Extend8S",
+    "I32GeS",
+    "I32GeU",
+    "I32GtS",
+    "I32GtU",
+    "I32LeS",
+    "I32LeU",
+    "I32Load",
+    "I32Load16S",
+    "I32Load16U",
+    "I32Load8S",
+    "I32Load8U",
+    "I32LtS",
+    "I32LtU",
+    "I32Mul",
+    "I32Ne",
+    "I32Or",
+    "I32Popcnt",
+    "I32ReinterpretF32",
+    "I32RemS",
+    "I32RemU",
+    "I32Rotl",
+    "I32Rotr",
+    "I32Shl",
+    "I32ShrS",
+    "I32ShrU",
+    "I32Store",
+    "I32Store16",
+    "I32Store8",
+    "I32Sub",
+    "I32TruncF32S",
+    "I32TruncF32U",
+    "I32TruncF64S",
+    "I32TruncF64U",
+    "I32TruncSatF32S",
+    "I32TruncSatF32U",
+    "I32TruncSatF64S",
+    "I32TruncSatF64U",
+    "I32WrapI64",
+    "I32Xor",
+    "I32x4Abs",
+    "I32x4Add",
+    "I32x4AllTrue",
+    "I32x4Bitmask",
+    "I32x4DotI16x8S",
+    "I32x4Eq",
+    "I32x4ExtAddPairwiseI16x8S",
+    "I32x4ExtAddPairwiseI16x8U",
+    "I32x4ExtMulHighI16x8S",
+    "I32x4ExtMulHighI16x8U",
+    "I32x4ExtMulLowI16x8S",
+    "I32x4ExtMulLowI16x8U",
+    "I32x4ExtendHighI16x8S",
+    "I32x4ExtendHighI16x8U",
+    "I32x4ExtendLowI16x8S",
+    "I32x4ExtendLowI16x8U",
+    "I32x4ExtractLane",
+    "I32x4GeS",
+    "I32x4GeU",
+    "I32x4GtS",
+    "I32x4GtU",
+    "I32x4LeS",
+    "I32x4LeU",
+    "I32x4LtS",
+    "I32x4LtU",
+    "I32x4MaxS",
+    "I32x4MaxU",
+    "I32x4MinS",
+    "I32x4MinU",
+    "I32x4Mul",
+    "I32x4Ne",
+    "I32x4Neg",
+    "I32x4ReplaceLane",
+    "I32x4Shl",
+    "I32x4ShrS",
+    "I32x4ShrU",
+    "I32x4Splat",
+    "I32x4Sub",
+    "I32x4TruncSatF32x4S",
+    "I32x4TruncSatF32x4U",
+    "I32x4TruncSatF64x2SZero",
+    "I32x4TruncSatF64x2UZero",
+    "I64Add",
+    "I64And",
+    "I64AtomicLoad",
+    "I64AtomicLoad16U",
+    "I64AtomicLoad32U",
+    "I64AtomicLoad8U",
+    "I64AtomicRmw16AddU",
+    "I64AtomicRmw16AndU",
+    "I64AtomicRmw16CmpxchgU",
+    "I64AtomicRmw16OrU",
+    "I64AtomicRmw16SubU",
+    "I64AtomicRmw16XchgU",
+    "I64AtomicRmw16XorU",
+    "I64AtomicRmw32AddU",
+    "I64AtomicRmw32AndU",
+    "I64AtomicRmw32CmpxchgU",
+    "I64AtomicRmw32OrU",
+    "I64AtomicRmw32SubU",
+    "I64AtomicRmw32XchgU",
+    "I64AtomicRmw32XorU",
+    "I64AtomicRmw8AddU",
+    "I64AtomicRmw8AndU",
+    "I64AtomicRmw8CmpxchgU",
+    "I64AtomicRmw8OrU",
+    "I64AtomicRmw8SubU",
+    "I64AtomicRmw8XchgU",
+    "I64AtomicRmw8XorU",
+    "I64AtomicRmwAdd",
+    "I64AtomicRmwAnd",
+    "I64AtomicRmwCmpxchg",
+    "I64AtomicRmwOr",
+    "I64AtomicRmwSub",
+    "I64AtomicRmwXchg",
+    "I64AtomicRmwXor",
+    "I64AtomicStore",
+    "I64AtomicStore16",
+    "I64AtomicStore32",
+    "I64AtomicStore8",
+    "I64Clz",
+    "I64Const",
+    "I64Ctz",
+    "I64DivS",
+    "I64DivU",
+    "I64Eq",
+    "I64Eqz",
+    "I64Extend16S",
+    "I64Extend32S",
+    "I64Extend8S",
+    "I64ExtendI32S",
+    "I64ExtendI32U",
+    "I64GeS",
+    "I64GeU",
+    "I64GtS",
+    "I64GtU",
+    "I64LeS",
+    "I64LeU",
+    "I64Load",
+    "I64Load16S",
+    "I64Load16U",
+    "I64Load32S",
+    "I64Load32U",
+    "I64Load8S",
+    "I64Load8U",
+    "I64LtS",
+    "I64LtU",
+    "I64Mul",
+    "I64Ne",
+    "I64Or",
+    "I64Popcnt",
+    "I64ReinterpretF64",
+    "I64RemS",
+    "I64RemU",
+    "I64Rotl",
+    "I64Rotr",
+    "I64Shl",
+    "I64ShrS",
+    "I64ShrU",
+    "I64Store",
+    "I64Store16",
+    "I64Store32",
+    "I64Store8",
+    "I64Sub",
+    "I64TruncF32S",
+    "I64TruncF32U",
+    "I64TruncF64S",
+    "I64TruncF64U",
+    "I64TruncSatF32S",
+    "I64TruncSatF32U",
+    "I64TruncSatF64S",
+    "I64TruncSatF64U",
+    "I64Xor",
+    "I64x2Abs",
+    "I64x2Add",
+    "I64x2AllTrue",
+    "I64x2Bitmask",
+    "I64x2Eq",
+    "I64x2ExtMulHighI32x4S",
+    "I64x2ExtMulHighI32x4U",
+    "I64x2ExtMulLowI32x4S",
+    "I64x2ExtMulLowI32x4U",
+    "I64x2ExtendHighI32x4S",
+    "I64x2ExtendHighI32x4U",
+    "I64x2ExtendLowI32x4S",
+    "I64x2ExtendLowI32x4U",
+    "I64x2ExtractLane",
+    "I64x2GeS",
+    "I64x2GtS",
+    "I64x2LeS",
+    "I64x2LtS",
+    "I64x2Mul",
+    "I64x2Ne",
+    "I64x2Neg",
+    "I64x2ReplaceLane",
+    "I64x2Shl",
+    "I64x2ShrS",
+    "I64x2ShrU",
+    "I64x2Splat",
+    "I64x2Sub",
+    "I8x16Abs",
+    "I8x16Add",
+    "I8x16AddSatS",
+    "I8x16AddSatU",
+    "I8x16AllTrue",
+    "I8x16Bitmask",
+    "I8x16Eq",
+    "I8x16ExtractLaneS",
+    "I8x16ExtractLaneU",
+    "I8x16GeS",
+    "I8x16GeU",
+    "I8x16GtS",
+    "I8x16GtU",
+    "I8x16LeS",
+    "I8x16LeU",
+    "I8x16LtS",
+    "I8x16LtU",
+    "I8x16MaxS",
+    "I8x16MaxU",
+    "I8x16MinS",
+    "I8x16MinU",
+    "I8x16NarrowI16x8S",
+    "I8x16NarrowI16x8U",
+    "I8x16Ne",
+    "I8x16Neg",
+    "I8x16Popcnt",
+    "I8x16ReplaceLane",
+    "I8x16RoundingAverageU",
+    "I8x16Shl",
+    "I8x16ShrS",
+    "I8x16ShrU",
+    "I8x16Shuffle",
+    "I8x16Splat",
+    "I8x16Sub",
+    "I8x16SubSatS",
+    "I8x16SubSatU",
+    "I8x16Swizzle",
+    "If",
+    "LocalGet",
+    "LocalSet",
+    "LocalTee",
+    "Loop",
+    "MemoryAtomicNotify",
+    "MemoryAtomicWait32",
+    "MemoryAtomicWait64",
+    "MemoryCopy",
+    "MemoryFill",
+    "MemoryGrow",
+    "MemoryInit",
+    "MemorySize",
+    "Nop",
+    "RefFunc",
+    "RefIsNull",
+    "RefNull",
+    "Rethrow",
+    "Return",
+    "ReturnCall",
+    "ReturnCallIndirect",
+    "Select",
+    "TableCopy",
+    "TableFill",
+    "TableGet",
+    "TableGrow",
+    "TableInit",
+    "TableSet",
+    "TableSize",
+    "Throw",
+    "Try",
+    "TypedSelect",
+    "Unreachable",
+    "Unwind",
+    "V128And",
+    "V128AndNot",
+    "V128AnyTrue",
+    "V128Bitselect",
+    "V128Const",
+    "V128Load",
+    "V128Load16Lane",
+    "V128Load16Splat",
+    "V128Load16x4S",
+    "V128Load16x4U",
+    "V128Load32Lane",
+    "V128Load32Splat",
+    "V128Load32Zero",
+    "V128Load32x2S",
+    "V128Load32x2U",
+    "V128Load64Lane",
+    "V128Load64Splat",
+    "V128Load64Zero",
+    "V128Load8Lane",
+    "V128Load8Splat",
+    "V128Load8x8S",
+    "V128Load8x8U",
+    "V128Not",
+    "V128Or",
+    "V128Store",
+    "V128Store16Lane",
+    "V128Store32Lane",
+    "V128Store64Lane",
+    "V128Store8Lane",
+    "V128Xor",
+];
+
+/// Returns whether `name` is the name of an operator, as given by
+/// [`operator_info`].
+pub fn is_operator_name(name: &str) -> bool {
+    OPERATOR_NAMES.binary_search(&name).is_ok()
+}
+
+/// Describes an operator.
+pub fn operator_info(operator: &Operator) -> OperatorInfo {
+    use OperatorCategory::*;
+
+    // No wildcard: a new operator must be classified, and its name
+    // added to `OPERATOR_NAMES`, when `wasmparser` is upgraded.
+    let (name, category, scalar_float) = match operator {
+        Operator::AtomicFence { .. } => ("AtomicFence", Atomic, false),
+        Operator::Block { .. } => ("Block", Control, false),
+        Operator::Br { .. } => ("Br", Control, false),
+        Operator::BrIf { .. } => ("BrIf", Control, false),
+        Operator::BrTable { .. } => ("BrTable", Control, false),
+        Operator::Call { .. } => ("Call", Call, false),
+        Operator::CallIndirect { .. } => ("CallIndirect", Call, false),
+        Operator::Catch { .. } => ("Catch", Control, false),
+        Operator::CatchAll => ("CatchAll", Control, false),
+        Operator::DataDrop { .. } => ("DataDrop", Memory, false),
+        Operator::Delegate { .. } => ("Delegate", Control, false),
+        Operator::Drop => ("Drop", Parametric, false),
+        Operator::ElemDrop { .. } => ("ElemDrop", Reference, false),
+        Operator::Else => ("Else", Control, false),
+        Operator::End => ("End", Control, false),
+        Operator::F32Abs => ("F32Abs", Float, true),
+        Operator::F32Add => ("F32Add", Float, true),
+        Operator::F32Ceil => ("F32Ceil", Float, true),
+        Operator::F32Const { .. } => ("F32Const", Constant, true),
+        Operator::F32ConvertI32S => ("F32ConvertI32S", Float, true),
+        Operator::F32ConvertI32U => ("F32ConvertI32U", Float, true),
+        Operator::F32ConvertI64S => ("F32ConvertI64S", Float, true),
+        Operator::F32ConvertI64U => ("F32ConvertI64U", Float, true),
+        Operator::F32Copysign => ("F32Copysign", Float, true),
+        Operator::F32DemoteF64 => ("F32DemoteF64", Float, true),
+        Operator::F32Div => ("F32Div", Float, true),
+        Operator::F32Eq => ("F32Eq", Float, true),
+        Operator::F32Floor => ("F32Floor", Float, true),
+        Operator::F32Ge => ("F32Ge", Float, true),
+        Operator::F32Gt => ("F32Gt", Float, true),
+        Operator::F32Le => ("F32Le", Float, true),
+        Operator::F32Load { .. } => ("F32Load", Load, true),
+        Operator::F32Lt => ("F32Lt", Float, true),
+        Operator::F32Max => ("F32Max", Float, true),
+        Operator::F32Min => ("F32Min", Float, true),
+        Operator::F32Mul => ("F32Mul", Float, true),
+        Operator::F32Ne => ("F32Ne", Float, true),
+        Operator::F32Nearest => ("F32Nearest", Float, true),
+        Operator::F32Neg => ("F32Neg", Float, true),
+        Operator::F32ReinterpretI32 => ("F32ReinterpretI32", Float, true),
+        Operator::F32Sqrt => ("F32Sqrt", Float, true),
+        Operator::F32Store { .. } => ("F32Store", Store, true),
+        Operator::F32Sub => ("F32Sub", Float, true),
+        Operator::F32Trunc => ("F32Trunc", Float, true),
+        Operator::F32x4Abs => ("F32x4Abs", Simd, false),
+        Operator::F32x4Add => ("F32x4Add", Simd, false),
+        Operator::F32x4Ceil => ("F32x4Ceil", Simd, false),
+        Operator::F32x4ConvertI32x4S => ("F32x4ConvertI32x4S", Simd, false),
+        Operator::F32x4ConvertI32x4U => ("F32x4ConvertI32x4U", Simd, false),
+        Operator::F32x4DemoteF64x2Zero => ("F32x4DemoteF64x2Zero", Simd, false),
+        Operator::F32x4Div => ("F32x4Div", Simd, false),
+        Operator::F32x4Eq => ("F32x4Eq", Simd, false),
+        Operator::F32x4ExtractLane { .. } => ("F32x4ExtractLane", Simd, false),
+        Operator::F32x4Floor => ("F32x4Floor", Simd, false),
+        Operator::F32x4Ge => ("F32x4Ge", Simd, false),
+        Operator::F32x4Gt => ("F32x4Gt", Simd, false),
+        Operator::F32x4Le => ("F32x4Le", Simd, false),
+        Operator::F32x4Lt => ("F32x4Lt", Simd, false),
+        Operator::F32x4Max => ("F32x4Max", Simd, false),
+        Operator::F32x4Min => ("F32x4Min", Simd, false),
+        Operator::F32x4Mul => ("F32x4Mul", Simd, false),
+        Operator::F32x4Ne => ("F32x4Ne", Simd, false),
+        Operator::F32x4Nearest => ("F32x4Nearest", Simd, false),
+        Operator::F32x4Neg => ("F32x4Neg", Simd, false),
+        Operator::F32x4PMax => ("F32x4PMax", Simd, false),
+        Operator::F32x4PMin => ("F32x4PMin", Simd, false),
+        Operator::F32x4ReplaceLane { .. } => ("F32x4ReplaceLane", Simd, false),
+        Operator::F32x4Splat => ("F32x4Splat", Simd, false),
+        Operator::F32x4Sqrt => ("F32x4Sqrt", Simd, false),
+        Operator::F32x4Sub => ("F32x4Sub", Simd, false),
+        Operator::F32x4Trunc => ("F32x4Trunc", Simd, false),
+        Operator::F64Abs => ("F64Abs", Float, true),
+        Operator::F64Add => ("F64Add", Float, true),
+        Operator::F64Ceil => ("F64Ceil", Float, true),
+        Operator::F64Const { .. } => ("F64Const", Constant, true),
+        Operator::F64ConvertI32S => ("F64ConvertI32S", Float, true),
+        Operator::F64ConvertI32U => ("F64ConvertI32U", Float, true),
+        Operator::F64ConvertI64S => ("F64ConvertI64S", Float, true),
+        Operator::F64ConvertI64U => ("F64ConvertI64U", Float, true),
+        Operator::F64Copysign => ("F64Copysign", Float, true),
+        Operator::F64Div => ("F64Div", Float, true),
+        Operator::F64Eq => ("F64Eq", Float, true),
+        Operator::F64Floor => ("F64Floor", Float, true),
+        Operator::F64Ge => ("F64Ge", Float, true),
+        Operator::F64Gt => ("F64Gt", Float, true),
+        Operator::F64Le => ("F64Le", Float, true),
+        Operator::F64Load { .. } => ("F64Load", Load, true),
+        Operator::F64Lt => ("F64Lt", Float, true),
+        Operator::F64Max => ("F64Max", Float, true),
+        Operator::F64Min => ("F64Min", Float, true),
+        Operator::F64Mul => ("F64Mul", Float, true),
+        Operator::F64Ne => ("F64Ne", Float, true),
+        Operator::F64Nearest => ("F64Nearest", Float, true),
+        Operator::F64Neg => ("F64Neg", Float, true),
+        Operator::F64PromoteF32 => ("F64PromoteF32", Float, true),
+        Operator::F64ReinterpretI64 => ("F64ReinterpretI64", Float, true),
+        Operator::F64Sqrt => ("F64Sqrt", Float, true),
+        Operator::F64Store { .. } => ("F64Store", Store, true),
+        Operator::F64Sub => ("F64Sub", Float, true),
+        Operator::F64Trunc => ("F64Trunc", Float, true),
+        Operator::F64x2Abs => ("F64x2Abs", Simd, false),
+        Operator::F64x2Add => ("F64x2Add", Simd, false),
+        Operator::F64x2Ceil => ("F64x2Ceil", Simd, false),
+        Operator::F64x2ConvertLowI32x4S => ("F64x2ConvertLowI32x4S", Simd, false),
+        Operator::F64x2ConvertLowI32x4U => ("F64x2ConvertLowI32x4U", Simd, false),
+        Operator::F64x2Div => ("F64x2Div", Simd, false),
+        Operator::F64x2Eq => ("F64x2Eq", Simd, false),
+        Operator::F64x2ExtractLane { .. } => ("F64x2ExtractLane", Simd, false),
+        Operator::F64x2Floor => ("F64x2Floor", Simd, false),
+        Operator::F64x2Ge => ("F64x2Ge", Simd, false),
+        Operator::F64x2Gt => ("F64x2Gt", Simd, false),
+        Operator::F64x2Le => ("F64x2Le", Simd, false),
+        Operator::F64x2Lt => ("F64x2Lt", Simd, false),
+        Operator::F64x2Max => ("F64x2Max", Simd, false),
+        Operator::F64x2Min => ("F64x2Min", Simd, false),
+        Operator::F64x2Mul => ("F64x2Mul", Simd, false),
+        Operator::F64x2Ne => ("F64x2Ne", Simd, false),
+        Operator::F64x2Nearest => ("F64x2Nearest", Simd, false),
+        Operator::F64x2Neg => ("F64x2Neg", Simd, false),
+        Operator::F64x2PMax => ("F64x2PMax", Simd, false),
+        Operator::F64x2PMin => ("F64x2PMin", Simd, false),
+        Operator::F64x2PromoteLowF32x4 => ("F64x2PromoteLowF32x4", Simd, false),
+        Operator::F64x2ReplaceLane { .. } => ("F64x2ReplaceLane", Simd, false),
+        Operator::F64x2Splat => ("F64x2Splat", Simd, false),
+        Operator::F64x2Sqrt => ("F64x2Sqrt", Simd, false),
+        Operator::F64x2Sub => ("F64x2Sub", Simd, false),
+        Operator::F64x2Trunc => ("F64x2Trunc", Simd, false),
+        Operator::GlobalGet { .. } => ("GlobalGet", Variable, false),
+        Operator::GlobalSet { .. } => ("GlobalSet", Variable, false),
+        Operator::I16x8Abs => ("I16x8Abs", Simd, false),
+        Operator::I16x8Add => ("I16x8Add", Simd, false),
+        Operator::I16x8AddSatS => ("I16x8AddSatS", Simd, false),
+        Operator::I16x8AddSatU => ("I16x8AddSatU", Simd, false),
+        Operator::I16x8AllTrue => ("I16x8AllTrue", Simd, false),
+        Operator::I16x8Bitmask => ("I16x8Bitmask", Simd, false),
+        Operator::I16x8Eq => ("I16x8Eq", Simd, false),
+        Operator::I16x8ExtAddPairwiseI8x16S => ("I16x8ExtAddPairwiseI8x16S", Simd, false),
+        Operator::I16x8ExtAddPairwiseI8x16U => ("I16x8ExtAddPairwiseI8x16U", Simd, false),
+        Operator::I16x8ExtMulHighI8x16S => ("I16x8ExtMulHighI8x16S", Simd, false),
+        Operator::I16x8ExtMulHighI8x16U => ("I16x8ExtMulHighI8x16U", Simd, false),
+        Operator::I16x8ExtMulLowI8x16S => ("I16x8ExtMulLowI8x16S", Simd, false),
+        Operator::I16x8ExtMulLowI8x16U => ("I16x8ExtMulLowI8x16U", Simd, false),
+        Operator::I16x8ExtendHighI8x16S => ("I16x8ExtendHighI8x16S", Simd, false),
+        Operator::I16x8ExtendHighI8x16U => ("I16x8ExtendHighI8x16U", Simd, false),
+        Operator::I16x8ExtendLowI8x16S => ("I16x8ExtendLowI8x16S", Simd, false),
+        Operator::I16x8ExtendLowI8x16U => ("I16x8ExtendLowI8x16U", Simd, false),
+        Operator::I16x8ExtractLaneS { .. } => ("I16x8ExtractLaneS", Simd, false),
+        Operator::I16x8ExtractLaneU { .. } => ("I16x8ExtractLaneU", Simd, false),
+        Operator::I16x8GeS => ("I16x8GeS", Simd, false),
+        Operator::I16x8GeU => ("I16x8GeU", Simd, false),
+        Operator::I16x8GtS => ("I16x8GtS", Simd, false),
+        Operator::I16x8GtU => ("I16x8GtU", Simd, false),
+        Operator::I16x8LeS => ("I16x8LeS", Simd, false),
+        Operator::I16x8LeU => ("I16x8LeU", Simd, false),
+        Operator::I16x8LtS => ("I16x8LtS", Simd, false),
+        Operator::I16x8LtU => ("I16x8LtU", Simd, false),
+        Operator::I16x8MaxS => ("I16x8MaxS", Simd, false),
+        Operator::I16x8MaxU => ("I16x8MaxU", Simd, false),
+        Operator::I16x8MinS => ("I16x8MinS", Simd, false),
+        Operator::I16x8MinU => ("I16x8MinU", Simd, false),
+        Operator::I16x8Mul => ("I16x8Mul", Simd, false),
+        Operator::I16x8NarrowI32x4S => ("I16x8NarrowI32x4S", Simd, false),
+        Operator::I16x8NarrowI32x4U => ("I16x8NarrowI32x4U", Simd, false),
+        Operator::I16x8Ne => ("I16x8Ne", Simd, false),
+        Operator::I16x8Neg => ("I16x8Neg", Simd, false),
+        Operator::I16x8Q15MulrSatS => ("I16x8Q15MulrSatS", Simd, false),
+        Operator::I16x8ReplaceLane { .. } => ("I16x8ReplaceLane", Simd, false),
+        Operator::I16x8RoundingAverageU => ("I16x8RoundingAverageU", Simd, false),
+        Operator::I16x8Shl => ("I16x8Shl", Simd, false),
+        Operator::I16x8ShrS => ("I16x8ShrS", Simd, false),
+        Operator::I16x8ShrU => ("I16x8ShrU", Simd, false),
+        Operator::I16x8Splat => ("I16x8Splat", Simd, false),
+        Operator::I16x8Sub => ("I16x8Sub", Simd, false),
+        Operator::I16x8SubSatS => ("I16x8SubSatS", Simd, false),
+        Operator::I16x8SubSatU => ("I16x8SubSatU", Simd, false),
+        Operator::I32Add => ("I32Add", Integer, false),
+        Operator::I32And => ("I32And", Integer, false),
+        Operator::I32AtomicLoad { .. } => ("I32AtomicLoad", Atomic, false),
+        Operator::I32AtomicLoad16U { .. } => ("I32AtomicLoad16U", Atomic, false),
+        Operator::I32AtomicLoad8U { .. } => ("I32AtomicLoad8U", Atomic, false),
+        Operator::I32AtomicRmw16AddU { .. } => ("I32AtomicRmw16AddU", Atomic, false),
+        Operator::I32AtomicRmw16AndU { .. } => ("I32AtomicRmw16AndU", Atomic, false),
+        Operator::I32AtomicRmw16CmpxchgU { .. } => ("I32AtomicRmw16CmpxchgU", Atomic, false),
+        Operator::I32AtomicRmw16OrU { .. } => ("I32AtomicRmw16OrU", Atomic, false),
+        Operator::I32AtomicRmw16SubU { .. } => ("I32AtomicRmw16SubU", Atomic, false),
+        Operator::I32AtomicRmw16XchgU { .. } => ("I32AtomicRmw16XchgU", Atomic, false),
+        Operator::I32AtomicRmw16XorU { .. } => ("I32AtomicRmw16XorU", Atomic, false),
+        Operator::I32AtomicRmw8AddU { .. } => ("I32AtomicRmw8AddU", Atomic, false),
+        Operator::I32AtomicRmw8AndU { .. } => ("I32AtomicRmw8AndU", Atomic, false),
+        Operator::I32AtomicRmw8CmpxchgU { .. } => ("I32AtomicRmw8CmpxchgU", Atomic, false),
+        Operator::I32AtomicRmw8OrU { .. } => ("I32AtomicRmw8OrU", Atomic, false),
+        Operator::I32AtomicRmw8SubU { .. } => ("I32AtomicRmw8SubU", Atomic, false),
+        Operator::I32AtomicRmw8XchgU { .. } => ("I32AtomicRmw8XchgU", Atomic, false),
+        Operator::I32AtomicRmw8XorU { .. } => ("I32AtomicRmw8XorU", Atomic, false),
+        Operator::I32AtomicRmwAdd { .. } => ("I32AtomicRmwAdd", Atomic, false),
+        Operator::I32AtomicRmwAnd { .. } => ("I32AtomicRmwAnd", Atomic, false),
+        Operator::I32AtomicRmwCmpxchg { .. } => ("I32AtomicRmwCmpxchg", Atomic, false),
+        Operator::I32AtomicRmwOr { .. } => ("I32AtomicRmwOr", Atomic, false),
+        Operator::I32AtomicRmwSub { .. } => ("I32AtomicRmwSub", Atomic, false),
+        Operator::I32AtomicRmwXchg { .. } => ("I32AtomicRmwXchg", Atomic, false),
+        Operator::I32AtomicRmwXor { .. } => ("I32AtomicRmwXor", Atomic, false),
+        Operator::I32AtomicStore { .. } => ("I32AtomicStore", Atomic, false),
+        Operator::I32AtomicStore16 { .. } => ("I32AtomicStore16", Atomic, false),
+        Operator::I32AtomicStore8 { .. } => ("I32AtomicStore8", Atomic, false),
+        Operator::I32Clz => ("I32Clz", Integer, false),
+        Operator::I32Const { .. } => ("I32Const", Constant, false),
+        Operator::I32Ctz => ("I32Ctz", Integer, false),
+        Operator::I32DivS => ("I32DivS", Integer, false),
+        Operator::I32DivU => ("I32DivU", Integer, false),
+        Operator::I32Eq => ("I32Eq", Integer, false),
+        Operator::I32Eqz => ("I32Eqz", Integer, false),
+        Operator::I32Extend16S => ("I32Extend16S", Integer, false),
+        Operator::I32Extend8S => ("I32Extend8S", Integer, false),
+        Operator::I32GeS => ("I32GeS", Integer, false),
+        Operator::I32GeU => ("I32GeU", Integer, false),
+        Operator::I32GtS => ("I32GtS", Integer, false),
+        Operator::I32GtU => ("I32GtU", Integer, false),
+        Operator::I32LeS => ("I32LeS", Integer, false),
+        Operator::I32LeU => ("I32LeU", Integer, false),
+        Operator::I32Load { .. } => ("I32Load", Load, false),
+        Operator::I32Load16S { .. } => ("I32Load16S", Load, false),
+        Operator::I32Load16U { .. } => ("I32Load16U", Load, false),
+        Operator::I32Load8S { .. } => ("I32Load8S", Load, false),
+        Operator::I32Load8U { .. } => ("I32Load8U", Load, false),
+        Operator::I32LtS => ("I32LtS", Integer, false),
+        Operator::I32LtU => ("I32LtU", Integer, false),
+        Operator::I32Mul => ("I32Mul", Integer, false),
+        Operator::I32Ne => ("I32Ne", Integer, false),
+        Operator::I32Or => ("I32Or", Integer, false),
+        Operator::I32Popcnt => ("I32Popcnt", Integer, false),
+        Operator::I32ReinterpretF32 => ("I32ReinterpretF32", Integer, true),
+        Operator::I32RemS => ("I32RemS", Integer, false),
+        Operator::I32RemU => ("I32RemU", Integer, false),
+        Operator::I32Rotl => ("I32Rotl", Integer, false),
+        Operator::I32Rotr => ("I32Rotr", Integer, false),
+        Operator::I32Shl => ("I32Shl", Integer, false),
+        Operator::I32ShrS => ("I32ShrS", Integer, false),
+        Operator::I32ShrU => ("I32ShrU", Integer, false),
+        Operator::I32Store { .. } => ("I32Store", Store, false),
+        Operator::I32Store16 { .. } => ("I32Store16", Store, false),
+        Operator::I32Store8 { .. } => ("I32Store8", Store, false),
+        Operator::I32Sub => ("I32Sub", Integer, false),
+        Operator::I32TruncF32S => ("I32TruncF32S", Integer, true),
+        Operator::I32TruncF32U => ("I32TruncF32U", Integer, true),
+        Operator::I32TruncF64S => ("I32TruncF64S", Integer, true),
+        Operator::I32TruncF64U => ("I32TruncF64U", Integer, true),
+        Operator::I32TruncSatF32S => ("I32TruncSatF32S", Integer, true),
+        Operator::I32TruncSatF32U => ("I32TruncSatF32U", Integer, true),
+        Operator::I32TruncSatF64S => ("I32TruncSatF64S", Integer, true),
+        Operator::I32TruncSatF64U => ("I32TruncSatF64U", Integer, true),
+        Operator::I32WrapI64 => ("I32WrapI64", Integer, false),
+        Operator::I32Xor => ("I32Xor", Integer, false),
+        Operator::I32x4Abs => ("I32x4Abs", Simd, false),
+        Operator::I32x4Add => ("I32x4Add", Simd, false),
+        Operator::I32x4AllTrue => ("I32x4AllTrue", Simd, false),
+        Operator::I32x4Bitmask => ("I32x4Bitmask", Simd, false),
+        Operator::I32x4DotI16x8S => ("I32x4DotI16x8S", Simd, false),
+        Operator::I32x4Eq => ("I32x4Eq", Simd, false),
+        Operator::I32x4ExtAddPairwiseI16x8S => ("I32x4ExtAddPairwiseI16x8S", Simd, false),
+        Operator::I32x4ExtAddPairwiseI16x8U => ("I32x4ExtAddPairwiseI16x8U", Simd, false),
+        Operator::I32x4ExtMulHighI16x8S => ("I32x4ExtMulHighI16x8S", Simd, false),
+        Operator::I32x4ExtMulHighI16x8U => ("I32x4ExtMulHighI16x8U", Simd, false),
+        Operator::I32x4ExtMulLowI16x8S => ("I32x4ExtMulLowI16x8S", Simd, false),
+        Operator::I32x4ExtMulLowI16x8U => ("I32x4ExtMulLowI16x8U", Simd, false),
+        Operator::I32x4ExtendHighI16x8S => ("I32x4ExtendHighI16x8S", Simd, false),
+        Operator::I32x4ExtendHighI16x8U => ("I32x4ExtendHighI16x8U", Simd, false),
+        Operator::I32x4ExtendLowI16x8S => ("I32x4ExtendLowI16x8S", Simd, false),
+        Operator::I32x4ExtendLowI16x8U => ("I32x4ExtendLowI16x8U", Simd, false),
+        Operator::I32x4ExtractLane { .. } => ("I32x4ExtractLane", Simd, false),
+        Operator::I32x4GeS => ("I32x4GeS", Simd, false),
+        Operator::I32x4GeU => ("I32x4GeU", Simd, false),
+        Operator::I32x4GtS => ("I32x4GtS", Simd, false),
+        Operator::I32x4GtU => ("I32x4GtU", Simd, false),
+        Operator::I32x4LeS => ("I32x4LeS", Simd, false),
+        Operator::I32x4LeU => ("I32x4LeU", Simd, false),
+        Operator::I32x4LtS => ("I32x4LtS", Simd, false),
+        Operator::I32x4LtU => ("I32x4LtU", Simd, false),
+        Operator::I32x4MaxS => ("I32x4MaxS", Simd, false),
+        Operator::I32x4MaxU => ("I32x4MaxU", Simd, false),
+        Operator::I32x4MinS => ("I32x4MinS", Simd, false),
+        Operator::I32x4MinU => ("I32x4MinU", Simd, false),
+        Operator::I32x4Mul => ("I32x4Mul", Simd, false),
+        Operator::I32x4Ne => ("I32x4Ne", Simd, false),
+        Operator::I32x4Neg => ("I32x4Neg", Simd, false),
+        Operator::I32x4ReplaceLane { .. } => ("I32x4ReplaceLane", Simd, false),
+        Operator::I32x4Shl => ("I32x4Shl", Simd, false),
+        Operator::I32x4ShrS => ("I32x4ShrS", Simd, false),
+        Operator::I32x4ShrU => ("I32x4ShrU", Simd, false),
+        Operator::I32x4Splat => ("I32x4Splat", Simd, false),
+        Operator::I32x4Sub => ("I32x4Sub", Simd, false),
+        Operator::I32x4TruncSatF32x4S => ("I32x4TruncSatF32x4S", Simd, false),
+        Operator::I32x4TruncSatF32x4U => ("I32x4TruncSatF32x4U", Simd, false),
+        Operator::I32x4TruncSatF64x2SZero => ("I32x4TruncSatF64x2SZero", Simd, false),
+        Operator::I32x4TruncSatF64x2UZero => ("I32x4TruncSatF64x2UZero", Simd, false),
+        Operator::I64Add => ("I64Add", Integer, false),
+        Operator::I64And => ("I64And", Integer, false),
+        Operator::I64AtomicLoad { .. } => ("I64AtomicLoad", Atomic, false),
+        Operator::I64AtomicLoad16U { .. } => ("I64AtomicLoad16U", Atomic, false),
+        Operator::I64AtomicLoad32U { .. } => ("I64AtomicLoad32U", Atomic, false),
+        Operator::I64AtomicLoad8U { .. } => ("I64AtomicLoad8U", Atomic, false),
+        Operator::I64AtomicRmw16AddU { .. } => ("I64AtomicRmw16AddU", Atomic, false),
+        Operator::I64AtomicRmw16AndU { .. } => ("I64AtomicRmw16AndU", Atomic, false),
+        Operator::I64AtomicRmw16CmpxchgU { .. } => ("I64AtomicRmw16CmpxchgU", Atomic, false),
+        Operator::I64AtomicRmw16OrU { .. } => ("I64AtomicRmw16OrU", Atomic, false),
+        Operator::I64AtomicRmw16SubU { .. } => ("I64AtomicRmw16SubU", Atomic, false),
+        Operator::I64AtomicRmw16XchgU { .. } => ("I64AtomicRmw16XchgU", Atomic, false),
+        Operator::I64AtomicRmw16XorU { .. } => ("I64AtomicRmw16XorU", Atomic, false),
+        Operator::I64AtomicRmw32AddU { .. } => ("I64AtomicRmw32AddU", Atomic, false),
+        Operator::I64AtomicRmw32AndU { .. } => ("I64AtomicRmw32AndU", Atomic, false),
+        Operator::I64AtomicRmw32CmpxchgU { .. } => ("I64AtomicRmw32CmpxchgU", Atomic, false),
+        Operator::I64AtomicRmw32OrU { .. } => ("I64AtomicRmw32OrU", Atomic, false),
+        Operator::I64AtomicRmw32SubU { .. } => ("I64AtomicRmw32SubU", Atomic, false),
+        Operator::I64AtomicRmw32XchgU { .. } => ("I64AtomicRmw32XchgU", Atomic, false),
+        Operator::I64AtomicRmw32XorU { .. } => ("I64AtomicRmw32XorU", Atomic, false),
+        Operator::I64AtomicRmw8AddU { .. } => ("I64AtomicRmw8AddU", Atomic, false),
+        Operator::I64AtomicRmw8AndU { .. } => ("I64AtomicRmw8AndU", Atomic, false),
+        Operator::I64AtomicRmw8CmpxchgU { .. } => ("I64AtomicRmw8CmpxchgU", Atomic, false),
+        Operator::I64AtomicRmw8OrU { .. } => ("I64AtomicRmw8OrU", Atomic, false),
+        Operator::I64AtomicRmw8SubU { .. } => ("I64AtomicRmw8SubU", Atomic, false),
+        Operator::I64AtomicRmw8XchgU { .. } => ("I64AtomicRmw8XchgU", Atomic, false),
+        Operator::I64AtomicRmw8XorU { .. } => ("I64AtomicRmw8XorU", Atomic, false),
+        Operator::I64AtomicRmwAdd { .. } => ("I64AtomicRmwAdd", Atomic, false),
+        Operator::I64AtomicRmwAnd { .. } => ("I64AtomicRmwAnd", Atomic, false),
+        Operator::I64AtomicRmwCmpxchg { .. } => ("I64AtomicRmwCmpxchg", Atomic, false),
+        Operator::I64AtomicRmwOr { .. } => ("I64AtomicRmwOr", Atomic, false),
+        Operator::I64AtomicRmwSub { .. } => ("I64AtomicRmwSub", Atomic, false),
+        Operator::I64AtomicRmwXchg { .. } => ("I64AtomicRmwXchg", Atomic, false),
+        Operator::I64AtomicRmwXor { .. } => ("I64AtomicRmwXor", Atomic, false),
+        Operator::I64AtomicStore { .. } => ("I64AtomicStore", Atomic, false),
+        Operator::I64AtomicStore16 { .. } => ("I64AtomicStore16", Atomic, false),
+        Operator::I64AtomicStore32 { .. } => ("I64AtomicStore32", Atomic, false),
+        Operator::I64AtomicStore8 { .. } => ("I64AtomicStore8", Atomic, false),
+        Operator::I64Clz => ("I64Clz", Integer, false),
+        Operator::I64Const { .. } => ("I64Const", Constant, false),
+        Operator::I64Ctz => ("I64Ctz", Integer, false),
+        Operator::I64DivS => ("I64DivS", Integer, false),
+        Operator::I64DivU => ("I64DivU", Integer, false),
+        Operator::I64Eq => ("I64Eq", Integer, false),
+        Operator::I64Eqz => ("I64Eqz", Integer, false),
+        Operator::I64Extend16S => ("I64Extend16S", Integer, false),
+        Operator::I64Extend32S => ("I64Extend32S", Integer, false),
+        Operator::I64Extend8S => ("I64Extend8S", Integer, false),
+        Operator::I64ExtendI32S => ("I64ExtendI32S", Integer, false),
+        Operator::I64ExtendI32U => ("I64ExtendI32U", Integer, false),
+        Operator::I64GeS => ("I64GeS", Integer, false),
+        Operator::I64GeU => ("I64GeU", Integer, false),
+        Operator::I64GtS => ("I64GtS", Integer, false),
+        Operator::I64GtU => ("I64GtU", Integer, false),
+        Operator::I64LeS => ("I64LeS", Integer, false),
+        Operator::I64LeU => ("I64LeU", Integer, false),
+        Operator::I64Load { .. } => ("I64Load", Load, false),
+        Operator::I64Load16S { .. } => ("I64Load16S", Load, false),
+        Operator::I64Load16U { .. } => ("I64Load16U", Load, false),
+        Operator::I64Load32S { .. } => ("I64Load32S", Load, false),
+        Operator::I64Load32U { .. } => ("I64Load32U", Load, false),
+        Operator::I64Load8S { .. } => ("I64Load8S", Load, false),
+        Operator::I64Load8U { .. } => ("I64Load8U", Load, false),
+        Operator::I64LtS => ("I64LtS", Integer, false),
+        Operator::I64LtU => ("I64LtU", Integer, false),
+        Operator::I64Mul => ("I64Mul", Integer, false),
+        Operator::I64Ne => ("I64Ne", Integer, false),
+        Operator::I64Or => ("I64Or", Integer, false),
+        Operator::I64Popcnt => ("I64Popcnt", Integer, false),
+        Operator::I64ReinterpretF64 => ("I64ReinterpretF64", Integer, true),
+        Operator::I64RemS => ("I64RemS", Integer, false),
+        Operator::I64RemU => ("I64RemU", Integer, false),
+        Operator::I64Rotl => ("I64Rotl", Integer, false),
+        Operator::I64Rotr => ("I64Rotr", Integer, false),
+        Operator::I64Shl => ("I64Shl", Integer, false),
+        Operator::I64ShrS => ("I64ShrS", Integer, false),
+        Operator::I64ShrU => ("I64ShrU", Integer, false),
+        Operator::I64Store { .. } => ("I64Store", Store, false),
+        Operator::I64Store16 { .. } => ("I64Store16", Store, false),
+        Operator::I64Store32 { .. } => ("I64Store32", Store, false),
+        Operator::I64Store8 { .. } => ("I64Store8", Store, false),
+        Operator::I64Sub => ("I64Sub", Integer, false),
+        Operator::I64TruncF32S => ("I64TruncF32S", Integer, true),
+        Operator::I64TruncF32U => ("I64TruncF32U", Integer, true),
+        Operator::I64TruncF64S => ("I64TruncF64S", Integer, true),
+        Operator::I64TruncF64U => ("I64TruncF64U", Integer, true),
+        Operator::I64TruncSatF32S => ("I64TruncSatF32S", Integer, true),
+        Operator::I64TruncSatF32U => ("I64TruncSatF32U", Integer, true),
+        Operator::I64TruncSatF64S => ("I64TruncSatF64S", Integer, true),
+        Operator::I64TruncSatF64U => ("I64TruncSatF64U", Integer, true),
+        Operator::I64Xor => ("I64Xor", Integer, false),
+        Operator::I64x2Abs => ("I64x2Abs", Simd, false),
+        Operator::I64x2Add => ("I64x2Add", Simd, false),
+        Operator::I64x2AllTrue => ("I64x2AllTrue", Simd, false),
+        Operator::I64x2Bitmask => ("I64x2Bitmask", Simd, false),
+        Operator::I64x2Eq => ("I64x2Eq", Simd, false),
+        Operator::I64x2ExtMulHighI32x4S => ("I64x2ExtMulHighI32x4S", Simd, false),
+        Operator::I64x2ExtMulHighI32x4U => ("I64x2ExtMulHighI32x4U", Simd, false),
+        Operator::I64x2ExtMulLowI32x4S => ("I64x2ExtMulLowI32x4S", Simd, false),
+        Operator::I64x2ExtMulLowI32x4U => ("I64x2ExtMulLowI32x4U", Simd, false),
+        Operator::I64x2ExtendHighI32x4S => ("I64x2ExtendHighI32x4S", Simd, false),
+        Operator::I64x2ExtendHighI32x4U => ("I64x2ExtendHighI32x4U", Simd, false),
+        Operator::I64x2ExtendLowI32x4S => ("I64x2ExtendLowI32x4S", Simd, false),
+        Operator::I64x2ExtendLowI32x4U => ("I64x2ExtendLowI32x4U", Simd, false),
+        Operator::I64x2ExtractLane { .. } => ("I64x2ExtractLane", Simd, false),
+        Operator::I64x2GeS => ("I64x2GeS", Simd, false),
+        Operator::I64x2GtS => ("I64x2GtS", Simd, false),
+        Operator::I64x2LeS => ("I64x2LeS", Simd, false),
+        Operator::I64x2LtS => ("I64x2LtS", Simd, false),
+        Operator::I64x2Mul => ("I64x2Mul", Simd, false),
+        Operator::I64x2Ne => ("I64x2Ne", Simd, false),
+        Operator::I64x2Neg => ("I64x2Neg", Simd, false),
+        Operator::I64x2ReplaceLane { .. } => ("I64x2ReplaceLane", Simd, false),
+        Operator::I64x2Shl => ("I64x2Shl", Simd, false),
+        Operator::I64x2ShrS => ("I64x2ShrS", Simd, false),
+        Operator::I64x2ShrU => ("I64x2ShrU", Simd, false),
+        Operator::I64x2Splat => ("I64x2Splat", Simd, false),
+        Operator::I64x2Sub => ("I64x2Sub", Simd, false),
+        Operator::I8x16Abs => ("I8x16Abs", Simd, false),
+        Operator::I8x16Add => ("I8x16Add", Simd, false),
+        Operator::I8x16AddSatS => ("I8x16AddSatS", Simd, false),
+        Operator::I8x16AddSatU => ("I8x16AddSatU", Simd, false),
+        Operator::I8x16AllTrue => ("I8x16AllTrue", Simd, false),
+        Operator::I8x16Bitmask => ("I8x16Bitmask", Simd, false),
+        Operator::I8x16Eq => ("I8x16Eq", Simd, false),
+        Operator::I8x16ExtractLaneS { .. } => ("I8x16ExtractLaneS", Simd, false),
+        Operator::I8x16ExtractLaneU { .. } => ("I8x16ExtractLaneU", Simd, false),
+        Operator::I8x16GeS => ("I8x16GeS", Simd, false),
+        Operator::I8x16GeU => ("I8x16GeU", Simd, false),
+        Operator::I8x16GtS => ("I8x16GtS", Simd, false),
+        Operator::I8x16GtU => ("I8x16GtU", Simd, false),
+        Operator::I8x16LeS => ("I8x16LeS", Simd, false),
+        Operator::I8x16LeU => ("I8x16LeU", Simd, false),
+        Operator::I8x16LtS => ("I8x16LtS", Simd, false),
+        Operator::I8x16LtU => ("I8x16LtU", Simd, false),
+        Operator::I8x16MaxS => ("I8x16MaxS", Simd, false),
+        Operator::I8x16MaxU => ("I8x16MaxU", Simd, false),
+        Operator::I8x16MinS => ("I8x16MinS", Simd, false),
+        Operator::I8x16MinU => ("I8x16MinU", Simd, false),
+        Operator::I8x16NarrowI16x8S => ("I8x16NarrowI16x8S", Simd, false),
+        Operator::I8x16NarrowI16x8U => ("I8x16NarrowI16x8U", Simd, false),
+        Operator::I8x16Ne => ("I8x16Ne", Simd, false),
+        Operator::I8x16Neg => ("I8x16Neg", Simd, false),
+        Operator::I8x16Popcnt => ("I8x16Popcnt", Simd, false),
+        Operator::I8x16ReplaceLane { .. } => ("I8x16ReplaceLane", Simd, false),
+        Operator::I8x16RoundingAverageU => ("I8x16RoundingAverageU", Simd, false),
+        Operator::I8x16Shl => ("I8x16Shl", Simd, false),
+        Operator::I8x16ShrS => ("I8x16ShrS", Simd, false),
+        Operator::I8x16ShrU => ("I8x16ShrU", Simd, false),
+        Operator::I8x16Shuffle { .. } => ("I8x16Shuffle", Simd, false),
+        Operator::I8x16Splat => ("I8x16Splat", Simd, false),
+        Operator::I8x16Sub => ("I8x16Sub", Simd, false),
+        Operator::I8x16SubSatS => ("I8x16SubSatS", Simd, false),
+        Operator::I8x16SubSatU => ("I8x16SubSatU", Simd, false),
+        Operator::I8x16Swizzle => ("I8x16Swizzle", Simd, false),
+        Operator::If { .. } => ("If", Control, false),
+        Operator::LocalGet { .. } => ("LocalGet", Variable, false),
+        Operator::LocalSet { .. } => ("LocalSet", Variable, false),
+        Operator::LocalTee { .. } => ("LocalTee", Variable, false),
+        Operator::Loop { .. } => ("Loop", Control, false),
+        Operator::MemoryAtomicNotify { .. } => ("MemoryAtomicNotify", Atomic, false),
+        Operator::MemoryAtomicWait32 { .. } => ("MemoryAtomicWait32", Atomic, false),
+        Operator::MemoryAtomicWait64 { .. } => ("MemoryAtomicWait64", Atomic, false),
+        Operator::MemoryCopy { .. } => ("MemoryCopy", Memory, false),
+        Operator::MemoryFill { .. } => ("MemoryFill", Memory, false),
+        Operator::MemoryGrow { .. } => ("MemoryGrow", Memory, false),
+        Operator::MemoryInit { .. } => ("MemoryInit", Memory, false),
+        Operator::MemorySize { .. } => ("MemorySize", Memory, false),
+        Operator::Nop => ("Nop", Control, false),
+        Operator::RefFunc { .. } => ("RefFunc", Reference, false),
+        Operator::RefIsNull => ("RefIsNull", Reference, false),
+        Operator::RefNull { .. } => ("RefNull", Reference, false),
+        Operator::Rethrow { .. } => ("Rethrow", Control, false),
+        Operator::Return => ("Return", Control, false),
+        Operator::ReturnCall { .. } => ("ReturnCall", Call, false),
+        Operator::ReturnCallIndirect { .. } => ("ReturnCallIndirect", Call, false),
+        Operator::Select => ("Select", Parametric, false),
+        Operator::TableCopy { .. } => ("TableCopy", Reference, false),
+        Operator::TableFill { .. } => ("TableFill", Reference, false),
+        Operator::TableGet { .. } => ("TableGet", Reference, false),
+        Operator::TableGrow { .. } => ("TableGrow", Reference, false),
+        Operator::TableInit { .. } => ("TableInit", Reference, false),
+        Operator::TableSet { .. } => ("TableSet", Reference, false),
+        Operator::TableSize { .. } => ("TableSize", Reference, false),
+        Operator::Throw { .. } => ("Throw", Control, false),
+        Operator::Try { .. } => ("Try", Control, false),
+        Operator::TypedSelect { .. } => ("TypedSelect", Parametric, false),
+        Operator::Unreachable => ("Unreachable", Control, false),
+        Operator::Unwind => ("Unwind", Control, false),
+        Operator::V128And => ("V128And", Simd, false),
+        Operator::V128AndNot => ("V128AndNot", Simd, false),
+        Operator::V128AnyTrue => ("V128AnyTrue", Simd, false),
+        Operator::V128Bitselect => ("V128Bitselect", Simd, false),
+        Operator::V128Const { .. } => ("V128Const", Simd, false),
+        Operator::V128Load { .. } => ("V128Load", Simd, false),
+        Operator::V128Load16Lane { .. } => ("V128Load16Lane", Simd, false),
+        Operator::V128Load16Splat { .. } => ("V128Load16Splat", Simd, false),
+        Operator::V128Load16x4S { .. } => ("V128Load16x4S", Simd, false),
+        Operator::V128Load16x4U { .. } => ("V128Load16x4U", Simd, false),
+        Operator::V128Load32Lane { .. } => ("V128Load32Lane", Simd, false),
+        Operator::V128Load32Splat { .. } => ("V128Load32Splat", Simd, false),
+        Operator::V128Load32Zero { .. } => ("V128Load32Zero", Simd, false),
+        Operator::V128Load32x2S { .. } => ("V128Load32x2S", Simd, false),
+        Operator::V128Load32x2U { .. } => ("V128Load32x2U", Simd, false),
+        Operator::V128Load64Lane { .. } => ("V128Load64Lane", Simd, false),
+        Operator::V128Load64Splat { .. } => ("V128Load64Splat", Simd, false),
+        Operator::V128Load64Zero { .. } => ("V128Load64Zero", Simd, false),
+        Operator::V128Load8Lane { .. } => ("V128Load8Lane", Simd, false),
+        Operator::V128Load8Splat { .. } => ("V128Load8Splat", Simd, false),
+        Operator::V128Load8x8S { .. } => ("V128Load8x8S", Simd, false),
+        Operator::V128Load8x8U { .. } => ("V128Load8x8U", Simd, false),
+        Operator::V128Not => ("V128Not", Simd, false),
+        Operator::V128Or => ("V128Or", Simd, false),
+        Operator::V128Store { .. } => ("V128Store", Simd, false),
+        Operator::V128Store16Lane { .. } => ("V128Store16Lane", Simd, false),
+        Operator::V128Store32Lane { .. } => ("V128Store32Lane", Simd, false),
+        Operator::V128Store64Lane { .. } => ("V128Store64Lane", Simd, false),
+        Operator::V128Store8Lane { .. } => ("V128Store8Lane", Simd, false),
+        Operator::V128Xor => ("V128Xor", Simd, false),
+    };
+
+    OperatorInfo {
+        name,
+        category,
+        scalar_float,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_operators() {
+        let info = operator_info(&Operator::I64TruncF64S);
+        assert_eq!(info.name, "I64TruncF64S");
+        assert_eq!(info.category, OperatorCategory::Integer);
+        assert!(info.scalar_float);
+
+        let info = operator_info(&Operator::F32ConvertI32S);
+        assert_eq!(info.category, OperatorCategory::Float);
+        assert!(info.scalar_float);
+
+        let info = operator_info(&Operator::F32x4Add);
+        assert_eq!(info.category, OperatorCategory::Simd);
+        assert!(!info.scalar_float);
+
+        assert_eq!(
+            operator_info(&Operator::LocalGet { local_index: 0 }).name,
+            "LocalGet"
+        );
+        assert_eq!(
+            operator_info(&Operator::AtomicFence { flags: 0 }).category,
+            OperatorCategory::Atomic
+        );
+        assert_eq!(
+            operator_info(&Operator::ReturnCallIndirect {
+                index: 0,
+                table_index: 0,
+            })
+            .category,
+            OperatorCategory::Call
+        );
+        assert_eq!(
+            operator_info(&Operator::Drop).category,
+            OperatorCategory::Parametric
+        );
+    }
+
+    #[test]
+    fn operator_names() {
+        assert!(OPERATOR_NAMES.windows(2).all(|names| names[0] < names[1]));
+        assert!(is_operator_name("MemoryGrow"));
+        assert!(is_operator_name(operator_info(&Operator::V128Xor).name));
+        assert!(!is_operator_name("F32Ad"));
+        assert!(!is_operator_name("f32.add"));
+    }
+}
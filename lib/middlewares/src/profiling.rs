@@ -0,0 +1,440 @@
+//! `profiling` is a middleware for attributing the cost of an
+//! execution to the functions of a WebAssembly module. It counts, for
+//! each function defined in the module, how many times it has been
+//! called and how many points its own operators consumed.
+//!
+//! Unlike [`Metering`][crate::Metering], `Profiling` never stops the
+//! execution. Both middlewares can be used together, with the same
+//! cost function, to find which functions consumed the points of a
+//! metered execution.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::Operator;
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::GlobalIndex;
+use wasmer_vm::ModuleInfo;
+
+/// The global indexes holding the counters of a function.
+#[derive(Clone, Copy, MemoryUsage)]
+struct ProfilingGlobalIndexes(GlobalIndex, GlobalIndex);
+
+impl ProfilingGlobalIndexes {
+    /// The global index in the current module for the number of calls.
+    fn calls(&self) -> GlobalIndex {
+        self.0
+    }
+
+    /// The global index in the current module for the consumed points.
+    fn points(&self) -> GlobalIndex {
+        self.1
+    }
+}
+
+impl fmt::Debug for ProfilingGlobalIndexes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProfilingGlobalIndexes")
+            .field("calls", &self.calls())
+            .field("points", &self.points())
+            .finish()
+    }
+}
+
+/// The module-level profiling middleware.
+///
+/// # Panic
+///
+/// An instance of `Profiling` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// global indexes to store the counters. Attempts to use a `Profiling`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::{wasmparser::Operator, CompilerConfig};
+/// use wasmer_middlewares::Profiling;
+///
+/// fn create_profiling_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's define a dummy cost function,
+///     // which counts 1 for all operators.
+///     let cost_function = |_operator: &Operator| -> u64 { 1 };
+///
+///     // Let's create the profiling middleware.
+///     let profiling = Arc::new(Profiling::new(cost_function));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(profiling);
+/// }
+/// ```
+pub struct Profiling<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// Function that maps each operator to a cost in "points".
+    cost_function: Arc<F>,
+
+    /// The global indexes of the counters, for each local function.
+    global_indexes: Mutex<Option<Vec<ProfilingGlobalIndexes>>>,
+}
+
+/// The function-level profiling middleware.
+pub struct FunctionProfiling<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// Function that maps each operator to a cost in "points".
+    cost_function: Arc<F>,
+
+    /// The global indexes of the counters of this function.
+    global_indexes: ProfilingGlobalIndexes,
+
+    /// Whether the call counter has already been incremented.
+    counted_call: bool,
+
+    /// Accumulated cost of the current basic block.
+    accumulated_cost: u64,
+}
+
+/// The counters of a function, as returned by [`get_profile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    /// The number of times the function has been called.
+    pub calls: u64,
+
+    /// The points consumed by the operators of the function, not
+    /// including the points consumed by the functions it called.
+    pub points: u64,
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> Profiling<F> {
+    /// Creates a `Profiling` middleware.
+    pub fn new(cost_function: F) -> Self {
+        Self {
+            cost_function: Arc::new(cost_function),
+            global_indexes: Mutex::new(None),
+        }
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Profiling<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Profiling")
+            .field("cost_function", &"<function>")
+            .field("global_indexes", &self.global_indexes)
+            .finish()
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Profiling<F> {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let global_indexes =
+            self.global_indexes.lock().unwrap().as_ref().unwrap()[local_function_index.index()];
+        Box::new(FunctionProfiling {
+            cost_function: self.cost_function.clone(),
+            global_indexes,
+            counted_call: false,
+            accumulated_cost: 0,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut global_indexes = self.global_indexes.lock().unwrap();
+
+        if global_indexes.is_some() {
+            panic!("Profiling::transform_module_info: Attempting to use a `Profiling` middleware from multiple modules.");
+        }
+
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        let mut indexes = Vec::with_capacity(num_local_functions);
+        for local_function_index in 0..num_local_functions {
+            let mut counter = |kind: &str| {
+                let global_index = module_info
+                    .globals
+                    .push(GlobalType::new(Type::I64, Mutability::Var));
+                module_info
+                    .global_initializers
+                    .push(GlobalInit::I64Const(0));
+                module_info.exports.insert(
+                    format!("wasmer_profiling_{}_{}", kind, local_function_index),
+                    ExportIndex::Global(global_index),
+                );
+                global_index
+            };
+            let calls = counter("calls");
+            let points = counter("points");
+            indexes.push(ProfilingGlobalIndexes(calls, points));
+        }
+
+        *global_indexes = Some(indexes);
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> MemoryUsage for Profiling<F> {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.global_indexes.size_of_val(tracker)
+            - mem::size_of_val(&self.global_indexes)
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionProfiling<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionProfiling")
+            .field("cost_function", &"<function>")
+            .field("global_indexes", &self.global_indexes)
+            .finish()
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionProfiling<F> {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // Count the call before the first operator of the function.
+        if !self.counted_call {
+            self.counted_call = true;
+            let calls = self.global_indexes.calls().as_u32();
+            state.extend(&[
+                // globals[calls_index] += 1;
+                Operator::GlobalGet {
+                    global_index: calls,
+                },
+                Operator::I64Const { value: 1 },
+                Operator::I64Add,
+                Operator::GlobalSet {
+                    global_index: calls,
+                },
+            ]);
+        }
+
+        // Get the cost of the current operator, and add it to the accumulator.
+        self.accumulated_cost += (self.cost_function)(&operator);
+
+        // Possible sources and targets of a branch. Record the cost of
+        // the previous basic block, with the same boundaries as `Metering`.
+        match operator {
+            Operator::Loop { .. }
+            | Operator::End
+            | Operator::Else
+            | Operator::Br { .. }
+            | Operator::BrTable { .. }
+            | Operator::BrIf { .. }
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::Return => {
+                if self.accumulated_cost > 0 {
+                    let points = self.global_indexes.points().as_u32();
+                    state.extend(&[
+                        // globals[points_index] += self.accumulated_cost;
+                        Operator::GlobalGet {
+                            global_index: points,
+                        },
+                        Operator::I64Const {
+                            value: self.accumulated_cost as i64,
+                        },
+                        Operator::I64Add,
+                        Operator::GlobalSet {
+                            global_index: points,
+                        },
+                    ]);
+
+                    self.accumulated_cost = 0;
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Returns the name used in the profile for the given local function:
+/// its name from the `name` section, or else the name it's exported
+/// with, or else `func{index}`.
+pub(crate) fn function_name(
+    module_info: &ModuleInfo,
+    local_function_index: LocalFunctionIndex,
+) -> String {
+    let function_index = module_info.func_index(local_function_index);
+    if let Some(name) = module_info.function_names.get(&function_index) {
+        return name.clone();
+    }
+    module_info
+        .exports
+        .iter()
+        .find(|(_, export)| **export == ExportIndex::Function(function_index))
+        .map(|(name, _)| name.clone())
+        .unwrap_or_else(|| format!("func{}", function_index.index()))
+}
+
+/// Get the profile of an [`Instance`][wasmer::Instance]: the number of
+/// calls and consumed points of each function defined in the module,
+/// by function name.
+///
+/// Functions are named after the `name` section, or the name they
+/// are exported with, or else `func{index}`. The counters of functions
+/// sharing the same name are added up.
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`Profiling`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::Instance;
+/// use wasmer_middlewares::profiling::get_profile;
+///
+/// /// Print the functions that consumed the most points.
+/// fn print_hot_functions(instance: &Instance) {
+///     let mut profile = get_profile(instance).into_iter().collect::<Vec<_>>();
+///     profile.sort_by_key(|(_, function)| std::cmp::Reverse(function.points));
+///     for (name, function) in profile.iter().take(10) {
+///         println!("{}: {} calls, {} points", name, function.calls, function.points);
+///     }
+/// }
+/// ```
+pub fn get_profile(instance: &Instance) -> BTreeMap<String, FunctionProfile> {
+    let module_info = instance.module().info();
+    let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+    let mut profile = BTreeMap::<String, FunctionProfile>::new();
+    for index in 0..num_local_functions {
+        let counter = |kind: &str| -> u64 {
+            let name = format!("wasmer_profiling_{}_{}", kind, index);
+            instance
+                .exports
+                .get_global(&name)
+                .unwrap_or_else(|_| panic!("Can't get `{}` from Instance", name))
+                .get()
+                .try_into()
+                .unwrap_or_else(|_| panic!("`{}` from Instance has wrong type", name))
+        };
+        let function = profile
+            .entry(function_name(module_info, LocalFunctionIndex::new(index)))
+            .or_default();
+        function.calls += counter("calls");
+        function.points += counter("points");
+    }
+    profile
+}
+
+/// Reset all the counters of an [`Instance`][wasmer::Instance] to zero.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`Profiling`] middleware at compile time, otherwise this will
+/// panic.
+pub fn reset_profile(instance: &Instance) {
+    let module_info = instance.module().info();
+    let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+    for index in 0..num_local_functions {
+        for kind in &["calls", "points"] {
+            let name = format!("wasmer_profiling_{}_{}", kind, index);
+            instance
+                .exports
+                .get_global(&name)
+                .unwrap_or_else(|_| panic!("Can't get `{}` from Instance", name))
+                .set(0i64.into())
+                .unwrap_or_else(|_| panic!("Can't set `{}` in Instance", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn cost_function(operator: &Operator) -> u64 {
+        match operator {
+            Operator::LocalGet { .. } | Operator::I32Const { .. } => 1,
+            Operator::I32Add { .. } => 2,
+            _ => 0,
+        }
+    }
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (type $add_t (func (param i32) (result i32)))
+            (func $add_one_f (type $add_t) (param $value i32) (result i32)
+                local.get $value
+                i32.const 1
+                i32.add)
+            (func $add_two_f (type $add_t) (param $value i32) (result i32)
+                local.get $value
+                call $add_one_f
+                call $add_one_f)
+            (func (export "unused"))
+            (export "add_two" (func $add_two_f)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn get_profile_works() {
+        let profiling = Arc::new(Profiling::new(cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(profiling);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        let add_two = instance
+            .exports
+            .get_function("add_two")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        assert_eq!(add_two.call(1).unwrap(), 3);
+        assert_eq!(add_two.call(2).unwrap(), 4);
+
+        let profile = get_profile(&instance);
+        assert_eq!(profile.len(), 3);
+        // `add_one_f` costs 4 points per call: 1 for `local.get`, 1 for
+        // `i32.const` and 2 for `i32.add`.
+        assert_eq!(
+            profile["add_one_f"],
+            FunctionProfile {
+                calls: 4,
+                points: 16
+            }
+        );
+        // `add_two_f` costs 1 point per call, for its own `local.get`.
+        assert_eq!(
+            profile["add_two_f"],
+            FunctionProfile {
+                calls: 2,
+                points: 2
+            }
+        );
+        assert_eq!(profile["unused"], FunctionProfile::default());
+
+        reset_profile(&instance);
+        assert!(get_profile(&instance)
+            .values()
+            .all(|function| *function == FunctionProfile::default()));
+    }
+}
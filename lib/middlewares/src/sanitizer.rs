@@ -0,0 +1,1094 @@
+//! `sanitizer` is a middleware for detecting invalid memory accesses in
+//! WebAssembly modules compiled from languages managing their memory
+//! by hand, like C.
+//!
+//! Every load and store of the module calls a host function checking
+//! the access against a shadow of the linear memory, which tracks the
+//! regions returned by the `malloc`, `calloc` and `realloc` functions
+//! of the module, and released by its `free` function. It detects:
+//!
+//! * the accesses to freed regions (use-after-free),
+//! * the accesses overflowing an allocated region, or falling between
+//!   the allocated regions (heap out-of-bounds),
+//! * the releases of regions which are not allocated (invalid free).
+//!
+//! The host functions are imported by the module from the
+//! [`SANITIZER_NAMESPACE`] namespace, and are provided by a
+//! [`ShadowMemory`].
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{MemoryImmediate, Operator};
+use wasmer::{
+    Exports, ExternType, Function, FunctionMiddleware, FunctionType, ImportedFunctions,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Module, ModuleMiddleware,
+    RuntimeError, Type, Val, WasmerEnv,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::SignatureIndex;
+use wasmer_vm::ModuleInfo;
+
+use crate::instrumentation::{BlockType, FunctionExits};
+use crate::profiling::function_name;
+
+/// The namespace of the host functions imported by the modules
+/// processed with the [`Sanitizer`] middleware.
+pub const SANITIZER_NAMESPACE: &str = "wasmer_sanitizer";
+
+/// The types of the values stored by the instrumented stores, with the
+/// names of the functions checking them.
+const STORE_TYPES: [(Type, &str); 4] = [
+    (Type::I32, "store_i32"),
+    (Type::I64, "store_i64"),
+    (Type::F32, "store_f32"),
+    (Type::F64, "store_f64"),
+];
+
+/// An allocator function of the module, whose calls update the shadow
+/// memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+enum AllocatorFunction {
+    Malloc,
+    Calloc,
+    Realloc,
+    Free,
+}
+
+impl AllocatorFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "malloc" => Some(Self::Malloc),
+            "calloc" => Some(Self::Calloc),
+            "realloc" => Some(Self::Realloc),
+            "free" => Some(Self::Free),
+            _ => None,
+        }
+    }
+
+    /// Returns the signature the function must have to be hooked.
+    fn signature(self) -> FunctionType {
+        match self {
+            Self::Malloc => FunctionType::new(vec![Type::I32], vec![Type::I32]),
+            Self::Calloc | Self::Realloc => {
+                FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32])
+            }
+            Self::Free => FunctionType::new(vec![Type::I32], vec![]),
+        }
+    }
+
+    /// Returns the operators pushing the released address and the size
+    /// of the allocated region on the stack.
+    ///
+    /// The size is an `i64`, so that the size of the `calloc` regions
+    /// doesn't wrap around.
+    fn arguments<'a>(self) -> Vec<Operator<'a>> {
+        match self {
+            Self::Malloc => vec![
+                Operator::I32Const { value: 0 },
+                Operator::LocalGet { local_index: 0 },
+                Operator::I64ExtendI32U,
+            ],
+            Self::Calloc => vec![
+                Operator::I32Const { value: 0 },
+                Operator::LocalGet { local_index: 0 },
+                Operator::I64ExtendI32U,
+                Operator::LocalGet { local_index: 1 },
+                Operator::I64ExtendI32U,
+                Operator::I64Mul,
+            ],
+            Self::Realloc => vec![
+                Operator::LocalGet { local_index: 0 },
+                Operator::LocalGet { local_index: 1 },
+                Operator::I64ExtendI32U,
+            ],
+            Self::Free => vec![
+                Operator::LocalGet { local_index: 0 },
+                Operator::I64Const { value: 0 },
+            ],
+        }
+    }
+}
+
+/// What the function middleware of an allocator function needs to know.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+struct Allocator {
+    function: AllocatorFunction,
+
+    /// The type of the block wrapping the function body, so that the
+    /// branches to the function label return through the call to the
+    /// exit function.
+    block_type: Option<BlockType>,
+}
+
+/// What the function middlewares need to know about the module.
+#[derive(Debug, MemoryUsage)]
+struct ModuleState {
+    /// The imported sanitizer functions, none if the module has no
+    /// memory to sanitize.
+    imported: ImportedFunctions,
+
+    /// The hooked allocator functions.
+    allocators: HashMap<LocalFunctionIndex, Allocator>,
+}
+
+/// The indexes of the imported sanitizer functions, in import order.
+const LOAD: usize = 0;
+const STORES: usize = 1;
+const ALLOCATOR_ENTER: usize = STORES + STORE_TYPES.len();
+const ALLOCATOR_EXIT: usize = ALLOCATOR_ENTER + 1;
+
+/// The module-level sanitizer middleware.
+///
+/// The module imports the sanitizer functions from the
+/// [`SANITIZER_NAMESPACE`] namespace, which can be provided by a
+/// [`ShadowMemory`]. Importing them shifts the indexes of the functions
+/// defined in the module by the number of sanitizer functions.
+///
+/// The allocator functions are found by name: `malloc`, `calloc`,
+/// `realloc` and `free`, with their C signatures. The accesses they
+/// make, including through the functions they call, are not checked.
+///
+/// Only the accesses to the first memory are checked. The atomic
+/// operators, the bulk memory operators, and the SIMD operators storing
+/// to memory or loading a single lane are not instrumented. The
+/// instrumented stores return their operands from a host function,
+/// which requires the multi-value feature, so the middleware can't be
+/// used with Singlepass.
+///
+/// # Panic
+///
+/// An instance of `Sanitizer` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// indexes of the sanitizer functions. Attempts to use a `Sanitizer`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::Sanitizer;
+///
+/// fn create_sanitizer_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     let sanitizer = Arc::new(Sanitizer::new());
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(sanitizer);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Sanitizer {
+    state: Mutex<Option<Arc<ModuleState>>>,
+}
+
+/// The function-level sanitizer middleware.
+pub struct FunctionSanitizer {
+    state: Arc<ModuleState>,
+
+    /// The index of the function among the local functions, which,
+    /// unlike its function index, isn't shifted by the functions the
+    /// next middlewares import.
+    local_function_index: u32,
+
+    allocator: Option<Allocator>,
+
+    /// Whether the first operator of the function has been fed.
+    started: bool,
+
+    exits: FunctionExits,
+}
+
+impl Sanitizer {
+    /// Creates a `Sanitizer` middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sanitizer")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Sanitizer {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let allocator = state.allocators.get(&local_function_index).copied();
+        Box::new(FunctionSanitizer {
+            state,
+            local_function_index: local_function_index.as_u32(),
+            allocator,
+            started: false,
+            exits: FunctionExits::default(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("Sanitizer::transform_module_info: Attempting to use a `Sanitizer` middleware from multiple modules.");
+        }
+
+        if module_info.memories.is_empty() {
+            *state = Some(Arc::new(ModuleState {
+                imported: ImportedFunctions::insert(module_info, SANITIZER_NAMESPACE, &[]),
+                allocators: HashMap::new(),
+            }));
+            return;
+        }
+
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+
+        let allocators = (0..num_local_functions)
+            .map(LocalFunctionIndex::new)
+            .filter_map(|local_function_index| {
+                let function = AllocatorFunction::from_name(&function_name(
+                    module_info,
+                    local_function_index,
+                ))?;
+                let signature_index =
+                    module_info.functions[module_info.func_index(local_function_index)];
+                if module_info.signatures[signature_index] != function.signature() {
+                    return None;
+                }
+                Some((local_function_index, function))
+            })
+            .collect::<Vec<_>>();
+
+        let mut imports: Vec<(String, SignatureIndex)> = vec![];
+        let load_signature_index = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32; 5], vec![Type::I32]));
+        imports.push(("load".to_string(), load_signature_index));
+        for (ty, name) in STORE_TYPES.iter() {
+            let store_signature_index = module_info.signatures.push(FunctionType::new(
+                vec![Type::I32, *ty, Type::I32, Type::I32, Type::I32, Type::I32],
+                vec![Type::I32, *ty],
+            ));
+            imports.push((name.to_string(), store_signature_index));
+        }
+        let enter_signature_index = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32, Type::I64], vec![]));
+        imports.push(("allocator_enter".to_string(), enter_signature_index));
+        let exit_signature_index = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32], vec![Type::I32]));
+        imports.push(("allocator_exit".to_string(), exit_signature_index));
+
+        let imported = ImportedFunctions::insert(module_info, SANITIZER_NAMESPACE, &imports);
+
+        let allocators = allocators
+            .into_iter()
+            .map(|(local_function_index, function)| {
+                let function_index = module_info.func_index(local_function_index);
+                let allocator = Allocator {
+                    function,
+                    block_type: BlockType::of_body(module_info, function_index),
+                };
+                (local_function_index, allocator)
+            })
+            .collect();
+
+        *state = Some(Arc::new(ModuleState {
+            imported,
+            allocators,
+        }));
+    }
+}
+
+impl MemoryUsage for Sanitizer {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.state.size_of_val(tracker) - mem::size_of_val(&self.state)
+    }
+}
+
+impl fmt::Debug for FunctionSanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionSanitizer")
+            .field("local_function_index", &self.local_function_index)
+            .field("allocator", &self.allocator)
+            .field("exits", &self.exits)
+            .finish()
+    }
+}
+
+/// A memory access which is checked by the shadow memory.
+enum MemoryAccess<'a> {
+    Load(&'a MemoryImmediate, u32),
+    /// A store, with the index of the type of the stored value in
+    /// [`STORE_TYPES`].
+    Store(&'a MemoryImmediate, u32, usize),
+}
+
+impl<'a> MemoryAccess<'a> {
+    fn of(operator: &'a Operator) -> Option<Self> {
+        Some(match operator {
+            Operator::V128Load8Splat { memarg }
+            | Operator::I32Load8S { memarg }
+            | Operator::I32Load8U { memarg }
+            | Operator::I64Load8S { memarg }
+            | Operator::I64Load8U { memarg } => Self::Load(memarg, 1),
+            Operator::V128Load16Splat { memarg }
+            | Operator::I32Load16S { memarg }
+            | Operator::I32Load16U { memarg }
+            | Operator::I64Load16S { memarg }
+            | Operator::I64Load16U { memarg } => Self::Load(memarg, 2),
+            Operator::V128Load32Splat { memarg }
+            | Operator::V128Load32Zero { memarg }
+            | Operator::I32Load { memarg }
+            | Operator::F32Load { memarg }
+            | Operator::I64Load32S { memarg }
+            | Operator::I64Load32U { memarg } => Self::Load(memarg, 4),
+            Operator::V128Load64Splat { memarg }
+            | Operator::V128Load64Zero { memarg }
+            | Operator::V128Load8x8S { memarg }
+            | Operator::V128Load8x8U { memarg }
+            | Operator::V128Load16x4S { memarg }
+            | Operator::V128Load16x4U { memarg }
+            | Operator::V128Load32x2S { memarg }
+            | Operator::V128Load32x2U { memarg }
+            | Operator::I64Load { memarg }
+            | Operator::F64Load { memarg } => Self::Load(memarg, 8),
+            Operator::V128Load { memarg } => Self::Load(memarg, 16),
+            Operator::I32Store8 { memarg } => Self::Store(memarg, 1, 0),
+            Operator::I32Store16 { memarg } => Self::Store(memarg, 2, 0),
+            Operator::I32Store { memarg } => Self::Store(memarg, 4, 0),
+            Operator::I64Store8 { memarg } => Self::Store(memarg, 1, 1),
+            Operator::I64Store16 { memarg } => Self::Store(memarg, 2, 1),
+            Operator::I64Store32 { memarg } => Self::Store(memarg, 4, 1),
+            Operator::I64Store { memarg } => Self::Store(memarg, 8, 1),
+            Operator::F32Store { memarg } => Self::Store(memarg, 4, 2),
+            Operator::F64Store { memarg } => Self::Store(memarg, 8, 3),
+            _ => return None,
+        })
+    }
+}
+
+impl FunctionSanitizer {
+    /// Calls the exit function of the allocator.
+    fn exit<'a>(&self, allocator: &Allocator, state: &mut MiddlewareReaderState<'a>) {
+        let exit = Operator::Call {
+            function_index: self.state.imported.index(ALLOCATOR_EXIT).as_u32(),
+        };
+        if allocator.function == AllocatorFunction::Free {
+            state.extend(&[Operator::I32Const { value: 0 }, exit, Operator::Drop]);
+        } else {
+            state.push_operator(exit);
+        }
+    }
+}
+
+impl FunctionMiddleware for FunctionSanitizer {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let imported = self.state.imported;
+
+        if !self.started {
+            self.started = true;
+            if let Some(allocator) = &self.allocator {
+                // Report the arguments of the allocator to the shadow
+                // memory, which doesn't check the accesses until the
+                // allocator returns.
+                state.extend(allocator.function.arguments());
+                state.push_operator(Operator::Call {
+                    function_index: imported.index(ALLOCATOR_ENTER).as_u32(),
+                });
+                if let Some(block_type) = allocator.block_type {
+                    state.push_operator(Operator::Block {
+                        ty: block_type.to_wasmparser(),
+                    });
+                }
+            }
+        }
+
+        if self.exits.is_exit(&operator) {
+            if let Some(allocator) = &self.allocator {
+                match operator {
+                    Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
+                        return Err(MiddlewareError::new(
+                            "sanitizer",
+                            format!(
+                                "tail calls are not supported in the allocator functions (local function {})",
+                                self.local_function_index
+                            ),
+                        ));
+                    }
+                    Operator::End if allocator.block_type.is_some() => {
+                        state.push_operator(Operator::End);
+                    }
+                    _ => {}
+                }
+                self.exit(allocator, state);
+            }
+        }
+
+        if let Some(access) = MemoryAccess::of(&operator) {
+            let (memarg, size, function_index) = match access {
+                MemoryAccess::Load(memarg, size) => (memarg, size, imported.index(LOAD)),
+                MemoryAccess::Store(memarg, size, ty) => {
+                    (memarg, size, imported.index(STORES + ty))
+                }
+            };
+            if memarg.memory == 0 {
+                // The address, and the stored value, are returned by
+                // the check.
+                let site = state.operator_offset();
+                state.extend(&[
+                    Operator::I32Const {
+                        value: memarg.offset as i32,
+                    },
+                    Operator::I32Const { value: size as i32 },
+                    Operator::I32Const {
+                        value: self.local_function_index as i32,
+                    },
+                    Operator::I32Const { value: site as i32 },
+                    Operator::Call {
+                        function_index: function_index.as_u32(),
+                    },
+                ]);
+            }
+        }
+
+        state.push_operator(imported.shift_operator(operator));
+
+        Ok(())
+    }
+}
+
+/// The kind of a memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Load,
+    Store,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load => write!(f, "load"),
+            Self::Store => write!(f, "store"),
+        }
+    }
+}
+
+/// The kind of a violation detected by a [`ShadowMemory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// An access to a freed region.
+    UseAfterFree(Access),
+
+    /// An access overflowing an allocated region, or falling between
+    /// the allocated regions.
+    HeapOutOfBounds(Access),
+
+    /// A release of a region which is not allocated, for instance
+    /// because it has already been freed.
+    InvalidFree,
+}
+
+/// Where an invalid memory access happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The index of the function, including the imported sanitizer
+    /// functions.
+    pub function_index: u32,
+
+    /// The name of the function, from the `name` section, or the name
+    /// it is exported with, or else `func{index}`.
+    pub name: String,
+
+    /// The offset of the accessing operator in the module.
+    pub offset: usize,
+}
+
+/// A violation detected by a [`ShadowMemory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+
+    /// The accessed address, including the offset of the operator, or
+    /// the released address.
+    pub address: u64,
+
+    /// The number of accessed bytes, or 0 for a release.
+    pub size: u32,
+
+    /// Where the memory was accessed. `None` for a release.
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, access) = match self.kind {
+            ViolationKind::UseAfterFree(access) => ("use-after-free", access),
+            ViolationKind::HeapOutOfBounds(access) => ("heap out-of-bounds", access),
+            ViolationKind::InvalidFree => {
+                return write!(f, "invalid free of address {:#x}", self.address)
+            }
+        };
+        write!(
+            f,
+            "{}: {} of {} bytes at address {:#x}",
+            kind, access, self.size, self.address
+        )?;
+        if let Some(location) = &self.location {
+            write!(
+                f,
+                ", in function `{}` (index {}) at offset {}",
+                location.name, location.function_index, location.offset
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A region of the shadow memory.
+#[derive(Debug)]
+struct Region {
+    end: u64,
+    freed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shadow {
+    /// The allocated and freed regions, by start address.
+    regions: BTreeMap<u64, Region>,
+
+    /// The range of addresses which have been allocated so far.
+    heap: Option<(u64, u64)>,
+
+    /// The released address and the allocated size of the allocator
+    /// calls which have not returned yet, innermost last.
+    allocator_calls: Vec<(u32, u64)>,
+
+    violations: Vec<Violation>,
+}
+
+impl Shadow {
+    fn allocate(&mut self, start: u64, size: u64) {
+        let end = start + size;
+
+        // The regions overlapping the new one have been freed.
+        let overlapping = self
+            .regions
+            .range(..end.max(start + 1))
+            .filter(|(region_start, region)| region.end.max(**region_start + 1) > start)
+            .map(|(region_start, _)| *region_start)
+            .collect::<Vec<_>>();
+        for region_start in overlapping {
+            self.regions.remove(&region_start);
+        }
+        self.regions.insert(start, Region { end, freed: false });
+
+        self.heap = Some(match self.heap {
+            Some((heap_start, heap_end)) => (heap_start.min(start), heap_end.max(end)),
+            None => (start, end),
+        });
+    }
+
+    fn free(&mut self, start: u64) -> Option<ViolationKind> {
+        match self.regions.get_mut(&start) {
+            Some(region) if !region.freed => {
+                region.freed = true;
+                None
+            }
+            _ => Some(ViolationKind::InvalidFree),
+        }
+    }
+
+    fn allocator_exit(&mut self, new: u32) -> Option<Violation> {
+        let (old, size) = self.allocator_calls.pop().unwrap_or((0, 0));
+        if !self.allocator_calls.is_empty() {
+            // Only the outermost allocator call is tracked, `realloc`
+            // may call `malloc` and `free` for instance.
+            return None;
+        }
+
+        // `realloc` releases the old region if it succeeds, or if the
+        // new size is 0.
+        let mut violation = None;
+        if old != 0 && (new != 0 || size == 0) {
+            violation = self.free(old as u64).map(|kind| Violation {
+                kind,
+                address: old as u64,
+                size: 0,
+                location: None,
+            });
+        }
+        // No region larger than the 32-bit address space can be
+        // allocated: `calloc` failed, or wrapped the size around.
+        if new != 0 && size <= u64::from(u32::MAX) {
+            self.allocate(new as u64, size);
+        }
+        violation
+    }
+
+    fn check(&self, access: Access, address: u64, size: u32) -> Option<ViolationKind> {
+        if !self.allocator_calls.is_empty() {
+            return None;
+        }
+
+        let end = address + size as u64;
+        match self.regions.range(..=address).next_back() {
+            Some((_, region)) if address < region.end => {
+                if region.freed {
+                    Some(ViolationKind::UseAfterFree(access))
+                } else if end > region.end {
+                    Some(ViolationKind::HeapOutOfBounds(access))
+                } else {
+                    None
+                }
+            }
+            _ => match self.heap {
+                Some((heap_start, heap_end)) if address < heap_end && end > heap_start => {
+                    Some(ViolationKind::HeapOutOfBounds(access))
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// The environment of the sanitizer functions.
+#[derive(Clone)]
+struct ShadowMemoryEnv {
+    shadow: Arc<Mutex<Shadow>>,
+    recover: bool,
+    /// The function indexes and names of the local functions.
+    functions: Arc<Vec<(u32, String)>>,
+}
+
+impl WasmerEnv for ShadowMemoryEnv {}
+
+impl ShadowMemoryEnv {
+    fn report(&self, shadow: &mut Shadow, violation: Violation) -> Result<(), RuntimeError> {
+        let error = if self.recover {
+            None
+        } else {
+            Some(RuntimeError::new(violation.to_string()))
+        };
+        shadow.violations.push(violation);
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Checks an access, whose arguments are the address, the offset of
+    /// the operator, the size, the local function index and the location of
+    /// the operator.
+    fn check(&self, access: Access, arguments: &[Val]) -> Result<(), RuntimeError> {
+        let mut shadow = self.shadow.lock().unwrap();
+        let address =
+            arguments[0].unwrap_i32() as u32 as u64 + arguments[1].unwrap_i32() as u32 as u64;
+        let size = arguments[2].unwrap_i32() as u32;
+        let kind = match shadow.check(access, address, size) {
+            Some(kind) => kind,
+            None => return Ok(()),
+        };
+        let local_function_index = arguments[3].unwrap_i32() as u32;
+        let (function_index, name) = self
+            .functions
+            .get(local_function_index as usize)
+            .cloned()
+            .unwrap_or_else(|| (local_function_index, "<unknown>".to_string()));
+        let violation = Violation {
+            kind,
+            address,
+            size,
+            location: Some(Location {
+                function_index,
+                name,
+                offset: arguments[4].unwrap_i32() as u32 as usize,
+            }),
+        };
+        self.report(&mut shadow, violation)
+    }
+}
+
+/// The host side of the [`Sanitizer`] middleware: it provides the
+/// sanitizer functions imported by the modules, and checks the memory
+/// accesses against a shadow of the allocated regions.
+///
+/// By default, a violation interrupts the module with a trap. A
+/// recovering `ShadowMemory` only records it.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{imports, Instance, Module};
+/// use wasmer_middlewares::sanitizer::{ShadowMemory, SANITIZER_NAMESPACE};
+///
+/// fn print_violations(module: &Module) -> Result<(), Box<dyn std::error::Error>> {
+///     let shadow_memory = ShadowMemory::new().recovering();
+///     let mut import_object = imports! {};
+///     import_object.register(SANITIZER_NAMESPACE, shadow_memory.imports(module));
+///     let instance = Instance::new(module, &import_object)?;
+///
+///     instance.exports.get_function("main")?.call(&[])?;
+///     for violation in shadow_memory.take_violations() {
+///         println!("{}", violation);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ShadowMemory {
+    shadow: Arc<Mutex<Shadow>>,
+    recover: bool,
+}
+
+impl ShadowMemory {
+    /// Creates a new `ShadowMemory`, without any allocated region.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the violations without interrupting the module.
+    pub fn recovering(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Creates the sanitizer functions imported by the given module,
+    /// which must have been processed with the [`Sanitizer`]
+    /// middleware.
+    ///
+    /// The returned exports must be registered in the import object
+    /// with the [`SANITIZER_NAMESPACE`] namespace.
+    pub fn imports(&self, module: &Module) -> Exports {
+        let module_info = module.info();
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        let functions = (0..num_local_functions)
+            .map(LocalFunctionIndex::new)
+            .map(|local_function_index| {
+                (
+                    module_info.func_index(local_function_index).as_u32(),
+                    function_name(module_info, local_function_index),
+                )
+            })
+            .collect();
+        let env = ShadowMemoryEnv {
+            shadow: self.shadow.clone(),
+            recover: self.recover,
+            functions: Arc::new(functions),
+        };
+
+        let mut exports = Exports::new();
+        for import in module.imports() {
+            let function_type = match (import.module(), import.ty()) {
+                (SANITIZER_NAMESPACE, ExternType::Function(function_type)) => function_type,
+                _ => continue,
+            };
+            let function = match import.name() {
+                "load" => Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &ShadowMemoryEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        env.check(Access::Load, arguments)?;
+                        Ok(vec![arguments[0].clone()])
+                    },
+                ),
+                "allocator_enter" => Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &ShadowMemoryEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        env.shadow.lock().unwrap().allocator_calls.push((
+                            arguments[0].unwrap_i32() as u32,
+                            arguments[1].unwrap_i64() as u64,
+                        ));
+                        Ok(vec![])
+                    },
+                ),
+                "allocator_exit" => Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &ShadowMemoryEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        let mut shadow = env.shadow.lock().unwrap();
+                        if let Some(violation) =
+                            shadow.allocator_exit(arguments[0].unwrap_i32() as u32)
+                        {
+                            env.report(&mut shadow, violation)?;
+                        }
+                        Ok(vec![arguments[0].clone()])
+                    },
+                ),
+                // The stores take the stored value after the address.
+                _ => Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &ShadowMemoryEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        let mut check_arguments = vec![arguments[0].clone()];
+                        check_arguments.extend_from_slice(&arguments[2..]);
+                        env.check(Access::Store, &check_arguments)?;
+                        Ok(arguments[..2].to_vec())
+                    },
+                ),
+            };
+            exports.insert(import.name(), function);
+        }
+        exports
+    }
+
+    /// Takes the violations detected so far, in order.
+    pub fn take_violations(&self) -> Vec<Violation> {
+        mem::take(&mut self.shadow.lock().unwrap().violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store, Universal,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (memory (export "memory") 2)
+            (global $next (mut i32) (i32.const 1024))
+            ;; A bump allocator, storing the size of the regions in a
+            ;; header.
+            (func $malloc (export "malloc") (param i32) (result i32)
+                global.get $next
+                local.get 0
+                i32.store
+                global.get $next
+                global.get $next
+                local.get 0
+                i32.add
+                i32.const 8
+                i32.add
+                global.set $next
+                i32.const 4
+                i32.add)
+            ;; A `calloc` wrapping the size around.
+            (func $calloc (export "calloc") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.mul
+                call $malloc)
+            (func $free (export "free") (param i32)
+                local.get 0
+                i32.const 4
+                i32.sub
+                i32.const 0
+                i32.store)
+            (func $in_bounds (export "in_bounds") (result i32)
+                (local i32)
+                i32.const 8
+                call $malloc
+                local.tee 0
+                i64.const 42
+                i64.store
+                local.get 0
+                i32.load offset=4
+                local.get 0
+                i32.load
+                i32.add)
+            (func $use_after_free (export "use_after_free") (result i32)
+                (local i32)
+                i32.const 4
+                call $malloc
+                local.tee 0
+                call $free
+                local.get 0
+                i32.load)
+            (func $overflow (export "overflow") (param i32)
+                (local i32)
+                i32.const 8
+                call $malloc
+                local.set 1
+                i32.const 8
+                call $malloc
+                drop
+                local.get 1
+                local.get 0
+                i32.add
+                i32.const 1
+                i32.store8)
+            (func $calloc_overflow (export "calloc_overflow")
+                (local i32)
+                i32.const 8
+                call $malloc
+                drop
+                i32.const 0x10000
+                i32.const 0x10001
+                call $calloc
+                local.set 0
+                i32.const 8
+                call $malloc
+                drop
+                local.get 0
+                i32.const 1
+                i32.store)
+            (func $double_free (export "double_free")
+                (local i32)
+                i32.const 4
+                call $malloc
+                local.tee 0
+                call $free
+                local.get 0
+                call $free))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(shadow_memory: &ShadowMemory) -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(Sanitizer::new()));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let mut import_object = imports! {};
+        import_object.register(SANITIZER_NAMESPACE, shadow_memory.imports(&module));
+        Instance::new(&module, &import_object).unwrap()
+    }
+
+    #[test]
+    fn valid_accesses() {
+        let shadow_memory = ShadowMemory::new();
+        let instance = instantiate(&shadow_memory);
+        let in_bounds = instance
+            .exports
+            .get_function("in_bounds")
+            .unwrap()
+            .native::<(), i32>()
+            .unwrap();
+        assert_eq!(in_bounds.call().unwrap(), 42);
+        assert!(shadow_memory.take_violations().is_empty());
+    }
+
+    #[test]
+    fn use_after_free() {
+        let shadow_memory = ShadowMemory::new();
+        let instance = instantiate(&shadow_memory);
+        let use_after_free = instance.exports.get_function("use_after_free").unwrap();
+        let error = use_after_free.call(&[]).unwrap_err();
+        assert!(error
+            .message()
+            .starts_with("use-after-free: load of 4 bytes"));
+
+        let violations = shadow_memory.take_violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].kind,
+            ViolationKind::UseAfterFree(Access::Load)
+        );
+        assert_eq!(violations[0].address, 1028);
+        assert_eq!(
+            violations[0].location.as_ref().unwrap().name,
+            "use_after_free"
+        );
+    }
+
+    #[test]
+    fn sanitize_with_other_middlewares() {
+        // The `Sanitizer` imports its functions before the tracing
+        // functions, which shifts the indexes of the local functions.
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(Sanitizer::new()));
+        compiler_config.push_middleware(Arc::new(crate::Tracing::new()));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let shadow_memory = ShadowMemory::new();
+        let tracer = crate::tracing::Tracer::new();
+        let mut import_object = imports! {};
+        import_object.register(SANITIZER_NAMESPACE, shadow_memory.imports(&module));
+        import_object.register(crate::tracing::TRACING_NAMESPACE, tracer.imports(&module));
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        let use_after_free = instance.exports.get_function("use_after_free").unwrap();
+        assert!(use_after_free.call(&[]).is_err());
+        let violations = shadow_memory.take_violations();
+        assert_eq!(violations.len(), 1);
+        let location = violations[0].location.as_ref().unwrap();
+        assert_eq!(location.name, "use_after_free");
+        assert_eq!(
+            location.function_index,
+            tracer.take_calls()[0].function_index
+        );
+    }
+
+    #[test]
+    fn heap_out_of_bounds() {
+        let shadow_memory = ShadowMemory::new().recovering();
+        let instance = instantiate(&shadow_memory);
+        let overflow = instance
+            .exports
+            .get_function("overflow")
+            .unwrap()
+            .native::<i32, ()>()
+            .unwrap();
+        overflow.call(7).unwrap();
+        assert!(shadow_memory.take_violations().is_empty());
+
+        // Between two regions.
+        overflow.call(8).unwrap();
+        // Inside the header of the next region.
+        overflow.call(12).unwrap();
+        let violations = shadow_memory.take_violations();
+        assert_eq!(
+            violations
+                .iter()
+                .map(|violation| (violation.kind, violation.size))
+                .collect::<Vec<_>>(),
+            vec![
+                (ViolationKind::HeapOutOfBounds(Access::Store), 1),
+                (ViolationKind::HeapOutOfBounds(Access::Store), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn calloc_overflow() {
+        let shadow_memory = ShadowMemory::new().recovering();
+        let instance = instantiate(&shadow_memory);
+        let calloc_overflow = instance.exports.get_function("calloc_overflow").unwrap();
+        calloc_overflow.call(&[]).unwrap();
+
+        // The size wrapped around to 64 KiB, which is not allocated.
+        let violations = shadow_memory.take_violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].kind,
+            ViolationKind::HeapOutOfBounds(Access::Store)
+        );
+        assert_eq!(violations[0].address, 1044);
+    }
+
+    #[test]
+    fn invalid_free() {
+        let shadow_memory = ShadowMemory::new().recovering();
+        let instance = instantiate(&shadow_memory);
+        let double_free = instance.exports.get_function("double_free").unwrap();
+        double_free.call(&[]).unwrap();
+
+        let violations = shadow_memory.take_violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].kind, ViolationKind::InvalidFree);
+        assert_eq!(violations[0].address, 1028);
+    }
+}
@@ -0,0 +1,1300 @@
+//! `stack_limit` is a middleware for putting a deterministic limit on
+//! the height of the WebAssembly stack. The WebAssembly instance
+//! execution is stopped when a call would exceed the limit.
+//!
+//! The native stack usage of a function depends on the compiler and
+//! on the platform, so a limit on the native stack traps at different
+//! recursion depths depending on how the module was compiled. Instead,
+//! `StackLimit` measures the stack in WebAssembly values: the prologue
+//! of each function adds the height of its frame (its parameters, its
+//! locals, the maximum height of its operand stack, and one more value
+//! standing for the frame itself) to a counter and checks the limit,
+//! and its epilogue removes it. Since the height only depends on the
+//! WebAssembly code, the limit is reached at exactly the same call
+//! depth with all the compilers and on all the platforms.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::convert::TryInto;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{
+    BinaryReader, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer::{
+    ExportIndex, FunctionBodyData, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, Type,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, GlobalIndex};
+use wasmer_vm::ModuleInfo;
+
+use crate::instrumentation::BlockType;
+
+#[derive(Clone, MemoryUsage)]
+struct StackLimitGlobalIndexes(GlobalIndex, GlobalIndex);
+
+impl StackLimitGlobalIndexes {
+    /// The global index in the current module for the stack height.
+    fn stack_height(&self) -> GlobalIndex {
+        self.0
+    }
+
+    /// The global index in the current module for a boolean indicating
+    /// whether the limit has been exceeded or not.
+    /// This boolean is represented as a i32 global:
+    ///   * 0: the limit has not been exceeded
+    ///   * 1: the limit has been exceeded
+    fn limit_exceeded(&self) -> GlobalIndex {
+        self.1
+    }
+}
+
+impl fmt::Debug for StackLimitGlobalIndexes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StackLimitGlobalIndexes")
+            .field("stack_height", &self.stack_height())
+            .field("limit_exceeded", &self.limit_exceeded())
+            .finish()
+    }
+}
+
+/// The number of parameters and results of a signature.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+struct Arity {
+    params: u32,
+    results: u32,
+}
+
+impl Arity {
+    fn of(signature: &FunctionType) -> Self {
+        Self {
+            params: signature.params().len() as u32,
+            results: signature.results().len() as u32,
+        }
+    }
+
+    /// The arity of the function type at the given index of the type
+    /// section.
+    fn of_type(module_info: &ModuleInfo, type_index: u32) -> Self {
+        module_info.signature_index(type_index).map_or(
+            Self {
+                params: 0,
+                results: 0,
+            },
+            |signature_index| Self::of(&module_info.signatures[signature_index]),
+        )
+    }
+
+    fn of_function(module_info: &ModuleInfo, function_index: u32) -> Self {
+        let signature_index = module_info.functions[FunctionIndex::from_u32(function_index)];
+        Self::of(&module_info.signatures[signature_index])
+    }
+
+    fn of_block(module_info: &ModuleInfo, ty: WpTypeOrFuncType) -> Self {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => Self {
+                params: 0,
+                results: 0,
+            },
+            WpTypeOrFuncType::Type(_) => Self {
+                params: 0,
+                results: 1,
+            },
+            WpTypeOrFuncType::FuncType(type_index) => Self::of_type(module_info, type_index),
+        }
+    }
+}
+
+/// What the function middlewares need to know about the module.
+#[derive(Debug, Clone, MemoryUsage)]
+struct ModuleState {
+    global_indexes: StackLimitGlobalIndexes,
+
+    /// The maximum height of the operand stack of each local function
+    /// of the original module.
+    operand_heights: Vec<u32>,
+
+    /// The number of parameters of each local function.
+    params: Vec<u32>,
+
+    /// The type of a block producing the results of each local
+    /// function, if the module declares one.
+    block_types: Vec<Option<BlockType>>,
+}
+
+/// The module-level stack limit middleware.
+///
+/// The height of the operand stack of a function is measured on its
+/// original code. The functions added by the previous middlewares of
+/// the chain are charged for their parameters and locals only.
+///
+/// # Panic
+///
+/// An instance of `StackLimit` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// global index to store the stack height. Attempts to use a
+/// `StackLimit` instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::StackLimit;
+///
+/// fn create_stack_limit_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's allow up to 64Ki values on the stack.
+///     let stack_limit = Arc::new(StackLimit::new(64 * 1024));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(stack_limit);
+/// }
+/// ```
+pub struct StackLimit {
+    /// The maximum height of the stack, in values.
+    limit: u32,
+
+    /// The maximum height of the operand stack of each function body
+    /// fed to the middleware, until the `ModuleInfo` is transformed.
+    operand_heights: Mutex<Option<Vec<u32>>>,
+
+    /// The module information used by the function middlewares.
+    state: Mutex<Option<Arc<ModuleState>>>,
+}
+
+/// The function-level stack limit middleware.
+pub struct FunctionStackLimit {
+    limit: u32,
+
+    state: Arc<ModuleState>,
+
+    /// The maximum height of the operand stack of the function.
+    operand_height: u32,
+
+    /// The number of parameters and locals of the function.
+    locals: u32,
+
+    /// The type of a block producing the results of the function, if
+    /// the module declares one.
+    block_type: Option<BlockType>,
+
+    /// Whether the prologue has been emitted.
+    entered: bool,
+
+    /// The number of blocks enclosing the current operator.
+    depth: u32,
+}
+
+/// A block of a function body whose operand stack is measured.
+#[derive(Debug)]
+struct BlockFrame {
+    /// The height of the operand stack when entering the block,
+    /// without the block parameters.
+    height: u32,
+
+    arity: Arity,
+}
+
+/// The operand stack of a function body, as far as its height is
+/// concerned.
+#[derive(Debug, Default)]
+struct OperandStack {
+    /// The current height of the operand stack.
+    height: u32,
+
+    /// The maximum height reached so far.
+    max_height: u32,
+
+    /// The blocks enclosing the current operator.
+    frames: Vec<BlockFrame>,
+}
+
+impl StackLimit {
+    /// Creates a `StackLimit` middleware allowing up to `limit` values
+    /// on the stack.
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            operand_heights: Mutex::new(None),
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for StackLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StackLimit")
+            .field("limit", &self.limit)
+            .field(
+                "global_indexes",
+                &self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|state| state.global_indexes.clone()),
+            )
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for StackLimit {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let index = local_function_index.index();
+        Box::new(FunctionStackLimit {
+            limit: self.limit,
+            operand_height: state.operand_heights.get(index).copied().unwrap_or(0),
+            locals: state.params[index],
+            block_type: state.block_types[index],
+            state,
+            entered: false,
+            depth: 0,
+        })
+    }
+
+    /// Measures the operand stack of the function bodies.
+    fn feed_function_bodies(
+        &self,
+        module_info: &ModuleInfo,
+        _code_section_offset: usize,
+        function_bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        *self.operand_heights.lock().unwrap() = Some(
+            function_bodies
+                .values()
+                .map(|function_body| operand_height(module_info, function_body))
+                .collect(),
+        );
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("StackLimit::transform_module_info: Attempting to use a `StackLimit` middleware from multiple modules.");
+        }
+
+        // Append a global for the stack height and initialize it.
+        let stack_height_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            "wasmer_stack_limit_height".to_string(),
+            ExportIndex::Global(stack_height_global_index),
+        );
+
+        // Append a global for the exceeded limit boolean and initialize it.
+        let limit_exceeded_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            "wasmer_stack_limit_exceeded".to_string(),
+            ExportIndex::Global(limit_exceeded_global_index),
+        );
+
+        // The body of each function is wrapped in a block producing its
+        // results, so that the epilogue runs whichever way it returns.
+        let local_functions = (module_info.num_imported_functions..module_info.functions.len())
+            .map(FunctionIndex::new);
+        let params = local_functions
+            .clone()
+            .map(|function_index| {
+                module_info.signatures[module_info.functions[function_index]]
+                    .params()
+                    .len() as u32
+            })
+            .collect();
+        let block_types = local_functions
+            .map(|function_index| BlockType::of_body(module_info, function_index))
+            .collect();
+
+        *state = Some(Arc::new(ModuleState {
+            global_indexes: StackLimitGlobalIndexes(
+                stack_height_global_index,
+                limit_exceeded_global_index,
+            ),
+            operand_heights: self
+                .operand_heights
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_default(),
+            params,
+            block_types,
+        }));
+    }
+}
+
+impl MemoryUsage for StackLimit {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.operand_heights.size_of_val(tracker)
+            - mem::size_of_val(&self.operand_heights)
+            + self.state.size_of_val(tracker)
+            - mem::size_of_val(&self.state)
+    }
+}
+
+/// Returns the maximum height of the operand stack of a function body.
+fn operand_height(module_info: &ModuleInfo, function_body: &FunctionBodyData) -> u32 {
+    let mut reader = BinaryReader::new_with_offset(function_body.data, function_body.module_offset);
+    let mut stack = OperandStack::default();
+
+    let mut skip_locals = || -> Result<(), wasmer::wasmparser::BinaryReaderError> {
+        for _ in 0..reader.read_var_u32()? {
+            reader.read_var_u32()?;
+            reader.read_type()?;
+        }
+        Ok(())
+    };
+    if skip_locals().is_err() {
+        return 0;
+    }
+
+    while !reader.eof() {
+        match reader.read_operator() {
+            Ok(operator) => stack.apply(module_info, &operator),
+            Err(_) => break,
+        }
+    }
+
+    stack.max_height
+}
+
+impl fmt::Debug for FunctionStackLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionStackLimit")
+            .field("limit", &self.limit)
+            .field("global_indexes", &self.state.global_indexes)
+            .field("operand_height", &self.operand_height)
+            .field("locals", &self.locals)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl OperandStack {
+    /// The height of the operand stack at the start of the innermost
+    /// block, below which operators can't pop values.
+    fn block_height(&self) -> u32 {
+        self.frames.last().map_or(0, |frame| frame.height)
+    }
+
+    fn pop(&mut self, count: u32) {
+        self.height = self.height.saturating_sub(count).max(self.block_height());
+    }
+
+    fn push(&mut self, count: u32) {
+        self.height = self.height.saturating_add(count);
+        self.max_height = self.max_height.max(self.height);
+    }
+
+    fn enter_block(&mut self, arity: Arity) {
+        self.pop(arity.params);
+        self.frames.push(BlockFrame {
+            height: self.height,
+            arity,
+        });
+        self.push(arity.params);
+    }
+
+    /// Drops the values of the innermost block after an unconditional
+    /// branch: the following code is unreachable.
+    fn unreachable(&mut self) {
+        self.height = self.block_height();
+    }
+
+    /// Updates the operand stack height for the given operator.
+    fn apply(&mut self, module_info: &ModuleInfo, operator: &Operator) {
+        let (pops, pushes) = match *operator {
+            Operator::Block { ty } | Operator::Loop { ty } | Operator::Try { ty } => {
+                self.enter_block(Arity::of_block(module_info, ty));
+                return;
+            }
+            Operator::If { ty } => {
+                self.pop(1);
+                self.enter_block(Arity::of_block(module_info, ty));
+                return;
+            }
+            Operator::Else | Operator::Catch { .. } | Operator::CatchAll | Operator::Unwind => {
+                if let Some(frame) = self.frames.last() {
+                    self.height = frame.height + frame.arity.params;
+                }
+                return;
+            }
+            Operator::End | Operator::Delegate { .. } => {
+                if let Some(frame) = self.frames.pop() {
+                    self.height = frame.height + frame.arity.results;
+                }
+                return;
+            }
+            Operator::Br { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Unreachable
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::Throw { .. }
+            | Operator::Rethrow { .. } => {
+                self.unreachable();
+                return;
+            }
+            Operator::BrIf { .. } => (1, 0),
+            Operator::Call { function_index } => {
+                let arity = Arity::of_function(module_info, function_index);
+                (arity.params, arity.results)
+            }
+            Operator::CallIndirect { index, .. } => {
+                let arity = Arity::of_type(module_info, index);
+                (arity.params + 1, arity.results)
+            }
+
+            Operator::AtomicFence { .. }
+            | Operator::DataDrop { .. }
+            | Operator::ElemDrop { .. }
+            | Operator::Nop => (0, 0),
+
+            Operator::Drop | Operator::GlobalSet { .. } | Operator::LocalSet { .. } => (1, 0),
+
+            Operator::F32Const { .. }
+            | Operator::F64Const { .. }
+            | Operator::GlobalGet { .. }
+            | Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::LocalGet { .. }
+            | Operator::MemorySize { .. }
+            | Operator::RefFunc { .. }
+            | Operator::RefNull { .. }
+            | Operator::TableSize { .. }
+            | Operator::V128Const { .. } => (0, 1),
+
+            Operator::F32Abs
+            | Operator::F32Ceil
+            | Operator::F32ConvertI32S
+            | Operator::F32ConvertI32U
+            | Operator::F32ConvertI64S
+            | Operator::F32ConvertI64U
+            | Operator::F32DemoteF64
+            | Operator::F32Floor
+            | Operator::F32Load { .. }
+            | Operator::F32Nearest
+            | Operator::F32Neg
+            | Operator::F32ReinterpretI32
+            | Operator::F32Sqrt
+            | Operator::F32Trunc
+            | Operator::F32x4Abs
+            | Operator::F32x4Ceil
+            | Operator::F32x4ConvertI32x4S
+            | Operator::F32x4ConvertI32x4U
+            | Operator::F32x4DemoteF64x2Zero
+            | Operator::F32x4ExtractLane { .. }
+            | Operator::F32x4Floor
+            | Operator::F32x4Nearest
+            | Operator::F32x4Neg
+            | Operator::F32x4Splat
+            | Operator::F32x4Sqrt
+            | Operator::F32x4Trunc
+            | Operator::F64Abs
+            | Operator::F64Ceil
+            | Operator::F64ConvertI32S
+            | Operator::F64ConvertI32U
+            | Operator::F64ConvertI64S
+            | Operator::F64ConvertI64U
+            | Operator::F64Floor
+            | Operator::F64Load { .. }
+            | Operator::F64Nearest
+            | Operator::F64Neg
+            | Operator::F64PromoteF32
+            | Operator::F64ReinterpretI64
+            | Operator::F64Sqrt
+            | Operator::F64Trunc
+            | Operator::F64x2Abs
+            | Operator::F64x2Ceil
+            | Operator::F64x2ConvertLowI32x4S
+            | Operator::F64x2ConvertLowI32x4U
+            | Operator::F64x2ExtractLane { .. }
+            | Operator::F64x2Floor
+            | Operator::F64x2Nearest
+            | Operator::F64x2Neg
+            | Operator::F64x2PromoteLowF32x4
+            | Operator::F64x2Splat
+            | Operator::F64x2Sqrt
+            | Operator::F64x2Trunc
+            | Operator::I16x8Abs
+            | Operator::I16x8AllTrue
+            | Operator::I16x8Bitmask
+            | Operator::I16x8ExtAddPairwiseI8x16S
+            | Operator::I16x8ExtAddPairwiseI8x16U
+            | Operator::I16x8ExtendHighI8x16S
+            | Operator::I16x8ExtendHighI8x16U
+            | Operator::I16x8ExtendLowI8x16S
+            | Operator::I16x8ExtendLowI8x16U
+            | Operator::I16x8ExtractLaneS { .. }
+            | Operator::I16x8ExtractLaneU { .. }
+            | Operator::I16x8Neg
+            | Operator::I16x8Splat
+            | Operator::I32AtomicLoad { .. }
+            | Operator::I32AtomicLoad16U { .. }
+            | Operator::I32AtomicLoad8U { .. }
+            | Operator::I32Clz
+            | Operator::I32Ctz
+            | Operator::I32Eqz
+            | Operator::I32Extend16S
+            | Operator::I32Extend8S
+            | Operator::I32Load { .. }
+            | Operator::I32Load16S { .. }
+            | Operator::I32Load16U { .. }
+            | Operator::I32Load8S { .. }
+            | Operator::I32Load8U { .. }
+            | Operator::I32Popcnt
+            | Operator::I32ReinterpretF32
+            | Operator::I32TruncF32S
+            | Operator::I32TruncF32U
+            | Operator::I32TruncF64S
+            | Operator::I32TruncF64U
+            | Operator::I32TruncSatF32S
+            | Operator::I32TruncSatF32U
+            | Operator::I32TruncSatF64S
+            | Operator::I32TruncSatF64U
+            | Operator::I32WrapI64
+            | Operator::I32x4Abs
+            | Operator::I32x4AllTrue
+            | Operator::I32x4Bitmask
+            | Operator::I32x4ExtAddPairwiseI16x8S
+            | Operator::I32x4ExtAddPairwiseI16x8U
+            | Operator::I32x4ExtendHighI16x8S
+            | Operator::I32x4ExtendHighI16x8U
+            | Operator::I32x4ExtendLowI16x8S
+            | Operator::I32x4ExtendLowI16x8U
+            | Operator::I32x4ExtractLane { .. }
+            | Operator::I32x4Neg
+            | Operator::I32x4Splat
+            | Operator::I32x4TruncSatF32x4S
+            | Operator::I32x4TruncSatF32x4U
+            | Operator::I32x4TruncSatF64x2SZero
+            | Operator::I32x4TruncSatF64x2UZero
+            | Operator::I64AtomicLoad { .. }
+            | Operator::I64AtomicLoad16U { .. }
+            | Operator::I64AtomicLoad32U { .. }
+            | Operator::I64AtomicLoad8U { .. }
+            | Operator::I64Clz
+            | Operator::I64Ctz
+            | Operator::I64Eqz
+            | Operator::I64Extend16S
+            | Operator::I64Extend32S
+            | Operator::I64Extend8S
+            | Operator::I64ExtendI32S
+            | Operator::I64ExtendI32U
+            | Operator::I64Load { .. }
+            | Operator::I64Load16S { .. }
+            | Operator::I64Load16U { .. }
+            | Operator::I64Load32S { .. }
+            | Operator::I64Load32U { .. }
+            | Operator::I64Load8S { .. }
+            | Operator::I64Load8U { .. }
+            | Operator::I64Popcnt
+            | Operator::I64ReinterpretF64
+            | Operator::I64TruncF32S
+            | Operator::I64TruncF32U
+            | Operator::I64TruncF64S
+            | Operator::I64TruncF64U
+            | Operator::I64TruncSatF32S
+            | Operator::I64TruncSatF32U
+            | Operator::I64TruncSatF64S
+            | Operator::I64TruncSatF64U
+            | Operator::I64x2Abs
+            | Operator::I64x2AllTrue
+            | Operator::I64x2Bitmask
+            | Operator::I64x2ExtendHighI32x4S
+            | Operator::I64x2ExtendHighI32x4U
+            | Operator::I64x2ExtendLowI32x4S
+            | Operator::I64x2ExtendLowI32x4U
+            | Operator::I64x2ExtractLane { .. }
+            | Operator::I64x2Neg
+            | Operator::I64x2Splat
+            | Operator::I8x16Abs
+            | Operator::I8x16AllTrue
+            | Operator::I8x16Bitmask
+            | Operator::I8x16ExtractLaneS { .. }
+            | Operator::I8x16ExtractLaneU { .. }
+            | Operator::I8x16Neg
+            | Operator::I8x16Popcnt
+            | Operator::I8x16Splat
+            | Operator::LocalTee { .. }
+            | Operator::MemoryGrow { .. }
+            | Operator::RefIsNull
+            | Operator::TableGet { .. }
+            | Operator::V128AnyTrue
+            | Operator::V128Load { .. }
+            | Operator::V128Load16Splat { .. }
+            | Operator::V128Load16x4S { .. }
+            | Operator::V128Load16x4U { .. }
+            | Operator::V128Load32Splat { .. }
+            | Operator::V128Load32Zero { .. }
+            | Operator::V128Load32x2S { .. }
+            | Operator::V128Load32x2U { .. }
+            | Operator::V128Load64Splat { .. }
+            | Operator::V128Load64Zero { .. }
+            | Operator::V128Load8Splat { .. }
+            | Operator::V128Load8x8S { .. }
+            | Operator::V128Load8x8U { .. }
+            | Operator::V128Not => (1, 1),
+
+            Operator::F32Store { .. }
+            | Operator::F64Store { .. }
+            | Operator::I32AtomicStore { .. }
+            | Operator::I32AtomicStore16 { .. }
+            | Operator::I32AtomicStore8 { .. }
+            | Operator::I32Store { .. }
+            | Operator::I32Store16 { .. }
+            | Operator::I32Store8 { .. }
+            | Operator::I64AtomicStore { .. }
+            | Operator::I64AtomicStore16 { .. }
+            | Operator::I64AtomicStore32 { .. }
+            | Operator::I64AtomicStore8 { .. }
+            | Operator::I64Store { .. }
+            | Operator::I64Store16 { .. }
+            | Operator::I64Store32 { .. }
+            | Operator::I64Store8 { .. }
+            | Operator::TableSet { .. }
+            | Operator::V128Store { .. }
+            | Operator::V128Store16Lane { .. }
+            | Operator::V128Store32Lane { .. }
+            | Operator::V128Store64Lane { .. }
+            | Operator::V128Store8Lane { .. } => (2, 0),
+
+            Operator::F32Add
+            | Operator::F32Copysign
+            | Operator::F32Div
+            | Operator::F32Eq
+            | Operator::F32Ge
+            | Operator::F32Gt
+            | Operator::F32Le
+            | Operator::F32Lt
+            | Operator::F32Max
+            | Operator::F32Min
+            | Operator::F32Mul
+            | Operator::F32Ne
+            | Operator::F32Sub
+            | Operator::F32x4Add
+            | Operator::F32x4Div
+            | Operator::F32x4Eq
+            | Operator::F32x4Ge
+            | Operator::F32x4Gt
+            | Operator::F32x4Le
+            | Operator::F32x4Lt
+            | Operator::F32x4Max
+            | Operator::F32x4Min
+            | Operator::F32x4Mul
+            | Operator::F32x4Ne
+            | Operator::F32x4PMax
+            | Operator::F32x4PMin
+            | Operator::F32x4ReplaceLane { .. }
+            | Operator::F32x4Sub
+            | Operator::F64Add
+            | Operator::F64Copysign
+            | Operator::F64Div
+            | Operator::F64Eq
+            | Operator::F64Ge
+            | Operator::F64Gt
+            | Operator::F64Le
+            | Operator::F64Lt
+            | Operator::F64Max
+            | Operator::F64Min
+            | Operator::F64Mul
+            | Operator::F64Ne
+            | Operator::F64Sub
+            | Operator::F64x2Add
+            | Operator::F64x2Div
+            | Operator::F64x2Eq
+            | Operator::F64x2Ge
+            | Operator::F64x2Gt
+            | Operator::F64x2Le
+            | Operator::F64x2Lt
+            | Operator::F64x2Max
+            | Operator::F64x2Min
+            | Operator::F64x2Mul
+            | Operator::F64x2Ne
+            | Operator::F64x2PMax
+            | Operator::F64x2PMin
+            | Operator::F64x2ReplaceLane { .. }
+            | Operator::F64x2Sub
+            | Operator::I16x8Add
+            | Operator::I16x8AddSatS
+            | Operator::I16x8AddSatU
+            | Operator::I16x8Eq
+            | Operator::I16x8ExtMulHighI8x16S
+            | Operator::I16x8ExtMulHighI8x16U
+            | Operator::I16x8ExtMulLowI8x16S
+            | Operator::I16x8ExtMulLowI8x16U
+            | Operator::I16x8GeS
+            | Operator::I16x8GeU
+            | Operator::I16x8GtS
+            | Operator::I16x8GtU
+            | Operator::I16x8LeS
+            | Operator::I16x8LeU
+            | Operator::I16x8LtS
+            | Operator::I16x8LtU
+            | Operator::I16x8MaxS
+            | Operator::I16x8MaxU
+            | Operator::I16x8MinS
+            | Operator::I16x8MinU
+            | Operator::I16x8Mul
+            | Operator::I16x8NarrowI32x4S
+            | Operator::I16x8NarrowI32x4U
+            | Operator::I16x8Ne
+            | Operator::I16x8Q15MulrSatS
+            | Operator::I16x8ReplaceLane { .. }
+            | Operator::I16x8RoundingAverageU
+            | Operator::I16x8Shl
+            | Operator::I16x8ShrS
+            | Operator::I16x8ShrU
+            | Operator::I16x8Sub
+            | Operator::I16x8SubSatS
+            | Operator::I16x8SubSatU
+            | Operator::I32Add
+            | Operator::I32And
+            | Operator::I32AtomicRmw16AddU { .. }
+            | Operator::I32AtomicRmw16AndU { .. }
+            | Operator::I32AtomicRmw16OrU { .. }
+            | Operator::I32AtomicRmw16SubU { .. }
+            | Operator::I32AtomicRmw16XchgU { .. }
+            | Operator::I32AtomicRmw16XorU { .. }
+            | Operator::I32AtomicRmw8AddU { .. }
+            | Operator::I32AtomicRmw8AndU { .. }
+            | Operator::I32AtomicRmw8OrU { .. }
+            | Operator::I32AtomicRmw8SubU { .. }
+            | Operator::I32AtomicRmw8XchgU { .. }
+            | Operator::I32AtomicRmw8XorU { .. }
+            | Operator::I32AtomicRmwAdd { .. }
+            | Operator::I32AtomicRmwAnd { .. }
+            | Operator::I32AtomicRmwOr { .. }
+            | Operator::I32AtomicRmwSub { .. }
+            | Operator::I32AtomicRmwXchg { .. }
+            | Operator::I32AtomicRmwXor { .. }
+            | Operator::I32DivS
+            | Operator::I32DivU
+            | Operator::I32Eq
+            | Operator::I32GeS
+            | Operator::I32GeU
+            | Operator::I32GtS
+            | Operator::I32GtU
+            | Operator::I32LeS
+            | Operator::I32LeU
+            | Operator::I32LtS
+            | Operator::I32LtU
+            | Operator::I32Mul
+            | Operator::I32Ne
+            | Operator::I32Or
+            | Operator::I32RemS
+            | Operator::I32RemU
+            | Operator::I32Rotl
+            | Operator::I32Rotr
+            | Operator::I32Shl
+            | Operator::I32ShrS
+            | Operator::I32ShrU
+            | Operator::I32Sub
+            | Operator::I32Xor
+            | Operator::I32x4Add
+            | Operator::I32x4DotI16x8S
+            | Operator::I32x4Eq
+            | Operator::I32x4ExtMulHighI16x8S
+            | Operator::I32x4ExtMulHighI16x8U
+            | Operator::I32x4ExtMulLowI16x8S
+            | Operator::I32x4ExtMulLowI16x8U
+            | Operator::I32x4GeS
+            | Operator::I32x4GeU
+            | Operator::I32x4GtS
+            | Operator::I32x4GtU
+            | Operator::I32x4LeS
+            | Operator::I32x4LeU
+            | Operator::I32x4LtS
+            | Operator::I32x4LtU
+            | Operator::I32x4MaxS
+            | Operator::I32x4MaxU
+            | Operator::I32x4MinS
+            | Operator::I32x4MinU
+            | Operator::I32x4Mul
+            | Operator::I32x4Ne
+            | Operator::I32x4ReplaceLane { .. }
+            | Operator::I32x4Shl
+            | Operator::I32x4ShrS
+            | Operator::I32x4ShrU
+            | Operator::I32x4Sub
+            | Operator::I64Add
+            | Operator::I64And
+            | Operator::I64AtomicRmw16AddU { .. }
+            | Operator::I64AtomicRmw16AndU { .. }
+            | Operator::I64AtomicRmw16OrU { .. }
+            | Operator::I64AtomicRmw16SubU { .. }
+            | Operator::I64AtomicRmw16XchgU { .. }
+            | Operator::I64AtomicRmw16XorU { .. }
+            | Operator::I64AtomicRmw32AddU { .. }
+            | Operator::I64AtomicRmw32AndU { .. }
+            | Operator::I64AtomicRmw32OrU { .. }
+            | Operator::I64AtomicRmw32SubU { .. }
+            | Operator::I64AtomicRmw32XchgU { .. }
+            | Operator::I64AtomicRmw32XorU { .. }
+            | Operator::I64AtomicRmw8AddU { .. }
+            | Operator::I64AtomicRmw8AndU { .. }
+            | Operator::I64AtomicRmw8OrU { .. }
+            | Operator::I64AtomicRmw8SubU { .. }
+            | Operator::I64AtomicRmw8XchgU { .. }
+            | Operator::I64AtomicRmw8XorU { .. }
+            | Operator::I64AtomicRmwAdd { .. }
+            | Operator::I64AtomicRmwAnd { .. }
+            | Operator::I64AtomicRmwOr { .. }
+            | Operator::I64AtomicRmwSub { .. }
+            | Operator::I64AtomicRmwXchg { .. }
+            | Operator::I64AtomicRmwXor { .. }
+            | Operator::I64DivS
+            | Operator::I64DivU
+            | Operator::I64Eq
+            | Operator::I64GeS
+            | Operator::I64GeU
+            | Operator::I64GtS
+            | Operator::I64GtU
+            | Operator::I64LeS
+            | Operator::I64LeU
+            | Operator::I64LtS
+            | Operator::I64LtU
+            | Operator::I64Mul
+            | Operator::I64Ne
+            | Operator::I64Or
+            | Operator::I64RemS
+            | Operator::I64RemU
+            | Operator::I64Rotl
+            | Operator::I64Rotr
+            | Operator::I64Shl
+            | Operator::I64ShrS
+            | Operator::I64ShrU
+            | Operator::I64Sub
+            | Operator::I64Xor
+            | Operator::I64x2Add
+            | Operator::I64x2Eq
+            | Operator::I64x2ExtMulHighI32x4S
+            | Operator::I64x2ExtMulHighI32x4U
+            | Operator::I64x2ExtMulLowI32x4S
+            | Operator::I64x2ExtMulLowI32x4U
+            | Operator::I64x2GeS
+            | Operator::I64x2GtS
+            | Operator::I64x2LeS
+            | Operator::I64x2LtS
+            | Operator::I64x2Mul
+            | Operator::I64x2Ne
+            | Operator::I64x2ReplaceLane { .. }
+            | Operator::I64x2Shl
+            | Operator::I64x2ShrS
+            | Operator::I64x2ShrU
+            | Operator::I64x2Sub
+            | Operator::I8x16Add
+            | Operator::I8x16AddSatS
+            | Operator::I8x16AddSatU
+            | Operator::I8x16Eq
+            | Operator::I8x16GeS
+            | Operator::I8x16GeU
+            | Operator::I8x16GtS
+            | Operator::I8x16GtU
+            | Operator::I8x16LeS
+            | Operator::I8x16LeU
+            | Operator::I8x16LtS
+            | Operator::I8x16LtU
+            | Operator::I8x16MaxS
+            | Operator::I8x16MaxU
+            | Operator::I8x16MinS
+            | Operator::I8x16MinU
+            | Operator::I8x16NarrowI16x8S
+            | Operator::I8x16NarrowI16x8U
+            | Operator::I8x16Ne
+            | Operator::I8x16ReplaceLane { .. }
+            | Operator::I8x16RoundingAverageU
+            | Operator::I8x16Shl
+            | Operator::I8x16ShrS
+            | Operator::I8x16ShrU
+            | Operator::I8x16Shuffle { .. }
+            | Operator::I8x16Sub
+            | Operator::I8x16SubSatS
+            | Operator::I8x16SubSatU
+            | Operator::I8x16Swizzle
+            | Operator::MemoryAtomicNotify { .. }
+            | Operator::TableGrow { .. }
+            | Operator::V128And
+            | Operator::V128AndNot
+            | Operator::V128Load16Lane { .. }
+            | Operator::V128Load32Lane { .. }
+            | Operator::V128Load64Lane { .. }
+            | Operator::V128Load8Lane { .. }
+            | Operator::V128Or
+            | Operator::V128Xor => (2, 1),
+
+            Operator::MemoryCopy { .. }
+            | Operator::MemoryFill { .. }
+            | Operator::MemoryInit { .. }
+            | Operator::TableCopy { .. }
+            | Operator::TableFill { .. }
+            | Operator::TableInit { .. } => (3, 0),
+
+            Operator::I32AtomicRmw16CmpxchgU { .. }
+            | Operator::I32AtomicRmw8CmpxchgU { .. }
+            | Operator::I32AtomicRmwCmpxchg { .. }
+            | Operator::I64AtomicRmw16CmpxchgU { .. }
+            | Operator::I64AtomicRmw32CmpxchgU { .. }
+            | Operator::I64AtomicRmw8CmpxchgU { .. }
+            | Operator::I64AtomicRmwCmpxchg { .. }
+            | Operator::MemoryAtomicWait32 { .. }
+            | Operator::MemoryAtomicWait64 { .. }
+            | Operator::Select
+            | Operator::TypedSelect { .. }
+            | Operator::V128Bitselect => (3, 1),
+        };
+        self.pop(pops);
+        self.push(pushes);
+    }
+}
+
+impl FunctionStackLimit {
+    /// The height of the frame of the function.
+    fn frame_height(&self) -> i32 {
+        self.locals
+            .saturating_add(self.operand_height)
+            .saturating_add(1)
+            .min(i32::MAX as u32) as i32
+    }
+}
+
+impl FunctionMiddleware for FunctionStackLimit {
+    fn feed_local_decl(&mut self, count: u32, _ty: WpType) {
+        self.locals = self.locals.saturating_add(count);
+    }
+
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let frame_height = self.frame_height();
+        let stack_height = self.state.global_indexes.stack_height().as_u32();
+
+        if !self.entered {
+            self.entered = true;
+            let block_type = self.block_type.ok_or_else(|| {
+                MiddlewareError::new(
+                    "StackLimit",
+                    "a function returning several values needs a type of the type section without parameters returning the same values",
+                )
+            })?;
+            let limit_exceeded = self.state.global_indexes.limit_exceeded().as_u32();
+
+            state.extend(&[
+                // globals[stack_height_index] += frame_height;
+                Operator::GlobalGet {
+                    global_index: stack_height,
+                },
+                Operator::I32Const {
+                    value: frame_height,
+                },
+                Operator::I32Add,
+                Operator::GlobalSet {
+                    global_index: stack_height,
+                },
+                // if unsigned(globals[stack_height_index]) > unsigned(self.limit) { throw(); }
+                Operator::GlobalGet {
+                    global_index: stack_height,
+                },
+                Operator::I32Const {
+                    value: self.limit as i32,
+                },
+                Operator::I32GtU,
+                Operator::If {
+                    ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+                Operator::I32Const { value: 1 },
+                Operator::GlobalSet {
+                    global_index: limit_exceeded,
+                },
+                Operator::Unreachable,
+                Operator::End,
+                // The block wrapping the body: the branches to the
+                // function label now branch to it.
+                Operator::Block {
+                    ty: block_type.to_wasmparser(),
+                },
+            ]);
+        }
+
+        match operator {
+            Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
+                return Err(MiddlewareError::new(
+                    "StackLimit",
+                    "tail calls are not supported",
+                ));
+            }
+            Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::CatchAll
+            | Operator::Throw { .. }
+            | Operator::Rethrow { .. }
+            | Operator::Unwind
+            | Operator::Delegate { .. } => {
+                return Err(MiddlewareError::new(
+                    "StackLimit",
+                    "exceptions are not supported",
+                ));
+            }
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                self.depth += 1;
+                state.push_operator(operator);
+            }
+            Operator::End if self.depth > 0 => {
+                self.depth -= 1;
+                state.push_operator(operator);
+            }
+            Operator::End => {
+                // The end of the function: close the block wrapping the
+                // body, then run the epilogue.
+                state.extend(&[
+                    Operator::End,
+                    // globals[stack_height_index] -= frame_height;
+                    Operator::GlobalGet {
+                        global_index: stack_height,
+                    },
+                    Operator::I32Const {
+                        value: frame_height,
+                    },
+                    Operator::I32Sub,
+                    Operator::GlobalSet {
+                        global_index: stack_height,
+                    },
+                ]);
+                state.push_operator(operator);
+            }
+            Operator::Return => {
+                // Branch to the block wrapping the body, which is
+                // followed by the epilogue.
+                state.push_operator(Operator::Br {
+                    relative_depth: self.depth,
+                });
+            }
+            operator => state.push_operator(operator),
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents the state of the stack of an
+/// [`Instance`][wasmer::Instance], either `Height` or `Exceeded`.
+///
+/// # Example
+///
+/// See the [`get_stack_height`] function to get an example.
+#[derive(Debug, PartialEq)]
+pub enum StackHeight {
+    /// The stack has the given height, in values. It's 0 when no
+    /// function of the instance is running.
+    Height(u32),
+
+    /// The execution was terminated because a call exceeded the stack
+    /// limit. You can recover from this state with
+    /// [`reset_stack_height`].
+    Exceeded,
+}
+
+/// Get the stack height of an [`Instance`][wasmer::Instance].
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`StackLimit`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::Instance;
+/// use wasmer_middlewares::stack_limit::{get_stack_height, StackHeight};
+///
+/// /// Check whether the instance has been stopped by the stack limit.
+/// fn exceeded_stack_limit(instance: &Instance) -> bool {
+///     get_stack_height(instance) == StackHeight::Exceeded
+/// }
+/// ```
+pub fn get_stack_height(instance: &Instance) -> StackHeight {
+    let exceeded: i32 = instance
+        .exports
+        .get_global("wasmer_stack_limit_exceeded")
+        .expect("Can't get `wasmer_stack_limit_exceeded` from Instance")
+        .get()
+        .try_into()
+        .expect("`wasmer_stack_limit_exceeded` from Instance has wrong type");
+
+    if exceeded > 0 {
+        return StackHeight::Exceeded;
+    }
+
+    let height: i32 = instance
+        .exports
+        .get_global("wasmer_stack_limit_height")
+        .expect("Can't get `wasmer_stack_limit_height` from Instance")
+        .get()
+        .try_into()
+        .expect("`wasmer_stack_limit_height` from Instance has wrong type");
+
+    StackHeight::Height(height as u32)
+}
+
+/// Reset the stack height of an [`Instance`][wasmer::Instance] to 0.
+///
+/// A trap unwinds the stack without running the code removing the
+/// frames from the stack height, so this must be called before using
+/// an instance again after a trap.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`StackLimit`] middleware at compile time, otherwise this
+/// will panic.
+pub fn reset_stack_height(instance: &Instance) {
+    instance
+        .exports
+        .get_global("wasmer_stack_limit_height")
+        .expect("Can't get `wasmer_stack_limit_height` from Instance")
+        .set(0i32.into())
+        .expect("Can't set `wasmer_stack_limit_height` in Instance");
+
+    instance
+        .exports
+        .get_global("wasmer_stack_limit_exceeded")
+        .expect("Can't get `wasmer_stack_limit_exceeded` from Instance")
+        .set(0i32.into())
+        .expect("Can't set `wasmer_stack_limit_exceeded` in Instance");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $count_down (export "count_down") (param $n i32) (result i32)
+                local.get $n
+                i32.eqz
+                if (result i32)
+                    i32.const 0
+                else
+                    local.get $n
+                    i32.const 1
+                    i32.sub
+                    call $count_down
+                    i32.const 1
+                    i32.add
+                end))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn stack_limit_works() {
+        // Each call pushes a frame of height 4: one value for the frame,
+        // one for the `$n` parameter, and two for the operand stack.
+        // `count_down(10)` runs 11 nested calls, which fit in 44.
+        let stack_limit = Arc::new(StackLimit::new(44));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(stack_limit);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let count_down = instance
+            .exports
+            .get_function("count_down")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+
+        assert_eq!(count_down.call(10).unwrap(), 10);
+        assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+
+        assert!(count_down.call(11).is_err());
+        assert_eq!(get_stack_height(&instance), StackHeight::Exceeded);
+
+        reset_stack_height(&instance);
+        assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+        assert_eq!(count_down.call(10).unwrap(), 10);
+    }
+
+    #[test]
+    fn every_return_releases_the_frame() {
+        let stack_limit = Arc::new(StackLimit::new(100));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(stack_limit);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let bytecode = wat2wasm(
+            br#"
+            (module
+            (type (func (result i32 i64)))
+            (func (export "returns") (param $n i32) (result i32 i64)
+                local.get $n
+                i64.const 1
+                local.get $n
+                i32.const 1
+                i32.eq
+                br_if 0
+                drop
+                drop
+                local.get $n
+                i32.const 2
+                i32.eq
+                if
+                    i32.const 2
+                    i64.const 2
+                    return
+                end
+                i32.const 0
+                i64.const 0))
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&store, bytecode).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+        let returns = instance
+            .exports
+            .get_function("returns")
+            .unwrap()
+            .native::<i32, (i32, i64)>()
+            .unwrap();
+
+        assert_eq!(returns.call(1).unwrap(), (1, 1));
+        assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+        assert_eq!(returns.call(2).unwrap(), (2, 2));
+        assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+        assert_eq!(returns.call(3).unwrap(), (0, 0));
+        assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+    }
+}
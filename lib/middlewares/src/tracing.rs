@@ -0,0 +1,696 @@
+//! `tracing` is a middleware for tracing the calls of the functions of
+//! a WebAssembly module. It calls host functions when the traced
+//! functions are entered, with their index and their arguments, and
+//! when they return.
+//!
+//! The host functions are imported by the module from the
+//! [`TRACING_NAMESPACE`] namespace, and are provided by a [`Tracer`],
+//! which records the calls in a call tree with their duration.
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wasmer::wasmparser::Operator;
+use wasmer::{
+    Exports, ExternType, Function, FunctionMiddleware, FunctionType, ImportedFunctions,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Module, ModuleMiddleware,
+    RuntimeError, Type, Val, WasmerEnv,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, SignatureIndex};
+use wasmer_vm::ModuleInfo;
+
+use crate::instrumentation::{BlockType, FunctionExits};
+use crate::profiling::function_name;
+
+/// The namespace of the host functions imported by the modules
+/// processed with the [`Tracing`] middleware.
+pub const TRACING_NAMESPACE: &str = "wasmer_tracing";
+
+/// What the function middleware of a traced function needs to know.
+#[derive(Debug, Clone, Copy, MemoryUsage)]
+struct TracedFunction {
+    /// The index of the imported function called on entry.
+    enter: FunctionIndex,
+
+    num_params: u32,
+
+    /// The type of the block wrapping the function body, so that the
+    /// branches to the function label return through the call to the
+    /// exit function. `None` if the module has no type to wrap a body
+    /// returning multiple values.
+    block_type: Option<BlockType>,
+}
+
+/// What the function middlewares need to know about the module.
+#[derive(Debug, MemoryUsage)]
+struct ModuleState {
+    /// The imported tracing functions.
+    imported: ImportedFunctions,
+
+    /// The index of the imported function called on exit.
+    exit: FunctionIndex,
+
+    /// The traced local functions.
+    traced_functions: HashMap<LocalFunctionIndex, TracedFunction>,
+}
+
+/// The module-level tracing middleware.
+///
+/// The module imports the tracing functions from the
+/// [`TRACING_NAMESPACE`] namespace, which can be provided by a
+/// [`Tracer`]. Importing them shifts the indexes of the functions
+/// defined in the module by the number of tracing functions.
+///
+/// The exit of a function is not traced when it is interrupted by a
+/// trap. It is not traced either when a function returning multiple
+/// values branches to its outermost block, unless the module declares
+/// a type without parameters returning the same values.
+///
+/// # Panic
+///
+/// An instance of `Tracing` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// indexes of the tracing functions. Attempts to use a `Tracing`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::Tracing;
+///
+/// fn create_tracing_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's only trace the `fib` function.
+///     let tracing = Arc::new(Tracing::new().only(&["fib"]));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(tracing);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Tracing {
+    /// The names of the traced functions, or `None` to trace all of them.
+    only: Option<HashSet<String>>,
+
+    state: Mutex<Option<Arc<ModuleState>>>,
+}
+
+/// The function-level tracing middleware.
+pub struct FunctionTracing {
+    state: Arc<ModuleState>,
+
+    /// The index of the function among the local functions, which,
+    /// unlike its function index, isn't shifted by the functions the
+    /// next middlewares import.
+    local_function_index: u32,
+
+    traced: Option<TracedFunction>,
+
+    /// Whether the first operator of the function has been fed.
+    started: bool,
+
+    exits: FunctionExits,
+}
+
+impl Tracing {
+    /// Creates a `Tracing` middleware tracing all the functions defined
+    /// in the module.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only traces the functions with the given names.
+    ///
+    /// Functions are named after the `name` section, or the name they
+    /// are exported with, or else `func{index}`.
+    pub fn only<S: AsRef<str>>(mut self, names: &[S]) -> Self {
+        self.only = Some(names.iter().map(|name| name.as_ref().to_string()).collect());
+        self
+    }
+}
+
+impl fmt::Debug for Tracing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracing")
+            .field("only", &self.only)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Tracing {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let traced = state.traced_functions.get(&local_function_index).copied();
+        Box::new(FunctionTracing {
+            state,
+            local_function_index: local_function_index.as_u32(),
+            traced,
+            started: false,
+            exits: FunctionExits::default(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("Tracing::transform_module_info: Attempting to use a `Tracing` middleware from multiple modules.");
+        }
+
+        let num_imported_functions = module_info.num_imported_functions;
+        let num_local_functions = module_info.functions.len() - num_imported_functions;
+
+        let traced_functions = (0..num_local_functions)
+            .map(LocalFunctionIndex::new)
+            .filter(|local_function_index| match &self.only {
+                Some(names) => names.contains(&function_name(module_info, *local_function_index)),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+
+        // Import an entry function per list of parameters of the traced
+        // functions, and a single exit function.
+        let mut enter_params: Vec<Vec<Type>> = vec![];
+        let mut imports: Vec<(String, SignatureIndex)> = vec![];
+        let mut traced_enter = Vec::with_capacity(traced_functions.len());
+        for local_function_index in &traced_functions {
+            let signature = &module_info.signatures
+                [module_info.functions[module_info.func_index(*local_function_index)]];
+            let params = signature.params().to_vec();
+            let import = match enter_params.iter().position(|other| *other == params) {
+                Some(import) => import,
+                None => {
+                    let mut enter_type = vec![Type::I32];
+                    enter_type.extend_from_slice(&params);
+                    let signature_index = module_info
+                        .signatures
+                        .push(FunctionType::new(enter_type, vec![]));
+                    imports.push((format!("enter_{}", enter_params.len()), signature_index));
+                    enter_params.push(params);
+                    imports.len() - 1
+                }
+            };
+            traced_enter.push(import);
+        }
+        let exit_signature_index = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32], vec![]));
+        imports.push(("exit".to_string(), exit_signature_index));
+
+        let imported = ImportedFunctions::insert(module_info, TRACING_NAMESPACE, &imports);
+
+        let traced_functions = traced_functions
+            .into_iter()
+            .zip(traced_enter)
+            .map(|(local_function_index, import)| {
+                let function_index = module_info.func_index(local_function_index);
+                let signature = &module_info.signatures[module_info.functions[function_index]];
+                let traced = TracedFunction {
+                    enter: imported.index(import),
+                    num_params: signature.params().len() as u32,
+                    block_type: BlockType::of_body(module_info, function_index),
+                };
+                (local_function_index, traced)
+            })
+            .collect();
+
+        *state = Some(Arc::new(ModuleState {
+            imported,
+            exit: imported.index(imports.len() - 1),
+            traced_functions,
+        }));
+    }
+}
+
+impl MemoryUsage for Tracing {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.only.size_of_val(tracker) - mem::size_of_val(&self.only)
+            + self.state.size_of_val(tracker)
+            - mem::size_of_val(&self.state)
+    }
+}
+
+impl fmt::Debug for FunctionTracing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionTracing")
+            .field("local_function_index", &self.local_function_index)
+            .field("traced", &self.traced)
+            .field("exits", &self.exits)
+            .finish()
+    }
+}
+
+impl FunctionTracing {
+    /// Calls the exit function.
+    fn exit<'a>(&self, state: &mut MiddlewareReaderState<'a>) {
+        state.extend(&[
+            Operator::I32Const {
+                value: self.local_function_index as i32,
+            },
+            Operator::Call {
+                function_index: self.state.exit.as_u32(),
+            },
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionTracing {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.started {
+            self.started = true;
+            if let Some(traced) = &self.traced {
+                // Call the entry function with the function index and the
+                // arguments.
+                state.push_operator(Operator::I32Const {
+                    value: self.local_function_index as i32,
+                });
+                state.extend(
+                    (0..traced.num_params).map(|local_index| Operator::LocalGet { local_index }),
+                );
+                state.push_operator(Operator::Call {
+                    function_index: traced.enter.as_u32(),
+                });
+                if let Some(block_type) = traced.block_type {
+                    state.push_operator(Operator::Block {
+                        ty: block_type.to_wasmparser(),
+                    });
+                }
+            }
+        }
+
+        if self.exits.is_exit(&operator) {
+            if let Some(traced) = &self.traced {
+                if matches!(operator, Operator::End) && traced.block_type.is_some() {
+                    state.push_operator(Operator::End);
+                }
+                self.exit(state);
+            }
+        }
+        let operator = self.state.imported.shift_operator(operator);
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// A call of a traced function, as recorded by a [`Tracer`].
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// The index of the called function, including the imported
+    /// tracing functions.
+    pub function_index: u32,
+
+    /// The name of the function, from the `name` section, or the name
+    /// it is exported with, or else `func{index}`.
+    pub name: String,
+
+    /// The arguments of the call.
+    pub arguments: Vec<Val>,
+
+    /// The time spent in the call, including the calls it made.
+    pub duration: Duration,
+
+    /// Whether the call returned. It didn't if it was interrupted by a
+    /// trap, or if its exit was not traced.
+    pub returned: bool,
+
+    /// The calls made by this call.
+    pub children: Vec<Call>,
+}
+
+/// A call which has not returned yet.
+#[derive(Debug)]
+struct Frame {
+    call: Call,
+    start: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CallTree {
+    /// The calls which have not returned yet, innermost last.
+    stack: Vec<Frame>,
+
+    /// The outermost calls which have returned.
+    roots: Vec<Call>,
+}
+
+impl CallTree {
+    fn enter(&mut self, call: Call) {
+        self.stack.push(Frame {
+            call,
+            start: Instant::now(),
+        });
+    }
+
+    /// Closes the innermost call.
+    fn close(&mut self, returned: bool) {
+        let Frame { mut call, start } = self.stack.pop().unwrap();
+        call.duration = start.elapsed();
+        call.returned = returned;
+        match self.stack.last_mut() {
+            Some(parent) => parent.call.children.push(call),
+            None => self.roots.push(call),
+        }
+    }
+
+    fn exit(&mut self, function_index: u32) {
+        // The calls which are still open have been interrupted.
+        while let Some(frame) = self.stack.last() {
+            let returned = frame.call.function_index == function_index;
+            self.close(returned);
+            if returned {
+                break;
+            }
+        }
+    }
+}
+
+/// The environment of the tracing functions.
+#[derive(Clone)]
+struct TracerEnv {
+    call_tree: Arc<Mutex<CallTree>>,
+    /// The function indexes and names of the local functions.
+    functions: Arc<Vec<(u32, String)>>,
+}
+
+impl WasmerEnv for TracerEnv {}
+
+impl TracerEnv {
+    /// Returns the function index and the name of a local function.
+    fn function(&self, local_function_index: u32) -> (u32, String) {
+        self.functions
+            .get(local_function_index as usize)
+            .cloned()
+            .unwrap_or_else(|| (local_function_index, "<unknown>".to_string()))
+    }
+}
+
+/// The host side of the [`Tracing`] middleware: it provides the
+/// tracing functions imported by the modules, and records the traced
+/// calls in a call tree.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{imports, Instance, Module};
+/// use wasmer_middlewares::tracing::{Tracer, TRACING_NAMESPACE};
+///
+/// fn print_calls(module: &Module) -> Result<(), Box<dyn std::error::Error>> {
+///     let tracer = Tracer::new();
+///     let mut import_object = imports! {};
+///     import_object.register(TRACING_NAMESPACE, tracer.imports(module));
+///     let instance = Instance::new(module, &import_object)?;
+///
+///     instance.exports.get_function("main")?.call(&[])?;
+///     for call in tracer.take_calls() {
+///         println!("{} took {:?}", call.name, call.duration);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Tracer {
+    call_tree: Arc<Mutex<CallTree>>,
+}
+
+impl Tracer {
+    /// Creates a new `Tracer`, with an empty call tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the tracing functions imported by the given module,
+    /// which must have been processed with the [`Tracing`] middleware.
+    ///
+    /// The returned exports must be registered in the import object
+    /// with the [`TRACING_NAMESPACE`] namespace.
+    pub fn imports(&self, module: &Module) -> Exports {
+        let module_info = module.info();
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        let functions = (0..num_local_functions)
+            .map(LocalFunctionIndex::new)
+            .map(|local_function_index| {
+                (
+                    module_info.func_index(local_function_index).as_u32(),
+                    function_name(module_info, local_function_index),
+                )
+            })
+            .collect();
+        let env = TracerEnv {
+            call_tree: self.call_tree.clone(),
+            functions: Arc::new(functions),
+        };
+
+        let mut exports = Exports::new();
+        for import in module.imports() {
+            let function_type = match (import.module(), import.ty()) {
+                (TRACING_NAMESPACE, ExternType::Function(function_type)) => function_type,
+                _ => continue,
+            };
+            let function = if import.name() == "exit" {
+                Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &TracerEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        let (function_index, _) = env.function(arguments[0].unwrap_i32() as u32);
+                        env.call_tree.lock().unwrap().exit(function_index);
+                        Ok(vec![])
+                    },
+                )
+            } else {
+                Function::new_with_env(
+                    module.store(),
+                    function_type,
+                    env.clone(),
+                    |env: &TracerEnv, arguments: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                        let (function_index, name) = env.function(arguments[0].unwrap_i32() as u32);
+                        env.call_tree.lock().unwrap().enter(Call {
+                            function_index,
+                            name,
+                            arguments: arguments[1..].to_vec(),
+                            duration: Duration::default(),
+                            returned: false,
+                            children: vec![],
+                        });
+                        Ok(vec![])
+                    },
+                )
+            };
+            exports.insert(import.name(), function);
+        }
+        exports
+    }
+
+    /// Takes the calls recorded so far, outermost first, leaving the
+    /// call tree empty.
+    ///
+    /// The calls which have not returned yet, for instance because they
+    /// have been interrupted by a trap, are closed.
+    pub fn take_calls(&self) -> Vec<Call> {
+        let mut call_tree = self.call_tree.lock().unwrap();
+        while !call_tree.stack.is_empty() {
+            call_tree.close(false);
+        }
+        mem::take(&mut call_tree.roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store, Universal,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $double (export "double") (param i32) (result i32)
+                local.get 0
+                local.get 0
+                i32.add)
+            (func $sum_doubles (export "sum_doubles") (param i32 i32) (result i32)
+                local.get 0
+                call $double
+                local.get 1
+                call $double
+                i32.add
+                ;; Branch to the function label.
+                br 0)
+            (func $fail (export "fail")
+                i32.const 1
+                call $double
+                drop
+                unreachable))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(tracing: Tracing, tracer: &Tracer) -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(tracing));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let mut import_object = imports! {};
+        import_object.register(TRACING_NAMESPACE, tracer.imports(&module));
+        Instance::new(&module, &import_object).unwrap()
+    }
+
+    fn summary(calls: &[Call]) -> Vec<(String, Vec<i32>, bool, usize)> {
+        calls
+            .iter()
+            .map(|call| {
+                (
+                    call.name.clone(),
+                    call.arguments.iter().map(Val::unwrap_i32).collect(),
+                    call.returned,
+                    call.children.len(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trace_calls() {
+        let tracer = Tracer::new();
+        let instance = instantiate(Tracing::new(), &tracer);
+        let sum_doubles = instance
+            .exports
+            .get_function("sum_doubles")
+            .unwrap()
+            .native::<(i32, i32), i32>()
+            .unwrap();
+        assert_eq!(sum_doubles.call(3, 4).unwrap(), 14);
+
+        let calls = tracer.take_calls();
+        assert_eq!(
+            summary(&calls),
+            vec![("sum_doubles".to_string(), vec![3, 4], true, 2)]
+        );
+        assert_eq!(
+            summary(&calls[0].children),
+            vec![
+                ("double".to_string(), vec![3], true, 0),
+                ("double".to_string(), vec![4], true, 0),
+            ]
+        );
+        assert!(calls[0].duration >= calls[0].children[0].duration);
+
+        // A trap interrupts the call.
+        let fail = instance.exports.get_function("fail").unwrap();
+        assert!(fail.call(&[]).is_err());
+        let calls = tracer.take_calls();
+        assert_eq!(
+            summary(&calls),
+            vec![("fail".to_string(), vec![], false, 1)]
+        );
+        assert_eq!(
+            summary(&calls[0].children),
+            vec![("double".to_string(), vec![1], true, 0)]
+        );
+        assert!(tracer.take_calls().is_empty());
+    }
+
+    #[test]
+    fn trace_with_other_middlewares() {
+        // The `Sanitizer` imports its functions after the tracing
+        // functions, which shifts the indexes of the local functions.
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(Tracing::new()));
+        compiler_config.push_middleware(Arc::new(crate::Sanitizer::new()));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let bytecode = wat2wasm(
+            br#"
+            (module
+            (memory 1)
+            (func $load (param i32) (result i32)
+                local.get 0
+                i32.load)
+            (func $sum (export "sum") (param i32 i32) (result i32)
+                local.get 0
+                call $load
+                local.get 1
+                call $load
+                i32.add))
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&store, bytecode).unwrap();
+        let tracer = Tracer::new();
+        let shadow_memory = crate::sanitizer::ShadowMemory::new();
+        let mut import_object = imports! {};
+        import_object.register(TRACING_NAMESPACE, tracer.imports(&module));
+        import_object.register(
+            crate::sanitizer::SANITIZER_NAMESPACE,
+            shadow_memory.imports(&module),
+        );
+        let instance = Instance::new(&module, &import_object).unwrap();
+        let sum = instance
+            .exports
+            .get_function("sum")
+            .unwrap()
+            .native::<(i32, i32), i32>()
+            .unwrap();
+        assert_eq!(sum.call(0, 4).unwrap(), 0);
+
+        let calls = tracer.take_calls();
+        assert_eq!(
+            summary(&calls),
+            vec![("sum".to_string(), vec![0, 4], true, 2)]
+        );
+        assert_eq!(
+            summary(&calls[0].children),
+            vec![
+                ("load".to_string(), vec![0], true, 0),
+                ("load".to_string(), vec![4], true, 0),
+            ]
+        );
+        let module_info = module.info();
+        assert_eq!(
+            calls[0].function_index,
+            module_info.func_index(LocalFunctionIndex::new(1)).as_u32()
+        );
+    }
+
+    #[test]
+    fn trace_selected_functions() {
+        let tracer = Tracer::new();
+        let instance = instantiate(Tracing::new().only(&["double"]), &tracer);
+        let sum_doubles = instance
+            .exports
+            .get_function("sum_doubles")
+            .unwrap()
+            .native::<(i32, i32), i32>()
+            .unwrap();
+        assert_eq!(sum_doubles.call(1, 2).unwrap(), 6);
+
+        assert_eq!(
+            summary(&tracer.take_calls()),
+            vec![
+                ("double".to_string(), vec![1], true, 0),
+                ("double".to_string(), vec![2], true, 0),
+            ]
+        );
+    }
+}
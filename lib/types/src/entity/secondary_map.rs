@@ -11,6 +11,8 @@ use crate::lib::std::marker::PhantomData;
 use crate::lib::std::ops::{Index, IndexMut};
 use crate::lib::std::slice;
 use crate::lib::std::vec::Vec;
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::{MemoryUsage, MemoryUsageTracker};
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -35,6 +37,7 @@ use std::mem;
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct SecondaryMap<K, V>
 where
     K: EntityRef,
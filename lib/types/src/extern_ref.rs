@@ -252,7 +252,6 @@ impl ExternRef {
         }
     }
 
-    #[cfg(feature = "experimental-reference-types-extern-ref")]
     /// Make a new extern reference
     pub fn new<T>(value: T) -> Self
     where
@@ -263,7 +262,6 @@ impl ExternRef {
         }
     }
 
-    #[cfg(feature = "experimental-reference-types-extern-ref")]
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
     where
@@ -1,3 +1,5 @@
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -14,6 +16,7 @@ use serde::{Deserialize, Serialize};
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct Features {
     /// Threads proposal should be enabled
     pub threads: bool,
@@ -35,6 +38,8 @@ pub struct Features {
     pub memory64: bool,
     /// Wasm exceptions proposal should be enabled
     pub exceptions: bool,
+    /// Only deterministic features should be enabled
+    pub deterministic_only: bool,
 }
 
 impl Features {
@@ -55,9 +60,21 @@ impl Features {
             multi_memory: false,
             memory64: false,
             exceptions: false,
+            deterministic_only: false,
         }
     }
 
+    /// Creates the features of the deterministic execution profile.
+    ///
+    /// Those are the default features, restricted to the ones giving
+    /// the same results on every compiler and platform. See
+    /// [`Self::deterministic_only`].
+    pub fn deterministic() -> Self {
+        let mut features = Self::new();
+        features.deterministic_only(true);
+        features
+    }
+
     /// Configures whether the WebAssembly threads proposal will be enabled.
     ///
     /// The [WebAssembly threads proposal][threads] is not currently fully
@@ -231,6 +248,28 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether only deterministic features will be enabled.
+    ///
+    /// Deterministic execution is required when several hosts must
+    /// compute bit-for-bit identical results from the same module, for
+    /// instance for consensus. When enabled:
+    ///
+    /// * Modules using non-deterministic proposals, such as threads
+    ///   (shared memories and atomics), are rejected during validation,
+    ///   whether or not those proposals are enabled.
+    /// * The engines enable NaN canonicalization in the compiler, so
+    ///   floating-point operations produce the same NaN bit patterns on
+    ///   every backend.
+    ///
+    /// This is `false` by default.
+    pub fn deterministic_only(&mut self, enable: bool) -> &mut Self {
+        self.deterministic_only = enable;
+        if enable {
+            self.threads(false);
+        }
+        self
+    }
 }
 
 impl Default for Features {
@@ -258,6 +297,7 @@ mod test_features {
                 multi_memory: false,
                 memory64: false,
                 exceptions: false,
+                deterministic_only: false,
             }
         );
     }
@@ -337,4 +377,13 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn enable_deterministic_only() {
+        let mut features = Features::new();
+        features.threads(true).deterministic_only(true);
+        assert!(features.deterministic_only);
+        assert!(!features.threads);
+        assert_eq!(Features::deterministic(), features);
+    }
 }
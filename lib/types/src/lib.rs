@@ -72,8 +72,8 @@ pub use crate::extern_ref::{ExternRef, VMExternRef};
 pub use crate::features::Features;
 pub use crate::indexes::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, ImportIndex,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    SignatureIndex, TableIndex,
+    InstanceIndex, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryIndex, ModuleIndex, SignatureIndex, TableIndex,
 };
 pub use crate::initializers::{
     DataInitializer, DataInitializerLocation, OwnedDataInitializer, TableInitializer,
@@ -85,8 +85,8 @@ pub use crate::units::{
 };
 pub use crate::values::{Value, WasmValueType};
 pub use types::{
-    ExportType, ExternType, FunctionType, GlobalInit, GlobalType, ImportType, MemoryType,
-    Mutability, TableType, Type, V128,
+    ExportType, ExternType, FunctionType, GlobalInit, GlobalType, ImportType, InstanceType,
+    MemoryType, ModuleType, Mutability, TableType, Type, V128,
 };
 
 #[cfg(feature = "enable-rkyv")]
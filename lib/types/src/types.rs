@@ -8,6 +8,8 @@ use crate::units::Pages;
 use crate::values::{Value, WasmValueType};
 use loupe::{MemoryUsage, MemoryUsageTracker};
 
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
@@ -24,6 +26,7 @@ use serde::{Deserialize, Serialize};
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub enum Type {
     /// Signed 32 bit integer.
     I32,
@@ -69,6 +72,7 @@ impl fmt::Display for Type {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 /// The WebAssembly V128 type
 pub struct V128(pub(crate) [u8; 16]);
 
@@ -132,6 +136,12 @@ pub enum ExternType {
     Table(TableType),
     /// This external type is the type of a WebAssembly memory.
     Memory(MemoryType),
+    /// This external type is the type of a WebAssembly instance, with
+    /// the module linking proposal.
+    Instance(InstanceType),
+    /// This external type is the type of a WebAssembly module, with
+    /// the module linking proposal.
+    Module(ModuleType),
 }
 
 fn is_global_compatible(exported: GlobalType, imported: GlobalType) -> bool {
@@ -222,6 +232,8 @@ impl ExternType {
         (Global(GlobalType) global unwrap_global)
         (Table(TableType) table unwrap_table)
         (Memory(MemoryType) memory unwrap_memory)
+        (Instance(InstanceType) instance unwrap_instance)
+        (Module(ModuleType) module unwrap_module)
     }
     /// Check if two externs are compatible
     pub fn is_compatible_with(&self, other: &Self) -> bool {
@@ -230,6 +242,8 @@ impl ExternType {
             (Self::Global(a), Self::Global(b)) => is_global_compatible(*a, *b),
             (Self::Table(a), Self::Table(b)) => is_table_compatible(a, b),
             (Self::Memory(a), Self::Memory(b)) => is_memory_compatible(a, b),
+            (Self::Instance(a), Self::Instance(b)) => a.is_compatible_with(b),
+            (Self::Module(a), Self::Module(b)) => a.is_compatible_with(b),
             // The rest of possibilities, are not compatible
             _ => false,
         }
@@ -248,6 +262,7 @@ impl ExternType {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct FunctionType {
     /// The parameters of the function
     params: Box<[Type]>,
@@ -337,6 +352,7 @@ impl From<&FunctionType> for FunctionType {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub enum Mutability {
     /// The global is constant and its value does not change
     Const,
@@ -377,6 +393,7 @@ impl From<Mutability> for bool {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct GlobalType {
     /// The type of the value stored in the global.
     pub ty: Type,
@@ -424,6 +441,7 @@ impl fmt::Display for GlobalType {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub enum GlobalInit {
     /// An `i32.const`.
     I32Const(i32),
@@ -484,6 +502,7 @@ impl GlobalInit {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct TableType {
     /// The type of data stored in elements of the table.
     pub ty: Type,
@@ -527,6 +546,7 @@ impl fmt::Display for TableType {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct MemoryType {
     /// The minimum number of pages in the memory.
     pub minimum: Pages,
@@ -645,6 +665,83 @@ impl<T> ExportType<T> {
     }
 }
 
+// Module Linking Types
+
+/// The type of an instance, with the module linking proposal: the
+/// types of its exports.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct InstanceType {
+    exports: Vec<ExportType>,
+}
+
+impl InstanceType {
+    /// Creates a new instance type with the given exports.
+    pub fn new(exports: Vec<ExportType>) -> Self {
+        Self { exports }
+    }
+
+    /// Returns the types of the exports of the instance.
+    pub fn exports(&self) -> &[ExportType] {
+        &self.exports
+    }
+
+    /// Check if an instance of this type can be used where an
+    /// instance of the `imported` type is expected, that is if it
+    /// has a compatible export for each of its exports.
+    pub fn is_compatible_with(&self, imported: &Self) -> bool {
+        are_exports_compatible(&self.exports, &imported.exports)
+    }
+}
+
+/// The type of a module, with the module linking proposal: the types
+/// of its imports and of its exports.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ModuleType {
+    imports: Vec<ImportType>,
+    exports: Vec<ExportType>,
+}
+
+impl ModuleType {
+    /// Creates a new module type with the given imports and exports.
+    pub fn new(imports: Vec<ImportType>, exports: Vec<ExportType>) -> Self {
+        Self { imports, exports }
+    }
+
+    /// Returns the types of the imports of the module.
+    pub fn imports(&self) -> &[ImportType] {
+        &self.imports
+    }
+
+    /// Returns the types of the exports of the module.
+    pub fn exports(&self) -> &[ExportType] {
+        &self.exports
+    }
+
+    /// Check if a module of this type can be used where a module of
+    /// the `imported` type is expected: it must not need more than
+    /// the imports the expected type provides, and it must have a
+    /// compatible export for each of its exports.
+    pub fn is_compatible_with(&self, imported: &Self) -> bool {
+        self.imports.iter().all(|import| {
+            imported.imports.iter().any(|provided| {
+                provided.module() == import.module()
+                    && provided.name() == import.name()
+                    && provided.ty().is_compatible_with(import.ty())
+            })
+        }) && are_exports_compatible(&self.exports, &imported.exports)
+    }
+}
+
+fn are_exports_compatible(exported: &[ExportType], imported: &[ExportType]) -> bool {
+    imported.iter().all(|import| {
+        exported.iter().any(|export| {
+            export.name() == import.name() && export.ty().is_compatible_with(import.ty())
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -677,4 +774,27 @@ mod tests {
         assert_eq!(ty.params().len(), 9);
         assert_eq!(ty.results().len(), 9);
     }
+
+    #[test]
+    fn instance_and_module_types_compatibility() {
+        let func = ExternType::Function(I32_I32_TO_VOID.into());
+        let global = ExternType::Global(GlobalType::new(Type::I32, Mutability::Const));
+        let small = InstanceType::new(vec![ExportType::new("f", func.clone())]);
+        let large = InstanceType::new(vec![
+            ExportType::new("f", func.clone()),
+            ExportType::new("g", global.clone()),
+        ]);
+        assert!(large.is_compatible_with(&small));
+        assert!(!small.is_compatible_with(&large));
+
+        // A module may export more and import less than expected.
+        let expected = ModuleType::new(
+            vec![ImportType::new("env", "g", global)],
+            vec![ExportType::new("f", func.clone())],
+        );
+        let provided = ModuleType::new(vec![], large.exports().to_vec());
+        assert!(provided.is_compatible_with(&expected));
+        assert!(!expected
+            .is_compatible_with(&ModuleType::new(vec![], vec![ExportType::new("f", func)])));
+    }
 }
@@ -1,19 +1,33 @@
-//! Runtime build script compiles C code using setjmp for trap handling.
+//! Runtime build script compiles C code using setjmp for trap handling,
+//! and ucontext for the stack switching of the coroutines.
 
 use std::env;
 
 fn main() {
     println!("cargo:rerun-if-changed=src/trap/handlers.c");
+    println!("cargo:rerun-if-changed=src/coroutine.c");
 
-    cc::Build::new()
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+
+    let mut build = cc::Build::new();
+    build
         .warnings(true)
-        .define(
-            &format!(
-                "CFG_TARGET_OS_{}",
-                env::var("CARGO_CFG_TARGET_OS").unwrap().to_uppercase()
-            ),
-            None,
-        )
+        .define(&format!("CFG_TARGET_OS_{}", target_os.to_uppercase()), None)
         .file("src/trap/handlers.c")
-        .compile("handlers");
+        .file("src/coroutine.c");
+
+    // Only glibc, and not musl, implements the `ucontext` functions
+    // on Linux.
+    let has_ucontext = match target_os.as_str() {
+        "linux" => target_env == "gnu",
+        "macos" | "freebsd" => true,
+        _ => false,
+    };
+    if has_ucontext {
+        println!("cargo:rustc-cfg=coroutines");
+        build.define("CFG_COROUTINES", None);
+    }
+
+    build.compile("handlers");
 }
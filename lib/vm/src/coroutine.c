@@ -0,0 +1,72 @@
+// Stack switching for the coroutines, built on the `ucontext`
+// functions. The build script only defines `CFG_COROUTINES` on the
+// platforms whose libc provides them.
+
+#ifdef CFG_COROUTINES
+
+// macOS only declares the `ucontext` functions for XSI programs.
+#ifdef CFG_TARGET_OS_MACOS
+#define _XOPEN_SOURCE 600
+#pragma clang diagnostic ignored "-Wdeprecated-declarations"
+#endif
+
+#include <stdlib.h>
+#include <ucontext.h>
+
+struct wasmer_coroutine {
+  // The context of the last `wasmer_coroutine_switch_in` caller.
+  ucontext_t caller;
+  // The context of the coroutine, on its own stack.
+  ucontext_t coroutine;
+  void (*body)(void*);
+  void *payload;
+};
+
+// `makecontext` can only pass `int` arguments to the entry point, so the
+// coroutine being started is passed through this variable instead.
+static __thread struct wasmer_coroutine *starting;
+
+static void wasmer_coroutine_start(void) {
+  struct wasmer_coroutine *coroutine = starting;
+  coroutine->body(coroutine->payload);
+  // Returning switches back to the caller, through `uc_link`.
+}
+
+void *wasmer_coroutine_new(
+    void *stack,
+    size_t stack_size,
+    void (*body)(void*),
+    void *payload) {
+  struct wasmer_coroutine *coroutine = calloc(1, sizeof(struct wasmer_coroutine));
+  if (coroutine == NULL) {
+    return NULL;
+  }
+  if (getcontext(&coroutine->coroutine) != 0) {
+    free(coroutine);
+    return NULL;
+  }
+  coroutine->coroutine.uc_stack.ss_sp = stack;
+  coroutine->coroutine.uc_stack.ss_size = stack_size;
+  coroutine->coroutine.uc_link = &coroutine->caller;
+  coroutine->body = body;
+  coroutine->payload = payload;
+  makecontext(&coroutine->coroutine, wasmer_coroutine_start, 0);
+  return coroutine;
+}
+
+int wasmer_coroutine_switch_in(void *coroutine) {
+  struct wasmer_coroutine *c = (struct wasmer_coroutine*) coroutine;
+  starting = c;
+  return swapcontext(&c->caller, &c->coroutine);
+}
+
+int wasmer_coroutine_switch_out(void *coroutine) {
+  struct wasmer_coroutine *c = (struct wasmer_coroutine*) coroutine;
+  return swapcontext(&c->coroutine, &c->caller);
+}
+
+void wasmer_coroutine_free(void *coroutine) {
+  free(coroutine);
+}
+
+#endif
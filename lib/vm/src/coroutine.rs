@@ -0,0 +1,352 @@
+//! Coroutines: calls running on their own native stack, which can
+//! suspend themselves and be resumed later.
+//!
+//! A [`Coroutine`] runs its body, typically a call into Wasm, on a
+//! separately allocated stack with a guard page. A host function
+//! called by that Wasm code can [`suspend`] the coroutine, returning
+//! to the caller of [`Coroutine::resume`]; the next call to
+//! `resume` continues the host function where it left off. This
+//! makes generators and green threads possible in the guests, and
+//! bounds the stack of deeply recursive guests without growing the
+//! stack of the host thread.
+
+use crate::mmap::Mmap;
+use crate::trap::{TlsBase, TlsSegment};
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use thiserror::Error;
+
+extern "C" {
+    fn wasmer_coroutine_new(
+        stack: *mut u8,
+        stack_size: usize,
+        body: extern "C" fn(*mut u8),
+        payload: *mut u8,
+    ) -> *mut u8;
+    fn wasmer_coroutine_switch_in(coroutine: *mut u8) -> i32;
+    fn wasmer_coroutine_switch_out(coroutine: *mut u8) -> i32;
+    fn wasmer_coroutine_free(coroutine: *mut u8);
+}
+
+thread_local! {
+    /// The coroutine running on this thread, if any.
+    static RUNNING: Cell<*const Inner<'static>> = Cell::new(ptr::null());
+}
+
+/// An error from a coroutine.
+#[derive(Error, Debug)]
+pub enum CoroutineError {
+    /// The stack of the coroutine could not be allocated.
+    #[error("failed to allocate the stack of the coroutine: {0}")]
+    Stack(String),
+
+    /// No coroutine runs on this thread, so there is nothing to
+    /// suspend.
+    #[error("no coroutine is running on this thread")]
+    NotRunning,
+}
+
+/// The state of a coroutine, after it has been resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoroutineState {
+    /// The coroutine has suspended itself, and can be resumed again.
+    Suspended,
+    /// The body of the coroutine has returned.
+    Finished,
+}
+
+enum State {
+    Running,
+    Suspended,
+    Finished,
+    Panicked(Box<dyn Any + Send>),
+}
+
+struct Inner<'a> {
+    /// The context of the coroutine, allocated by `wasmer_coroutine_new`.
+    context: *mut u8,
+
+    /// The lowest address and the size of the stack, without its guard
+    /// page.
+    stack_bounds: (usize, usize),
+
+    body: Cell<Option<Box<dyn FnOnce() + 'a>>>,
+    state: Cell<State>,
+
+    /// The TLS state when the coroutine was last resumed.
+    tls_base: Cell<Option<TlsBase>>,
+
+    /// The TLS states of the calls into Wasm suspended with the
+    /// coroutine.
+    tls_segment: Cell<Option<TlsSegment>>,
+}
+
+/// A call running on its own native stack, which can suspend itself
+/// with [`suspend`] and be resumed with [`Coroutine::resume`].
+///
+/// The coroutine must be resumed on the thread it was created on,
+/// which is why it is neither `Send` nor `Sync`.
+pub struct Coroutine<'a> {
+    inner: Box<Inner<'a>>,
+    /// Keeps the stack mapped for as long as the coroutine lives.
+    _stack: Mmap,
+}
+
+impl<'a> Coroutine<'a> {
+    /// Creates a coroutine which will run `body` on a new stack of
+    /// `stack_size` bytes, rounded up to the page size, when it is
+    /// first resumed.
+    ///
+    /// The stack is followed by an inaccessible guard page: overflowing
+    /// it in Wasm code traps with `TrapCode::StackOverflow`.
+    pub fn new(stack_size: usize, body: impl FnOnce() + 'a) -> Result<Self, CoroutineError> {
+        let page_size = region::page::size();
+        let stack_size = (stack_size.max(1) + page_size - 1) & !(page_size - 1);
+        let stack =
+            Mmap::accessible_reserved(0, page_size + stack_size).map_err(CoroutineError::Stack)?;
+        stack
+            .make_accessible(page_size, stack_size)
+            .map_err(CoroutineError::Stack)?;
+        let stack_start = stack.as_ptr() as usize + page_size;
+
+        let mut inner = Box::new(Inner {
+            context: ptr::null_mut(),
+            stack_bounds: (stack_start, stack_size),
+            body: Cell::new(Some(Box::new(body))),
+            state: Cell::new(State::Suspended),
+            tls_base: Cell::new(None),
+            tls_segment: Cell::new(None),
+        });
+        inner.context = unsafe {
+            wasmer_coroutine_new(
+                stack_start as *mut u8,
+                stack_size,
+                run_body,
+                &*inner as *const Inner as *mut u8,
+            )
+        };
+        if inner.context.is_null() {
+            return Err(CoroutineError::Stack(
+                "failed to create the context of the coroutine".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            inner,
+            _stack: stack,
+        })
+    }
+
+    /// Runs the coroutine until it suspends itself or its body
+    /// returns.
+    ///
+    /// If the body panics, the panic is resumed here.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coroutine is not suspended: it has finished, or it
+    /// is running.
+    pub fn resume(&mut self) -> CoroutineState {
+        let inner = &*self.inner;
+        match inner.state.replace(State::Running) {
+            State::Suspended => {}
+            state => {
+                inner.state.set(state);
+                panic!("the coroutine is not suspended");
+            }
+        }
+
+        // The calls into Wasm made by the coroutine are stacked on the
+        // TLS state of the caller.
+        inner.tls_base.set(Some(TlsBase::current()));
+        if let Some(segment) = inner.tls_segment.take() {
+            unsafe { segment.attach() }.expect("tls should be previously initialized");
+        }
+
+        let running = inner as *const Inner as *const Inner<'static>;
+        let previous = RUNNING.with(|current| current.replace(running));
+        unsafe {
+            wasmer_coroutine_switch_in(inner.context);
+        }
+        RUNNING.with(|current| current.set(previous));
+
+        match inner.state.replace(State::Finished) {
+            State::Suspended => {
+                inner.state.set(State::Suspended);
+                CoroutineState::Suspended
+            }
+            State::Finished => CoroutineState::Finished,
+            State::Panicked(payload) => panic::resume_unwind(payload),
+            State::Running => unreachable!("the coroutine switched out while running"),
+        }
+    }
+
+    /// Returns whether the body of the coroutine has returned.
+    pub fn is_finished(&self) -> bool {
+        let state = self.inner.state.replace(State::Finished);
+        let is_finished = matches!(state, State::Finished);
+        self.inner.state.set(state);
+        is_finished
+    }
+}
+
+/// Suspends the coroutine running on this thread, returning to the
+/// [`Coroutine::resume`] call which last resumed it. Returns when the
+/// coroutine is resumed again.
+///
+/// It is typically called from a host function, called by Wasm code
+/// running in the coroutine: the Wasm frames are suspended with it.
+///
+/// # Errors
+///
+/// Returns an error if no coroutine is running on this thread.
+pub fn suspend() -> Result<(), CoroutineError> {
+    let running = RUNNING.with(|current| current.get());
+    if running.is_null() {
+        return Err(CoroutineError::NotRunning);
+    }
+    let inner = unsafe { &*running };
+
+    // The calls into Wasm made by the coroutine must not be seen by the
+    // traps of the caller while the coroutine is suspended.
+    let tls_base = inner
+        .tls_base
+        .get()
+        .expect("the coroutine has been resumed");
+    inner
+        .tls_segment
+        .set(unsafe { TlsSegment::detach(tls_base) });
+
+    inner.state.set(State::Suspended);
+    unsafe {
+        wasmer_coroutine_switch_out(inner.context);
+    }
+    Ok(())
+}
+
+/// Returns the lowest address and the size of the stack of the
+/// coroutine running on this thread, if any.
+///
+/// It is called when entering Wasm, so that the signal handlers can
+/// tell stack overflows from other faults without touching `RUNNING`.
+pub(crate) fn running_stack() -> Option<(usize, usize)> {
+    let running = RUNNING.with(|current| current.get());
+    if running.is_null() {
+        return None;
+    }
+    Some(unsafe { (*running).stack_bounds })
+}
+
+/// The entry point of the coroutines, on their own stack.
+extern "C" fn run_body(payload: *mut u8) {
+    let inner = unsafe { &*(payload as *const Inner) };
+    let body = inner.body.take().expect("the coroutine is started once");
+    // The panics can't unwind through the C frames at the bottom of the
+    // stack, so they are resumed by `Coroutine::resume` instead.
+    let state = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(()) => State::Finished,
+        Err(payload) => State::Panicked(payload),
+    };
+    inner.state.set(state);
+    // Returning switches back to the caller of `Coroutine::resume`.
+}
+
+impl Drop for Coroutine<'_> {
+    /// Frees the coroutine and its stack.
+    ///
+    /// The destructors of the frames of a suspended coroutine are not
+    /// run: what they own is leaked.
+    fn drop(&mut self) {
+        unsafe { wasmer_coroutine_free(self.inner.context) }
+    }
+}
+
+impl fmt::Debug for Coroutine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Coroutine")
+            .field("stack_bounds", &self.inner.stack_bounds)
+            .field("is_finished", &self.is_finished())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn suspend_and_resume() {
+        let log = Rc::new(Cell::new(0));
+        let body_log = log.clone();
+        let mut coroutine = Coroutine::new(64 * 1024, move || {
+            body_log.set(1);
+            suspend().unwrap();
+            body_log.set(2);
+            suspend().unwrap();
+            body_log.set(3);
+        })
+        .unwrap();
+
+        assert_eq!(log.get(), 0);
+        assert_eq!(coroutine.resume(), CoroutineState::Suspended);
+        assert_eq!(log.get(), 1);
+        assert_eq!(coroutine.resume(), CoroutineState::Suspended);
+        assert_eq!(log.get(), 2);
+        assert!(!coroutine.is_finished());
+        assert_eq!(coroutine.resume(), CoroutineState::Finished);
+        assert_eq!(log.get(), 3);
+        assert!(coroutine.is_finished());
+    }
+
+    #[test]
+    fn suspend_outside_of_a_coroutine() {
+        assert!(matches!(suspend(), Err(CoroutineError::NotRunning)));
+    }
+
+    #[test]
+    fn nested_coroutines() {
+        let log = Rc::new(Cell::new(Vec::new()));
+        let push = |log: &Rc<Cell<Vec<&'static str>>>, entry| {
+            let mut entries = log.take();
+            entries.push(entry);
+            log.set(entries);
+        };
+
+        let outer_log = log.clone();
+        let mut outer = Coroutine::new(64 * 1024, move || {
+            let inner_log = outer_log.clone();
+            let mut inner = Coroutine::new(64 * 1024, move || {
+                push(&inner_log, "inner");
+                suspend().unwrap();
+                push(&inner_log, "inner resumed");
+            })
+            .unwrap();
+            assert_eq!(inner.resume(), CoroutineState::Suspended);
+            push(&outer_log, "outer");
+            // Suspends the outer coroutine, not the inner one.
+            suspend().unwrap();
+            assert_eq!(inner.resume(), CoroutineState::Finished);
+            push(&outer_log, "outer resumed");
+        })
+        .unwrap();
+
+        assert_eq!(outer.resume(), CoroutineState::Suspended);
+        assert_eq!(outer.resume(), CoroutineState::Finished);
+        assert_eq!(
+            log.take(),
+            vec!["inner", "outer", "inner resumed", "outer resumed"]
+        );
+    }
+
+    #[test]
+    fn panics_are_resumed() {
+        let mut coroutine = Coroutine::new(64 * 1024, || panic!("in the coroutine")).unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| coroutine.resume()));
+        assert!(result.is_err());
+        assert!(coroutine.is_finished());
+    }
+}
@@ -1,10 +1,12 @@
 use super::{Instance, InstanceRef};
+use crate::pool::{PoolSlot, PoolingAllocator};
 use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
 use crate::{ModuleInfo, VMOffsets};
 use std::alloc::{self, Layout};
 use std::convert::TryFrom;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::Arc;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
 
@@ -25,7 +27,9 @@ use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
 /// The [`InstanceAllocator::instance_layout`] computes the correct
 /// layout to represent the wanted [`Instance`].
 ///
-/// Then we use this layout to allocate an empty `Instance` properly.
+/// Then we use this layout to allocate an empty `Instance` properly,
+/// either with the global allocator, or in a slot of a
+/// [`PoolingAllocator`].
 pub struct InstanceAllocator {
     /// The buffer that will contain the [`Instance`] and dynamic fields.
     instance_ptr: NonNull<Instance>,
@@ -41,11 +45,16 @@ pub struct InstanceAllocator {
     /// `instance_ptr` buffer. If it has not when being dropped,
     /// the buffer should be freed.
     consumed: bool,
+
+    /// The slot of the `instance_ptr` buffer, if it has been allocated
+    /// in a pool rather than with the global allocator. The slot is
+    /// given back to the pool when dropped.
+    slot: Option<PoolSlot>,
 }
 
 impl Drop for InstanceAllocator {
     fn drop(&mut self) {
-        if !self.consumed {
+        if !self.consumed && self.slot.is_none() {
             // If `consumed` has not been set, then we still have ownership
             // over the buffer and must free it.
             let instance_ptr = self.instance_ptr.as_ptr();
@@ -91,16 +100,64 @@ impl InstanceAllocator {
             instance_layout,
             offsets,
             consumed: false,
+            slot: None,
+        };
+
+        allocator.with_definition_locations()
+    }
+
+    /// Allocates instance data in a slot of `pool`, like
+    /// [`InstanceAllocator::new`] does with the global allocator.
+    ///
+    /// Returns an error if all the instance slots of the pool are in
+    /// use, or if the instance doesn't fit in a slot.
+    pub fn new_pooled(
+        module: &ModuleInfo,
+        pool: &Arc<PoolingAllocator>,
+    ) -> Result<
+        (
+            Self,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let instance_layout = Self::instance_layout(&offsets);
+        let slot = pool.allocate_instance(instance_layout)?;
+
+        // The slot is page-aligned, so it is aligned for `Instance`.
+        #[allow(clippy::cast_ptr_alignment)]
+        let instance_ptr = NonNull::new(slot.ptr() as *mut Instance).unwrap();
+
+        let allocator = Self {
+            instance_ptr,
+            instance_layout,
+            offsets,
+            consumed: false,
+            slot: Some(slot),
         };
 
+        Ok(allocator.with_definition_locations())
+    }
+
+    /// Returns this allocator with the locations of the memory and
+    /// table definitions in its buffer.
+    fn with_definition_locations(
+        self,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         // # Safety
-        // Both of these calls are safe because we allocate the pointer
-        // above with the same `offsets` that these functions use.
+        // Both of these calls are safe because the pointer has been
+        // allocated with the same `offsets` that these functions use.
         // Thus there will be enough valid memory for both of them.
-        let memories = unsafe { allocator.memory_definition_locations() };
-        let tables = unsafe { allocator.table_definition_locations() };
+        let memories = unsafe { self.memory_definition_locations() };
+        let tables = unsafe { self.table_definition_locations() };
 
-        (allocator, memories, tables)
+        (self, memories, tables)
     }
 
     /// Calculate the appropriate layout for the [`Instance`].
@@ -201,10 +258,11 @@ impl InstanceAllocator {
         }
         let instance = self.instance_ptr;
         let instance_layout = self.instance_layout;
+        let slot = self.slot.take();
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        unsafe { InstanceRef::new(instance, instance_layout) }
+        unsafe { InstanceRef::new(instance, instance_layout, slot) }
     }
 
     /// Get the [`VMOffsets`] for the allocated buffer.
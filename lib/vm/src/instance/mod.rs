@@ -9,15 +9,19 @@
 
 mod allocator;
 mod r#ref;
+mod snapshot;
 
 pub use allocator::InstanceAllocator;
 pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
+pub use snapshot::InstanceSnapshot;
 
 use crate::export::VMExtern;
 use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
+use crate::resource_limiter::InstanceId;
+use crate::stack_limit::VMStackLimit;
 use crate::table::{Table, TableElement};
 use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
@@ -65,6 +69,10 @@ pub(crate) struct Instance {
     /// The `ModuleInfo` this `Instance` was instantiated from.
     module: Arc<ModuleInfo>,
 
+    /// The identifier of this `Instance`.
+    #[loupe(skip)]
+    id: InstanceId,
+
     /// Offsets in the `vmctx` region.
     offsets: VMOffsets,
 
@@ -376,6 +384,11 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
     }
 
+    /// Return a pointer to the pointer to the stack limit.
+    fn stack_limit_ptr(&self) -> *mut *const usize {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_stack_limit()) }
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     fn vmctx(&self) -> &VMContext {
         &self.vmctx
@@ -689,25 +702,8 @@ impl Instance {
         item: TableElement,
         len: u32,
     ) -> Result<(), Trap> {
-        // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-init
-
         let table = self.get_table(table_index);
-        let table_size = table.size() as usize;
-
-        if start_index
-            .checked_add(len)
-            .map_or(true, |n| n as usize > table_size)
-        {
-            return Err(Trap::lib(TrapCode::TableAccessOutOfBounds));
-        }
-
-        for i in start_index..(start_index + len) {
-            table
-                .set(i, item.clone())
-                .expect("should never panic because we already did the bounds check above");
-        }
-
-        Ok(())
+        table.fill(start_index, item, len)
     }
 
     /// Drop an element.
@@ -914,6 +910,14 @@ impl InstanceHandle {
             .into_boxed_slice();
         let passive_data = RefCell::new(module.passive_data.clone());
 
+        let id = InstanceId::new();
+        for memory in finished_memories.values() {
+            memory.set_instance(id);
+        }
+        for table in finished_tables.values() {
+            table.set_instance(id);
+        }
+
         let handle = {
             let offsets = allocator.offsets().clone();
             // use dummy value to create an instance so we can get the vmctx pointer
@@ -921,6 +925,7 @@ impl InstanceHandle {
             // Create the `Instance`. The unique, the One.
             let instance = Instance {
                 module,
+                id,
                 offsets,
                 memories: finished_memories,
                 tables: finished_tables,
@@ -994,6 +999,7 @@ impl InstanceHandle {
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        ptr::write(instance.stack_limit_ptr(), VMStackLimit::none_ptr());
 
         // Perform infallible initialization in this constructor, while fallible
         // initialization is deferred to the `initialize` method.
@@ -1008,6 +1014,18 @@ impl InstanceHandle {
         &self.instance
     }
 
+    /// Checks the stack used by the functions of this instance against
+    /// `stack_limit`, instead of letting them use the whole stack.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation, and
+    /// `stack_limit` must outlive the instance.
+    pub unsafe fn set_stack_limit(&self, stack_limit: &VMStackLimit) {
+        let instance = self.instance().as_ref();
+        ptr::write(instance.stack_limit_ptr(), stack_limit.limit_ptr());
+    }
+
     /// Finishes the instantiation process started by `Instance::new`.
     ///
     /// # Safety
@@ -1030,6 +1048,27 @@ impl InstanceHandle {
         Ok(())
     }
 
+    /// Takes a snapshot of the mutable state of this instance, from
+    /// which new instances of the same module can be made.
+    ///
+    /// The instance must not be running while the snapshot is taken.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, String> {
+        InstanceSnapshot::new(self.instance().as_ref())
+    }
+
+    /// Finishes the instantiation process started by `Instance::new`
+    /// by restoring a snapshot of another instance of the same module,
+    /// instead of applying the initializers and invoking the start
+    /// function.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation, with the
+    /// memories made from the images of the snapshot.
+    pub unsafe fn restore_snapshot(&self, snapshot: &InstanceSnapshot) -> Result<(), String> {
+        snapshot.restore(self.instance().as_ref())
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     pub fn vmctx(&self) -> &VMContext {
         self.instance().as_ref().vmctx()
@@ -1052,25 +1091,38 @@ impl InstanceHandle {
         self.instance().as_ref().module()
     }
 
+    /// Return the identifier of the instance, passed to the
+    /// [`ResourceLimiter`](crate::ResourceLimiter) of its memories
+    /// and tables.
+    pub fn id(&self) -> InstanceId {
+        self.instance().as_ref().id
+    }
+
     /// Return a reference to a module.
     pub fn module_ref(&self) -> &ModuleInfo {
         self.instance().as_ref().module_ref()
     }
 
     /// Lookup an export with the given name.
+    ///
+    /// The instances and modules exported with the module linking
+    /// proposal are not part of the instance, and are not found.
     pub fn lookup(&self, field: &str) -> Option<VMExtern> {
         let export = self.module_ref().exports.get(field)?;
 
-        Some(self.lookup_by_declaration(&export))
+        self.lookup_by_declaration(&export)
     }
 
     /// Lookup an export with the given export declaration.
+    ///
+    /// Returns `None` for the instances and modules exported with the
+    /// module linking proposal, which are not part of the instance.
     // TODO: maybe EngineExport
-    pub fn lookup_by_declaration(&self, export: &ExportIndex) -> VMExtern {
+    pub fn lookup_by_declaration(&self, export: &ExportIndex) -> Option<VMExtern> {
         let instance = self.instance().clone();
         let instance_ref = instance.as_ref();
 
-        match export {
+        Some(match export {
             ExportIndex::Function(index) => {
                 let sig_index = &instance_ref.module.functions[*index];
                 let (address, vmctx, _function_ptr) =
@@ -1145,7 +1197,8 @@ impl InstanceHandle {
                 }
                 .into()
             }
-        }
+            ExportIndex::Instance(_) | ExportIndex::Module(_) => return None,
+        })
     }
 
     /// Return an iterator over the exports of this instance.
@@ -1,4 +1,5 @@
 use super::Instance;
+use crate::pool::PoolSlot;
 use loupe::{MemoryUsage, MemoryUsageTracker};
 use std::alloc::Layout;
 use std::convert::TryFrom;
@@ -21,6 +22,11 @@ struct InstanceInner {
     /// The layout of `Instance` (which can vary).
     instance_layout: Layout,
 
+    /// The slot of `Instance`, if it has been allocated in a pool
+    /// rather than with `alloc`. The slot is given back to the pool
+    /// when dropped, after `Instance`.
+    slot: Option<PoolSlot>,
+
     /// The `Instance` itself. It must be the last field of
     /// `InstanceRef` since `Instance` is dyamically-sized.
     ///
@@ -45,7 +51,9 @@ impl InstanceInner {
         let instance_ptr = self.instance.as_ptr();
 
         ptr::drop_in_place(instance_ptr);
-        std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        if self.slot.is_none() {
+            std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        }
     }
 
     /// Get a reference to the `Instance`.
@@ -123,10 +131,16 @@ impl InstanceRef {
     /// `instance` must a non-null, non-dangling, properly aligned,
     /// and correctly initialized pointer to `Instance`. See
     /// [`InstanceAllocator`] for an example of how to correctly use
-    /// this API.
-    pub(super) unsafe fn new(instance: NonNull<Instance>, instance_layout: Layout) -> Self {
+    /// this API. `slot` must be the slot of `instance`, if it has
+    /// been allocated in a pool.
+    pub(super) unsafe fn new(
+        instance: NonNull<Instance>,
+        instance_layout: Layout,
+        slot: Option<PoolSlot>,
+    ) -> Self {
         Self(Arc::new(InstanceInner {
             instance_layout,
+            slot,
             instance,
         }))
     }
@@ -0,0 +1,236 @@
+//! Snapshots of the mutable state of instances, from which new
+//! instances can be made without running the initializers of their
+//! module again.
+
+use super::Instance;
+use crate::func_data_registry::VMFuncRef;
+use crate::memory_image::{MemoryImage, MemoryImages};
+use crate::module::ModuleInfo;
+use crate::table::TableElement;
+use std::collections::HashMap;
+use std::slice;
+use std::sync::Arc;
+use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::{
+    DataIndex, ElemIndex, ExternRef, FunctionIndex, LocalGlobalIndex, LocalMemoryIndex,
+    LocalTableIndex, Mutability, Type,
+};
+
+/// A value of a global or an element of a table in a snapshot.
+#[derive(Debug, Clone)]
+enum SnapshotValue {
+    /// The bits of a number or of a vector.
+    Bits(u128),
+    /// A function of the instance, by index, or null.
+    FuncRef(Option<FunctionIndex>),
+    /// A reference to host data, shared by the instances made from
+    /// the snapshot.
+    ExternRef(ExternRef),
+}
+
+impl SnapshotValue {
+    /// Returns the function reference of this value in `instance`.
+    fn funcref(index: Option<FunctionIndex>, instance: &Instance) -> VMFuncRef {
+        index.map_or_else(VMFuncRef::null, |index| instance.funcrefs[index])
+    }
+
+    /// Returns this value as an element of a table of `instance`.
+    fn to_table_element(&self, instance: &Instance) -> TableElement {
+        match self {
+            Self::Bits(_) => unreachable!("the tables only hold references"),
+            Self::FuncRef(index) => TableElement::FuncRef(Self::funcref(*index, instance)),
+            Self::ExternRef(extern_ref) => TableElement::ExternRef(extern_ref.clone()),
+        }
+    }
+}
+
+/// A snapshot of the mutable state of an instance: the contents of
+/// its local memories, the values of its mutable local globals, the
+/// elements of its local tables and the passive segments it has not
+/// dropped.
+///
+/// The instances made from a snapshot start with this state, instead
+/// of running the initializers and the start function of their
+/// module. The contents of the memories are kept in
+/// [`MemoryImages`], mapped copy-on-write into the memories of these
+/// instances when possible.
+///
+/// The state of the imported memories, tables and globals is not part
+/// of the snapshot: it belongs to the instance, or the host, exporting
+/// them.
+#[derive(Debug)]
+pub struct InstanceSnapshot {
+    /// The module of the instance.
+    module: Arc<ModuleInfo>,
+
+    /// The contents of the local memories.
+    memory_images: MemoryImages,
+
+    /// The values of the mutable local globals.
+    globals: Vec<(LocalGlobalIndex, SnapshotValue)>,
+
+    /// The elements of the local tables.
+    tables: BoxedSlice<LocalTableIndex, Box<[SnapshotValue]>>,
+
+    /// The passive elements not dropped yet.
+    passive_elements: Vec<ElemIndex>,
+
+    /// The passive data segments not dropped yet.
+    passive_data: Vec<DataIndex>,
+}
+
+/// # Safety
+/// The extern refs held by the snapshot are atomically reference
+/// counted, and the data they point to is `Send + Sync`.
+unsafe impl Send for InstanceSnapshot {}
+/// # Safety
+/// The snapshot is never mutated after it is taken.
+unsafe impl Sync for InstanceSnapshot {}
+
+impl InstanceSnapshot {
+    /// Takes a snapshot of `instance`.
+    ///
+    /// Fails if a table or a global of the instance holds a function
+    /// of another instance.
+    pub(super) fn new(instance: &Instance) -> Result<Self, String> {
+        // The function references are kept by index, to be resolved
+        // in the instances made from the snapshot.
+        let function_indices = instance
+            .funcrefs
+            .iter()
+            .map(|(index, funcref)| (*funcref, index))
+            .collect::<HashMap<VMFuncRef, FunctionIndex>>();
+        let function_index = |funcref: VMFuncRef| {
+            if funcref.is_null() {
+                return Ok(None);
+            }
+            match function_indices.get(&funcref) {
+                Some(index) => Ok(Some(*index)),
+                None => {
+                    Err("a function of another instance can't be part of a snapshot".to_string())
+                }
+            }
+        };
+
+        let memory_images = instance
+            .memories
+            .values()
+            .map(|memory| {
+                let definition = unsafe { memory.vmmemory().as_ref() };
+                let length = definition.current_length as usize;
+                let segments = if length == 0 {
+                    vec![]
+                } else {
+                    vec![(0, unsafe { slice::from_raw_parts(definition.base, length) })]
+                };
+                MemoryImage::new(length, segments).map(Some)
+            })
+            .collect::<Result<PrimaryMap<LocalMemoryIndex, _>, _>>()?;
+
+        let mut globals = Vec::new();
+        for (index, global) in instance.globals.iter() {
+            if global.ty().mutability != Mutability::Var {
+                continue;
+            }
+            let definition = unsafe { global.vmglobal().as_ref() };
+            let value = match global.ty().ty {
+                Type::FuncRef => SnapshotValue::FuncRef(function_index(definition.to_funcref())?),
+                Type::ExternRef => {
+                    SnapshotValue::ExternRef(definition.to_externref().ref_clone().into())
+                }
+                _ => SnapshotValue::Bits(definition.to_u128()),
+            };
+            globals.push((index, value));
+        }
+
+        let tables = instance
+            .tables
+            .values()
+            .map(|table| {
+                (0..table.size())
+                    .map(|index| match table.get(index) {
+                        Some(TableElement::FuncRef(funcref)) => {
+                            Ok(SnapshotValue::FuncRef(function_index(funcref)?))
+                        }
+                        Some(TableElement::ExternRef(extern_ref)) => {
+                            Ok(SnapshotValue::ExternRef(extern_ref))
+                        }
+                        None => unreachable!("the index is within the size of the table"),
+                    })
+                    .collect::<Result<Box<[_]>, String>>()
+            })
+            .collect::<Result<PrimaryMap<LocalTableIndex, _>, _>>()?;
+
+        Ok(Self {
+            module: instance.module.clone(),
+            memory_images: MemoryImages::from_images(memory_images),
+            globals,
+            tables: tables.into_boxed_slice(),
+            passive_elements: instance.passive_elements.borrow().keys().copied().collect(),
+            passive_data: instance.passive_data.borrow().keys().copied().collect(),
+        })
+    }
+
+    /// Returns the images of the local memories, to create the
+    /// memories of the instances made from the snapshot.
+    pub fn memory_images(&self) -> &MemoryImages {
+        &self.memory_images
+    }
+
+    /// Restores the snapshot into `instance`, a new instance of the
+    /// same module whose memories have been made from the images of
+    /// the snapshot.
+    pub(super) fn restore(&self, instance: &Instance) -> Result<(), String> {
+        if !Arc::ptr_eq(&self.module, &instance.module) {
+            return Err("the snapshot was taken from an instance of another module".to_string());
+        }
+
+        for (index, value) in &self.globals {
+            unsafe {
+                let definition = &mut *instance.globals[*index].vmglobal().as_ptr();
+                match value {
+                    SnapshotValue::Bits(bits) => *definition.as_u128_mut() = *bits,
+                    SnapshotValue::FuncRef(function_index) => {
+                        *definition.as_funcref_mut() =
+                            SnapshotValue::funcref(*function_index, instance)
+                    }
+                    SnapshotValue::ExternRef(extern_ref) => {
+                        let definition = definition.as_externref_mut();
+                        definition.ref_drop();
+                        *definition = extern_ref.clone().into();
+                    }
+                }
+            }
+        }
+
+        for (index, elements) in self.tables.iter() {
+            let table = &instance.tables[index];
+            let size = elements.len() as u32;
+            if table.size() < size {
+                let init_value = match table.ty().ty {
+                    Type::ExternRef => TableElement::ExternRef(ExternRef::null()),
+                    _ => TableElement::FuncRef(VMFuncRef::null()),
+                };
+                table
+                    .grow(size - table.size(), init_value)
+                    .ok_or_else(|| format!("the table could not grow to {} elements", size))?;
+            }
+            for (element_index, element) in elements.iter().enumerate() {
+                table
+                    .set(element_index as u32, element.to_table_element(instance))
+                    .map_err(|_| "the table is smaller than in the snapshot".to_string())?;
+            }
+        }
+
+        instance
+            .passive_elements
+            .borrow_mut()
+            .retain(|index, _| self.passive_elements.contains(index));
+        instance
+            .passive_data
+            .borrow_mut()
+            .retain(|index, _| self.passive_data.contains(index));
+
+        Ok(())
+    }
+}
@@ -21,16 +21,23 @@
     )
 )]
 
+#[cfg(coroutines)]
+mod coroutine;
 mod export;
 mod func_data_registry;
 mod global;
 mod imports;
 mod instance;
 mod memory;
+mod memory_image;
 mod mmap;
 mod module;
+mod module_linking;
+mod pool;
 mod probestack;
+mod resource_limiter;
 mod sig_registry;
+mod stack_limit;
 mod table;
 mod trap;
 mod vmcontext;
@@ -38,19 +45,29 @@ mod vmoffsets;
 
 pub mod libcalls;
 
+#[cfg(coroutines)]
+pub use crate::coroutine::{suspend, Coroutine, CoroutineError, CoroutineState};
 pub use crate::export::*;
 pub use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
     ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
-    WeakOrStrongInstanceRef,
+    InstanceSnapshot, WeakOrStrongInstanceRef,
 };
 pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
+pub use crate::memory_image::{MemoryImage, MemoryImages};
 pub use crate::mmap::Mmap;
 pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
+pub use crate::module_linking::{
+    EntityType, InstanceDefinition, InstanceSignature, ModuleDefinition, ModuleLinking,
+    ModuleSignature, TypeDefinition,
+};
+pub use crate::pool::{PooledMemory, PooledTable, PoolingAllocator, PoolingLimits};
 pub use crate::probestack::PROBESTACK;
+pub use crate::resource_limiter::{InstanceId, ResourceLimiter, ResourceOwner};
 pub use crate::sig_registry::SignatureRegistry;
+pub use crate::stack_limit::{VMStackLimit, DEFAULT_MAX_WASM_STACK};
 pub use crate::table::{LinearTable, Table, TableElement, TableStyle};
 pub use crate::trap::*;
 pub use crate::vmcontext::{
@@ -5,8 +5,12 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::MemoryImage;
 use crate::mmap::Mmap;
+use crate::resource_limiter::{AttachedLimiter, InstanceId, ResourceLimiter};
 use crate::vmcontext::VMMemoryDefinition;
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::MemoryUsage;
 use more_asserts::assert_ge;
 #[cfg(feature = "enable-rkyv")]
@@ -17,7 +21,7 @@ use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
 
@@ -28,7 +32,7 @@ pub enum MemoryError {
     #[error("Error when allocating memory: {0}")]
     Region(String),
     /// The operation would cause the size of the memory to exceed the maximum or would cause
-    /// an overflow leading to unindexable memory.
+    /// an overflow leading to unindexable memory, or it was denied by the resource limiter.
     #[error("The memory could not grow: current size {} pages, requested increase: {} pages", current.0, attempted_delta.0)]
     CouldNotGrow {
         /// The current size in pages.
@@ -69,6 +73,7 @@ pub enum MemoryError {
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub enum MemoryStyle {
     /// The actual memory can be resized and moved.
     Dynamic {
@@ -120,6 +125,11 @@ pub trait Memory: fmt::Debug + Send + Sync + MemoryUsage {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Records the instance defining this memory, which is passed to
+    /// its [`ResourceLimiter`], if any. Called once the instance is
+    /// created.
+    fn set_instance(&self, _instance: InstanceId) {}
 }
 
 /// A linear memory instance.
@@ -147,6 +157,10 @@ pub struct LinearMemory {
     // Records whether we're using a bounds-checking strategy which requires
     // handlers to catch trapping accesses.
     pub(crate) needs_signal_handlers: bool,
+
+    /// The policy consulted before growing the memory, if any.
+    #[loupe(skip)]
+    resource_limiter: Option<AttachedLimiter>,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -206,6 +220,38 @@ impl LinearMemory {
         Self::new_internal(memory, style, Some(vm_memory_location))
     }
 
+    /// Create a new linear memory instance with specified minimum and maximum number of wasm pages,
+    /// and with an image of its initial contents mapped copy-on-write at its start.
+    ///
+    /// This creates a `LinearMemory` with metadata owned by a VM, pointed to by
+    /// `vm_memory_location`, like [`LinearMemory::from_definition`].
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn from_definition_with_image(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+        image: &MemoryImage,
+    ) -> Result<Self, MemoryError> {
+        if image.len() > memory.minimum.bytes().0 {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the image ({} bytes) is larger than the minimum ({} pages)",
+                    image.len(),
+                    memory.minimum.0
+                ),
+            });
+        }
+
+        let linear_memory = Self::new_internal(memory, style, Some(vm_memory_location))?;
+        image
+            .map_at(linear_memory.mmap.lock().unwrap().alloc.as_mut_ptr())
+            .map_err(MemoryError::Region)?;
+
+        Ok(linear_memory)
+    }
+
     /// Build a `LinearMemory` with either self-owned or VM owned metadata.
     unsafe fn new_internal(
         memory: &MemoryType,
@@ -290,9 +336,17 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
+            resource_limiter: None,
         })
     }
 
+    /// Consults the given [`ResourceLimiter`] before every growth of
+    /// this memory.
+    pub fn with_resource_limiter(mut self, resource_limiter: Arc<dyn ResourceLimiter>) -> Self {
+        self.resource_limiter = Some(AttachedLimiter::new(resource_limiter));
+        self
+    }
+
     /// Get the `VMMemoryDefinition`.
     ///
     /// # Safety
@@ -374,6 +428,46 @@ impl Memory for LinearMemory {
             });
         }
 
+        if let Some(resource_limiter) = &self.resource_limiter {
+            if !resource_limiter.memory_growing(prev_pages, new_pages, self.maximum) {
+                return Err(MemoryError::CouldNotGrow {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                });
+            }
+        }
+
+        let result = self.grow_mmap(mmap, delta, new_pages);
+        if let (Err(_), Some(resource_limiter)) = (&result, &self.resource_limiter) {
+            resource_limiter.memory_grow_failed(prev_pages, new_pages);
+        }
+        result
+    }
+
+    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        let _mmap_guard = self.mmap.lock().unwrap();
+        unsafe { self.get_vm_memory_definition() }
+    }
+
+    /// Records the instance defining this memory.
+    fn set_instance(&self, instance: InstanceId) {
+        if let Some(resource_limiter) = &self.resource_limiter {
+            resource_limiter.set_instance(instance);
+        }
+    }
+}
+
+impl LinearMemory {
+    /// Grows the allocation of the memory to `new_pages`, once the
+    /// growth has been allowed, and returns the previous size.
+    fn grow_mmap(
+        &self,
+        mmap: &mut WasmMmap,
+        delta: Pages,
+        new_pages: Pages,
+    ) -> Result<Pages, MemoryError> {
+        let prev_pages = mmap.size;
         let delta_bytes = delta.bytes().0;
         let prev_bytes = prev_pages.bytes().0;
         let new_bytes = new_pages.bytes().0;
@@ -416,10 +510,4 @@ impl Memory for LinearMemory {
 
         Ok(prev_pages)
     }
-
-    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
-    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
-        let _mmap_guard = self.mmap.lock().unwrap();
-        unsafe { self.get_vm_memory_definition() }
-    }
 }
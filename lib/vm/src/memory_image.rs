@@ -0,0 +1,331 @@
+//! Copy-on-write images of the initial contents of linear memories.
+//!
+//! Instead of copying the data segments of a module into every new
+//! memory, the segments with a constant offset are laid out once in a
+//! [`MemoryImage`], backed by an in-memory file, which is then mapped
+//! privately into the new memories: the pages are shared until they
+//! are written to. Where in-memory files are not available, the image
+//! is kept on the heap and copied into the new memories.
+
+use crate::memory::{Memory, MemoryError};
+use crate::mmap::round_up_to_page_size;
+use crate::module::ModuleInfo;
+#[cfg(target_os = "linux")]
+use std::fs::File;
+use std::ptr;
+use std::slice;
+use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::{DataInitializer, LocalMemoryIndex};
+
+/// The initial contents of a linear memory, ready to be mapped
+/// copy-on-write at the start of the memory.
+#[derive(Debug)]
+pub struct MemoryImage {
+    contents: Contents,
+
+    /// The size in bytes of the image, a multiple of the page size.
+    len: usize,
+}
+
+/// Where the contents of a [`MemoryImage`] are kept.
+#[derive(Debug)]
+enum Contents {
+    /// An in-memory file, mapped copy-on-write into the memories.
+    #[cfg(target_os = "linux")]
+    File(File),
+
+    /// A copy on the heap, copied into the memories.
+    Heap(Box<[u8]>),
+}
+
+impl MemoryImage {
+    /// Lays out the given segments, made of an offset and the data to
+    /// write at this offset, in a new image of at least `len` bytes.
+    ///
+    /// The segments are written in order, so that the later ones
+    /// overwrite the earlier ones where they overlap.
+    pub fn new<'data>(
+        len: usize,
+        segments: impl IntoIterator<Item = (usize, &'data [u8])>,
+    ) -> Result<Self, String> {
+        let len = round_up_to_page_size(len, region::page::size());
+        let segments = segments.into_iter().collect::<Vec<_>>();
+        if segments
+            .iter()
+            .any(|(offset, data)| offset.checked_add(data.len()).map_or(true, |end| end > len))
+        {
+            return Err("the segment is out of the bounds of the image".to_string());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Ok(file) = Self::new_file(len, &segments) {
+                return Ok(Self {
+                    contents: Contents::File(file),
+                    len,
+                });
+            }
+        }
+
+        let mut heap = vec![0; len].into_boxed_slice();
+        for (offset, data) in segments {
+            heap[offset..offset + data.len()].copy_from_slice(data);
+        }
+        Ok(Self {
+            contents: Contents::Heap(heap),
+            len,
+        })
+    }
+
+    /// Writes the segments in a new in-memory file of `len` bytes.
+    #[cfg(target_os = "linux")]
+    fn new_file(len: usize, segments: &[(usize, &[u8])]) -> Result<File, String> {
+        use std::io;
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe {
+            libc::memfd_create(
+                b"wasmer_memory_image\0".as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len(len as u64).map_err(|e| e.to_string())?;
+
+        for (offset, data) in segments {
+            file.write_all_at(data, *offset as u64)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(file)
+    }
+
+    /// Returns the size in bytes of the image.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the image is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maps the image copy-on-write at `base`, replacing the pages
+    /// already mapped there, or copies it there if it is kept on the
+    /// heap.
+    ///
+    /// # Safety
+    /// - `base` must be page-aligned, and point to at least
+    ///   `self.len()` bytes of accessible memory owned by the caller.
+    pub unsafe fn map_at(&self, base: *mut u8) -> Result<(), String> {
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        match &self.contents {
+            #[cfg(target_os = "linux")]
+            Contents::File(file) => {
+                use std::io;
+                use std::os::unix::io::AsRawFd;
+
+                let ptr = libc::mmap(
+                    base as *mut libc::c_void,
+                    self.len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_FIXED,
+                    file.as_raw_fd(),
+                    0,
+                );
+                if ptr as isize == -1_isize {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+            }
+            Contents::Heap(heap) => ptr::copy_nonoverlapping(heap.as_ptr(), base, self.len),
+        }
+
+        Ok(())
+    }
+
+    /// Copies the image at the start of `memory`, for the memories the
+    /// image can't be mapped into.
+    pub fn copy_into(&self, memory: &dyn Memory) -> Result<(), MemoryError> {
+        let definition = unsafe { memory.vmmemory().as_ref() };
+        let current_length = definition.current_length as usize;
+        if current_length < self.len {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the memory ({} bytes) is smaller than its image ({} bytes)",
+                    current_length, self.len
+                ),
+            });
+        }
+
+        let destination = unsafe { slice::from_raw_parts_mut(definition.base, self.len) };
+        match &self.contents {
+            #[cfg(target_os = "linux")]
+            Contents::File(file) => {
+                use std::os::unix::fs::FileExt;
+
+                file.read_exact_at(destination, 0)
+                    .map_err(|e| MemoryError::Region(e.to_string()))
+            }
+            Contents::Heap(heap) => {
+                destination.copy_from_slice(heap);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The images of the local memories of a module.
+#[derive(Debug)]
+pub struct MemoryImages {
+    images: BoxedSlice<LocalMemoryIndex, Option<MemoryImage>>,
+
+    /// The number of data initializers laid out in the images, which
+    /// are the first data initializers of the module.
+    num_initializers: usize,
+}
+
+impl MemoryImages {
+    /// Lays out the data initializers of a module in images of its
+    /// local memories.
+    ///
+    /// The data initializers are laid out in order, up to the first
+    /// one which can't be: its offset depends on a global, its memory
+    /// is imported, or it doesn't fit in the initial size of its
+    /// memory. This one and the following ones are left to be applied
+    /// when instantiating.
+    pub fn new(module: &ModuleInfo, data_initializers: &[DataInitializer]) -> Self {
+        let num_local_memories = module.memories.len() - module.num_imported_memories;
+        let mut segments: PrimaryMap<LocalMemoryIndex, Vec<(usize, &[u8])>> =
+            (0..num_local_memories).map(|_| vec![]).collect();
+
+        let num_initializers = data_initializers
+            .iter()
+            .take_while(|initializer| {
+                let location = &initializer.location;
+                let local_index = match module.local_memory_index(location.memory_index) {
+                    Some(local_index) if location.base.is_none() => local_index,
+                    _ => return false,
+                };
+                let minimum = module.memories[location.memory_index].minimum.bytes().0;
+                match location.offset.checked_add(initializer.data.len()) {
+                    Some(end) if end <= minimum => {
+                        segments[local_index].push((location.offset, initializer.data));
+                        true
+                    }
+                    _ => false,
+                }
+            })
+            .count();
+
+        let mut images = PrimaryMap::with_capacity(num_local_memories);
+        for memory_segments in segments.values() {
+            let len = memory_segments
+                .iter()
+                .map(|(offset, data)| offset + data.len())
+                .max();
+            let image = match len {
+                Some(len) => match MemoryImage::new(len, memory_segments.iter().copied()) {
+                    Ok(image) => Some(image),
+                    Err(_) => return Self::empty(),
+                },
+                None => None,
+            };
+            images.push(image);
+        }
+
+        Self {
+            images: images.into_boxed_slice(),
+            num_initializers,
+        }
+    }
+
+    /// Returns the given images of the local memories, which lay out
+    /// none of the data initializers.
+    pub fn from_images(images: PrimaryMap<LocalMemoryIndex, Option<MemoryImage>>) -> Self {
+        Self {
+            images: images.into_boxed_slice(),
+            num_initializers: 0,
+        }
+    }
+
+    /// Returns images of no memory, leaving all the data initializers to
+    /// be applied when instantiating.
+    pub fn empty() -> Self {
+        Self {
+            images: PrimaryMap::new().into_boxed_slice(),
+            num_initializers: 0,
+        }
+    }
+
+    /// Returns the image of a local memory, if any.
+    pub fn get(&self, index: LocalMemoryIndex) -> Option<&MemoryImage> {
+        self.images.get(index)?.as_ref()
+    }
+
+    /// Returns the number of data initializers laid out in the images,
+    /// which must not be applied again when instantiating.
+    pub fn num_initializers(&self) -> usize {
+        self.num_initializers
+    }
+
+    /// Returns the number of images.
+    pub fn len(&self) -> usize {
+        self.images.values().filter(|image| image.is_some()).count()
+    }
+
+    /// Returns whether there is no image.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmap::Mmap;
+
+    #[test]
+    fn map_image_copy_on_write() {
+        let image = MemoryImage::new(10, vec![(0, &b"hello"[..]), (3, &b"p!"[..])]).unwrap();
+        assert_eq!(image.len(), region::page::size());
+
+        let mut first = Mmap::with_at_least(image.len()).unwrap();
+        let mut second = Mmap::with_at_least(image.len()).unwrap();
+        unsafe {
+            image.map_at(first.as_mut_ptr()).unwrap();
+            image.map_at(second.as_mut_ptr()).unwrap();
+        }
+        assert_eq!(&first.as_slice()[..6], b"help!\0");
+
+        // The writes stay private to each mapping.
+        first.as_mut_slice()[0] = b'y';
+        assert_eq!(&first.as_slice()[..5], b"yelp!");
+        assert_eq!(&second.as_slice()[..5], b"help!");
+    }
+
+    #[test]
+    fn copy_heap_image() {
+        let image = MemoryImage {
+            contents: Contents::Heap(b"hello".to_vec().into_boxed_slice()),
+            len: 5,
+        };
+
+        let mut mmap = Mmap::with_at_least(region::page::size()).unwrap();
+        unsafe {
+            image.map_at(mmap.as_mut_ptr()).unwrap();
+        }
+        assert_eq!(&mmap.as_slice()[..6], b"hello\0");
+    }
+
+    #[test]
+    fn segments_out_of_the_image() {
+        assert!(MemoryImage::new(4, vec![(usize::MAX, &b"x"[..])]).is_err());
+    }
+}
@@ -6,13 +6,12 @@
 
 use loupe::{MemoryUsage, MemoryUsageTracker};
 use more_asserts::assert_le;
-use more_asserts::assert_lt;
 use std::io;
 use std::ptr;
 use std::slice;
 
 /// Round `size` up to the nearest multiple of `page_size`.
-fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+pub(crate) fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
     (size + (page_size - 1)) & !(page_size - 1)
 }
 
@@ -183,12 +182,12 @@ impl Mmap {
     /// `start` and `len` must be native page-size multiples and describe a range within
     /// `self`'s reserved memory.
     #[cfg(not(target_os = "windows"))]
-    pub fn make_accessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+    pub fn make_accessible(&self, start: usize, len: usize) -> Result<(), String> {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -200,15 +199,15 @@ impl Mmap {
     /// `start` and `len` must be native page-size multiples and describe a range within
     /// `self`'s reserved memory.
     #[cfg(target_os = "windows")]
-    pub fn make_accessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+    pub fn make_accessible(&self, start: usize, len: usize) -> Result<(), String> {
         use winapi::ctypes::c_void;
         use winapi::um::memoryapi::VirtualAlloc;
         use winapi::um::winnt::{MEM_COMMIT, PAGE_READWRITE};
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -228,6 +227,77 @@ impl Mmap {
         Ok(())
     }
 
+    /// Make the memory starting at `start` and extending for `len` bytes
+    /// inaccessible again, and release its physical pages, so that it
+    /// reads as zeros once it is made accessible again. `start` and
+    /// `len` must be native page-size multiples and describe a range
+    /// within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn decommit(&self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        let ptr = unsafe { (self.ptr as *mut u8).add(start) } as *mut libc::c_void;
+
+        // `MADV_DONTNEED` zeroes the private anonymous pages on Linux
+        // only: elsewhere, replace the pages with a fresh mapping.
+        #[cfg(target_os = "linux")]
+        {
+            if unsafe { libc::madvise(ptr, len, libc::MADV_DONTNEED) } != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            if unsafe { libc::mprotect(ptr, len, libc::PROT_NONE) } != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let new_ptr = unsafe {
+                libc::mmap(
+                    ptr,
+                    len,
+                    libc::PROT_NONE,
+                    libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                    -1,
+                    0,
+                )
+            };
+            if new_ptr as isize == -1_isize {
+                return Err(io::Error::last_os_error().to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make the memory starting at `start` and extending for `len` bytes
+    /// inaccessible again, and release its physical pages, so that it
+    /// reads as zeros once it is made accessible again. `start` and
+    /// `len` must be native page-size multiples and describe a range
+    /// within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn decommit(&self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -4,6 +4,9 @@
 //! Data structure for representing WebAssembly modules in a
 //! `wasmer::Module`.
 
+use crate::module_linking::{EntityType, ModuleLinking, TypeDefinition};
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use indexmap::IndexMap;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
@@ -24,9 +27,9 @@ use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::ArchivableIndexMap;
 use wasmer_types::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex,
-    FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, LocalFunctionIndex,
-    LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType, SignatureIndex,
-    TableIndex, TableInitializer, TableType,
+    FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, InstanceType,
+    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    MemoryType, ModuleType, SignatureIndex, TableIndex, TableInitializer, TableType,
 };
 
 #[derive(Debug, Clone, MemoryUsage)]
@@ -34,6 +37,7 @@ use wasmer_types::{
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub struct ModuleId {
     id: usize,
 }
@@ -129,11 +133,15 @@ pub struct ModuleInfo {
 
     /// Number of imported globals in the module.
     pub num_imported_globals: usize,
+
+    /// The instances, modules and types of the module linking proposal.
+    pub module_linking: ModuleLinking,
 }
 
 /// Mirror version of ModuleInfo that can derive rkyv traits
 #[cfg(feature = "enable-rkyv")]
 #[derive(RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive(derive(CheckBytes))]
 pub struct ArchivableModuleInfo {
     name: Option<String>,
     imports: ArchivableIndexMap<(String, String, u32), ImportIndex>,
@@ -155,6 +163,7 @@ pub struct ArchivableModuleInfo {
     num_imported_tables: usize,
     num_imported_memories: usize,
     num_imported_globals: usize,
+    module_linking: ModuleLinking,
 }
 
 #[cfg(feature = "enable-rkyv")]
@@ -181,6 +190,7 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            module_linking: it.module_linking,
         }
     }
 }
@@ -210,6 +220,7 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            module_linking: it.module_linking,
         }
     }
 }
@@ -272,6 +283,7 @@ impl PartialEq for ModuleInfo {
             && self.num_imported_tables == other.num_imported_tables
             && self.num_imported_memories == other.num_imported_memories
             && self.num_imported_globals == other.num_imported_globals
+            && self.module_linking == other.module_linking
     }
 }
 
@@ -302,6 +314,7 @@ impl ModuleInfo {
             num_imported_globals: 0,
             custom_sections: IndexMap::new(),
             custom_sections_data: PrimaryMap::new(),
+            module_linking: ModuleLinking::default(),
         }
     }
 
@@ -346,6 +359,14 @@ impl ModuleInfo {
                     let global_type = self.globals.get(*i).unwrap();
                     ExternType::Global(*global_type)
                 }
+                ExportIndex::Instance(i) => {
+                    let signature = self.module_linking.instances[*i];
+                    ExternType::Instance(self.instance_type(signature))
+                }
+                ExportIndex::Module(i) => {
+                    let signature = self.module_linking.modules[*i];
+                    ExternType::Module(self.module_type(signature))
+                }
             };
             ExportType::new(name, extern_type)
         });
@@ -355,11 +376,15 @@ impl ModuleInfo {
         }
     }
 
-    /// Get the export types of the module
+    /// Get the import types of the module.
+    ///
+    /// The aliases of the exports of the instances are not imports of
+    /// the module, and are skipped.
     pub fn imports<'a>(&'a self) -> ImportsIterator<impl Iterator<Item = ImportType> + 'a> {
         let iter = self
             .imports
             .iter()
+            .filter(move |((_, _, index), _)| !self.module_linking.aliases.contains_key(index))
             .map(move |((module, field, _), import_index)| {
                 let extern_type = match import_index {
                     ImportIndex::Function(i) => {
@@ -379,15 +404,92 @@ impl ModuleInfo {
                         let global_type = self.globals.get(*i).unwrap();
                         ExternType::Global(*global_type)
                     }
+                    ImportIndex::Instance(i) => {
+                        let signature = self.module_linking.instances[*i];
+                        ExternType::Instance(self.instance_type(signature))
+                    }
+                    ImportIndex::Module(i) => {
+                        let signature = self.module_linking.modules[*i];
+                        ExternType::Module(self.module_type(signature))
+                    }
                 };
                 ImportType::new(module, field, extern_type)
             });
         ImportsIterator {
             iter,
-            size: self.imports.len(),
+            size: self.imports.len() - self.module_linking.aliases.len(),
+        }
+    }
+
+    /// Get the signature of the function type of the given index in
+    /// the type section, if it is a function type.
+    pub fn signature_index(&self, type_index: u32) -> Option<SignatureIndex> {
+        match self.module_linking.types.get(type_index as usize)? {
+            TypeDefinition::Function(signature) => Some(*signature),
+            TypeDefinition::Instance(_) | TypeDefinition::Module(_) => None,
         }
     }
 
+    /// Get the function types of the type section, as their index in
+    /// the type section and their signature.
+    ///
+    /// The signatures added to the module after its translation, by
+    /// the middlewares for instance, have no index in the type section.
+    pub fn function_types(&self) -> impl Iterator<Item = (u32, SignatureIndex)> + '_ {
+        self.module_linking
+            .types
+            .iter()
+            .enumerate()
+            .filter_map(|(type_index, ty)| match ty {
+                TypeDefinition::Function(signature) => Some((type_index as u32, *signature)),
+                TypeDefinition::Instance(_) | TypeDefinition::Module(_) => None,
+            })
+    }
+
+    /// Get the `ExternType` of an entity imported or exported by an
+    /// instance or a module.
+    pub fn entity_type(&self, ty: &EntityType) -> ExternType {
+        match ty {
+            EntityType::Function(signature) => {
+                ExternType::Function(self.signatures[*signature].clone())
+            }
+            EntityType::Table(table_type) => ExternType::Table(*table_type),
+            EntityType::Memory(memory_type) => ExternType::Memory(*memory_type),
+            EntityType::Global(global_type) => ExternType::Global(*global_type),
+            EntityType::Instance(signature) => ExternType::Instance(self.instance_type(*signature)),
+            EntityType::Module(signature) => ExternType::Module(self.module_type(*signature)),
+        }
+    }
+
+    /// Get the type of the instances of the given instance signature.
+    pub fn instance_type(&self, signature: u32) -> InstanceType {
+        let signature = &self.module_linking.instance_signatures[signature as usize];
+        InstanceType::new(
+            signature
+                .exports
+                .iter()
+                .map(|(name, ty)| ExportType::new(name, self.entity_type(ty)))
+                .collect(),
+        )
+    }
+
+    /// Get the type of the modules of the given module signature.
+    pub fn module_type(&self, signature: u32) -> ModuleType {
+        let signature = &self.module_linking.module_signatures[signature as usize];
+        ModuleType::new(
+            signature
+                .imports
+                .iter()
+                .map(|(module, field, ty)| ImportType::new(module, field, self.entity_type(ty)))
+                .collect(),
+            signature
+                .exports
+                .iter()
+                .map(|(name, ty)| ExportType::new(name, self.entity_type(ty)))
+                .collect(),
+        )
+    }
+
     /// Get the custom sections of the module given a `name`.
     pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Arc<[u8]>> + 'a {
         self.custom_sections
@@ -0,0 +1,191 @@
+//! The entities of the module linking proposal in a `ModuleInfo`:
+//! the instance and module types, the instances and modules of the
+//! module, and the aliases of the exports of its instances.
+//!
+//! Everything refers to other entities by index, so that these can
+//! be archived as the rest of the `ModuleInfo`.
+
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
+use loupe::MemoryUsage;
+#[cfg(feature = "enable-rkyv")]
+use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{
+    ExportIndex, GlobalType, InstanceIndex, MemoryType, ModuleIndex, SignatureIndex, TableType,
+};
+
+/// The type of an entity imported or exported by an instance or a
+/// module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub enum EntityType {
+    /// A function of the given signature.
+    Function(SignatureIndex),
+    /// A table.
+    Table(TableType),
+    /// A memory.
+    Memory(MemoryType),
+    /// A global.
+    Global(GlobalType),
+    /// An instance, of the instance signature of the given index.
+    Instance(u32),
+    /// A module, of the module signature of the given index.
+    Module(u32),
+}
+
+/// The type of an instance: the names and types of its exports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub struct InstanceSignature {
+    /// The exports of the instance.
+    pub exports: Vec<(String, EntityType)>,
+}
+
+/// The type of a module: the modules, names and types of its imports,
+/// and the names and types of its exports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub struct ModuleSignature {
+    /// The imports of the module.
+    pub imports: Vec<(String, String, EntityType)>,
+    /// The exports of the module.
+    pub exports: Vec<(String, EntityType)>,
+}
+
+/// A type of the type section of the module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub enum TypeDefinition {
+    /// A function type, of the given signature.
+    Function(SignatureIndex),
+    /// An instance type, of the instance signature of the given index.
+    Instance(u32),
+    /// A module type, of the module signature of the given index.
+    Module(u32),
+}
+
+/// How an instance of the module is defined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub enum InstanceDefinition {
+    /// The instance is imported.
+    Imported,
+    /// The instance is created by instantiating a module of the
+    /// module with the given arguments, by name.
+    Instantiated {
+        /// The module to instantiate.
+        module: ModuleIndex,
+        /// The arguments to instantiate the module with.
+        args: Vec<(String, ExportIndex)>,
+    },
+    /// The instance is an export of another instance.
+    Alias {
+        /// The instance exporting it.
+        instance: InstanceIndex,
+        /// The name of the export.
+        export: String,
+    },
+}
+
+/// How a module of the module is defined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub enum ModuleDefinition {
+    /// The module is imported.
+    Imported,
+    /// The module is nested in the module, and compiled along with
+    /// it. The value is the index of the module among the nested
+    /// modules.
+    Nested(u32),
+    /// The module is an export of an instance.
+    Alias {
+        /// The instance exporting it.
+        instance: InstanceIndex,
+        /// The name of the export.
+        export: String,
+    },
+}
+
+/// The entities of the module linking proposal in a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
+pub struct ModuleLinking {
+    /// The types of the type section, by type index.
+    ///
+    /// The function types of the type section are also the first
+    /// signatures of the module, but the type indices differ from the
+    /// signature indices as soon as the section declares instance or
+    /// module types.
+    pub types: Vec<TypeDefinition>,
+
+    /// The instance signatures.
+    pub instance_signatures: Vec<InstanceSignature>,
+
+    /// The module signatures.
+    pub module_signatures: Vec<ModuleSignature>,
+
+    /// The instance signatures of the instances (imported, instantiated
+    /// and aliased).
+    pub instances: PrimaryMap<InstanceIndex, u32>,
+
+    /// The definitions of the instances.
+    pub instance_definitions: PrimaryMap<InstanceIndex, InstanceDefinition>,
+
+    /// The module signatures of the modules (imported, nested and
+    /// aliased).
+    pub modules: PrimaryMap<ModuleIndex, u32>,
+
+    /// The definitions of the modules.
+    pub module_definitions: PrimaryMap<ModuleIndex, ModuleDefinition>,
+
+    /// The number of nested modules.
+    pub num_nested_modules: u32,
+
+    /// The functions, tables, memories and globals which are aliases of
+    /// the exports of an instance, with the instance and the name of
+    /// the export, by index of the import.
+    ///
+    /// They take a slot in the imports of the module, as they are
+    /// resolved when instantiating it like imports are, but they are
+    /// not imports of the module.
+    pub aliases: HashMap<u32, (InstanceIndex, String)>,
+}
+
+impl ModuleLinking {
+    /// Returns whether the module uses none of the module linking
+    /// entities.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty() && self.modules.is_empty() && self.aliases.is_empty()
+    }
+}
@@ -0,0 +1,994 @@
+//! Pooling allocation of instances, memories and tables.
+//!
+//! A [`PoolingAllocator`] reserves, once and for all, the address
+//! space of a fixed number of slots for the instances, the linear
+//! memories and the tables. Instantiating a module then takes free
+//! slots instead of allocating, and dropping the instance, memories
+//! and tables gives their slots back after releasing their pages, so
+//! that the next user of a slot finds it zeroed.
+
+use crate::memory::{Memory, MemoryError, MemoryStyle};
+use crate::mmap::{round_up_to_page_size, Mmap};
+use crate::resource_limiter::{AttachedLimiter, InstanceId, ResourceLimiter};
+use crate::table::{RawTableElement, Table, TableElement, TableStyle};
+use crate::trap::{Trap, TrapCode};
+use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
+use crate::VMExternRef;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::alloc::Layout;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::mem;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{Bytes, MemoryType, Pages, TableType, Type as ValType};
+
+/// The number and the size of the slots of a [`PoolingAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolingLimits {
+    /// The maximum number of instances alive at the same time.
+    pub instances: u32,
+
+    /// The maximum size in bytes of an instance, which grows with the
+    /// number of functions, globals, memories and tables of its module.
+    pub instance_size: usize,
+
+    /// The maximum number of memories alive at the same time, across
+    /// all the instances.
+    pub memories: u32,
+
+    /// The maximum size in wasm pages of a memory. The memories are
+    /// compiled with a static style bound to this size.
+    pub memory_pages: Pages,
+
+    /// The size in bytes of the offset guard after each memory.
+    pub memory_offset_guard_size: u64,
+
+    /// The maximum number of tables alive at the same time, across all
+    /// the instances.
+    pub tables: u32,
+
+    /// The maximum number of elements of a table.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x10_0000,
+            memories: 1000,
+            memory_pages: Pages(160),
+            memory_offset_guard_size: 0x1_0000,
+            tables: 1000,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// An allocator taking the instances, memories and tables from
+/// pre-reserved slots, which are reused when they are dropped.
+///
+/// The whole address space of the pool is reserved by
+/// [`PoolingAllocator::new`], which caps the memory used by the
+/// instances. Instantiating fails once all the slots of a kind are in
+/// use.
+#[derive(Debug)]
+pub struct PoolingAllocator {
+    limits: PoolingLimits,
+    instances: SlotRegion,
+    memories: SlotRegion,
+    tables: SlotRegion,
+}
+
+impl PoolingAllocator {
+    /// Reserves the address space of all the slots described by
+    /// `limits`.
+    pub fn new(limits: PoolingLimits) -> Result<Self, String> {
+        if limits.memory_pages > Pages::max_value() {
+            return Err(format!(
+                "the maximum size of the memories ({} pages) is greater than the maximum allowed ({} pages)",
+                limits.memory_pages.0,
+                Pages::max_value().0
+            ));
+        }
+        let memory_size = usize::try_from(limits.memory_offset_guard_size)
+            .ok()
+            .and_then(|guard_size| limits.memory_pages.bytes().0.checked_add(guard_size))
+            .ok_or_else(|| "the memory slots are too large".to_string())?;
+        let table_size = usize::try_from(limits.table_elements)
+            .ok()
+            .and_then(|elements| elements.checked_mul(mem::size_of::<RawTableElement>()))
+            .ok_or_else(|| "the table slots are too large".to_string())?;
+
+        Ok(Self {
+            limits,
+            instances: SlotRegion::new(limits.instances, limits.instance_size)?,
+            memories: SlotRegion::new(limits.memories, memory_size)?,
+            tables: SlotRegion::new(limits.tables, table_size)?,
+        })
+    }
+
+    /// Returns the limits of the pool.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.limits
+    }
+
+    /// Returns the size in bytes of the address space reserved by the
+    /// pool.
+    pub fn reserved_size(&self) -> usize {
+        self.instances.mmap.len() + self.memories.mmap.len() + self.tables.mmap.len()
+    }
+
+    /// Returns the number of instances which can still be allocated.
+    pub fn available_instances(&self) -> usize {
+        self.instances.free.lock().unwrap().len()
+    }
+
+    /// Returns the style of the memories allocated in the pool.
+    pub fn memory_style(&self) -> MemoryStyle {
+        MemoryStyle::Static {
+            bound: self.limits.memory_pages,
+            offset_guard_size: self.limits.memory_offset_guard_size,
+        }
+    }
+
+    /// Takes a slot for an instance of the given layout, with the slot
+    /// accessible.
+    pub(crate) fn allocate_instance(self: &Arc<Self>, layout: Layout) -> Result<PoolSlot, String> {
+        if layout.size() > self.instances.slot_size || layout.align() > region::page::size() {
+            return Err(format!(
+                "the instance needs {} bytes, more than the {} bytes of the instance slots of the pool",
+                layout.size(),
+                self.limits.instance_size
+            ));
+        }
+        let mut slot = self.take(SlotKind::Instance).ok_or_else(|| {
+            format!(
+                "all the {} instance slots of the pool are in use",
+                self.limits.instances
+            )
+        })?;
+        slot.extend(layout.size())?;
+
+        Ok(slot)
+    }
+
+    /// Create a memory owned by the VM in a slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn create_memory(
+        self: &Arc<Self>,
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<PooledMemory, MemoryError> {
+        let bound = match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } if *bound <= self.limits.memory_pages
+                && *offset_guard_size <= self.limits.memory_offset_guard_size =>
+            {
+                *bound
+            }
+            MemoryStyle::Dynamic { offset_guard_size }
+                if *offset_guard_size <= self.limits.memory_offset_guard_size =>
+            {
+                self.limits.memory_pages
+            }
+            _ => {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the style {:?} doesn't fit in the memory slots of the pool",
+                        style
+                    ),
+                })
+            }
+        };
+        if memory.minimum > bound {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: memory.minimum,
+                max_allowed: bound,
+            });
+        }
+        if let Some(max) = memory.maximum {
+            if max < memory.minimum {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the maximum ({} pages) is less than the minimum ({} pages)",
+                        max.0, memory.minimum.0
+                    ),
+                });
+            }
+        }
+
+        let mut slot = self.take(SlotKind::Memory).ok_or_else(|| {
+            MemoryError::Region(format!(
+                "all the {} memory slots of the pool are in use",
+                self.limits.memories
+            ))
+        })?;
+        slot.extend(memory.minimum.bytes().0)
+            .map_err(MemoryError::Region)?;
+
+        {
+            let mut ptr = vm_memory_location;
+            let md = ptr.as_mut();
+            md.base = slot.ptr();
+            md.current_length = memory.minimum.bytes().0.try_into().unwrap();
+        }
+
+        Ok(PooledMemory {
+            slot: Mutex::new(slot),
+            bound,
+            maximum: memory.maximum,
+            memory: *memory,
+            style: style.clone(),
+            vm_memory_definition: vm_memory_location,
+            resource_limiter: None,
+        })
+    }
+
+    /// Create a table owned by the VM in a slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub unsafe fn create_table(
+        self: &Arc<Self>,
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: NonNull<VMTableDefinition>,
+    ) -> Result<PooledTable, String> {
+        match table.ty {
+            ValType::FuncRef | ValType::ExternRef => (),
+            ty => {
+                return Err(format!(
+                    "tables of types other than funcref or externref ({})",
+                    ty
+                ))
+            }
+        };
+        if let Some(max) = table.maximum {
+            if max < table.minimum {
+                return Err(format!(
+                    "Table minimum ({}) is larger than maximum ({})!",
+                    table.minimum, max
+                ));
+            }
+        }
+        if table.minimum > self.limits.table_elements {
+            return Err(format!(
+                "Table minimum ({}) is larger than the {} elements of the table slots of the pool",
+                table.minimum, self.limits.table_elements
+            ));
+        }
+
+        let mut slot = self.take(SlotKind::Table).ok_or_else(|| {
+            format!(
+                "all the {} table slots of the pool are in use",
+                self.limits.tables
+            )
+        })?;
+        // The released pages read as zeros, that is as null references.
+        slot.extend(self.tables.slot_size)?;
+
+        {
+            let mut ptr = vm_table_location;
+            let td = ptr.as_mut();
+            td.base = slot.ptr();
+            td.current_elements = table.minimum;
+        }
+
+        match style {
+            TableStyle::CallerChecksSignature => Ok(PooledTable {
+                slot: Mutex::new(slot),
+                capacity: self.limits.table_elements,
+                maximum: table.maximum,
+                table: *table,
+                style: style.clone(),
+                vm_table_definition: vm_table_location,
+                resource_limiter: None,
+            }),
+        }
+    }
+
+    fn region(&self, kind: SlotKind) -> &SlotRegion {
+        match kind {
+            SlotKind::Instance => &self.instances,
+            SlotKind::Memory => &self.memories,
+            SlotKind::Table => &self.tables,
+        }
+    }
+
+    fn take(self: &Arc<Self>, kind: SlotKind) -> Option<PoolSlot> {
+        let index = self.region(kind).free.lock().unwrap().pop()?;
+        Some(PoolSlot {
+            pool: self.clone(),
+            kind,
+            index,
+            accessible_size: 0,
+        })
+    }
+}
+
+/// A region of address space divided into slots of the same size.
+#[derive(Debug)]
+struct SlotRegion {
+    mmap: Mmap,
+    slot_size: usize,
+
+    /// The indices of the free slots. The last released slot is
+    /// reused first.
+    free: Mutex<Vec<usize>>,
+}
+
+impl SlotRegion {
+    fn new(count: u32, size: usize) -> Result<Self, String> {
+        let count = usize::try_from(count).unwrap();
+        let slot_size = round_up_to_page_size(size, region::page::size());
+        let mapping_size = slot_size
+            .checked_mul(count)
+            .ok_or_else(|| "the pool is too large".to_string())?;
+
+        Ok(Self {
+            mmap: Mmap::accessible_reserved(0, mapping_size)?,
+            slot_size,
+            free: Mutex::new((0..count).rev().collect()),
+        })
+    }
+}
+
+/// The kind of a slot of a [`PoolingAllocator`].
+#[derive(Debug, Clone, Copy)]
+enum SlotKind {
+    Instance,
+    Memory,
+    Table,
+}
+
+/// A slot taken from a [`PoolingAllocator`]. The slot is given back
+/// to the pool when dropped.
+pub(crate) struct PoolSlot {
+    pool: Arc<PoolingAllocator>,
+    kind: SlotKind,
+    index: usize,
+
+    /// The size in bytes of the accessible part of the slot, at its
+    /// start.
+    accessible_size: usize,
+}
+
+impl PoolSlot {
+    /// Returns a pointer to the start of the slot.
+    pub(crate) fn ptr(&self) -> *mut u8 {
+        let region = self.pool.region(self.kind);
+        unsafe { (region.mmap.as_ptr() as *mut u8).add(self.index * region.slot_size) }
+    }
+
+    /// Makes `len` more bytes of the slot accessible.
+    fn extend(&mut self, len: usize) -> Result<(), String> {
+        let region = self.pool.region(self.kind);
+        let len = round_up_to_page_size(len, region::page::size());
+        if len == 0 {
+            return Ok(());
+        }
+        if self.accessible_size + len > region.slot_size {
+            return Err("the slot is full".to_string());
+        }
+
+        region
+            .mmap
+            .make_accessible(self.index * region.slot_size + self.accessible_size, len)?;
+        self.accessible_size += len;
+
+        Ok(())
+    }
+}
+
+impl Drop for PoolSlot {
+    fn drop(&mut self) {
+        let region = self.pool.region(self.kind);
+        if self.accessible_size > 0
+            && region
+                .mmap
+                .decommit(self.index * region.slot_size, self.accessible_size)
+                .is_err()
+        {
+            // Never reuse a slot which may not be zeroed.
+            return;
+        }
+
+        region.free.lock().unwrap().push(self.index);
+    }
+}
+
+impl fmt::Debug for PoolSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PoolSlot")
+            .field("kind", &self.kind)
+            .field("index", &self.index)
+            .field("accessible_size", &self.accessible_size)
+            .finish()
+    }
+}
+
+/// A linear memory allocated in a slot of a [`PoolingAllocator`].
+///
+/// The memory can grow in place up to the size of the slot.
+pub struct PooledMemory {
+    /// The slot of the memory, whose lock also guards the growth.
+    slot: Mutex<PoolSlot>,
+
+    /// The size in wasm pages the memory cannot grow beyond.
+    bound: Pages,
+
+    /// The optional maximum size in wasm pages of this linear memory.
+    maximum: Option<Pages>,
+
+    /// The WebAssembly linear memory description.
+    memory: MemoryType,
+
+    /// Our chosen implementation style.
+    style: MemoryStyle,
+
+    /// The memory definition used by the generated code, owned by the
+    /// instance.
+    vm_memory_definition: NonNull<VMMemoryDefinition>,
+
+    /// The policy consulted before growing the memory, if any.
+    resource_limiter: Option<AttachedLimiter>,
+}
+
+/// This is correct because synchronization of memory accesses is
+/// controlled by the VM, as for [`LinearMemory`].
+///
+/// [`LinearMemory`]: crate::LinearMemory
+unsafe impl Send for PooledMemory {}
+
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledMemory {}
+
+impl PooledMemory {
+    /// Consults the given [`ResourceLimiter`] before every growth of
+    /// this memory.
+    pub fn with_resource_limiter(mut self, resource_limiter: Arc<dyn ResourceLimiter>) -> Self {
+        self.resource_limiter = Some(AttachedLimiter::new(resource_limiter));
+        self
+    }
+}
+
+impl fmt::Debug for PooledMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledMemory")
+            .field("slot", &self.slot)
+            .field("bound", &self.bound)
+            .field("memory", &self.memory)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl MemoryUsage for PooledMemory {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.slot.lock().unwrap().accessible_size
+    }
+}
+
+impl Memory for PooledMemory {
+    /// Returns the type for this memory.
+    fn ty(&self) -> MemoryType {
+        let mut out = self.memory;
+        out.minimum = self.size();
+
+        out
+    }
+
+    /// Returns the memory style for this memory.
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    /// Returns the number of allocated wasm pages.
+    fn size(&self) -> Pages {
+        unsafe {
+            let md = self.vm_memory_definition.as_ref();
+            Bytes::from(md.current_length).try_into().unwrap()
+        }
+    }
+
+    /// Grow memory by the specified amount of wasm pages, in place.
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut slot = self.slot.lock().unwrap();
+        let prev_pages = self.size();
+        // Optimization of memory.grow 0 calls.
+        if delta.0 == 0 {
+            return Ok(prev_pages);
+        }
+
+        let could_not_grow = MemoryError::CouldNotGrow {
+            current: prev_pages,
+            attempted_delta: delta,
+        };
+        let new_pages = prev_pages
+            .checked_add(delta)
+            .ok_or_else(|| could_not_grow.clone())?;
+
+        if self.maximum.map_or(false, |maximum| new_pages > maximum) || new_pages > self.bound {
+            return Err(could_not_grow);
+        }
+        if let Some(resource_limiter) = &self.resource_limiter {
+            if !resource_limiter.memory_growing(prev_pages, new_pages, self.maximum) {
+                return Err(could_not_grow);
+            }
+        }
+
+        if let Err(error) = slot.extend(delta.bytes().0) {
+            if let Some(resource_limiter) = &self.resource_limiter {
+                resource_limiter.memory_grow_failed(prev_pages, new_pages);
+            }
+            return Err(MemoryError::Region(error));
+        }
+
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition;
+            let md = md_ptr.as_mut();
+            md.current_length = new_pages.bytes().0.try_into().unwrap();
+        }
+
+        Ok(prev_pages)
+    }
+
+    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        let _slot_guard = self.slot.lock().unwrap();
+        self.vm_memory_definition
+    }
+
+    /// Records the instance defining this memory.
+    fn set_instance(&self, instance: InstanceId) {
+        if let Some(resource_limiter) = &self.resource_limiter {
+            resource_limiter.set_instance(instance);
+        }
+    }
+}
+
+/// A table allocated in a slot of a [`PoolingAllocator`].
+///
+/// The table can grow in place up to the number of elements of the
+/// slot.
+pub struct PooledTable {
+    /// The slot of the table, whose lock also guards the elements.
+    slot: Mutex<PoolSlot>,
+
+    /// The number of elements the table cannot grow beyond.
+    capacity: u32,
+
+    maximum: Option<u32>,
+
+    /// The WebAssembly table description.
+    table: TableType,
+
+    /// Our chosen implementation style.
+    style: TableStyle,
+
+    /// The table definition used by the generated code, owned by the
+    /// instance.
+    vm_table_definition: NonNull<VMTableDefinition>,
+
+    /// The policy consulted before growing the table, if any.
+    resource_limiter: Option<AttachedLimiter>,
+}
+
+/// This is correct because there is no thread-specific data tied to this type.
+unsafe impl Send for PooledTable {}
+
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledTable {}
+
+impl PooledTable {
+    /// Consults the given [`ResourceLimiter`] before every growth of
+    /// this table.
+    pub fn with_resource_limiter(mut self, resource_limiter: Arc<dyn ResourceLimiter>) -> Self {
+        self.resource_limiter = Some(AttachedLimiter::new(resource_limiter));
+        self
+    }
+
+    /// Returns the first `len` elements of the table.
+    ///
+    /// # Safety
+    /// - `slot` must be the locked slot of this table, and `len` must
+    ///   not exceed its capacity.
+    unsafe fn elements<'a>(&self, slot: &'a mut PoolSlot, len: u32) -> &'a mut [RawTableElement] {
+        debug_assert!(len <= self.capacity);
+        slice::from_raw_parts_mut(slot.ptr() as *mut RawTableElement, len as usize)
+    }
+}
+
+impl Drop for PooledTable {
+    fn drop(&mut self) {
+        let mut slot = self.slot.lock().unwrap();
+        let elements = unsafe { self.elements(&mut slot, self.size()) };
+        for raw in elements {
+            if self.table.ty == ValType::ExternRef {
+                unsafe { raw.extern_ref.ref_drop() };
+            }
+            // The slot is decommitted when it's given back to the pool,
+            // but a slot which can't be decommitted must not keep the
+            // released references either.
+            *raw = RawTableElement::default();
+        }
+    }
+}
+
+impl fmt::Debug for PooledTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledTable")
+            .field("slot", &self.slot)
+            .field("capacity", &self.capacity)
+            .field("table", &self.table)
+            .field("style", &self.style)
+            .finish()
+    }
+}
+
+impl MemoryUsage for PooledTable {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.slot.lock().unwrap().accessible_size
+    }
+}
+
+impl Table for PooledTable {
+    /// Returns the type for this Table.
+    fn ty(&self) -> &TableType {
+        &self.table
+    }
+
+    /// Returns the style for this Table.
+    fn style(&self) -> &TableStyle {
+        &self.style
+    }
+
+    /// Returns the number of allocated elements.
+    fn size(&self) -> u32 {
+        unsafe { self.vm_table_definition.as_ref().current_elements }
+    }
+
+    /// Grow table by the specified amount of elements, in place.
+    ///
+    /// Returns `None` if table can't be grown by the specified amount
+    /// of elements, otherwise returns the previous size of the table.
+    fn grow(&self, delta: u32, init_value: TableElement) -> Option<u32> {
+        let mut slot = self.slot.lock().unwrap();
+        let size = self.size();
+        let new_len = size.checked_add(delta)?;
+        if new_len == size {
+            debug_assert_eq!(delta, 0);
+            return Some(size);
+        }
+        if self.maximum.map_or(false, |max| new_len > max) {
+            return None;
+        }
+        if let Some(resource_limiter) = &self.resource_limiter {
+            if !resource_limiter.table_growing(size, new_len, self.maximum) {
+                return None;
+            }
+        }
+        if new_len > self.capacity {
+            if let Some(resource_limiter) = &self.resource_limiter {
+                resource_limiter.table_grow_failed(size, new_len);
+            }
+            return None;
+        }
+
+        // Update the ref count
+        let element = match init_value {
+            TableElement::ExternRef(extern_ref) => {
+                let extern_ref: VMExternRef = extern_ref.into();
+                // We reduce the amount we increment by because `into` prevents
+                // dropping `init_value` (which is a caller-inc'd ref).
+                (new_len as usize)
+                    .checked_sub(size as usize + 1)
+                    .map(|val| extern_ref.ref_inc_by(val));
+                RawTableElement { extern_ref }
+            }
+            TableElement::FuncRef(func_ref) => RawTableElement { func_ref },
+        };
+
+        unsafe {
+            for raw in &mut self.elements(&mut slot, new_len)[size as usize..] {
+                *raw = element;
+            }
+
+            let mut td_ptr = self.vm_table_definition;
+            td_ptr.as_mut().current_elements = new_len;
+        }
+        Some(size)
+    }
+
+    /// Get reference to the specified element.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    fn get(&self, index: u32) -> Option<TableElement> {
+        let mut slot = self.slot.lock().unwrap();
+        let raw_data = *unsafe { self.elements(&mut slot, self.size()) }.get(index as usize)?;
+        Some(match self.table.ty {
+            ValType::ExternRef => {
+                TableElement::ExternRef(unsafe { raw_data.extern_ref.ref_clone() }.into())
+            }
+            ValType::FuncRef => TableElement::FuncRef(unsafe { raw_data.func_ref }),
+            _ => unreachable!("the element type of a table is checked when it is created"),
+        })
+    }
+
+    /// Set reference to the specified element.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of bounds.
+    fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap> {
+        let mut slot = self.slot.lock().unwrap();
+        let size = self.size();
+        match unsafe { self.elements(&mut slot, size) }.get_mut(index as usize) {
+            Some(raw) => {
+                match (self.table.ty, reference) {
+                    (ValType::ExternRef, TableElement::ExternRef(extern_ref)) => {
+                        let extern_ref = extern_ref.into();
+                        unsafe {
+                            raw.extern_ref.ref_drop();
+                            raw.extern_ref = extern_ref
+                        }
+                    }
+                    (ValType::FuncRef, r @ TableElement::FuncRef(_)) => {
+                        *raw = r.into();
+                    }
+                    // This path should never be hit by the generated code due to Wasm
+                    // validation.
+                    (ty, v) => {
+                        panic!(
+                            "Attempted to set a table of type {} with the value {:?}",
+                            ty, v
+                        )
+                    }
+                };
+
+                Ok(())
+            }
+            None => Err(Trap::lib(TrapCode::TableAccessOutOfBounds)),
+        }
+    }
+
+    /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        let _slot_guard = self.slot.lock().unwrap();
+        self.vm_table_definition
+    }
+
+    /// Records the instance defining this table.
+    fn set_instance(&self, instance: InstanceId) {
+        if let Some(resource_limiter) = &self.resource_limiter {
+            resource_limiter.set_instance(instance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_limiter::ResourceOwner;
+    use wasmer_types::ExternRef;
+
+    fn pool(instances: u32, memories: u32, tables: u32) -> Arc<PoolingAllocator> {
+        Arc::new(
+            PoolingAllocator::new(PoolingLimits {
+                instances,
+                instance_size: 0x1000,
+                memories,
+                memory_pages: Pages(4),
+                memory_offset_guard_size: 0x1_0000,
+                tables,
+                table_elements: 16,
+            })
+            .unwrap(),
+        )
+    }
+
+    fn memory_definition() -> Box<VMMemoryDefinition> {
+        Box::new(VMMemoryDefinition {
+            base: std::ptr::null_mut(),
+            current_length: 0,
+        })
+    }
+
+    #[test]
+    fn reserves_all_the_slots_up_front() {
+        let pool = pool(3, 2, 1);
+        let page_size = region::page::size();
+        assert_eq!(
+            pool.reserved_size(),
+            3 * round_up_to_page_size(0x1000, page_size)
+                + 2 * (4 * 0x1_0000 + 0x1_0000)
+                + round_up_to_page_size(16 * mem::size_of::<RawTableElement>(), page_size)
+        );
+    }
+
+    #[test]
+    fn instance_slots_are_reused() {
+        let pool = pool(2, 0, 0);
+        let layout = Layout::from_size_align(0x800, 16).unwrap();
+
+        let first = pool.allocate_instance(layout).unwrap();
+        let second = pool.allocate_instance(layout).unwrap();
+        assert_ne!(first.ptr(), second.ptr());
+        assert_eq!(pool.available_instances(), 0);
+        assert!(pool.allocate_instance(layout).is_err());
+
+        let first_ptr = first.ptr();
+        drop(first);
+        assert_eq!(pool.available_instances(), 1);
+        assert_eq!(pool.allocate_instance(layout).unwrap().ptr(), first_ptr);
+
+        let too_large = Layout::from_size_align(0x1_0000, 16).unwrap();
+        assert!(pool.allocate_instance(too_large).is_err());
+    }
+
+    #[test]
+    fn memories_are_zeroed_when_reused() {
+        let pool = pool(0, 1, 0);
+        let style = pool.memory_style();
+        let ty = MemoryType::new(1, Some(3), false);
+        let mut definition = memory_definition();
+
+        let memory = unsafe {
+            pool.create_memory(&ty, &style, NonNull::from(&mut *definition))
+                .unwrap()
+        };
+        unsafe { *definition.base.add(42) = 1 };
+        assert!(
+            unsafe { pool.create_memory(&ty, &style, NonNull::from(&mut *definition)) }.is_err()
+        );
+        drop(memory);
+
+        let _memory = unsafe {
+            pool.create_memory(&ty, &style, NonNull::from(&mut *definition))
+                .unwrap()
+        };
+        assert_eq!(unsafe { *definition.base.add(42) }, 0);
+    }
+
+    #[test]
+    fn memories_grow_in_place_up_to_the_bound() {
+        let pool = pool(0, 1, 0);
+        let style = pool.memory_style();
+        let ty = MemoryType::new(1, None, false);
+        let mut definition = memory_definition();
+
+        let memory = unsafe {
+            pool.create_memory(&ty, &style, NonNull::from(&mut *definition))
+                .unwrap()
+        };
+        let base = definition.base;
+        assert_eq!(memory.grow(Pages(3)).unwrap(), Pages(1));
+        assert_eq!(memory.size(), Pages(4));
+        assert_eq!(definition.base, base);
+        unsafe { *base.add(4 * 0x1_0000 - 1) = 1 };
+        assert!(memory.grow(Pages(1)).is_err());
+
+        let too_large = MemoryType::new(5, None, false);
+        assert!(
+            unsafe { pool.create_memory(&too_large, &style, NonNull::from(&mut *definition)) }
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn tables_grow_up_to_the_slot_capacity() {
+        let pool = pool(0, 0, 1);
+        let ty = TableType::new(ValType::FuncRef, 2, None);
+        let mut definition = Box::new(VMTableDefinition {
+            base: std::ptr::null_mut(),
+            current_elements: 0,
+        });
+
+        let table = unsafe {
+            pool.create_table(
+                &ty,
+                &TableStyle::CallerChecksSignature,
+                NonNull::from(&mut *definition),
+            )
+            .unwrap()
+        };
+        assert_eq!(table.size(), 2);
+        assert!(table.get(1).is_some());
+        assert!(table.get(2).is_none());
+        assert_eq!(table.grow(14, TableElement::default()), Some(2));
+        assert_eq!(table.grow(1, TableElement::default()), None);
+        assert_eq!(definition.current_elements, 16);
+    }
+
+    #[test]
+    fn tables_release_their_references_when_dropped() {
+        let pool = pool(0, 0, 1);
+        let ty = TableType::new(ValType::ExternRef, 2, None);
+        let mut definition = Box::new(VMTableDefinition {
+            base: std::ptr::null_mut(),
+            current_elements: 0,
+        });
+        let extern_ref = ExternRef::new(42u32);
+
+        let table = unsafe {
+            pool.create_table(
+                &ty,
+                &TableStyle::CallerChecksSignature,
+                NonNull::from(&mut *definition),
+            )
+            .unwrap()
+        };
+        table
+            .set(1, TableElement::ExternRef(extern_ref.clone()))
+            .unwrap();
+        assert_eq!(
+            table.grow(2, TableElement::ExternRef(extern_ref.clone())),
+            Some(2)
+        );
+        assert_eq!(extern_ref.strong_count(), 4);
+        let base = definition.base as *const RawTableElement;
+        drop(table);
+        assert_eq!(extern_ref.strong_count(), 1);
+
+        let table = unsafe {
+            pool.create_table(
+                &ty,
+                &TableStyle::CallerChecksSignature,
+                NonNull::from(&mut *definition),
+            )
+            .unwrap()
+        };
+        assert_eq!(definition.base as *const RawTableElement, base);
+        assert!(table
+            .grow(2, TableElement::ExternRef(ExternRef::null()))
+            .is_some());
+        for index in 0..4 {
+            match table.get(index) {
+                Some(TableElement::ExternRef(extern_ref)) => assert!(extern_ref.is_null()),
+                element => panic!("unexpected element {:?}", element),
+            }
+        }
+    }
+
+    /// Records the failed growths of the tables.
+    #[derive(Debug, Default)]
+    struct FailedGrowths(Mutex<Vec<(u32, u32)>>);
+
+    impl ResourceLimiter for FailedGrowths {
+        fn table_grow_failed(&self, _owner: ResourceOwner, current: u32, desired: u32) {
+            self.0.lock().unwrap().push((current, desired));
+        }
+    }
+
+    #[test]
+    fn tables_report_the_growths_beyond_the_slot_capacity() {
+        let pool = pool(0, 0, 1);
+        let ty = TableType::new(ValType::FuncRef, 2, None);
+        let mut definition = Box::new(VMTableDefinition {
+            base: std::ptr::null_mut(),
+            current_elements: 0,
+        });
+        let failed_growths = Arc::new(FailedGrowths::default());
+
+        let table = unsafe {
+            pool.create_table(
+                &ty,
+                &TableStyle::CallerChecksSignature,
+                NonNull::from(&mut *definition),
+            )
+            .unwrap()
+        }
+        .with_resource_limiter(failed_growths.clone());
+        assert_eq!(table.grow(15, TableElement::default()), None);
+        assert_eq!(table.grow(14, TableElement::default()), Some(2));
+        assert_eq!(*failed_growths.0.lock().unwrap(), vec![(2, 17)]);
+    }
+}
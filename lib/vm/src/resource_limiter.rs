@@ -0,0 +1,153 @@
+//! Dynamic limits on the growth of memories and tables.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer_types::Pages;
+
+/// A host-defined policy consulted every time a [`LinearMemory`] or a
+/// [`LinearTable`] is about to grow, either from WebAssembly with
+/// `memory.grow` and `table.grow`, or from the host.
+///
+/// Unlike the maximum of a memory or table type, which is fixed when
+/// the module is compiled, a limiter can take its decision at runtime,
+/// for instance to enforce a memory budget shared by all the instances
+/// of a tenant, or a budget per instance with the [`ResourceOwner`]
+/// of the memory or table.
+///
+/// The limiter is only consulted for the growths within the maximum
+/// of the type and, for a memory, its bounds. Denying a growth
+/// doesn't trap: `memory.grow` and `table.grow` return `-1` to the
+/// WebAssembly code, as when the maximum is reached.
+///
+/// [`LinearMemory`]: crate::LinearMemory
+/// [`LinearTable`]: crate::LinearTable
+pub trait ResourceLimiter: fmt::Debug + Send + Sync {
+    /// Called before a memory of `owner` grows from `current` to
+    /// `desired` pages, `maximum` being the maximum of its type, if
+    /// any.
+    ///
+    /// Returns whether the memory is allowed to grow.
+    fn memory_growing(
+        &self,
+        owner: ResourceOwner,
+        current: Pages,
+        desired: Pages,
+        maximum: Option<Pages>,
+    ) -> bool {
+        let _ = (owner, current, desired, maximum);
+        true
+    }
+
+    /// Called when a growth allowed by [`Self::memory_growing`] fails,
+    /// for instance because the pages can't be allocated. The memory
+    /// keeps its `current` size.
+    fn memory_grow_failed(&self, owner: ResourceOwner, current: Pages, desired: Pages) {
+        let _ = (owner, current, desired);
+    }
+
+    /// Called before a table of `owner` grows from `current` to
+    /// `desired` elements, `maximum` being the maximum of its type, if
+    /// any.
+    ///
+    /// Returns whether the table is allowed to grow.
+    fn table_growing(
+        &self,
+        owner: ResourceOwner,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> bool {
+        let _ = (owner, current, desired, maximum);
+        true
+    }
+
+    /// Called when a growth allowed by [`Self::table_growing`] fails,
+    /// for instance because the slot of a [`PooledTable`] has no room
+    /// for the new elements. The table keeps its `current` size.
+    ///
+    /// [`PooledTable`]: crate::PooledTable
+    fn table_grow_failed(&self, owner: ResourceOwner, current: u32, desired: u32) {
+        let _ = (owner, current, desired);
+    }
+}
+
+/// Identifies an instance, among all the instances created by the
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstanceId(u64);
+
+impl InstanceId {
+    /// Returns a new identifier, distinct from all the previous ones.
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the identifier as a number.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// What a memory or a table belongs to, for a [`ResourceLimiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceOwner {
+    /// The memory or table is defined by the given instance. It keeps
+    /// this owner when it's imported by other instances.
+    Instance(InstanceId),
+
+    /// The memory or table has been created from the host.
+    Host,
+}
+
+/// A [`ResourceLimiter`] consulted by a memory or a table, with the
+/// owner passed to it.
+#[derive(Debug)]
+pub(crate) struct AttachedLimiter {
+    limiter: Arc<dyn ResourceLimiter>,
+    owner: Mutex<ResourceOwner>,
+}
+
+impl AttachedLimiter {
+    pub(crate) fn new(limiter: Arc<dyn ResourceLimiter>) -> Self {
+        Self {
+            limiter,
+            owner: Mutex::new(ResourceOwner::Host),
+        }
+    }
+
+    /// Records the instance defining the memory or the table.
+    pub(crate) fn set_instance(&self, instance: InstanceId) {
+        *self.owner.lock().unwrap() = ResourceOwner::Instance(instance);
+    }
+
+    fn owner(&self) -> ResourceOwner {
+        *self.owner.lock().unwrap()
+    }
+
+    pub(crate) fn memory_growing(
+        &self,
+        current: Pages,
+        desired: Pages,
+        maximum: Option<Pages>,
+    ) -> bool {
+        self.limiter
+            .memory_growing(self.owner(), current, desired, maximum)
+    }
+
+    pub(crate) fn memory_grow_failed(&self, current: Pages, desired: Pages) {
+        self.limiter
+            .memory_grow_failed(self.owner(), current, desired)
+    }
+
+    pub(crate) fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>) -> bool {
+        self.limiter
+            .table_growing(self.owner(), current, desired, maximum)
+    }
+
+    pub(crate) fn table_grow_failed(&self, current: u32, desired: u32) {
+        self.limiter
+            .table_grow_failed(self.owner(), current, desired)
+    }
+}
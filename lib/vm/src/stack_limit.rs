@@ -0,0 +1,295 @@
+//! The limit on the native stack used by WebAssembly code.
+
+use std::cell::Cell;
+use std::fmt;
+use std::hint;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The maximum size of the stack used by WebAssembly code, when it
+/// isn't configured: the stack isn't limited.
+pub const DEFAULT_MAX_WASM_STACK: usize = usize::MAX;
+
+/// The limit of the instances which aren't given one: the prologues
+/// compare the stack pointer to `0`, which always succeeds.
+static NO_STACK_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// The limit stored while calls on several stacks are in progress: the
+/// prologues compare the stack pointer to `1`, which always succeeds.
+const SHARED: usize = 1;
+
+/// The depth stored while the last call resets the limit.
+const RESETTING: usize = usize::MAX;
+
+/// The bounds of the stack usable by WebAssembly code, shared by the
+/// instances of a store.
+///
+/// The prologue of every compiled function compares the stack pointer
+/// to the lowest address of the stack that it may use, and traps with
+/// `TrapCode::StackOverflow` below it. That address is computed when a
+/// thread calls into WebAssembly, from the stack pointer of the host
+/// and the maximum stack size: the WebAssembly code can then use the
+/// same stack depth whichever thread calls it, and its overflows trap
+/// like any other trap, with a backtrace of the WebAssembly frames.
+///
+/// The limit is a best effort: when calls are in progress on several
+/// stacks at once, such as on two threads, or on a thread and a
+/// [`Coroutine`](crate::Coroutine) it resumed, the prologues stop
+/// checking the stack until all the calls return. The guard page at the
+/// end of the stack still catches the overflows then, as it does when
+/// the stack is smaller than the maximum.
+#[repr(C)]
+pub struct VMStackLimit {
+    /// The lowest address of the stack usable by WebAssembly code, `0`
+    /// when no call is in progress, or `SHARED`. Read by compiled code:
+    /// it must stay the first field.
+    limit: AtomicUsize,
+
+    /// The maximum size of the stack used by WebAssembly code.
+    max_wasm_stack: AtomicUsize,
+
+    /// The number of stacks with a call into WebAssembly in progress,
+    /// or `RESETTING`.
+    ///
+    /// The calls nested in another one on the same stack, which the
+    /// TLS states of the thread tell, aren't counted.
+    depth: AtomicUsize,
+}
+
+impl VMStackLimit {
+    /// Creates a limit allowing WebAssembly code to use at most
+    /// `max_wasm_stack` bytes of stack.
+    pub fn new(max_wasm_stack: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(0),
+            max_wasm_stack: AtomicUsize::new(max_wasm_stack),
+            depth: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum size of the stack used by WebAssembly code.
+    pub fn max_wasm_stack(&self) -> usize {
+        self.max_wasm_stack.load(Ordering::Relaxed)
+    }
+
+    /// Sets the maximum size of the stack used by WebAssembly code.
+    ///
+    /// It applies from the next call into WebAssembly which isn't
+    /// nested in another one. `usize::MAX` disables the limit.
+    pub fn set_max_wasm_stack(&self, max_wasm_stack: usize) {
+        self.max_wasm_stack.store(max_wasm_stack, Ordering::Relaxed);
+    }
+
+    /// Returns the pointer stored in the `VMContext`s, to the address
+    /// compared to the stack pointer by the prologues.
+    pub fn limit_ptr(&self) -> *const usize {
+        &self.limit as *const AtomicUsize as *const usize
+    }
+
+    /// Returns the pointer stored in the `VMContext`s of the instances
+    /// without a limit.
+    pub(crate) fn none_ptr() -> *const usize {
+        &NO_STACK_LIMIT as *const AtomicUsize as *const usize
+    }
+
+    /// Records a call into WebAssembly, made from the current stack,
+    /// until the returned value is dropped.
+    ///
+    /// The calls nested in a call already recorded on the same stack
+    /// must not be recorded.
+    pub(crate) fn enter(&self) -> StackLimitEntry<'_> {
+        let limit = stack_pointer().saturating_sub(self.max_wasm_stack());
+        self.acquire(limit);
+        StackLimitEntry {
+            stack_limit: self,
+            limit,
+            active: Cell::new(true),
+        }
+    }
+
+    /// Counts a stack using the limit, which is set to `limit` if no
+    /// other stack uses it.
+    fn acquire(&self, limit: usize) {
+        let mut depth = self.depth.load(Ordering::Acquire);
+        loop {
+            if depth == RESETTING {
+                hint::spin_loop();
+                depth = self.depth.load(Ordering::Acquire);
+                continue;
+            }
+            match self.depth.compare_exchange_weak(
+                depth,
+                depth + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(current) => depth = current,
+            }
+        }
+
+        if depth == 0 {
+            // Another stack may already have marked the limit as shared,
+            // in which case it must stay so.
+            let _ = self
+                .limit
+                .compare_exchange(0, limit, Ordering::SeqCst, Ordering::SeqCst);
+        } else {
+            self.limit.store(SHARED, Ordering::SeqCst);
+        }
+    }
+
+    /// Uncounts a stack counted by [`VMStackLimit::acquire`]. The last
+    /// one resets the limit, while the stacks entering wait.
+    fn release(&self) {
+        let mut depth = self.depth.load(Ordering::Acquire);
+        loop {
+            let next = if depth == 1 { RESETTING } else { depth - 1 };
+            match self
+                .depth
+                .compare_exchange_weak(depth, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                Err(current) => depth = current,
+            }
+        }
+
+        if depth == 1 {
+            self.limit.store(0, Ordering::SeqCst);
+            self.depth.store(0, Ordering::Release);
+        }
+    }
+}
+
+impl Default for VMStackLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_WASM_STACK)
+    }
+}
+
+impl fmt::Debug for VMStackLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VMStackLimit")
+            .field("limit", &self.limit.load(Ordering::Relaxed))
+            .field("max_wasm_stack", &self.max_wasm_stack())
+            .finish()
+    }
+}
+
+/// A call into WebAssembly recorded by [`VMStackLimit::enter`].
+pub(crate) struct StackLimitEntry<'a> {
+    stack_limit: &'a VMStackLimit,
+    /// The limit computed when entering the call.
+    #[cfg_attr(not(coroutines), allow(dead_code))]
+    limit: usize,
+    /// Whether the stack of the call is counted, which it isn't while
+    /// the call is suspended with a coroutine.
+    active: Cell<bool>,
+}
+
+impl StackLimitEntry<'_> {
+    /// Stops counting the stack of the call, while it is suspended with
+    /// a coroutine: the coroutine may never be resumed.
+    pub(crate) fn suspend(&self) {
+        if self.active.replace(false) {
+            self.stack_limit.release();
+        }
+    }
+
+    /// Counts the stack of the call again, when its coroutine is
+    /// resumed.
+    #[cfg_attr(not(coroutines), allow(dead_code))]
+    pub(crate) fn resume(&self) {
+        if !self.active.replace(true) {
+            self.stack_limit.acquire(self.limit);
+        }
+    }
+}
+
+impl Drop for StackLimitEntry<'_> {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
+
+/// Returns an address close to the stack pointer of the caller.
+#[inline(never)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn limit(stack_limit: &VMStackLimit) -> usize {
+        unsafe { *stack_limit.limit_ptr() }
+    }
+
+    #[test]
+    fn calls_set_the_limit() {
+        let stack_limit = VMStackLimit::new(64 * 1024);
+        assert_eq!(limit(&stack_limit), 0);
+
+        let entry = stack_limit.enter();
+        let entry_limit = limit(&stack_limit);
+        assert!(entry_limit <= stack_pointer());
+        assert!(stack_pointer() - entry_limit <= 64 * 1024 + 4096);
+
+        drop(entry);
+        assert_eq!(limit(&stack_limit), 0);
+    }
+
+    #[test]
+    fn calls_from_other_threads_disable_the_limit() {
+        let stack_limit = Arc::new(VMStackLimit::new(64 * 1024));
+        let outer = stack_limit.enter();
+        assert_ne!(limit(&stack_limit), 0);
+
+        let other_thread_limit = stack_limit.clone();
+        thread::spawn(move || drop(other_thread_limit.enter()))
+            .join()
+            .unwrap();
+        assert_eq!(limit(&stack_limit), SHARED);
+
+        drop(outer);
+        assert_eq!(limit(&stack_limit), 0);
+        let _entry = stack_limit.enter();
+        assert_ne!(limit(&stack_limit), SHARED);
+    }
+
+    #[test]
+    fn suspended_calls_are_not_counted() {
+        let stack_limit = VMStackLimit::new(64 * 1024);
+        let suspended = stack_limit.enter();
+        suspended.suspend();
+        assert_eq!(limit(&stack_limit), 0);
+
+        let entry = stack_limit.enter();
+        assert_ne!(limit(&stack_limit), SHARED);
+        suspended.resume();
+        assert_eq!(limit(&stack_limit), SHARED);
+        drop(entry);
+        drop(suspended);
+        assert_eq!(limit(&stack_limit), 0);
+
+        // A call suspended with a coroutine which is never resumed
+        // doesn't keep the limit shared.
+        let abandoned = stack_limit.enter();
+        abandoned.suspend();
+        std::mem::forget(abandoned);
+        let _entry = stack_limit.enter();
+        assert_ne!(limit(&stack_limit), 0);
+        assert_ne!(limit(&stack_limit), SHARED);
+    }
+
+    #[test]
+    fn unlimited() {
+        let stack_limit = VMStackLimit::default();
+        assert_eq!(stack_limit.max_wasm_stack(), usize::MAX);
+        let _entry = stack_limit.enter();
+        assert_eq!(limit(&stack_limit), 0);
+    }
+}
@@ -6,9 +6,12 @@
 //! `Table` is to WebAssembly tables what `LinearMemory` is to WebAssembly linear memories.
 
 use crate::func_data_registry::VMFuncRef;
+use crate::resource_limiter::{AttachedLimiter, InstanceId, ResourceLimiter};
 use crate::trap::{Trap, TrapCode};
 use crate::vmcontext::VMTableDefinition;
 use crate::VMExternRef;
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use loupe::{MemoryUsage, MemoryUsageTracker};
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -18,7 +21,7 @@ use std::cell::UnsafeCell;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use wasmer_types::{ExternRef, TableType, Type as ValType};
 
 /// Implementation styles for WebAssembly tables.
@@ -27,6 +30,7 @@ use wasmer_types::{ExternRef, TableType, Type as ValType};
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 pub enum TableStyle {
     /// Signatures are stored in the table and checked in the caller.
     CallerChecksSignature,
@@ -109,6 +113,33 @@ pub trait Table: fmt::Debug + Send + Sync + MemoryUsage {
 
         Ok(())
     }
+
+    /// Set the `len` elements of `self[start_index..]` to `reference`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds of the table.
+    fn fill(&self, start_index: u32, reference: TableElement, len: u32) -> Result<(), Trap> {
+        // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-table-fill
+
+        if start_index
+            .checked_add(len)
+            .map_or(true, |n| n > self.size())
+        {
+            return Err(Trap::lib(TrapCode::TableAccessOutOfBounds));
+        }
+
+        for index in start_index..start_index + len {
+            self.set(index, reference.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the instance defining this table, which is passed to
+    /// its [`ResourceLimiter`], if any. Called once the instance is
+    /// created.
+    fn set_instance(&self, _instance: InstanceId) {}
 }
 
 /// A reference stored in a table. Can be either an externref or a funcref.
@@ -185,6 +216,9 @@ pub struct LinearTable {
     /// Our chosen implementation style.
     style: TableStyle,
     vm_table_definition: VMTableDefinitionOwnership,
+    /// The policy consulted before growing the table, if any.
+    #[loupe(skip)]
+    resource_limiter: Option<AttachedLimiter>,
 }
 
 /// A type to help manage who is responsible for the backing table of the
@@ -278,10 +312,18 @@ impl LinearTable {
                         },
                     )))
                 },
+                resource_limiter: None,
             }),
         }
     }
 
+    /// Consults the given [`ResourceLimiter`] before every growth of
+    /// this table.
+    pub fn with_resource_limiter(mut self, resource_limiter: Arc<dyn ResourceLimiter>) -> Self {
+        self.resource_limiter = Some(AttachedLimiter::new(resource_limiter));
+        self
+    }
+
     /// Get the `VMTableDefinition`.
     ///
     /// # Safety
@@ -327,13 +369,18 @@ impl Table for LinearTable {
         let vec = vec_guard.borrow_mut();
         let size = self.size();
         let new_len = size.checked_add(delta)?;
-        if self.maximum.map_or(false, |max| new_len > max) {
-            return None;
-        }
         if new_len == size {
             debug_assert_eq!(delta, 0);
             return Some(size);
         }
+        if self.maximum.map_or(false, |max| new_len > max) {
+            return None;
+        }
+        if let Some(resource_limiter) = &self.resource_limiter {
+            if !resource_limiter.table_growing(size, new_len, self.maximum) {
+                return None;
+            }
+        }
 
         // Update the ref count
         let element = match init_value {
@@ -372,7 +419,7 @@ impl Table for LinearTable {
                 TableElement::ExternRef(unsafe { raw_data.extern_ref.ref_clone() }.into())
             }
             ValType::FuncRef => TableElement::FuncRef(unsafe { raw_data.func_ref }),
-            _ => todo!("getting invalid type from table, handle this error"),
+            _ => unreachable!("the element type of a table is checked when it is created"),
         })
     }
 
@@ -420,4 +467,11 @@ impl Table for LinearTable {
         let _vec_guard = self.vec.lock().unwrap();
         unsafe { self.get_vm_table_definition() }
     }
+
+    /// Records the instance defining this table.
+    fn set_instance(&self, instance: InstanceId) {
+        if let Some(resource_limiter) = &self.resource_limiter {
+            resource_limiter.set_instance(instance);
+        }
+    }
 }
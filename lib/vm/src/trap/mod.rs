@@ -12,3 +12,5 @@ pub use traphandlers::{
     TlsRestore, Trap, TrapHandler, TrapHandlerFn,
 };
 pub use traphandlers::{init_traps, resume_panic};
+#[cfg(coroutines)]
+pub(crate) use traphandlers::{TlsBase, TlsSegment};
@@ -3,6 +3,8 @@
 
 //! Trap codes describing the reason for a trap.
 
+#[cfg(feature = "enable-rkyv")]
+use bytecheck::CheckBytes;
 use core::fmt::{self, Display, Formatter};
 use core::str::FromStr;
 use loupe::MemoryUsage;
@@ -19,6 +21,7 @@ use thiserror::Error;
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
+#[cfg_attr(feature = "enable-rkyv", archive(derive(CheckBytes)))]
 #[repr(u32)]
 pub enum TrapCode {
     /// The current stack space was exhausted.
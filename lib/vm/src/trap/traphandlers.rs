@@ -5,6 +5,7 @@
 //! signalhandling mechanisms.
 
 use super::trapcode::TrapCode;
+use crate::stack_limit::{StackLimitEntry, VMStackLimit};
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
 use backtrace::Backtrace;
 use std::any::Any;
@@ -15,6 +16,8 @@ use std::mem::{self, MaybeUninit};
 use std::ptr;
 use std::sync::Once;
 pub use tls::TlsRestore;
+#[cfg(coroutines)]
+pub(crate) use tls::{TlsBase, TlsSegment};
 
 cfg_if::cfg_if! {
     if #[cfg(unix)] {
@@ -109,6 +112,19 @@ cfg_if::cfg_if! {
             (stackaddr as usize, stacksize)
         }
 
+        /// Returns the stack the call into Wasm runs on: the stack of
+        /// its coroutine, if any, or else the stack of the thread.
+        #[cfg_attr(not(coroutines), allow(unused_variables))]
+        unsafe fn current_stack(info: &CallThreadState) -> (usize, usize) {
+            #[cfg(coroutines)]
+            {
+                if let Some(stack) = info.coroutine_stack {
+                    return stack;
+                }
+            }
+            thread_stack()
+        }
+
         unsafe extern "C" fn trap_handler(
             signum: libc::c_int,
             siginfo: *mut libc::siginfo_t,
@@ -121,22 +137,6 @@ cfg_if::cfg_if! {
                 libc::SIGILL => &PREV_SIGILL,
                 _ => panic!("unknown signal: {}", signum),
             };
-            // We try to get the Code trap associated to this signal
-            let maybe_signal_trap = match signum {
-                libc::SIGSEGV | libc::SIGBUS => {
-                    let addr = (*siginfo).si_addr() as usize;
-                    let (stackaddr, stacksize) = thread_stack();
-                    // The stack and its guard page covers the
-                    // range [stackaddr - guard pages .. stackaddr + stacksize).
-                    // We assume the guard page is 1 page, and pages are 4KiB (or 16KiB in Apple Silicon)
-                    if stackaddr - region::page::size() <= addr && addr < stackaddr + stacksize {
-                        Some(TrapCode::StackOverflow)
-                    } else {
-                        Some(TrapCode::HeapAccessOutOfBounds)
-                    }
-                }
-                _ => None,
-            };
             let handled = tls::with(|info| {
                 // If no wasm code is executing, we don't handle this as a wasm
                 // trap.
@@ -145,6 +145,25 @@ cfg_if::cfg_if! {
                     None => return false,
                 };
 
+                // We try to get the Code trap associated to this signal
+                let maybe_signal_trap = match signum {
+                    libc::SIGSEGV | libc::SIGBUS => {
+                        let addr = (*siginfo).si_addr() as usize;
+                        let (stackaddr, stacksize) = current_stack(info);
+                        // The stack and its guard page covers the
+                        // range [stackaddr - guard pages .. stackaddr + stacksize).
+                        // We assume the guard page is 1 page, and pages are 4KiB (or 16KiB in Apple Silicon)
+                        if stackaddr - region::page::size() <= addr
+                            && addr < stackaddr + stacksize
+                        {
+                            Some(TrapCode::StackOverflow)
+                        } else {
+                            Some(TrapCode::HeapAccessOutOfBounds)
+                        }
+                    }
+                    _ => None,
+                };
+
                 // If we hit an exception while handling a previous trap, that's
                 // quite bad, so bail out and let the system handle this
                 // recursive segfault.
@@ -606,6 +625,15 @@ pub struct CallThreadState<'a> {
     prev: Cell<tls::Ptr>,
     trap_handler: &'a (dyn TrapHandler + 'a),
     handling_trap: Cell<bool>,
+    /// The lowest address and the size of the stack of the coroutine
+    /// the call runs on, if any. It is looked up when entering Wasm,
+    /// as the signal handlers can't initialize a thread local.
+    #[cfg(coroutines)]
+    coroutine_stack: Option<(usize, usize)>,
+    /// The call recorded with the stack limit of the trap handler, if
+    /// it isn't nested in another call with the same limit on the same
+    /// stack.
+    stack_limit_entry: Option<StackLimitEntry<'a>>,
 }
 
 /// A package of functionality needed by `catch_traps` to figure out what to do
@@ -622,6 +650,12 @@ pub unsafe trait TrapHandler {
     ///
     /// Returns `true` if `call` returns true, otherwise returns `false`.
     fn custom_trap_handler(&self, call: &dyn Fn(&TrapHandlerFn) -> bool) -> bool;
+
+    /// Returns the limit on the stack used by the WebAssembly code
+    /// called with this handler, if any.
+    fn stack_limit(&self) -> Option<&VMStackLimit> {
+        None
+    }
 }
 
 enum UnwindReason {
@@ -642,13 +676,53 @@ enum UnwindReason {
 impl<'a> CallThreadState<'a> {
     #[inline]
     fn new(trap_handler: &'a (dyn TrapHandler + 'a)) -> CallThreadState<'a> {
-        Self {
+        let mut state = Self {
             unwind: UnsafeCell::new(MaybeUninit::uninit()),
             jmp_buf: Cell::new(ptr::null()),
             reset_guard_page: Cell::new(false),
             prev: Cell::new(ptr::null()),
             trap_handler,
             handling_trap: Cell::new(false),
+            #[cfg(coroutines)]
+            coroutine_stack: crate::coroutine::running_stack(),
+            stack_limit_entry: None,
+        };
+        state.stack_limit_entry = trap_handler
+            .stack_limit()
+            .filter(|stack_limit| !state.is_nested(stack_limit))
+            .map(VMStackLimit::enter);
+        state
+    }
+
+    /// Returns whether a call with `stack_limit` is already in progress
+    /// on the stack of this call, by walking the TLS states of the
+    /// thread.
+    fn is_nested(&self, stack_limit: &VMStackLimit) -> bool {
+        tls::with(|mut state| {
+            while let Some(current) = state {
+                let same_limit = current
+                    .trap_handler
+                    .stack_limit()
+                    .map_or(false, |limit| ptr::eq(limit, stack_limit));
+                if same_limit && current.is_on_the_same_stack(self) {
+                    return true;
+                }
+                state = unsafe { current.prev.get().as_ref() };
+            }
+            false
+        })
+    }
+
+    /// Returns whether this call runs on the same stack as `other`.
+    #[cfg_attr(not(coroutines), allow(unused_variables))]
+    fn is_on_the_same_stack(&self, other: &CallThreadState) -> bool {
+        #[cfg(coroutines)]
+        {
+            self.coroutine_stack == other.coroutine_stack
+        }
+        #[cfg(not(coroutines))]
+        {
+            true
         }
     }
 
@@ -863,6 +937,89 @@ mod tls {
         }
     }
 
+    /// The TLS state configured when a coroutine is resumed, below the
+    /// states of the calls made on the stack of the coroutine.
+    #[cfg(coroutines)]
+    #[derive(Clone, Copy)]
+    pub struct TlsBase(raw::Ptr);
+
+    #[cfg(coroutines)]
+    impl TlsBase {
+        /// Returns the TLS state that is currently configured.
+        pub fn current() -> Self {
+            Self(raw::get())
+        }
+    }
+
+    /// The TLS states of the calls made on the stack of a coroutine,
+    /// detached from the thread while the coroutine is suspended.
+    ///
+    /// Unlike [`TlsRestore`], which moves a single state, it moves all
+    /// the states above a [`TlsBase`], since a coroutine can be
+    /// suspended from a host function called by Wasm called by a host
+    /// function, and so on.
+    #[cfg(coroutines)]
+    pub struct TlsSegment {
+        top: raw::Ptr,
+        bottom: raw::Ptr,
+    }
+
+    #[cfg(coroutines)]
+    impl TlsSegment {
+        /// Detaches the TLS states configured above `base`, restoring
+        /// `base` as the current state. Returns `None` if there is no
+        /// such state.
+        ///
+        /// # Safety
+        ///
+        /// `base` must be below the current state, and the detached
+        /// states must not be used until they are attached again.
+        pub unsafe fn detach(base: TlsBase) -> Option<Self> {
+            let top = raw::get();
+            if top == base.0 {
+                return None;
+            }
+            let mut bottom = top;
+            loop {
+                // The suspended calls don't use the stack limits: the
+                // coroutine may never be resumed.
+                if let Some(entry) = &(*bottom).stack_limit_entry {
+                    entry.suspend();
+                }
+                let prev = (*bottom).prev.get();
+                if prev == base.0 {
+                    break;
+                }
+                assert!(!prev.is_null(), "the base is not below the current state");
+                bottom = prev;
+            }
+            (*bottom).prev.set(ptr::null());
+            raw::replace(base.0).expect("tls should be previously initialized");
+            Some(Self { top, bottom })
+        }
+
+        /// Attaches the detached TLS states back above the current
+        /// state.
+        ///
+        /// # Safety
+        ///
+        /// This is unsafe because it's intended to only be used within the
+        /// context of stack switching within wasmer.
+        pub unsafe fn attach(self) -> Result<(), Trap> {
+            assert!((*self.bottom).prev.get().is_null());
+            let mut state = self.top;
+            while !state.is_null() {
+                if let Some(entry) = &(*state).stack_limit_entry {
+                    entry.resume();
+                }
+                state = (*state).prev.get();
+            }
+            (*self.bottom).prev.set(raw::get());
+            raw::replace(self.top)?;
+            Ok(())
+        }
+    }
+
     /// Configures thread local state such that for the duration of the
     /// execution of `closure` any call to `with` will yield `ptr`, unless this
     /// is recursively called again.
@@ -454,10 +454,11 @@ impl VMOffsets {
             .unwrap()
     }
 
-    /// Return the size of the [`VMContext`] allocation.
+    /// The offset of the pointer to the [`VMStackLimit`] checked in
+    /// the prologue of the functions.
     ///
-    /// [`VMContext`]: crate::vmcontext::VMContext
-    pub fn size_of_vmctx(&self) -> u32 {
+    /// [`VMStackLimit`]: crate::stack_limit::VMStackLimit
+    pub fn vmctx_stack_limit(&self) -> u32 {
         self.vmctx_builtin_functions_begin()
             .checked_add(
                 VMBuiltinFunctionIndex::builtin_functions_total_number()
@@ -467,6 +468,15 @@ impl VMOffsets {
             .unwrap()
     }
 
+    /// Return the size of the [`VMContext`] allocation.
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub fn size_of_vmctx(&self) -> u32 {
+        self.vmctx_stack_limit()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap()
+    }
+
     /// Return the offset to [`VMSharedSignatureIndex`] index `index`.
     ///
     /// [`VMSharedSignatureIndex`]: crate::vmcontext::VMSharedSignatureIndex
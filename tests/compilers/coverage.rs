@@ -0,0 +1,55 @@
+use anyhow::Result;
+use wasmer_middlewares::coverage::{get_coverage, COVERAGE_MEMORY};
+use wasmer_middlewares::Coverage;
+
+use std::sync::Arc;
+use wasmer::*;
+
+#[compiler_test(coverage)]
+fn coverage_counters_memory(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let store = config.store();
+    // The counters memory follows the memory of the module, whose
+    // loads and stores must not be mixed up with the counters.
+    let wat = r#"(module
+        (memory (export "memory") 1)
+        (func (export "count") (param i32) (result i32)
+           (if (local.get 0)
+             (then
+               (i32.store (i32.const 0)
+                 (i32.add (i32.load (i32.const 0)) (i32.const 1)))))
+           (i32.load (i32.const 0)))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let exports = module
+        .exports()
+        .map(|export| export.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exports,
+        vec![
+            "memory".to_string(),
+            "count".to_string(),
+            COVERAGE_MEMORY.to_string()
+        ]
+    );
+
+    let instance = Instance::new(&module, &imports! {})?;
+    let count: NativeFunc<i32, i32> = instance.exports.get_native_function("count")?;
+    assert_eq!(count.call(1)?, 1);
+    assert_eq!(count.call(1)?, 2);
+    assert_eq!(count.call(0)?, 2);
+
+    // The blocks are: the function entry, the `then` branch, and after
+    // the `if`.
+    let report = get_coverage(&instance);
+    assert_eq!(
+        report.functions[0]
+            .blocks
+            .iter()
+            .map(|block| block.count)
+            .collect::<Vec<_>>(),
+        vec![3, 2, 3]
+    );
+    Ok(())
+}
@@ -6,12 +6,15 @@
 extern crate compiler_test_derive;
 
 mod config;
+mod coverage;
 mod imports;
 mod metering;
 mod middlewares;
+mod module_linking;
 // mod multi_value_imports;
 mod native_functions;
 mod serialize;
+mod stack_limit;
 mod traps;
 mod wasi;
 mod wast;
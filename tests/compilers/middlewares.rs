@@ -205,3 +205,127 @@ fn middleware_chain_order_2(mut config: crate::Config) -> Result<()> {
     assert_eq!(result, 48);
     Ok(())
 }
+
+#[derive(Debug, Default)]
+struct AddRecorderGen {
+    state: std::sync::Mutex<Option<(ImportedFunctions, LocalFunctionIndex)>>,
+}
+
+#[derive(Debug)]
+struct AddRecorder {
+    imported: ImportedFunctions,
+    helper: wasmer_types::FunctionIndex,
+}
+
+impl MemoryUsage for AddRecorderGen {
+    fn size_of_val(&self, _: &mut dyn loupe::MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for AddRecorderGen {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        let (imported, helper) = self.state.lock().unwrap().unwrap();
+        Box::new(AddRecorder {
+            imported,
+            helper: imported.func_index(helper),
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut vm::ModuleInfo) {
+        let record_signature = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32], vec![]));
+        let imported = ImportedFunctions::insert(
+            module_info,
+            "host",
+            &[("record".to_string(), record_signature)],
+        );
+        let add_signature = module_info.signatures.push(FunctionType::new(
+            vec![Type::I32, Type::I32],
+            vec![Type::I32],
+        ));
+        let helper = add_local_function(module_info, add_signature);
+        *self.state.lock().unwrap() = Some((imported, helper));
+    }
+
+    fn added_function_body(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Option<AddedFunctionBody> {
+        let (imported, helper) = self.state.lock().unwrap().unwrap();
+        if local_function_index != helper {
+            return None;
+        }
+        Some(AddedFunctionBody {
+            locals: vec![(1, wasmparser::Type::I32)],
+            operators: vec![
+                Operator::LocalGet { local_index: 0 },
+                Operator::LocalGet { local_index: 1 },
+                Operator::I32Add,
+                Operator::LocalTee { local_index: 2 },
+                Operator::Call {
+                    function_index: imported.index(0).as_u32(),
+                },
+                Operator::LocalGet { local_index: 2 },
+                Operator::End,
+            ],
+        })
+    }
+}
+
+impl FunctionMiddleware for AddRecorder {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        match operator {
+            Operator::I32Add => state.push_operator(Operator::Call {
+                function_index: self.helper.as_u32(),
+            }),
+            operator => state.push_operator(self.imported.shift_operator(operator)),
+        }
+        Ok(())
+    }
+}
+
+#[derive(WasmerEnv, Clone, Default)]
+struct RecordedSums {
+    sums: Arc<std::sync::Mutex<Vec<i32>>>,
+}
+
+#[compiler_test(middlewares)]
+fn middleware_added_functions(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![
+        Arc::new(AddRecorderGen::default()) as Arc<dyn ModuleMiddleware>
+    ]);
+    let store = config.store();
+    let wat = r#"(module
+        (func $add (export "add") (param i32 i32) (result i32)
+           (i32.add (local.get 0)
+                    (local.get 1)))
+        (func (export "double") (param i32) (result i32)
+           (call $add (local.get 0)
+                      (local.get 0)))
+)"#;
+    let module = Module::new(&store, wat).unwrap();
+
+    let recorded_sums = RecordedSums::default();
+    let import_object = imports! {
+        "host" => {
+            "record" => Function::new_native_with_env(&store, recorded_sums.clone(), |env: &RecordedSums, sum: i32| {
+                env.sums.lock().unwrap().push(sum);
+            }),
+        },
+    };
+
+    let instance = Instance::new(&module, &import_object)?;
+
+    let add: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("add")?;
+    assert_eq!(add.call(4, 5)?, 9);
+    let double: NativeFunc<i32, i32> = instance.exports.get_native_function("double")?;
+    assert_eq!(double.call(3)?, 6);
+    assert_eq!(*recorded_sums.sums.lock().unwrap(), vec![9, 6]);
+    Ok(())
+}
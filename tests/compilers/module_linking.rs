@@ -0,0 +1,208 @@
+//! Testing the module linking proposal: the instance and module
+//! imports, the nested modules and their instantiation, the aliases and
+//! the instance and module exports.
+
+use anyhow::Result;
+use wasmer::*;
+
+fn module_linking_store(mut config: crate::Config) -> Store {
+    let mut features = Features::new();
+    features.module_linking(true);
+    config.set_features(features);
+    config.store()
+}
+
+#[compiler_test(module_linking)]
+fn instance_imports_are_resolved_by_namespace(config: crate::Config) -> Result<()> {
+    let store = module_linking_store(config);
+    let library = Module::new(
+        &store,
+        r#"(module
+            (global (export "offset") i32 (i32.const 40))
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))"#,
+    )?;
+    let application = Module::new(
+        &store,
+        r#"(module
+            (import "library" (instance $library
+                (export "offset" (global i32))
+                (export "add" (func (param i32 i32) (result i32)))))
+            (alias $library "add" (func $add))
+            (alias $library "offset" (global $offset))
+            (func (export "run") (param i32) (result i32)
+                (call $add (local.get 0) (global.get $offset))))"#,
+    )?;
+
+    let imports = application
+        .imports()
+        .map(|import| {
+            (
+                import.module().to_string(),
+                import.name().to_string(),
+                matches!(import.ty(), ExternType::Instance(_)),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(imports, vec![("library".to_string(), "".to_string(), true)]);
+
+    let library = Instance::new(&library, &imports! {})?;
+    let mut import_object = ImportObject::new();
+    import_object.register("library", library.exports.clone());
+    let application = Instance::new(&application, &import_object)?;
+
+    let run = application.exports.get_native_function::<i32, i32>("run")?;
+    assert_eq!(run.call(2)?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(module_linking)]
+fn nested_modules_are_instantiated(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = module_linking_store(config);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "host" "answer" (func $answer (result i32)))
+            (module $inner
+                (import "answer" (func $answer (result i32)))
+                (func (export "run") (result i32)
+                    (i32.add (call $answer) (i32.const 2))))
+            (instance $inner (instantiate $inner (import "answer" (func $answer))))
+            (alias $inner "run" (func $run))
+            (export "run" (func $run)))"#,
+    )?;
+
+    let answer = Function::new_native(&store, || 40);
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "answer" => answer,
+            },
+        },
+    )?;
+
+    let run = instance.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(module_linking)]
+fn definitions_are_passed_to_nested_modules(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = module_linking_store(config);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (func $answer (result i32) (i32.const 40))
+            (module $inner
+                (import "answer" (func $answer (result i32)))
+                (func (export "run") (result i32)
+                    (i32.add (call $answer) (i32.const 2))))
+            (instance $inner (instantiate $inner (import "answer" (func $answer))))
+            (export "inner" (instance $inner)))"#,
+    )?;
+
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let inner = instance.exports.get_instance("inner")?;
+    let run = inner.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(module_linking)]
+fn instances_and_modules_are_exported(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = module_linking_store(config);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (module $inner
+                (global (export "answer") i32 (i32.const 42)))
+            (instance $inner (instantiate $inner))
+            (export "inner" (instance $inner))
+            (export "module" (module $inner)))"#,
+    )?;
+
+    let exports = module
+        .exports()
+        .map(|export| export.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(exports, vec!["inner".to_string(), "module".to_string()]);
+
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let inner = instance.exports.get_instance("inner")?;
+    assert_eq!(inner.get_global("answer")?.get(), Value::I32(42));
+
+    let exported_module = instance.exports.get_module("module")?;
+    let other = Instance::new(exported_module, &imports! {})?;
+    assert_eq!(other.exports.get_global("answer")?.get(), Value::I32(42));
+
+    Ok(())
+}
+
+#[compiler_test(module_linking)]
+fn module_imports_are_instantiated(config: crate::Config) -> Result<()> {
+    let store = module_linking_store(config);
+    let library = Module::new(
+        &store,
+        r#"(module
+            (import "answer" (func $answer (result i32)))
+            (func (export "run") (result i32)
+                (i32.add (call $answer) (i32.const 2))))"#,
+    )?;
+    let application = Module::new(
+        &store,
+        r#"(module
+            (import "host" "answer" (func $answer (result i32)))
+            (import "host" "library" (module $library
+                (import "answer" (func (result i32)))
+                (export "run" (func (result i32)))))
+            (instance $library (instantiate $library (import "answer" (func $answer))))
+            (alias $library "run" (func $run))
+            (export "run" (func $run)))"#,
+    )?;
+
+    let answer = Function::new_native(&store, || 40);
+    let instance = Instance::new(
+        &application,
+        &imports! {
+            "host" => {
+                "answer" => answer,
+                "library" => library,
+            },
+        },
+    )?;
+
+    let run = instance.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(module_linking)]
+fn nested_modules_are_not_supported_by_the_dylib_engine(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Dylib {
+        return Ok(());
+    }
+    let store = module_linking_store(config);
+    let result = Module::new(
+        &store,
+        "(module (module $inner) (instance (instantiate $inner)))",
+    );
+    assert!(matches!(result, Err(CompileError::UnsupportedFeature(_))));
+
+    Ok(())
+}
@@ -74,3 +74,49 @@ fn test_deserialize(config: crate::Config) -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[compiler_test(serialize)]
+fn test_deserialize_checked(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = config.store();
+    let wat = r#"
+        (module $name
+            (func $add_one (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                i32.add)
+            (func (export "add_two") (param i32) (result i32)
+                local.get 0
+                call $add_one
+                call $add_one)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let serialized_bytes = module.serialize()?;
+
+    let headless_store = config.headless_store();
+    let deserialized_module =
+        unsafe { Module::deserialize_checked(&headless_store, &serialized_bytes)? };
+    assert_eq!(deserialized_module.name(), Some("name"));
+
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let add_two = instance.exports.get_function("add_two")?;
+    assert_eq!(
+        add_two.call(&[Value::I32(40)])?.to_vec(),
+        vec![Value::I32(42)]
+    );
+
+    // Truncated data must be rejected instead of being read out of bounds.
+    let truncated = &serialized_bytes[..serialized_bytes.len() / 2];
+    assert!(unsafe { Module::deserialize_checked(&headless_store, truncated) }.is_err());
+
+    // So must an archive whose root position has been tampered with.
+    let mut tampered = serialized_bytes.clone();
+    let len = tampered.len();
+    tampered[len - 8..].copy_from_slice(&(len as u64).to_le_bytes());
+    assert!(unsafe { Module::deserialize_checked(&headless_store, &tampered) }.is_err());
+    Ok(())
+}
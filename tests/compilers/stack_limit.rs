@@ -0,0 +1,112 @@
+use anyhow::Result;
+use wasmer_middlewares::stack_limit::{get_stack_height, StackHeight};
+use wasmer_middlewares::StackLimit;
+
+use std::sync::Arc;
+use wasmer::*;
+
+/// Calls `$recurse` with the given depth, with a stack limit of 126
+/// values, returning whether the limit was exceeded.
+fn run_recursion(mut config: crate::Config, depth: i32) -> Result<bool> {
+    config.middlewares.push(Arc::new(StackLimit::new(126)));
+    let store = config.store();
+    let wat = r#"(module
+        (func $recurse (export "recurse") (param i32)
+           (local i64 f64)
+           (if (local.get 0)
+             (then
+               (call $recurse (i32.sub (local.get 0) (i32.const 1)))))
+        )
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let f: NativeFunc<i32, ()> = instance.exports.get_native_function("recurse")?;
+    let result = f.call(depth);
+    let height = get_stack_height(&instance);
+    match result {
+        Ok(()) => {
+            assert_eq!(height, StackHeight::Height(0));
+            Ok(false)
+        }
+        Err(_) => {
+            assert_eq!(height, StackHeight::Exceeded);
+            Ok(true)
+        }
+    }
+}
+
+#[compiler_test(stack_limit)]
+fn stack_limit_depth(config: crate::Config) -> Result<()> {
+    // Each call pushes a frame of height 6: one value for the frame,
+    // one for the parameter, two for the locals and two for the operand
+    // stack. `recurse(20)` runs 21 nested calls, which exactly fit in
+    // the limit, whatever the compiler.
+    assert!(!run_recursion(config.clone(), 20)?);
+    assert!(run_recursion(config, 21)?);
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn stack_limit_multi_value(mut config: crate::Config) -> Result<()> {
+    // Singlepass doesn't support multi-value.
+    if config.compiler == crate::Compiler::Singlepass {
+        return Ok(());
+    }
+    // The instance type shifts the function types in the type section,
+    // so their indices differ from the indices of their signatures.
+    let mut features = Features::new();
+    features.module_linking(true);
+    config.set_features(features);
+    config.middlewares.push(Arc::new(StackLimit::new(100)));
+    let store = config.store();
+    let wat = r#"(module
+        (type (instance))
+        (type $pair (func (result i32 i64)))
+        (type $swap (func (param i64 i32) (result i32 i64)))
+        (table 1 funcref)
+        (elem (i32.const 0) $swap)
+        (func $swap (type $swap)
+           (local.get 1)
+           (local.get 0))
+        (func (export "pair") (param i32) (result i32 i64)
+           (if (i32.eqz (local.get 0))
+             (then
+               (return (i32.const 1) (i64.const 2))))
+           (i64.const 3)
+           (local.get 0)
+           (block (type $swap)
+             (call_indirect (type $swap) (i32.const 0))))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let f: NativeFunc<i32, (i32, i64)> = instance.exports.get_native_function("pair")?;
+    assert_eq!(f.call(0)?, (1, 2));
+    assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+    assert_eq!(f.call(5)?, (5, 3));
+    assert_eq!(get_stack_height(&instance), StackHeight::Height(0));
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn stack_limit_multi_value_needs_a_declared_type(mut config: crate::Config) -> Result<()> {
+    if config.compiler == crate::Compiler::Singlepass {
+        return Ok(());
+    }
+    config.middlewares.push(Arc::new(StackLimit::new(100)));
+    let store = config.store();
+    // No type without parameters returns `i32 i64`: the body can't be
+    // wrapped in a block.
+    let wat = r#"(module
+        (func (export "pair") (param i32) (result i32 i64)
+           (local.get 0)
+           (i64.const 2))
+)"#;
+    let result = Module::new(&store, wat);
+    assert!(matches!(
+        result,
+        Err(CompileError::Wasm(WasmError::Middleware(_)))
+    ));
+    Ok(())
+}
@@ -147,6 +147,46 @@ fn test_trap_stack_overflow(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn max_wasm_stack(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $rec_mod
+            (func $recurse (export "recurse") (param i32)
+                (if (local.get 0)
+                    (then
+                        (call $recurse (i32.sub (local.get 0) (i32.const 1))))))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let recurse: NativeFunc<i32, ()> = instance.exports.get_native_function("recurse")?;
+
+    // The stack isn't limited by default.
+    assert_eq!(store.max_wasm_stack(), usize::MAX);
+    recurse.call(4000)?;
+
+    // Every frame uses at least 16 bytes, for the return address and
+    // the frame pointer.
+    store.set_max_wasm_stack(16 * 1024);
+    assert_eq!(store.max_wasm_stack(), 16 * 1024);
+    let e = recurse.call(4000).err().expect("error calling function");
+    assert_eq!(e.clone().to_trap(), Some(TrapCode::StackOverflow));
+    let trace = e.trace();
+    assert!(trace.len() >= 32);
+    for frame in trace {
+        assert_eq!(frame.module_name(), "rec_mod");
+        assert_eq!(frame.function_name(), Some("recurse"));
+    }
+
+    store.set_max_wasm_stack(1024 * 1024);
+    recurse.call(4000)?;
+
+    Ok(())
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn trap_display_pretty(config: crate::Config) -> Result<()> {